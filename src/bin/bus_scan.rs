@@ -0,0 +1,156 @@
+/// Sucht Kombisensoren an einer Modbus Leitung
+///
+/// Die gefundenen Kombisensoren werden ausgegeben und, wenn eine Konfigurationsdatei angegeben ist,
+/// in eine Zone dieser Konfiguration übernommen.
+extern crate clap;
+extern crate env_logger;
+extern crate serde_json;
+extern crate xmz_mod_touch_server;
+
+use clap::{App, Arg, ArgMatches};
+use std::error::Error;
+use std::fs::File;
+use std::io::prelude::*;
+use xmz_mod_touch_server::{KombisensorType, ModbusBus, Server};
+use xmz_mod_touch_server::errors::*;
+use xmz_mod_touch_server::modbus_bus::{RtsMode, SerialParameters};
+
+
+// Die verschiedenen Umgebungen, bestimmt den Typ der gefundenen Kombisensoren
+#[derive(Debug)]
+enum Environment {
+    Production,
+    Development,
+}
+
+fn run(matches: &ArgMatches) -> Result<()> {
+    let environment = match matches.value_of("environment").unwrap(){
+        "production"  => Environment::Production,
+        "development" => Environment::Development,
+        _ => unreachable!(),
+    };
+    let modbus_device = matches.value_of("modbus_device").unwrap();
+    let first_address: u8 = matches.value_of("first_address").unwrap().parse().chain_err(|| "invalid first_address")?;
+    let last_address: u8 = matches.value_of("last_address").unwrap().parse().chain_err(|| "invalid last_address")?;
+    let zone_id: usize = matches.value_of("zone").unwrap().parse().chain_err(|| "invalid zone")?;
+    let debug: bool = matches.value_of("debug").unwrap().parse().unwrap();
+    let response_timeout: u32 = matches.value_of("response_timeout").unwrap().parse().chain_err(|| "invalid response_timeout")?;
+
+    // Kombisensor Typ nach Environment
+    let kombisensor_type = match environment {
+        Environment::Development => KombisensorType::RAGasSimulation,
+        Environment::Production  => KombisensorType::RAGas,
+    };
+
+    // Auf der 'xMZ-Mod-Touch'-Hardware muss der RTS Pin genutzt werden
+    let mut serial_parameters = match kombisensor_type {
+        KombisensorType::RAGas => SerialParameters::new_with_rts_mode(RtsMode::Down),
+        _ => SerialParameters::new(),
+    };
+    // Jede Adresse ohne Teilnehmer kostet einen Response Timeout
+    serial_parameters.set_response_timeout_ms(response_timeout);
+    let mut bus = ModbusBus::new(modbus_device, serial_parameters);
+    println!("Suche Kombisensoren an '{}', Adressen {} bis {} ...", modbus_device, first_address, last_address);
+    let discovered = bus.scan(first_address, last_address, debug)?;
+
+    for discovered_kombisensor in &discovered {
+        println!("Adresse {:>3}: Firmware {}", discovered_kombisensor.get_modbus_address(), discovered_kombisensor.get_firmware_version());
+    }
+    println!("{} Kombisensoren gefunden", discovered.len());
+
+    if let Some(config_file) = matches.value_of("config_file") {
+        let mut config = String::new();
+        File::open(config_file)?.read_to_string(&mut config)?;
+        let mut xmz_mod_touch_server: Server = serde_json::from_str(&config)?;
+
+        let num_added = xmz_mod_touch_server.merge_discovered_kombisensors(zone_id, &discovered, kombisensor_type)?;
+
+        let xmz_mod_touch_server_json = serde_json::to_string_pretty(&xmz_mod_touch_server)?;
+        File::create(config_file)?.write_all(xmz_mod_touch_server_json.as_bytes())?;
+
+        println!("{} neue Kombisensoren in Zone {} nach '{}' geschrieben", num_added, zone_id, config_file);
+    } else {
+        println!("{}", serde_json::to_string_pretty(&discovered)?);
+    }
+
+    Ok(())
+}
+
+
+fn main() {
+    // Initalisiere Logger (erst nach diesem Aufruf sind `trace!()`, `debug!()` usw. functional)
+    env_logger::init().unwrap();
+
+    let matches = App::new("XBS - xMZ-Mod-Touch Bus Scan")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Sucht Kombisensoren an einer Modbus Leitung der 'xMZ-Mod-Touch-Platform'")
+        .author("Stefan Müller (zzeroo) <s.mueller@it.kls-glt.de>")
+        .arg(Arg::with_name("environment")
+            .help("Umgebung, bestimmt den Typ der gefundenen Kombisensoren")
+            .long("environment")
+            .short("e")
+            .possible_values(&["production", "development"])
+            .takes_value(true)
+            .required(true)
+            .default_value("production"))
+        .arg(Arg::with_name("modbus_device")
+            .help("Schnittstelle der Modbus Leitung, z.B. /dev/ttyS1 oder tcp://192.168.1.20:502")
+            .long("modbus_device")
+            .short("m")
+            .takes_value(true)
+            .required(true)
+            .default_value("/dev/ttyS1"))
+        .arg(Arg::with_name("first_address")
+            .help("erste Modbus Adresse die abgefragt wird")
+            .long("first_address")
+            .short("f")
+            .takes_value(true)
+            .required(true)
+            .default_value("1"))
+        .arg(Arg::with_name("last_address")
+            .help("letzte Modbus Adresse die abgefragt wird")
+            .long("last_address")
+            .short("l")
+            .takes_value(true)
+            .required(true)
+            .default_value("247"))
+        .arg(Arg::with_name("response_timeout")
+            .help("Wartezeit auf die Antwort eines Teilnehmers, in Millisekunden")
+            .long("response_timeout")
+            .short("t")
+            .takes_value(true)
+            .required(true)
+            .default_value("500"))
+        .arg(Arg::with_name("config_file")
+            .help("Konfigurationsdatei, in die die gefundenen Kombisensoren übernommen werden")
+            .long("config_file")
+            .short("c")
+            .takes_value(true))
+        .arg(Arg::with_name("zone")
+            .help("Zone (0 ist die erste Zone), in die die gefundenen Kombisensoren übernommen werden")
+            .long("zone")
+            .short("z")
+            .takes_value(true)
+            .required(true)
+            .default_value("0"))
+        .arg(Arg::with_name("debug")
+            .help("soll der Modbus DEBUG Modus gesetzt werden")
+            .long("debug")
+            .short("d")
+            .possible_values(&["true", "false"])
+            .takes_value(true)
+            .required(true)
+            .default_value("false"))
+        .get_matches();
+
+
+    if let Err(ref e) = run(&matches) {
+        println!("error: {}", e);
+
+        if let Some(cause) = e.cause() {
+            println!("caused by: {}", cause);
+        }
+
+        ::std::process::exit(1);
+    }
+}
@@ -7,11 +7,13 @@ use std::error::Error;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
-use xmz_mod_touch_server::server::Server;
+use xmz_mod_touch_server::server::{Acquisition, Server, ServerState};
+use xmz_mod_touch_server::GltSlave;
 use xmz_mod_touch_server::json_api;
 use xmz_mod_touch_server::errors::*;
 
 pub const UPDATE_INTERVALL_MS: u64 = 100;
+pub const STATE_SNAPSHOT_INTERVALL_SEC: u64 = 60;
 
 
 /// `start_basic_configuration` - Aufruf der Basis Konfiguration des erver
@@ -28,9 +30,76 @@ fn start_basic_configuration(server: Arc<Mutex<Server>>) -> Result<()> {
     Ok(())
 }
 
+/// `restore_state` - Stellt die Mittelwerte aus der Zustandsdatei wieder her
+///
+/// Eine fehlende oder defekte Zustandsdatei ist kein Fehler, die Mittelwerte beginnen dann neu.
+fn restore_state(server: Arc<Mutex<Server>>) -> Result<()> {
+    match server.lock() {
+        Ok(mut server) => {
+            match server.restore_state() {
+                Ok(restored) => info!("{} Mittelwerte aus der Zustandsdatei übernommen", restored),
+                Err(e) => warn!("Zustandsdatei nicht gelesen: {}", e),
+            }
+            Ok(())
+        }
+        Err(_) => Err("Could not lock Server".into()),
+    }
+}
+
+/// `start_state_snapshots` - Speichert regelmäßig den Zustand des Servers
+///
+/// Der Zustand wird unter dem Lock des Servers erfasst, geschrieben wird erst nach dessen Freigabe.
+fn start_state_snapshots(server: Arc<Mutex<Server>>) -> Result<()> {
+    thread::spawn(move || {
+        loop {
+            thread::sleep(Duration::from_secs(STATE_SNAPSHOT_INTERVALL_SEC));
+
+            let snapshot = match server.lock() {
+                Ok(server) => server.get_state_file().map(|state_file| (state_file, ServerState::from_server(&server))),
+                Err(_) => None,
+            };
+            if let Some((state_file, state)) = snapshot {
+                if let Err(e) = state.save(&state_file) {
+                    warn!("Zustand konnte nicht nach {} gespeichert werden: {}", state_file, e);
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// `start_traffic_recording` - Startet die Aufzeichnung bzw. das Abspielen der Modbus Kommunikation, wenn konfiguriert
+///
+fn start_traffic_recording(server: Arc<Mutex<Server>>) -> Result<()> {
+    match server.lock() {
+        Ok(server) => server.start_traffic_recording(),
+        Err(_) => Err("Could not lock Server".into()),
+    }
+}
+
+/// `load_scenario` - Übergibt den Gasverlauf der Szenario Datei an die simulierten Messzellen
+///
+fn load_scenario(server: Arc<Mutex<Server>>) -> Result<()> {
+    match server.lock() {
+        Ok(mut server) => {
+            let loaded = server.load_scenario()?;
+            if loaded > 0 {
+                info!("{} Gasverläufe aus der Szenario Datei übernommen", loaded);
+            }
+            Ok(())
+        }
+        Err(_) => Err("Could not lock Server".into()),
+    }
+}
+
 /// `start_update`  - Starte die Update Thread des erver
 ///
+/// Die Kombisensoren werden von der `Acquisition` abgefragt, mit einem Thread pro Modbus Leitung. Der Update
+/// Thread wertet nur noch aus und startet die Worker neu konfigurierter Leitungen.
 fn start_update(server: Arc<Mutex<Server>>) -> Result<()> {
+    let acquisition = Acquisition::new(server.clone(), Duration::from_millis(UPDATE_INTERVALL_MS));
+
     thread::spawn(move || {
         loop {
             {
@@ -38,13 +107,18 @@ fn start_update(server: Arc<Mutex<Server>>) -> Result<()> {
                 if let Ok(mut server) = server.lock() {
                     // Ausnahmen prüfen
                     server.check();
-                    // erver Kombonenten aktualisieren, Kombisensoren auslesen, ....
-                    server.update();
+                    // erver Kombonenten auswerten, Zonen Status, Mittelwerte, ....
+                    server.evaluate();
                     // println!("{:#?}", &*server);
                 }
 
             } // server.lock() frei gegeben
 
+            // Kombisensoren auslesen, ein Worker pro Leitung
+            if let Err(e) = acquisition.start_workers() {
+                error!("Messwerterfassung konnte nicht gestartet werden: {}", e);
+            }
+
             thread::sleep(Duration::from_millis(UPDATE_INTERVALL_MS));
         }
     });
@@ -52,6 +126,25 @@ fn start_update(server: Arc<Mutex<Server>>) -> Result<()> {
     Ok(())
 }
 
+/// `start_glt_slave` - Startet den Modbus TCP Slave für die GLT, wenn er konfiguriert ist
+///
+fn start_glt_slave(server: Arc<Mutex<Server>>) -> Result<()> {
+    let glt_listen_address = match server.lock() {
+        Ok(server) => server.get_glt_listen_address(),
+        Err(_) => return Err("Could not lock Server".into()),
+    };
+
+    if let Some(glt_listen_address) = glt_listen_address {
+        thread::spawn(move || {
+            if let Err(e) = GltSlave::new(&glt_listen_address).run(server) {
+                error!("GLT Modbus TCP Slave beendet: {}", e);
+            }
+        });
+    }
+
+    Ok(())
+}
+
 /// `start_web_interface` - Startet das JSON Web API
 ///
 fn start_web_interface(server: Arc<Mutex<Server>>) -> Result<()> {
@@ -67,9 +160,24 @@ fn run() -> Result<()> {
 
     start_basic_configuration(server.clone())?;
 
+    // Aufzeichnung der Modbus Kommunikation, bzw. Abspielen einer Aufzeichnung
+    start_traffic_recording(server.clone())?;
+
+    // Gasverlauf der simulierten Messzellen
+    load_scenario(server.clone())?;
+
+    // Mittelwerte vom letzten Lauf übernehmen
+    restore_state(server.clone())?;
+
     // Update thread
     start_update(server.clone())?;
 
+    // Zustand regelmäßig speichern
+    start_state_snapshots(server.clone())?;
+
+    // Modbus TCP Slave für die GLT
+    start_glt_slave(server.clone())?;
+
     // IPC/ Web Interface
     start_web_interface(server.clone())?;
 
@@ -7,9 +7,15 @@ pub enum ExceptionType {
     WartungsintervalReached,
     KombisensorOffline { num_zone: usize },
     KombisensorModbusError { num_zone: usize, num_kombisensor: usize },
+    KombisensorFirmwareUnsupported { num_zone: usize, num_kombisensor: usize },
     SensorAP3DirectValue { num_zone: usize, num_sensor: usize },
     SensorAP2Average15min { num_zone: usize, num_sensor: usize },
     SensorAP1Average15min { num_zone: usize, num_sensor: usize },
+    SensorAverage15minInvalid { num_zone: usize, num_kombisensor: usize, num_sensor: usize },
+    SensorCalibration { num_zone: usize, num_kombisensor: usize, num_sensor: usize },
+    SensorReplaceSoon { num_zone: usize, num_kombisensor: usize, num_sensor: usize },
+    SensorUnderRange { num_zone: usize, num_kombisensor: usize, num_sensor: usize },
+    SensorOverRange { num_zone: usize, num_kombisensor: usize, num_sensor: usize },
 }
 
 /// Ausnahme (Fehler die auftreten können)
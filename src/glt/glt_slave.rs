@@ -0,0 +1,223 @@
+//! Modbus TCP Slave der Gebäudeleittechnik
+//!
+use errors::*;
+use libmodbus_rs::{Modbus, ModbusMapping, ModbusServer, ModbusTCP, MODBUS_TCP_MAX_ADU_LENGTH};
+use server::Server;
+use server::zone::ZoneStatus;
+use server::zone::kombisensor::KombisensorStatus;
+use server::zone::kombisensor::sensor::SensorStatus;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Sender};
+
+/// Version des Register Layouts, steht im Input Register 0
+pub const GLT_LAYOUT_VERSION: u16 = 1;
+// Register des Layout Headers
+const REGISTER_LAYOUT_VERSION: usize = 0;
+const REGISTER_NUM_ZONES: usize = 1;
+// erstes Register der Zonen Status
+const REGISTER_ZONE_STATUS: usize = 10;
+// erstes Register der Sensor Blöcke
+const REGISTER_SENSORS: usize = 100;
+const SENSOR_BLOCK_SIZE: usize = 4;
+const MAX_ZONES: usize = 10;
+const MAX_KOMBISENSORS: usize = 32;
+const MAX_SENSORS: usize = 4;
+/// Anzahl der Input Register des Slaves
+pub const GLT_NUM_INPUT_REGISTERS: usize = REGISTER_SENSORS + MAX_ZONES * MAX_KOMBISENSORS * MAX_SENSORS * SENSOR_BLOCK_SIZE;
+
+// Fehler Bits eines Sensor Blocks
+const FLAG_STOERUNG: u16 = 1 << 0;
+const FLAG_DEAKTIVIERT: u16 = 1 << 1;
+const FLAG_MESSBEREICH: u16 = 1 << 2;
+
+
+/// Modbus TCP Slave, der den Zustand des Servers an die GLT liefert
+///
+/// Siehe [Register Layout](index.html)
+#[derive(Debug)]
+pub struct GltSlave {
+    listen_address: String,
+}
+
+impl GltSlave {
+    /// Erstellt einen neuen GLT Slave
+    ///
+    /// # Parameters
+    ///
+    /// * `listen_address`  - IP Adresse und Port, z.B. `0.0.0.0:502`
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::GltSlave;
+    ///
+    /// let glt_slave = GltSlave::new("0.0.0.0:502");
+    /// assert_eq!(glt_slave.get_listen_address(), "0.0.0.0:502".to_string());
+    /// ```
+    pub fn new(listen_address: &str) -> Self {
+        GltSlave {
+            listen_address: listen_address.to_string(),
+        }
+    }
+
+    /// IP Adresse und Port auf denen der Slave Verbindungen annimmt
+    pub fn get_listen_address(&self) -> String {
+        self.listen_address.clone()
+    }
+
+    /// Berechnet die Input Register aus dem Zustand des Servers
+    ///
+    /// # Parameters
+    ///
+    /// * `server`  - Server Instanz
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{GltSlave, Kombisensor, KombisensorType, Server, GLT_NUM_INPUT_REGISTERS};
+    ///
+    /// let mut xmz_mod_touch_server = Server::new();
+    /// xmz_mod_touch_server.add_zone();
+    /// xmz_mod_touch_server.get_zone_mut(0).unwrap()
+    ///     .add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+    ///
+    /// let registers = GltSlave::input_registers(&xmz_mod_touch_server);
+    /// assert_eq!(registers.len(), GLT_NUM_INPUT_REGISTERS);
+    /// assert_eq!(registers[0], 1);    // Layout Version
+    /// assert_eq!(registers[1], 1);    // Anzahl Zonen
+    /// assert_eq!(registers[10], 0);   // Zone 0 Normal
+    /// ```
+    pub fn input_registers(server: &Server) -> Vec<u16> {
+        let mut registers = vec![0u16; GLT_NUM_INPUT_REGISTERS];
+
+        registers[REGISTER_LAYOUT_VERSION] = GLT_LAYOUT_VERSION;
+        registers[REGISTER_NUM_ZONES] = server.get_zones().len() as u16;
+
+        for (zone_id, zone) in server.get_zones().iter().enumerate().take(MAX_ZONES) {
+            registers[REGISTER_ZONE_STATUS + zone_id] = match zone.get_status() {
+                ZoneStatus::Normal => 0,
+                ZoneStatus::AP1 => 1,
+                ZoneStatus::AP2 => 2,
+                ZoneStatus::DIW => 3,
+            };
+
+            for (kombisensor_id, kombisensor) in zone.get_kombisensors().iter().enumerate().take(MAX_KOMBISENSORS) {
+                let stoerung = kombisensor.get_status() != KombisensorStatus::Normal || kombisensor.is_firmware_unsupported();
+
+                for (sensor_id, sensor) in kombisensor.get_sensors().iter().enumerate().take(MAX_SENSORS) {
+                    let block = (zone_id * MAX_KOMBISENSORS + kombisensor_id) * MAX_SENSORS + sensor_id;
+                    let address = REGISTER_SENSORS + block * SENSOR_BLOCK_SIZE;
+
+                    let mut flags = 0;
+                    if stoerung { flags |= FLAG_STOERUNG; }
+                    if !sensor.is_enabled() { flags |= FLAG_DEAKTIVIERT; }
+                    if sensor.get_status() != SensorStatus::Normal { flags |= FLAG_MESSBEREICH; }
+
+                    registers[address] = to_register(sensor.get_concentration());
+                    registers[address + 1] = to_register(sensor.get_concentration_average_15min());
+                    registers[address + 2] = flags;
+                }
+            }
+        }
+
+        registers
+    }
+
+    /// Startet den Slave
+    ///
+    /// Der Slave nimmt Verbindungen nacheinander an, es wird immer nur eine GLT zur gleichen Zeit bedient.
+    /// Vor jeder Antwort werden die Input Register aus dem aktuellen Zustand des Servers neu berechnet.
+    ///
+    /// Diese Funktion kehrt nur im Fehlerfall zurück und sollte deshalb in einem eigenen Thread laufen.
+    ///
+    /// # Parameters
+    ///
+    /// * `server`  - Server Instanz, wird für jede Anfrage kurz gesperrt
+    pub fn run(&self, server: Arc<Mutex<Server>>) -> Result<()> {
+        let (ready, _) = mpsc::channel();
+        self.run_with_ready_signal(server, ready)
+    }
+
+    /// Startet den Slave und meldet, sobald er Verbindungen annimmt
+    ///
+    /// Wie [`run()`](#method.run), zusätzlich wird über `ready` ein Signal gesendet, sobald der Socket geöffnet
+    /// ist. Schlägt das Öffnen fehl, wird der Sender ohne Signal verworfen.
+    ///
+    /// # Parameters
+    ///
+    /// * `server`  - Server Instanz, wird für jede Anfrage kurz gesperrt
+    /// * `ready`   - Sender für das Signal
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::sync::{Arc, Mutex};
+    /// use std::sync::mpsc;
+    /// use std::thread;
+    /// use xmz_mod_touch_server::{GltSlave, Server};
+    ///
+    /// let server = Arc::new(Mutex::new(Server::new()));
+    /// let (ready, ready_rx) = mpsc::channel();
+    /// thread::spawn(move || {
+    ///     GltSlave::new("127.0.0.1:1502").run_with_ready_signal(server, ready).unwrap();
+    /// });
+    /// ready_rx.recv().unwrap();
+    /// ```
+    pub fn run_with_ready_signal(&self, server: Arc<Mutex<Server>>, ready: Sender<()>) -> Result<()> {
+        let (ip, port) = match self.listen_address.rfind(':') {
+            Some(pos) => (&self.listen_address[..pos], &self.listen_address[pos + 1..]),
+            None => bail!("Invalid GLT listen address: {}", self.listen_address),
+        };
+        let port: u32 = match port.parse() {
+            Ok(port) => port,
+            Err(_) => bail!("Invalid GLT listen address: {}", self.listen_address),
+        };
+
+        let mut modbus = Modbus::new_tcp(ip, port)?;
+        let mut socket = modbus.tcp_listen(1)?;
+        let modbus_mapping = ModbusMapping::new(0, 0, 0, GLT_NUM_INPUT_REGISTERS as i32)?;
+        info!("GLT Modbus TCP Slave: {}", self.listen_address);
+        let _ = ready.send(());
+
+        loop {
+            modbus.tcp_accept(&mut socket)?;
+            info!("GLT verbunden");
+
+            let mut query = vec![0u8; MODBUS_TCP_MAX_ADU_LENGTH as usize];
+            loop {
+                let len = match modbus.receive(&mut query) {
+                    Ok(0) => continue, // Anfrage wird von libmodbus ignoriert
+                    Ok(len) => len,
+                    // Verbindung von der GLT geschlossen
+                    Err(_) => break,
+                };
+
+                if let Ok(server) = server.lock() {
+                    modbus_mapping.get_input_registers_mut().copy_from_slice(&GltSlave::input_registers(&server));
+                } // server.lock() frei gegeben
+
+                if let Err(e) = modbus.reply(&query, len, &modbus_mapping) {
+                    warn!("GLT Antwort fehlgeschlagen: {}", e);
+                    break;
+                }
+            }
+
+            info!("GLT getrennt");
+            modbus.close();
+        }
+    }
+}
+
+// Skaliert einen Messwert auf 1/10 der SI Einheit
+//
+fn to_register(value: f64) -> u16 {
+    let value = (value * 10.0).round();
+
+    if value <= 0.0 {
+        0
+    } else if value >= u16::max_value() as f64 {
+        u16::max_value()
+    } else {
+        value as u16
+    }
+}
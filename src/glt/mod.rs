@@ -0,0 +1,26 @@
+//! Modbus TCP Slave für die Gebäudeleittechnik (GLT)
+//!
+//! Die GLT kann den Zustand der Zonen und die Messwerte der Sensoren als Input Register (Funktion 0x04) abfragen.
+//! Alle Werte stammen aus der selben `Server` Instanz, die auch das JSON Web API ausliefert.
+//!
+//! # Register Layout (Version 1)
+//!
+//! |Input Register|Inhalt|
+//! |--------------|------|
+//! |0|Version des Register Layouts (1)|
+//! |1|Anzahl der Zonen|
+//! |10 + `zone`|Status der Zone: 0 = Normal, 1 = AP1, 2 = AP2, 3 = DIW|
+//! |100 + Sensor Block * 4 + 0|Konzentration, in 1/10 der SI Einheit des Sensors|
+//! |100 + Sensor Block * 4 + 1|15 Minuten Mittelwert, in 1/10 der SI Einheit des Sensors|
+//! |100 + Sensor Block * 4 + 2|Fehler Bits: Bit 0 = Störung (Kabelbruch oder nicht unterstützte Firmware), Bit 1 = Messzelle deaktiviert, Bit 2 = Messbereich unter- oder überschritten|
+//! |100 + Sensor Block * 4 + 3|reserviert|
+//!
+//! Der Sensor Block ergibt sich aus `(zone * 32 + kombisensor) * 4 + sensor`. Es werden also bis zu 10 Zonen,
+//! 32 Kombisensoren pro Zone und 4 Messzellen pro Kombisensor abgebildet. Die Register nicht vorhandener
+//! Sensoren sind 0.
+//!
+//! Der Slave wird über den Konfigurationsparameter `glt_listen_address`, z.B. `"0.0.0.0:502"`, aktiviert.
+//!
+mod glt_slave;
+
+pub use self::glt_slave::{GltSlave, GLT_LAYOUT_VERSION, GLT_NUM_INPUT_REGISTERS};
@@ -8,22 +8,36 @@
 //! |[http://localhost:3000/api/v1](http://localhost:3000/api/v1)|Index, komplette Server Datenstruktur wird als JSON Objekt zurück gegeben|
 //! |[http://localhost:3000/api/v1/zones](http://localhost:3000/api/v1/zones)|Alle Zonen abfragen|
 //! |[http://localhost:3000/api/v1/zone/0](http://localhost:3000/api/v1/zone/0)|Eine Zone abfragen, ZoneID als Parameter übergeben|
+//! |`PUT` [http://localhost:3000/api/v1/zone/0/reset_alarms](http://localhost:3000/api/v1/zone/0/reset_alarms)|Selbsthaltende Alarme der Zone quittieren|
 //! |[http://localhost:3000/api/v1/zone/0/kombisensors](http://localhost:3000/api/v1/zone/0/kombisensors)|Alle Sensoren des Kombisensors abfragen|
 //! |[http://localhost:3000/api/v1/zone/0/kombisensor/0](http://localhost:3000/api/v1/zone/0/kombisensor/0)||
-//! |[http://localhost:3000/api/v1/zone/0/kombisensor/0/sensors](http://localhost:3000/api/v1/zone/0/kombisensor/0/sensors)|Alle Sensoren des Kombisensors abfragen|
+//! |[http://localhost:3000/api/v1/zone/0/kombisensor/0/communication_statistics](http://localhost:3000/api/v1/zone/0/kombisensor/0/communication_statistics)|Kommunikationsstatistik des Kombisensors, mit Histogramm der Antwortzeiten|
+//! |`PUT` [http://localhost:3000/api/v1/zone/0/kombisensor/0/communication_statistics/reset](http://localhost:3000/api/v1/zone/0/kombisensor/0/communication_statistics/reset)|Kommunikationsstatistik des Kombisensors zurück setzen|
+//! |`PUT` [http://localhost:3000/api/v1/zone/0/kombisensor/0/modbus_address](http://localhost:3000/api/v1/zone/0/kombisensor/0/modbus_address)|Neue Modbus Adresse in den Kombisensor schreiben|
+//! |[http://localhost:3000/api/v1/zone/0/kombisensor/0/sensors](http://localhost:3000/api/v1/zone/0/kombisensor/0/sensors)|Alle aktiven Sensoren des Kombisensors abfragen, mit der SensorID als `sensor_id`|
 //! |[http://localhost:3000/api/v1/zone/0/kombisensor/0/sensor/0](http://localhost:3000/api/v1/zone/0/kombisensor/0/sensor/0)|Ein Sensor abfragen, SensorID als weiteren Parameter mit übergeben|
+//! |`PUT` [http://localhost:3000/api/v1/zone/0/kombisensor/0/sensor/0/calibration](http://localhost:3000/api/v1/zone/0/kombisensor/0/sensor/0/calibration)|Kalibrierwerte eines Sensors setzen und in den Kombisensor schreiben|
+//! |`PUT` [http://localhost:3000/api/v1/zone/0/kombisensor/0/sensor/0/enabled](http://localhost:3000/api/v1/zone/0/kombisensor/0/sensor/0/enabled)|Sensor Messzelle im Kombisensor aktivieren oder deaktivieren|
+//! |[http://localhost:3000/api/v1/zone/0/kombisensor/0/sensor/0/health](http://localhost:3000/api/v1/zone/0/kombisensor/0/sensor/0/health)|Zustand der Messzelle: Verlauf von Kalibrierung und Nullpunkt, Empfindlichkeit und Drift|
+//! |`PUT` [http://localhost:3000/api/v1/zone/0/kombisensor/0/sensor/0/calibration/start](http://localhost:3000/api/v1/zone/0/kombisensor/0/sensor/0/calibration/start)|Geführte Kalibrierung der Messzelle mit dem Nullgas beginnen, sperrt die Alarmausgänge der Zone|
+//! |[http://localhost:3000/api/v1/calibration](http://localhost:3000/api/v1/calibration)|Laufende bzw. zuletzt beendete Kalibrierung abfragen|
+//! |`PUT` [http://localhost:3000/api/v1/calibration/messgas](http://localhost:3000/api/v1/calibration/messgas)|Messgas Schritt der Kalibrierung mit der angegebenen Konzentration beginnen|
+//! |`PUT` [http://localhost:3000/api/v1/calibration/commit](http://localhost:3000/api/v1/calibration/commit)|Kalibrierwerte übernehmen und in den Kombisensor schreiben|
+//! |`PUT` [http://localhost:3000/api/v1/calibration/abort](http://localhost:3000/api/v1/calibration/abort)|Kalibrierung abbrechen|
 //! |[http://localhost:3000/api/v1/exceptions](http://localhost:3000/api/v1/exceptions)|Ausnahmen des Servers erfragen|
 //!
 
 // TODO: https://disconnected.systems/blog/rover-rest-api/
 use errors::*;
 use iron::prelude::*;
+use modbus_bus::{CommunicationStatistics, LatencyBucket};
 use iron::status;
 use router::Router;
 use serde_json;
-use Server;
+use {Kombisensor, ModbusBusManager, Server};
 use std::error::Error;
 use std::fmt;
+use std::io::Read;
 use std::sync::{Arc, Mutex};
 
 /// Json Web Interface Fehler
@@ -93,6 +107,63 @@ fn zone_get(req: &mut Request,
     }
 }
 
+/// Beispiel Aufruf: `curl -X PUT http://0.0.0.0:3000/api/v1/zone/0/reset_alarms`
+///
+/// Quittiert die selbsthaltenden Alarme der Zone.
+fn zone_reset_alarms_put(req: &mut Request,
+                         xmz_mod_touch_server: Arc<Mutex<Server>>)
+                         -> IronResult<Response> {
+    if let Ok(mut xmz_mod_touch_server) = xmz_mod_touch_server.lock() {
+        // Extract the parameter(s)
+        let zone_id = req.extensions
+            .get::<Router>()
+            .unwrap()
+            .find("zone_id")
+            .unwrap_or("0")
+            .parse::<usize>()
+            .unwrap_or(0);
+
+        match xmz_mod_touch_server.get_zone_mut(zone_id) {
+            Some(zone) => {
+                zone.reset_alarms();
+                Ok(Response::with(status::Ok))
+            }
+            None => Err(IronError::new(StringError("Zone not found"),
+                                       status::NotFound)),
+        }
+    } else {
+        Err(IronError::new(StringError("Mutex Server lock failed"),
+                           status::BadRequest))
+    }
+}
+
+// Aktive Messzellen eines Kombisensors, wie sie die GUI bekommt
+//
+// Deaktivierte Messzellen fallen heraus. Damit die GUI eine Messzelle trotzdem adressieren kann, bekommt jede
+// Messzelle das Feld `sensor_id` mit ihrer Nummer im Kombisensor.
+fn enabled_sensors(kombisensor: &Kombisensor) -> Vec<serde_json::Value> {
+    kombisensor.get_sensors().iter().enumerate()
+        .filter(|&(_, sensor)| sensor.is_enabled())
+        .map(|(sensor_id, sensor)| {
+            let mut value = serde_json::to_value(sensor).unwrap();
+            if let serde_json::Value::Object(ref mut map) = value {
+                map.insert("sensor_id".to_string(), serde_json::Value::from(sensor_id));
+            }
+            value
+        })
+        .collect()
+}
+
+// Kombisensor, wie ihn die GUI bekommt, mit den aktiven Messzellen aus `enabled_sensors()`
+//
+fn kombisensor_summary(kombisensor: &Kombisensor) -> serde_json::Value {
+    let mut value = serde_json::to_value(kombisensor).unwrap();
+    if let serde_json::Value::Object(ref mut map) = value {
+        map.insert("sensors".to_string(), serde_json::Value::Array(enabled_sensors(kombisensor)));
+    }
+    value
+}
+
 /// Beispiel URL: http://0.0.0.0:3000/api/v1/zone/0/kombisensors
 fn kombisensors_index(req: &mut Request,
                       xmz_mod_touch_server: Arc<Mutex<Server>>)
@@ -107,9 +178,9 @@ fn kombisensors_index(req: &mut Request,
             .parse::<usize>()
             .unwrap_or(0);
 
-        // Get Kombisensors
+        // Get Kombisensors, ohne deaktivierte Messzellen
         let kombisensors = &xmz_mod_touch_server.get_zone(zone_id)
-            .map(|zone| zone.get_kombisensors());
+            .map(|zone| zone.get_kombisensors().iter().map(kombisensor_summary).collect::<Vec<_>>());
 
         let payload = serde_json::to_string_pretty(kombisensors).unwrap();
         Ok(Response::with((status::Ok, payload)))
@@ -140,9 +211,9 @@ fn kombisensor_get(req: &mut Request,
             .parse::<usize>()
             .unwrap_or(0);
 
-        // Get Kombisensor
+        // Get Kombisensor, ohne deaktivierte Messzellen
         let kombisensor = &xmz_mod_touch_server.get_zone(zone_id)
-            .map(|zone| zone.get_kombisensor(kombisensor_id));
+            .map(|zone| zone.get_kombisensor(kombisensor_id).map(kombisensor_summary));
 
         let payload = serde_json::to_string_pretty(kombisensor).unwrap();
         Ok(Response::with((status::Ok, payload)))
@@ -152,7 +223,93 @@ fn kombisensor_get(req: &mut Request,
     }
 }
 
+/// Kommunikationsstatistik eines Kombisensors, zusammen mit dem Histogramm der Antwortzeiten
+#[derive(Debug)]
+#[derive(Serialize)]
+struct CommunicationReport<'a> {
+    communication_statistics: &'a CommunicationStatistics,
+    latency_histogram: Vec<LatencyBucket>,
+}
+
+/// Beispiel URL: http://0.0.0.0:3000/api/v1/zone/0/kombisensor/0/communication_statistics
+fn communication_statistics_get(req: &mut Request,
+                   xmz_mod_touch_server: Arc<Mutex<Server>>)
+                   -> IronResult<Response> {
+    if let Ok(xmz_mod_touch_server) = xmz_mod_touch_server.lock() {
+        // Extract the parameter(s)
+        let zone_id = req.extensions
+            .get::<Router>()
+            .unwrap()
+            .find("zone_id")
+            .unwrap_or("0")
+            .parse::<usize>()
+            .unwrap_or(0);
+        let kombisensor_id = req.extensions
+            .get::<Router>()
+            .unwrap()
+            .find("kombisensor_id")
+            .unwrap_or("0")
+            .parse::<usize>()
+            .unwrap_or(0);
+
+        match xmz_mod_touch_server.get_zone(zone_id).and_then(|zone| zone.get_kombisensor(kombisensor_id)) {
+            Some(kombisensor) => {
+                let communication_statistics = kombisensor.get_communication_statistics();
+                let report = CommunicationReport {
+                    communication_statistics: communication_statistics,
+                    latency_histogram: communication_statistics.get_latency_histogram(),
+                };
+                let payload = serde_json::to_string_pretty(&report).unwrap();
+                Ok(Response::with((status::Ok, payload)))
+            }
+            None => Err(IronError::new(StringError("Kombisensor not found"),
+                                       status::NotFound)),
+        }
+    } else {
+        Err(IronError::new(StringError("Mutex Server lock failed"),
+                           status::BadRequest))
+    }
+}
+
+/// Beispiel Aufruf: `curl -X PUT http://0.0.0.0:3000/api/v1/zone/0/kombisensor/0/communication_statistics/reset`
+fn communication_statistics_reset(req: &mut Request,
+                   xmz_mod_touch_server: Arc<Mutex<Server>>)
+                   -> IronResult<Response> {
+    if let Ok(mut xmz_mod_touch_server) = xmz_mod_touch_server.lock() {
+        // Extract the parameter(s)
+        let zone_id = req.extensions
+            .get::<Router>()
+            .unwrap()
+            .find("zone_id")
+            .unwrap_or("0")
+            .parse::<usize>()
+            .unwrap_or(0);
+        let kombisensor_id = req.extensions
+            .get::<Router>()
+            .unwrap()
+            .find("kombisensor_id")
+            .unwrap_or("0")
+            .parse::<usize>()
+            .unwrap_or(0);
+
+        match xmz_mod_touch_server.get_zone_mut(zone_id).and_then(|zone| zone.get_kombisensor_mut(kombisensor_id)) {
+            Some(kombisensor) => {
+                kombisensor.reset_communication_statistics();
+                Ok(Response::with(status::Ok))
+            }
+            None => Err(IronError::new(StringError("Kombisensor not found"),
+                                       status::NotFound)),
+        }
+    } else {
+        Err(IronError::new(StringError("Mutex Server lock failed"),
+                           status::BadRequest))
+    }
+}
+
 /// Beispiel URL: http://0.0.0.0:3000/api/v1/zone/0/kombisensor/0/sensors
+///
+/// Deaktivierte Messzellen werden nicht ausgeliefert. Jede Messzelle bekommt zusätzlich das Feld `sensor_id`,
+/// die SensorID der übrigen URLs.
 fn sensors_index(req: &mut Request,
                  xmz_mod_touch_server: Arc<Mutex<Server>>)
                  -> IronResult<Response> {
@@ -173,9 +330,9 @@ fn sensors_index(req: &mut Request,
             .parse::<usize>()
             .unwrap_or(0);
 
-        // Get Sensors
+        // Get Sensors, ohne deaktivierte Messzellen
         let sensors = &xmz_mod_touch_server.get_zone(zone_id).map(|zone| {
-            zone.get_kombisensor(kombisensor_id).map(|kombisensor| kombisensor.get_sensors())
+            zone.get_kombisensor(kombisensor_id).map(enabled_sensors)
         });
 
         let payload = serde_json::to_string_pretty(sensors).unwrap();
@@ -228,6 +385,53 @@ fn sensor_get(req: &mut Request,
     }
 }
 
+/// Beispiel URL: http://0.0.0.0:3000/api/v1/zone/0/kombisensor/0/sensor/0/health
+///
+/// Liefert den Zustand der Messzelle, mit Empfindlichkeit, Drift des Nullpunkts und dem gespeicherten Verlauf.
+fn sensor_health_get(req: &mut Request,
+              xmz_mod_touch_server: Arc<Mutex<Server>>)
+              -> IronResult<Response> {
+    if let Ok(xmz_mod_touch_server) = xmz_mod_touch_server.lock() {
+        // Extract the parameter(s)
+        let zone_id = req.extensions
+            .get::<Router>()
+            .unwrap()
+            .find("zone_id")
+            .unwrap_or("0")
+            .parse::<usize>()
+            .unwrap_or(0);
+        let kombisensor_id = req.extensions
+            .get::<Router>()
+            .unwrap()
+            .find("kombisensor_id")
+            .unwrap_or("0")
+            .parse::<usize>()
+            .unwrap_or(0);
+        let sensor_id = req.extensions
+            .get::<Router>()
+            .unwrap()
+            .find("sensor_id")
+            .unwrap_or("0")
+            .parse::<usize>()
+            .unwrap_or(0);
+
+        let replace_sensitivity_percent = xmz_mod_touch_server.get_sensor_replace_sensitivity_percent();
+        let report = match xmz_mod_touch_server.get_zone(zone_id)
+            .and_then(|zone| zone.get_kombisensor(kombisensor_id))
+            .and_then(|kombisensor| kombisensor.get_sensor(sensor_id)) {
+            Some(sensor) => sensor.get_health().report(replace_sensitivity_percent),
+            None => return Err(IronError::new(StringError("Sensor not found"),
+                                              status::NotFound)),
+        };
+
+        let payload = serde_json::to_string_pretty(&report).unwrap();
+        Ok(Response::with((status::Ok, payload)))
+    } else {
+        Err(IronError::new(StringError("Mutex Server lock failed"),
+                           status::BadRequest))
+    }
+}
+
 /// Beispiel Aufruf: `curl -X PUT http://0.0.0.0:3000/api/v1/zone/0/kombisensor/0/sensor/0/reset_error_count`
 fn reset_error_count(req: &mut Request,
               xmz_mod_touch_server: Arc<Mutex<Server>>)
@@ -276,6 +480,390 @@ fn reset_error_count(req: &mut Request,
     }
 }
 
+/// Kalibrierwerte einer Sensor Messzelle
+///
+/// Nicht angegebene Werte bleiben unverändert. Die Konfiguration (`config`) gehört nicht dazu, die Messzelle wird
+/// nur über `PUT .../enabled` aktiviert bzw. deaktiviert.
+#[derive(Debug)]
+#[derive(Deserialize)]
+struct SensorCalibration {
+    adc_value_at_nullgas: Option<u16>,
+    adc_value_at_messgas: Option<u16>,
+    concentration_at_nullgas: Option<u16>,
+    concentration_at_messgas: Option<u16>,
+}
+
+/// Beispiel Aufruf: `curl -X PUT -d '{"adc_value_at_messgas": 920}' http://0.0.0.0:3000/api/v1/zone/0/kombisensor/0/sensor/0/calibration`
+///
+/// Die Werte werden gesetzt, in den Kombisensor geschrieben und zur Kontrolle zurück gelesen. Während der Modbus
+/// Kommunikation ist der Server nicht gesperrt, die Werte werden erst danach übernommen.
+fn sensor_calibration_put(req: &mut Request,
+              xmz_mod_touch_server: Arc<Mutex<Server>>)
+              -> IronResult<Response> {
+    let mut body = String::new();
+    if req.body.read_to_string(&mut body).is_err() {
+        return Err(IronError::new(StringError("Could not read request body"),
+                                  status::BadRequest));
+    }
+    let calibration: SensorCalibration = match serde_json::from_str(&body) {
+        Ok(calibration) => calibration,
+        Err(e) => return Err(IronError::new(e, status::BadRequest)),
+    };
+
+    // Extract the parameter(s)
+    let zone_id = req.extensions
+        .get::<Router>()
+        .unwrap()
+        .find("zone_id")
+        .unwrap_or("0")
+        .parse::<usize>()
+        .unwrap_or(0);
+    let kombisensor_id = req.extensions
+        .get::<Router>()
+        .unwrap()
+        .find("kombisensor_id")
+        .unwrap_or("0")
+        .parse::<usize>()
+        .unwrap_or(0);
+    let sensor_id = req.extensions
+        .get::<Router>()
+        .unwrap()
+        .find("sensor_id")
+        .unwrap_or("0")
+        .parse::<usize>()
+        .unwrap_or(0);
+
+    let (mut kombisensor, bus_manager) = copy_kombisensor(&xmz_mod_touch_server, zone_id, kombisensor_id)?;
+
+    // Set Sensor values
+    match kombisensor.get_sensor_mut(sensor_id) {
+        Some(sensor) => {
+            if let Some(value) = calibration.adc_value_at_nullgas { sensor.set_adc_value_at_nullgas(value) }
+            if let Some(value) = calibration.adc_value_at_messgas { sensor.set_adc_value_at_messgas(value) }
+            if let Some(value) = calibration.concentration_at_nullgas { sensor.set_concentration_at_nullgas(value) }
+            if let Some(value) = calibration.concentration_at_messgas { sensor.set_concentration_at_messgas(value) }
+        }
+        None => return Err(IronError::new(StringError("Sensor not found"),
+                                          status::NotFound)),
+    }
+
+    if let Err(e) = kombisensor.write_sensor_calibration(&bus_manager, sensor_id) {
+        return Err(IronError::new(e, status::InternalServerError));
+    }
+
+    if let Ok(mut xmz_mod_touch_server) = xmz_mod_touch_server.lock() {
+        match xmz_mod_touch_server.apply_sensor_write(zone_id, kombisensor_id, &kombisensor, sensor_id) {
+            Ok(_) => Ok(Response::with(status::Ok)),
+            Err(e) => Err(IronError::new(e, status::Conflict)),
+        }
+    } else {
+        Err(IronError::new(StringError("Mutex Server lock failed"),
+                           status::BadRequest))
+    }
+}
+
+// Kopie des Kombisensors und die Verwaltung der Modbus Leitungen, damit ein Schreibzugriff den Server nicht sperrt
+//
+fn copy_kombisensor(xmz_mod_touch_server: &Arc<Mutex<Server>>,
+                    zone_id: usize,
+                    kombisensor_id: usize)
+                    -> IronResult<(Kombisensor, Arc<ModbusBusManager>)> {
+    if let Ok(xmz_mod_touch_server) = xmz_mod_touch_server.lock() {
+        match xmz_mod_touch_server.copy_kombisensor(zone_id, kombisensor_id) {
+            Ok(kombisensor) => Ok((kombisensor, xmz_mod_touch_server.get_bus_manager())),
+            Err(_) => Err(IronError::new(StringError("Kombisensor not found"),
+                                         status::NotFound)),
+        }
+    } else {
+        Err(IronError::new(StringError("Mutex Server lock failed"),
+                           status::BadRequest))
+    }
+}
+
+/// Aktivierung einer Sensor Messzelle
+#[derive(Debug)]
+#[derive(Deserialize)]
+struct SensorEnabled {
+    enabled: bool,
+}
+
+/// Beispiel Aufruf: `curl -X PUT -d '{"enabled": false}' http://0.0.0.0:3000/api/v1/zone/0/kombisensor/0/sensor/1/enabled`
+///
+/// Das `enabled` Bit wird in der Firmware gesetzt bzw. gelöscht und zur Kontrolle zurück gelesen. Während der
+/// Modbus Kommunikation ist der Server nicht gesperrt.
+fn sensor_enabled_put(req: &mut Request,
+              xmz_mod_touch_server: Arc<Mutex<Server>>)
+              -> IronResult<Response> {
+    let mut body = String::new();
+    if req.body.read_to_string(&mut body).is_err() {
+        return Err(IronError::new(StringError("Could not read request body"),
+                                  status::BadRequest));
+    }
+    let sensor_enabled: SensorEnabled = match serde_json::from_str(&body) {
+        Ok(sensor_enabled) => sensor_enabled,
+        Err(e) => return Err(IronError::new(e, status::BadRequest)),
+    };
+
+    // Extract the parameter(s)
+    let zone_id = req.extensions
+        .get::<Router>()
+        .unwrap()
+        .find("zone_id")
+        .unwrap_or("0")
+        .parse::<usize>()
+        .unwrap_or(0);
+    let kombisensor_id = req.extensions
+        .get::<Router>()
+        .unwrap()
+        .find("kombisensor_id")
+        .unwrap_or("0")
+        .parse::<usize>()
+        .unwrap_or(0);
+    let sensor_id = req.extensions
+        .get::<Router>()
+        .unwrap()
+        .find("sensor_id")
+        .unwrap_or("0")
+        .parse::<usize>()
+        .unwrap_or(0);
+
+    let (mut kombisensor, bus_manager) = copy_kombisensor(&xmz_mod_touch_server, zone_id, kombisensor_id)?;
+    if kombisensor.get_sensor(sensor_id).is_none() {
+        return Err(IronError::new(StringError("Sensor not found"),
+                                  status::NotFound));
+    }
+
+    if let Err(e) = kombisensor.write_sensor_enabled(&bus_manager, sensor_id, sensor_enabled.enabled) {
+        return Err(IronError::new(e, status::InternalServerError));
+    }
+
+    if let Ok(mut xmz_mod_touch_server) = xmz_mod_touch_server.lock() {
+        match xmz_mod_touch_server.apply_sensor_write(zone_id, kombisensor_id, &kombisensor, sensor_id) {
+            Ok(_) => Ok(Response::with(status::Ok)),
+            Err(e) => Err(IronError::new(e, status::Conflict)),
+        }
+    } else {
+        Err(IronError::new(StringError("Mutex Server lock failed"),
+                           status::BadRequest))
+    }
+}
+
+/// Neue Modbus Adresse eines Kombisensors
+#[derive(Debug)]
+#[derive(Deserialize)]
+struct ModbusAddress {
+    modbus_address: u8,
+}
+
+/// Beispiel Aufruf: `curl -X PUT -d '{"modbus_address": 12}' http://0.0.0.0:3000/api/v1/zone/0/kombisensor/0/modbus_address`
+///
+/// Die Adresse wird in den Kombisensor geschrieben, unter der neuen Adresse kontrolliert und dann in die
+/// Konfiguration übernommen. Während der Modbus Kommunikation ist der Server nicht gesperrt.
+fn kombisensor_modbus_address_put(req: &mut Request,
+              xmz_mod_touch_server: Arc<Mutex<Server>>)
+              -> IronResult<Response> {
+    let mut body = String::new();
+    if req.body.read_to_string(&mut body).is_err() {
+        return Err(IronError::new(StringError("Could not read request body"),
+                                  status::BadRequest));
+    }
+    let modbus_address: ModbusAddress = match serde_json::from_str(&body) {
+        Ok(modbus_address) => modbus_address,
+        Err(e) => return Err(IronError::new(e, status::BadRequest)),
+    };
+
+    // Extract the parameter(s)
+    let zone_id = req.extensions
+        .get::<Router>()
+        .unwrap()
+        .find("zone_id")
+        .unwrap_or("0")
+        .parse::<usize>()
+        .unwrap_or(0);
+    let kombisensor_id = req.extensions
+        .get::<Router>()
+        .unwrap()
+        .find("kombisensor_id")
+        .unwrap_or("0")
+        .parse::<usize>()
+        .unwrap_or(0);
+
+    let (mut kombisensor, bus_manager) = if let Ok(xmz_mod_touch_server) = xmz_mod_touch_server.lock() {
+        if xmz_mod_touch_server.get_zone(zone_id).and_then(|zone| zone.get_kombisensor(kombisensor_id)).is_none() {
+            return Err(IronError::new(StringError("Kombisensor not found"),
+                                      status::NotFound));
+        }
+        match xmz_mod_touch_server.prepare_modbus_address_write(zone_id, kombisensor_id, modbus_address.modbus_address) {
+            Ok(kombisensor) => (kombisensor, xmz_mod_touch_server.get_bus_manager()),
+            Err(e) => return Err(IronError::new(e, status::InternalServerError)),
+        }
+    } else {
+        return Err(IronError::new(StringError("Mutex Server lock failed"),
+                                  status::BadRequest));
+    };
+    let previous_modbus_address = kombisensor.get_modbus_address();
+
+    if let Err(e) = kombisensor.write_modbus_address(&bus_manager, modbus_address.modbus_address) {
+        return Err(IronError::new(e, status::InternalServerError));
+    }
+
+    if let Ok(mut xmz_mod_touch_server) = xmz_mod_touch_server.lock() {
+        match xmz_mod_touch_server.apply_modbus_address_write(zone_id, kombisensor_id, previous_modbus_address, &kombisensor) {
+            Ok(_) => Ok(Response::with(status::Ok)),
+            Err(e) => Err(IronError::new(e, status::Conflict)),
+        }
+    } else {
+        Err(IronError::new(StringError("Mutex Server lock failed"),
+                           status::BadRequest))
+    }
+}
+
+/// Beispiel URL: http://0.0.0.0:3000/api/v1/calibration
+///
+/// Liefert die laufende bzw. zuletzt beendete Kalibrierung, `null` wenn es noch keine gab.
+fn calibration_get(_req: &mut Request,
+                   xmz_mod_touch_server: Arc<Mutex<Server>>)
+                   -> IronResult<Response> {
+    if let Ok(xmz_mod_touch_server) = xmz_mod_touch_server.lock() {
+        let payload = serde_json::to_string_pretty(&xmz_mod_touch_server.get_calibration_session())
+            .unwrap();
+        Ok(Response::with((status::Ok, payload)))
+    } else {
+        Err(IronError::new(StringError("Mutex Server lock failed"),
+                           status::BadRequest))
+    }
+}
+
+/// Beispiel Aufruf: `curl -X PUT http://0.0.0.0:3000/api/v1/zone/0/kombisensor/0/sensor/0/calibration/start`
+///
+/// Beginnt die Kalibrierung der Messzelle mit dem Nullgas. Die Alarmausgänge der Zone sind bis zum Ende der
+/// Kalibrierung gesperrt.
+fn calibration_start_put(req: &mut Request,
+              xmz_mod_touch_server: Arc<Mutex<Server>>)
+              -> IronResult<Response> {
+    if let Ok(mut xmz_mod_touch_server) = xmz_mod_touch_server.lock() {
+        // Extract the parameter(s)
+        let zone_id = req.extensions
+            .get::<Router>()
+            .unwrap()
+            .find("zone_id")
+            .unwrap_or("0")
+            .parse::<usize>()
+            .unwrap_or(0);
+        let kombisensor_id = req.extensions
+            .get::<Router>()
+            .unwrap()
+            .find("kombisensor_id")
+            .unwrap_or("0")
+            .parse::<usize>()
+            .unwrap_or(0);
+        let sensor_id = req.extensions
+            .get::<Router>()
+            .unwrap()
+            .find("sensor_id")
+            .unwrap_or("0")
+            .parse::<usize>()
+            .unwrap_or(0);
+
+        if xmz_mod_touch_server.get_zone(zone_id)
+            .and_then(|zone| zone.get_kombisensor(kombisensor_id))
+            .and_then(|kombisensor| kombisensor.get_sensor(sensor_id)).is_none() {
+            return Err(IronError::new(StringError("Sensor not found"),
+                                      status::NotFound));
+        }
+
+        match xmz_mod_touch_server.start_calibration(zone_id, kombisensor_id, sensor_id) {
+            Ok(_) => Ok(Response::with(status::Ok)),
+            Err(e) => Err(IronError::new(e, status::BadRequest)),
+        }
+    } else {
+        Err(IronError::new(StringError("Mutex Server lock failed"),
+                           status::BadRequest))
+    }
+}
+
+/// Konzentration des aufgegebenen Messgases
+#[derive(Debug)]
+#[derive(Deserialize)]
+struct CalibrationMessgas {
+    concentration: u16,
+}
+
+/// Beispiel Aufruf: `curl -X PUT -d '{"concentration": 20}' http://0.0.0.0:3000/api/v1/calibration/messgas`
+///
+/// Nachdem das Nullgas erfasst wurde, beginnt der Messgas Schritt mit der angegebenen Konzentration.
+fn calibration_messgas_put(req: &mut Request,
+              xmz_mod_touch_server: Arc<Mutex<Server>>)
+              -> IronResult<Response> {
+    let mut body = String::new();
+    if req.body.read_to_string(&mut body).is_err() {
+        return Err(IronError::new(StringError("Could not read request body"),
+                                  status::BadRequest));
+    }
+    let messgas: CalibrationMessgas = match serde_json::from_str(&body) {
+        Ok(messgas) => messgas,
+        Err(e) => return Err(IronError::new(e, status::BadRequest)),
+    };
+
+    if let Ok(mut xmz_mod_touch_server) = xmz_mod_touch_server.lock() {
+        match xmz_mod_touch_server.start_calibration_messgas(messgas.concentration) {
+            Ok(_) => Ok(Response::with(status::Ok)),
+            Err(e) => Err(IronError::new(e, status::BadRequest)),
+        }
+    } else {
+        Err(IronError::new(StringError("Mutex Server lock failed"),
+                           status::BadRequest))
+    }
+}
+
+/// Beispiel Aufruf: `curl -X PUT http://0.0.0.0:3000/api/v1/calibration/commit`
+///
+/// Die erfassten Werte werden in den Kombisensor geschrieben, zur Kontrolle zurück gelesen und dann in die
+/// Messzelle übernommen. Während der Modbus Kommunikation ist der Server nicht gesperrt.
+fn calibration_commit_put(_req: &mut Request,
+              xmz_mod_touch_server: Arc<Mutex<Server>>)
+              -> IronResult<Response> {
+    let (kombisensor, sensor_id, bus_manager) = if let Ok(xmz_mod_touch_server) = xmz_mod_touch_server.lock() {
+        let sensor_id = xmz_mod_touch_server.get_calibration_session().map_or(0, |session| session.get_sensor_id());
+        match xmz_mod_touch_server.prepare_calibration_commit() {
+            Ok(kombisensor) => (kombisensor, sensor_id, xmz_mod_touch_server.get_bus_manager()),
+            Err(e) => return Err(IronError::new(e, status::BadRequest)),
+        }
+    } else {
+        return Err(IronError::new(StringError("Mutex Server lock failed"),
+                                  status::BadRequest));
+    };
+
+    if let Err(e) = kombisensor.write_sensor_calibration(&bus_manager, sensor_id) {
+        return Err(IronError::new(e, status::InternalServerError));
+    }
+
+    if let Ok(mut xmz_mod_touch_server) = xmz_mod_touch_server.lock() {
+        match xmz_mod_touch_server.apply_calibration_commit(&kombisensor) {
+            Ok(_) => Ok(Response::with(status::Ok)),
+            Err(e) => Err(IronError::new(e, status::Conflict)),
+        }
+    } else {
+        Err(IronError::new(StringError("Mutex Server lock failed"),
+                           status::BadRequest))
+    }
+}
+
+/// Beispiel Aufruf: `curl -X PUT http://0.0.0.0:3000/api/v1/calibration/abort`
+fn calibration_abort_put(_req: &mut Request,
+              xmz_mod_touch_server: Arc<Mutex<Server>>)
+              -> IronResult<Response> {
+    if let Ok(mut xmz_mod_touch_server) = xmz_mod_touch_server.lock() {
+        match xmz_mod_touch_server.abort_calibration() {
+            Ok(_) => Ok(Response::with(status::Ok)),
+            Err(e) => Err(IronError::new(e, status::BadRequest)),
+        }
+    } else {
+        Err(IronError::new(StringError("Mutex Server lock failed"),
+                           status::BadRequest))
+    }
+}
+
 /// Beispiel URL: http://0.0.0.0:3000/api/v1/exceptions
 fn exceptions_index(_req: &mut Request,
                     xmz_mod_touch_server: Arc<Mutex<Server>>)
@@ -336,6 +924,12 @@ pub fn init(xmz_mod_touch_server: Arc<Mutex<Server>>)
                move |req: &mut Request| zone_get(req, xmz_mod_touch_server_clone.clone()),
                "zone_get");
 
+    /// `curl -X PUT http://0.0.0.0:3000/api/v1/zone/0/reset_alarms`
+    let xmz_mod_touch_server_clone = xmz_mod_touch_server.clone();
+    router.put("/api/v1/zone/:zone_id/reset_alarms",
+            move |req: &mut Request| zone_reset_alarms_put(req, xmz_mod_touch_server_clone.clone()),
+            "zone_reset_alarms_put");
+
     /// `curl http://0.0.0.0:3000/api/v1/zone/0/kombisensors`
     let xmz_mod_touch_server_clone = xmz_mod_touch_server.clone();
     router.get("/api/v1/zone/:zone_id/kombisensors",
@@ -350,6 +944,24 @@ pub fn init(xmz_mod_touch_server: Arc<Mutex<Server>>)
                move |req: &mut Request| kombisensor_get(req, xmz_mod_touch_server_clone.clone()),
                "kombisensor_get");
 
+    /// `curl http://0.0.0.0:3000/api/v1/zone/0/kombisensor/0/communication_statistics`
+    let xmz_mod_touch_server_clone = xmz_mod_touch_server.clone();
+    router.get("/api/v1/zone/:zone_id/kombisensor/:kombisensor_id/communication_statistics",
+               move |req: &mut Request| communication_statistics_get(req, xmz_mod_touch_server_clone.clone()),
+               "communication_statistics_get");
+
+    /// `curl -X PUT http://0.0.0.0:3000/api/v1/zone/0/kombisensor/0/communication_statistics/reset`
+    let xmz_mod_touch_server_clone = xmz_mod_touch_server.clone();
+    router.put("/api/v1/zone/:zone_id/kombisensor/:kombisensor_id/communication_statistics/reset",
+            move |req: &mut Request| communication_statistics_reset(req, xmz_mod_touch_server_clone.clone()),
+            "communication_statistics_reset");
+
+    /// `curl -X PUT -d '{"modbus_address": 12}' http://0.0.0.0:3000/api/v1/zone/0/kombisensor/0/modbus_address`
+    let xmz_mod_touch_server_clone = xmz_mod_touch_server.clone();
+    router.put("/api/v1/zone/:zone_id/kombisensor/:kombisensor_id/modbus_address",
+            move |req: &mut Request| kombisensor_modbus_address_put(req, xmz_mod_touch_server_clone.clone()),
+            "kombisensor_modbus_address_put");
+
     /// `curl http://0.0.0.0:3000/api/v1/zone/0/kombisensor/0/sensors`
     let xmz_mod_touch_server_clone = xmz_mod_touch_server.clone();
     router.get("/api/v1/zone/:zone_id/kombisensor/:kombisensor_id/sensors",
@@ -368,6 +980,54 @@ pub fn init(xmz_mod_touch_server: Arc<Mutex<Server>>)
             move |req: &mut Request| reset_error_count(req, xmz_mod_touch_server_clone.clone()),
             "reset_error_count");
 
+    /// `curl -X PUT -d '{"adc_value_at_messgas": 920}' http://0.0.0.0:3000/api/v1/zone/0/kombisensor/0/sensor/0/calibration`
+    let xmz_mod_touch_server_clone = xmz_mod_touch_server.clone();
+    router.put("/api/v1/zone/:zone_id/kombisensor/:kombisensor_id/sensor/:sensor_id/calibration",
+            move |req: &mut Request| sensor_calibration_put(req, xmz_mod_touch_server_clone.clone()),
+            "sensor_calibration_put");
+
+    /// `curl -X PUT -d '{"enabled": false}' http://0.0.0.0:3000/api/v1/zone/0/kombisensor/0/sensor/1/enabled`
+    let xmz_mod_touch_server_clone = xmz_mod_touch_server.clone();
+    router.put("/api/v1/zone/:zone_id/kombisensor/:kombisensor_id/sensor/:sensor_id/enabled",
+            move |req: &mut Request| sensor_enabled_put(req, xmz_mod_touch_server_clone.clone()),
+            "sensor_enabled_put");
+
+    /// `curl http://0.0.0.0:3000/api/v1/zone/0/kombisensor/0/sensor/0/health`
+    let xmz_mod_touch_server_clone = xmz_mod_touch_server.clone();
+    router.get("/api/v1/zone/:zone_id/kombisensor/:kombisensor_id/sensor/:sensor_id/health",
+               move |req: &mut Request| sensor_health_get(req, xmz_mod_touch_server_clone.clone()),
+               "sensor_health_get");
+
+    /// `curl -X PUT http://0.0.0.0:3000/api/v1/zone/0/kombisensor/0/sensor/0/calibration/start`
+    let xmz_mod_touch_server_clone = xmz_mod_touch_server.clone();
+    router.put("/api/v1/zone/:zone_id/kombisensor/:kombisensor_id/sensor/:sensor_id/calibration/start",
+            move |req: &mut Request| calibration_start_put(req, xmz_mod_touch_server_clone.clone()),
+            "calibration_start_put");
+
+    /// `curl http://0.0.0.0:3000/api/v1/calibration`
+    let xmz_mod_touch_server_clone = xmz_mod_touch_server.clone();
+    router.get("/api/v1/calibration",
+               move |req: &mut Request| calibration_get(req, xmz_mod_touch_server_clone.clone()),
+               "calibration_get");
+
+    /// `curl -X PUT -d '{"concentration": 20}' http://0.0.0.0:3000/api/v1/calibration/messgas`
+    let xmz_mod_touch_server_clone = xmz_mod_touch_server.clone();
+    router.put("/api/v1/calibration/messgas",
+            move |req: &mut Request| calibration_messgas_put(req, xmz_mod_touch_server_clone.clone()),
+            "calibration_messgas_put");
+
+    /// `curl -X PUT http://0.0.0.0:3000/api/v1/calibration/commit`
+    let xmz_mod_touch_server_clone = xmz_mod_touch_server.clone();
+    router.put("/api/v1/calibration/commit",
+            move |req: &mut Request| calibration_commit_put(req, xmz_mod_touch_server_clone.clone()),
+            "calibration_commit_put");
+
+    /// `curl -X PUT http://0.0.0.0:3000/api/v1/calibration/abort`
+    let xmz_mod_touch_server_clone = xmz_mod_touch_server.clone();
+    router.put("/api/v1/calibration/abort",
+            move |req: &mut Request| calibration_abort_put(req, xmz_mod_touch_server_clone.clone()),
+            "calibration_abort_put");
+
 
     // /// `curl -X POST -d "/dev/ttyS0" http://0.0.0.0:3000/api/v1/zone/0/kombisensor/0/sensor/0/reset_error_count`
     // let xmz_mod_touch_server_clone = xmz_mod_touch_server.clone();
@@ -24,13 +24,19 @@ extern crate sysfs_gpio;
 
 pub mod errors;
 pub mod exception;
+pub mod glt;
 pub mod json_api;
+pub mod modbus_bus;
+pub mod modbus_data;
 pub mod server;
 pub mod shift_register;
 
 pub use self::exception::{Action, Check, Exception, ExceptionType};
+pub use self::glt::{GltSlave, GLT_NUM_INPUT_REGISTERS};
+pub use self::modbus_bus::{DiscoveredKombisensor, ModbusBus, ModbusBusManager};
+pub use self::modbus_data::{ModbusData, RegisterMap};
 pub use self::server::{Server, ServerType};
 pub use self::server::zone::{Zone, ZoneStatus};
-pub use self::server::zone::kombisensor::{Kombisensor, KombisensorType};
-pub use self::server::zone::kombisensor::sensor::{Sensor, SensorType, SI};
+pub use self::server::zone::kombisensor::{Kombisensor, KombisensorStatus, KombisensorType};
+pub use self::server::zone::kombisensor::sensor::{Sensor, SensorStatus, SensorType, SI};
 pub use self::shift_register::{ShiftRegister, ShiftRegisterType};
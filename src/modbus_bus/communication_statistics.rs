@@ -0,0 +1,332 @@
+//! Kommunikationsstatistik eines Modbus Teilnehmers
+//!
+use ::chrono::{DateTime, Utc};
+use errors::*;
+use libmodbus_rs::MODBUS_ENOBASE;
+use std::collections::VecDeque;
+use std::io;
+use std::time::Duration;
+
+
+/// Anzahl der letzten Antwortzeiten, aus denen das Histogramm gebildet wird
+pub const LATENCY_WINDOW: usize = 100;
+/// Obergrenzen der Histogramm Klassen (in Millisekunden), die letzte Klasse nimmt alle längeren Antwortzeiten auf
+pub const LATENCY_BUCKETS_MS: &[u32] = &[10, 25, 50, 100, 250, 500, 1000];
+
+// libmodbus Fehlernummern, siehe `modbus.h`
+const EMBXILFUN: i32 = MODBUS_ENOBASE as i32 + 1;
+const EMBXGTAR: i32 = MODBUS_ENOBASE as i32 + 11;
+const EMBBADCRC: i32 = MODBUS_ENOBASE as i32 + 12;
+
+/// Art eines Kommunikationsfehlers
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub enum FailureKind {
+    // Teilnehmer hat nicht (rechtzeitig) geantwortet
+    Timeout,
+    // Antwort mit falscher Prüfsumme
+    Crc,
+    // Teilnehmer hat mit einer Modbus Exception geantwortet
+    Exception,
+    // alle anderen Fehler, z.B. Schnittstelle nicht vorhanden
+    Other,
+}
+
+impl FailureKind {
+    /// Ordnet einen Fehler einer Fehlerart zu
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::errors::Error;
+    /// use xmz_mod_touch_server::modbus_bus::FailureKind;
+    ///
+    /// let error: Error = "Schnittstelle nicht vorhanden".into();
+    /// assert_eq!(FailureKind::from_error(&error), FailureKind::Other);
+    /// ```
+    pub fn from_error(error: &Error) -> Self {
+        match *error.kind() {
+            ErrorKind::Libmodbus(::libmodbus_rs::errors::ErrorKind::Io(ref io_error)) => {
+                match io_error.raw_os_error() {
+                    Some(errno) if errno >= EMBXILFUN && errno <= EMBXGTAR => FailureKind::Exception,
+                    Some(errno) if errno == EMBBADCRC => FailureKind::Crc,
+                    _ if io_error.kind() == io::ErrorKind::TimedOut => FailureKind::Timeout,
+                    _ => FailureKind::Other,
+                }
+            }
+            _ => FailureKind::Other,
+        }
+    }
+
+    /// Erzeugt einen Fehler dieser Fehlerart
+    ///
+    /// Wird z.B. beim Abspielen einer Aufzeichnung verwendet, in der nur die Fehlerart gespeichert ist.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::modbus_bus::FailureKind;
+    ///
+    /// for kind in &[FailureKind::Timeout, FailureKind::Crc, FailureKind::Exception, FailureKind::Other] {
+    ///     assert_eq!(FailureKind::from_error(&kind.to_error()), *kind);
+    /// }
+    /// ```
+    pub fn to_error(&self) -> Error {
+        let io_error = match *self {
+            FailureKind::Timeout => io::Error::new(io::ErrorKind::TimedOut, "Connection timed out"),
+            FailureKind::Crc => io::Error::from_raw_os_error(EMBBADCRC),
+            FailureKind::Exception => io::Error::from_raw_os_error(EMBXILFUN),
+            FailureKind::Other => return "Modbus communication failed".into(),
+        };
+
+        ::libmodbus_rs::errors::Error::from(io_error).into()
+    }
+}
+
+/// Eine Klasse des Antwortzeit Histogramms
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct LatencyBucket {
+    /// Obergrenze der Klasse in Millisekunden, `None` bei der letzten Klasse
+    pub upper_bound_ms: Option<u32>,
+    /// Anzahl der Antwortzeiten in dieser Klasse
+    pub count: usize,
+}
+
+/// Kommunikationsstatistik eines Kombisensors
+///
+/// Im Gegensatz zum `error_count` des Kombisensors, der nach jeder erfolgreichen Abfrage wieder auf 0 gesetzt wird,
+/// zählt die Statistik alle Abfragen seit dem Start, bzw. seit dem letzten `reset()`. So lassen sich wackelige
+/// Leitungen erkennen, bevor der Kombisensor in den Status `Kabelbruch` wechselt.
+///
+/// # Examples
+///
+/// ```rust
+/// use xmz_mod_touch_server::modbus_bus::CommunicationStatistics;
+/// use std::time::Duration;
+///
+/// let mut statistics = CommunicationStatistics::new();
+/// statistics.record_success(Duration::from_millis(30));
+/// statistics.record_failure(&"Timeout".into());
+///
+/// assert_eq!(statistics.get_request_count(), 2);
+/// assert_eq!(statistics.get_failure_count(), 1);
+/// ```
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(Serialize, Deserialize)]
+pub struct CommunicationStatistics {
+    request_count: u64,
+    success_count: u64,
+    timeout_count: u64,
+    crc_error_count: u64,
+    exception_count: u64,
+    other_error_count: u64,
+    last_success: Option<DateTime<Utc>>,
+    last_failure: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    // Antwortzeiten der letzten erfolgreichen Abfragen, in Millisekunden. Sie stehen weder in der Konfiguration
+    // noch im JSON API, dort gibt es nur das Histogramm.
+    #[serde(skip_serializing, default)]
+    latencies_ms: VecDeque<u32>,
+}
+
+impl CommunicationStatistics {
+    /// Erstellt eine neue, leere Statistik
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::modbus_bus::CommunicationStatistics;
+    ///
+    /// let statistics = CommunicationStatistics::new();
+    /// assert_eq!(statistics.get_request_count(), 0);
+    /// assert!(statistics.get_last_success().is_none());
+    /// ```
+    pub fn new() -> Self {
+        CommunicationStatistics {
+            latencies_ms: VecDeque::with_capacity(LATENCY_WINDOW),
+            ..Default::default()
+        }
+    }
+
+    /// Zählt eine erfolgreiche Abfrage
+    ///
+    /// # Parameters
+    ///
+    /// * `latency`     - Antwortzeit des Teilnehmers
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::modbus_bus::CommunicationStatistics;
+    /// use std::time::Duration;
+    ///
+    /// let mut statistics = CommunicationStatistics::new();
+    /// statistics.record_success(Duration::from_millis(42));
+    ///
+    /// assert_eq!(statistics.get_success_count(), 1);
+    /// assert!(statistics.get_last_success().is_some());
+    /// assert_eq!(statistics.get_latencies_ms(), vec![42]);
+    /// ```
+    pub fn record_success(&mut self, latency: Duration) {
+        self.request_count += 1;
+        self.success_count += 1;
+        self.last_success = Some(Utc::now());
+
+        let latency_ms = latency.as_secs().saturating_mul(1000).saturating_add(latency.subsec_nanos() as u64 / 1_000_000);
+        if self.latencies_ms.len() >= LATENCY_WINDOW {
+            self.latencies_ms.pop_front();
+        }
+        self.latencies_ms.push_back(if latency_ms > u32::max_value() as u64 { u32::max_value() } else { latency_ms as u32 });
+    }
+
+    /// Zählt eine fehlgeschlagene Abfrage
+    ///
+    /// Der Fehler wird nach seiner Art ([`FailureKind`](enum.FailureKind.html)) gezählt.
+    ///
+    /// # Parameters
+    ///
+    /// * `error`       - Fehler der Abfrage
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::modbus_bus::CommunicationStatistics;
+    ///
+    /// let mut statistics = CommunicationStatistics::new();
+    /// statistics.record_failure(&"Schnittstelle nicht vorhanden".into());
+    ///
+    /// assert_eq!(statistics.get_other_error_count(), 1);
+    /// assert_eq!(statistics.get_last_error(), Some("Schnittstelle nicht vorhanden".to_string()));
+    /// ```
+    pub fn record_failure(&mut self, error: &Error) {
+        self.request_count += 1;
+        match FailureKind::from_error(error) {
+            FailureKind::Timeout => self.timeout_count += 1,
+            FailureKind::Crc => self.crc_error_count += 1,
+            FailureKind::Exception => self.exception_count += 1,
+            FailureKind::Other => self.other_error_count += 1,
+        }
+        self.last_failure = Some(Utc::now());
+        self.last_error = Some(error.to_string());
+    }
+
+    /// Setzt alle Zähler zurück
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::modbus_bus::CommunicationStatistics;
+    /// use std::time::Duration;
+    ///
+    /// let mut statistics = CommunicationStatistics::new();
+    /// statistics.record_success(Duration::from_millis(42));
+    /// statistics.reset();
+    ///
+    /// assert_eq!(statistics.get_request_count(), 0);
+    /// assert_eq!(statistics.get_latencies_ms().len(), 0);
+    /// ```
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Anzahl aller Abfragen
+    pub fn get_request_count(&self) -> u64 {
+        self.request_count
+    }
+
+    /// Anzahl der erfolgreichen Abfragen
+    pub fn get_success_count(&self) -> u64 {
+        self.success_count
+    }
+
+    /// Anzahl der fehlgeschlagenen Abfragen, aller Fehlerarten
+    pub fn get_failure_count(&self) -> u64 {
+        self.timeout_count + self.crc_error_count + self.exception_count + self.other_error_count
+    }
+
+    /// Anzahl der Abfragen, auf die der Teilnehmer nicht geantwortet hat
+    pub fn get_timeout_count(&self) -> u64 {
+        self.timeout_count
+    }
+
+    /// Anzahl der Antworten mit falscher Prüfsumme
+    pub fn get_crc_error_count(&self) -> u64 {
+        self.crc_error_count
+    }
+
+    /// Anzahl der Modbus Exception Antworten
+    pub fn get_exception_count(&self) -> u64 {
+        self.exception_count
+    }
+
+    /// Anzahl der sonstigen Fehler
+    pub fn get_other_error_count(&self) -> u64 {
+        self.other_error_count
+    }
+
+    /// Zeitpunkt der letzten erfolgreichen Abfrage
+    pub fn get_last_success(&self) -> Option<DateTime<Utc>> {
+        self.last_success
+    }
+
+    /// Zeitpunkt der letzten fehlgeschlagenen Abfrage
+    pub fn get_last_failure(&self) -> Option<DateTime<Utc>> {
+        self.last_failure
+    }
+
+    /// Fehlermeldung der letzten fehlgeschlagenen Abfrage
+    pub fn get_last_error(&self) -> Option<String> {
+        self.last_error.clone()
+    }
+
+    /// Antwortzeiten der letzten (maximal `LATENCY_WINDOW`) erfolgreichen Abfragen, in Millisekunden
+    pub fn get_latencies_ms(&self) -> Vec<u32> {
+        self.latencies_ms.iter().cloned().collect()
+    }
+
+    /// Histogramm der letzten (maximal `LATENCY_WINDOW`) Antwortzeiten
+    ///
+    /// Die Klassen sind durch [`LATENCY_BUCKETS_MS`](constant.LATENCY_BUCKETS_MS.html) festgelegt, eine
+    /// Antwortzeit zählt zur ersten Klasse deren Obergrenze sie nicht überschreitet.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::modbus_bus::{CommunicationStatistics, LATENCY_BUCKETS_MS};
+    /// use std::time::Duration;
+    ///
+    /// let mut statistics = CommunicationStatistics::new();
+    /// statistics.record_success(Duration::from_millis(5));
+    /// statistics.record_success(Duration::from_millis(10));
+    /// statistics.record_success(Duration::from_millis(3000));
+    ///
+    /// let histogram = statistics.get_latency_histogram();
+    /// assert_eq!(histogram.len(), LATENCY_BUCKETS_MS.len() + 1);
+    /// assert_eq!(histogram[0].upper_bound_ms, Some(10));
+    /// assert_eq!(histogram[0].count, 2);
+    /// assert_eq!(histogram[LATENCY_BUCKETS_MS.len()].upper_bound_ms, None);
+    /// assert_eq!(histogram[LATENCY_BUCKETS_MS.len()].count, 1);
+    /// ```
+    pub fn get_latency_histogram(&self) -> Vec<LatencyBucket> {
+        let mut histogram: Vec<LatencyBucket> = LATENCY_BUCKETS_MS.iter()
+            .map(|&upper_bound_ms| LatencyBucket { upper_bound_ms: Some(upper_bound_ms), count: 0 })
+            .collect();
+        histogram.push(LatencyBucket { upper_bound_ms: None, count: 0 });
+
+        for &latency_ms in &self.latencies_ms {
+            let index = LATENCY_BUCKETS_MS.iter()
+                .position(|&upper_bound_ms| latency_ms <= upper_bound_ms)
+                .unwrap_or(LATENCY_BUCKETS_MS.len());
+            histogram[index].count += 1;
+        }
+
+        histogram
+    }
+}
@@ -0,0 +1,113 @@
+//! Ergebnis eines Bus Scans
+//!
+use server::zone::kombisensor::{Kombisensor, KombisensorType};
+
+
+/// Ein Kombisensor, der bei einem Bus Scan geantwortet hat
+///
+/// Siehe [`ModbusBus::scan()`](struct.ModbusBus.html#method.scan)
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct DiscoveredKombisensor {
+    modbus_device: String,
+    modbus_address: u8,
+    firmware_version: String,
+}
+
+impl DiscoveredKombisensor {
+    /// Erstellt einen neuen gefundenen Kombisensor
+    ///
+    /// # Parameters
+    ///
+    /// * `modbus_device`       - Schnittstelle der Leitung, an der der Kombisensor gefunden wurde
+    /// * `modbus_address`      - Modbus Adresse unter der der Kombisensor geantwortet hat
+    /// * `firmware_version`    - Firmware Version des Kombisensors
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::DiscoveredKombisensor;
+    ///
+    /// let discovered = DiscoveredKombisensor::new("/dev/ttyS1", 1, "0.14.0");
+    /// assert_eq!(discovered.get_modbus_address(), 1);
+    /// ```
+    pub fn new(modbus_device: &str, modbus_address: u8, firmware_version: &str) -> Self {
+        DiscoveredKombisensor {
+            modbus_device: modbus_device.to_string(),
+            modbus_address: modbus_address,
+            firmware_version: firmware_version.to_string(),
+        }
+    }
+
+    /// Schnittstelle der Leitung, an der der Kombisensor gefunden wurde
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::DiscoveredKombisensor;
+    ///
+    /// let discovered = DiscoveredKombisensor::new("/dev/ttyS1", 1, "0.14.0");
+    /// assert_eq!(discovered.get_modbus_device(), "/dev/ttyS1".to_string());
+    /// ```
+    pub fn get_modbus_device(&self) -> String {
+        self.modbus_device.clone()
+    }
+
+    /// Modbus Adresse des Kombisensors
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::DiscoveredKombisensor;
+    ///
+    /// let discovered = DiscoveredKombisensor::new("/dev/ttyS1", 12, "0.14.0");
+    /// assert_eq!(discovered.get_modbus_address(), 12);
+    /// ```
+    pub fn get_modbus_address(&self) -> u8 {
+        self.modbus_address
+    }
+
+    /// Firmware Version des Kombisensors
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::DiscoveredKombisensor;
+    ///
+    /// let discovered = DiscoveredKombisensor::new("/dev/ttyS1", 1, "0.14.0");
+    /// assert_eq!(discovered.get_firmware_version(), "0.14.0".to_string());
+    /// ```
+    pub fn get_firmware_version(&self) -> String {
+        self.firmware_version.clone()
+    }
+
+    /// Erstellt einen Kombisensor Eintrag für die Konfiguration
+    ///
+    /// # Parameters
+    ///
+    /// * `kombisensor_type`    - Typ des Kombisensors
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{DiscoveredKombisensor, KombisensorType};
+    ///
+    /// let discovered = DiscoveredKombisensor::new("/dev/ttyS1", 12, "0.14.0");
+    /// let kombisensor = discovered.to_kombisensor(KombisensorType::RAGas);
+    ///
+    /// assert_eq!(kombisensor.get_modbus_device(), "/dev/ttyS1".to_string());
+    /// assert_eq!(kombisensor.get_modbus_address(), 12);
+    /// assert_eq!(kombisensor.get_firmware_version(), "0.14.0".to_string());
+    /// assert_eq!(kombisensor.get_sensors().len(), 2);
+    /// ```
+    pub fn to_kombisensor(&self, kombisensor_type: KombisensorType) -> Kombisensor {
+        let mut kombisensor = Kombisensor::new_with_type(kombisensor_type);
+        kombisensor.set_modbus_device(self.modbus_device.clone());
+        kombisensor.set_modbus_address(self.modbus_address);
+        kombisensor.set_firmware_version(self.firmware_version.clone());
+
+        kombisensor
+    }
+}
@@ -0,0 +1,123 @@
+//! Kombisensor Emulator für den Simulationsbetrieb
+//!
+use errors::*;
+use modbus_data::{REGISTER_MODBUS_ADDRESS, SIMULATION_DATA_STATIC};
+use std::collections::HashMap;
+
+
+/// Emuliert die Kombisensoren einer Leitung im Server Prozess
+///
+/// Für jede Modbus Adresse wird beim ersten Zugriff ein Register Abbild aus
+/// [`SIMULATION_DATA_STATIC`](../modbus_data/constant.SIMULATION_DATA_STATIC.html) angelegt. Lesezugriffe liefern
+/// die Register aus diesem Abbild, Schreibzugriffe ändern das Abbild. Eine Schnittstelle wird nie geöffnet.
+///
+/// Wie bei der echten Firmware antwortet ein Kombisensor nach dem Schreiben des Registers 3 unter der neuen
+/// Modbus Adresse.
+#[derive(Debug)]
+#[derive(Default)]
+pub struct Emulator {
+    register_images: HashMap<u8, Vec<u16>>,
+}
+
+impl Emulator {
+    /// Erstellt einen neuen Emulator, ohne Kombisensoren
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::modbus_bus::Emulator;
+    ///
+    /// let emulator = Emulator::new();
+    /// ```
+    pub fn new() -> Self {
+        Emulator {
+            register_images: HashMap::new(),
+        }
+    }
+
+    /// Liest `num` Register, ab der Adresse `address`, aus dem Abbild eines Kombisensors
+    ///
+    /// # Return values
+    ///
+    /// Liefert die Register, oder einen Fehler wenn der Bereich außerhalb des Abbilds liegt.
+    ///
+    /// # Parameters
+    ///
+    /// * `slave`       - Modbus Adresse des Kombisensors
+    /// * `address`     - Adresse des ersten Registers
+    /// * `num`         - Anzahl der zu lesenden Register
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::modbus_bus::Emulator;
+    ///
+    /// let mut emulator = Emulator::new();
+    /// assert_eq!(emulator.read_registers(12, 0, 4).unwrap(), vec![0, 14, 0, 12]);
+    /// assert!(emulator.read_registers(12, 250, 10).is_err());
+    /// ```
+    pub fn read_registers(&mut self, slave: u8, address: u16, num: u16) -> Result<Vec<u16>> {
+        let register_image = self.get_register_image(slave);
+        let (start, end) = (address as usize, address as usize + num as usize);
+
+        if end > register_image.len() {
+            bail!("Emulator: Register {}..{} of Kombisensor {} not available", start, end, slave)
+        }
+
+        Ok(register_image[start..end].to_vec())
+    }
+
+    /// Schreibt die Register `values`, ab der Adresse `address`, in das Abbild eines Kombisensors
+    ///
+    /// # Parameters
+    ///
+    /// * `slave`       - Modbus Adresse des Kombisensors
+    /// * `address`     - Adresse des ersten Registers
+    /// * `values`      - Werte der Register
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::modbus_bus::Emulator;
+    ///
+    /// let mut emulator = Emulator::new();
+    /// emulator.write_registers(1, 14, &[900, 550]).unwrap();
+    /// assert_eq!(emulator.read_registers(1, 14, 2).unwrap(), vec![900, 550]);
+    ///
+    /// // Neue Modbus Adresse
+    /// emulator.write_registers(1, 3, &[12]).unwrap();
+    /// assert_eq!(emulator.read_registers(12, 14, 2).unwrap(), vec![900, 550]);
+    /// ```
+    pub fn write_registers(&mut self, slave: u8, address: u16, values: &[u16]) -> Result<()> {
+        let modbus_address = {
+            let register_image = self.get_register_image(slave);
+            let (start, end) = (address as usize, address as usize + values.len());
+
+            if end > register_image.len() {
+                bail!("Emulator: Register {}..{} of Kombisensor {} not available", start, end, slave)
+            }
+            register_image[start..end].copy_from_slice(values);
+
+            register_image[REGISTER_MODBUS_ADDRESS as usize]
+        };
+
+        // Der Kombisensor antwortet ab jetzt unter der neuen Adresse
+        if modbus_address != slave as u16 && modbus_address > 0 && modbus_address <= u8::max_value() as u16 {
+            if let Some(register_image) = self.register_images.remove(&slave) {
+                self.register_images.insert(modbus_address as u8, register_image);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Liefert das Register Abbild eines Kombisensors, legt es beim ersten Zugriff an
+    //
+    fn get_register_image(&mut self, slave: u8) -> &mut Vec<u16> {
+        self.register_images.entry(slave).or_insert_with(|| {
+            let mut register_image = SIMULATION_DATA_STATIC.to_vec();
+            register_image[REGISTER_MODBUS_ADDRESS as usize] = slave as u16;
+            register_image
+        })
+    }
+}
@@ -0,0 +1,22 @@
+//! Modbus Bus Verwaltung
+//!
+//! Für jede Modbus Leitung (`modbus_device`) wird genau eine Verbindung offen gehalten. Alle Kombisensoren
+//! an dieser Leitung teilen sich diese Verbindung.
+//!
+mod communication_statistics;
+mod discovered_kombisensor;
+mod emulator;
+mod modbus_bus;
+mod modbus_bus_manager;
+mod poll_request;
+mod serial_parameters;
+mod traffic_recording;
+
+pub use self::communication_statistics::{CommunicationStatistics, FailureKind, LatencyBucket, LATENCY_BUCKETS_MS, LATENCY_WINDOW};
+pub use self::discovered_kombisensor::DiscoveredKombisensor;
+pub use self::emulator::Emulator;
+pub use self::modbus_bus::{ModbusBus, MODBUS_SIM_PREFIX, MODBUS_TCP_PREFIX};
+pub use self::modbus_bus_manager::ModbusBusManager;
+pub use self::poll_request::{PollRequest, PollResult};
+pub use self::serial_parameters::{RtsMode, SerialParameterOverrides, SerialParameters};
+pub use self::traffic_recording::{TrafficRecord, TrafficRecorder, TrafficReplay, TRAFFIC_RECORDING_MAX_BYTES};
@@ -0,0 +1,506 @@
+//! Modbus Bus, eine Modbus Leitung mit offener Verbindung
+//!
+use errors::*;
+use modbus_bus::{DiscoveredKombisensor, Emulator, RtsMode, SerialParameters};
+use modbus_data::{ModbusData, HEADER_NUM_REGISTERS, REGISTER_MODBUS_ADDRESS};
+use libmodbus_rs::{Modbus, ModbusClient, ModbusRTU, ModbusTCPPI, RequestToSendMode, MODBUS_ENOBASE};
+use std::fmt;
+use std::io;
+
+
+/// Präfix einer Modbus TCP Leitung, z.B. `tcp://192.168.1.20:502`
+pub const MODBUS_TCP_PREFIX: &str = "tcp://";
+/// Präfix einer emulierten Leitung, z.B. `sim:///dev/ttyUSB0`
+pub const MODBUS_SIM_PREFIX: &str = "sim://";
+// Standard Port für Modbus TCP, wenn in der Schnittstelle kein Port angegeben ist
+const MODBUS_TCP_DEFAULT_PORT: &str = "502";
+
+/// Offener libmodbus Kontext
+///
+/// Der libmodbus Kontext enthält einen Raw Pointer und ist deshalb nicht `Send`. Ein `ModbusBus` wird aber
+/// nur hinter einem Mutex verwendet, es greift also immer nur ein Thread zur gleichen Zeit auf den Kontext zu.
+struct Connection(Modbus);
+
+unsafe impl Send for Connection {}
+
+/// Eine Modbus Leitung, z.B. die RS485 Schnittstelle `/dev/ttyS1` der 'xMZ-Mod-Touch'-Hardware
+///
+/// Beginnt die Schnittstelle mit `tcp://`, z.B. `tcp://192.168.1.20:502`, dann wird die Leitung über Modbus TCP,
+/// z.B. über ein Ethernet Gateway, angesprochen. Ohne Port wird der Standard Port 502 verwendet.
+///
+/// Beginnt die Schnittstelle mit `sim://`, dann antwortet ein [`Emulator`](struct.Emulator.html) im Server Prozess.
+/// Es wird keine Schnittstelle geöffnet.
+///
+/// Der Bus hält die Verbindung zur Leitung offen. Die Verbindung wird beim ersten Zugriff aufgebaut und
+/// nur nach einem Verbindungsfehler wieder geschlossen. Beim nächsten Zugriff wird sie dann neu aufgebaut.
+///
+/// Timeouts und Modbus Protokollfehler (CRC, Exception Response, ...) einzelner Teilnehmer gelten nicht als
+/// Verbindungsfehler, die Verbindung bleibt in diesem Fall bestehen.
+///
+/// Baudrate, Parität, Daten- und Stop Bits sowie der RTS Modus werden beim Verbindungsaufbau aus den
+/// [`SerialParameters`](struct.SerialParameters.html) gesetzt, die Timeouts vor jeder Abfrage.
+pub struct ModbusBus {
+    modbus_device: String,
+    serial_parameters: SerialParameters,
+    connection: Option<Connection>,
+    // Emulierte Kombisensoren, nur bei `sim://` Leitungen
+    emulator: Option<Emulator>,
+    // Anzahl der Verbindungsaufbauten, seit der Bus erstellt wurde
+    connect_count: u64,
+}
+
+impl ModbusBus {
+    /// Erstellt einen neuen Modbus Bus
+    ///
+    /// Die Verbindung wird erst beim ersten Zugriff aufgebaut.
+    ///
+    /// # Parameters
+    ///
+    /// * `modbus_device`   - Schnittstelle der Leitung, z.B. `/dev/ttyS1` oder `tcp://192.168.1.20:502`
+    /// * `serial_parameters`   - Parameter der Leitung, z.B. Baudrate und RTS Modus
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::ModbusBus;
+    /// use xmz_mod_touch_server::modbus_bus::SerialParameters;
+    ///
+    /// let bus = ModbusBus::new("/dev/ttyS1", SerialParameters::new());
+    /// assert_eq!(bus.get_modbus_device(), "/dev/ttyS1".to_string());
+    /// assert!(!bus.is_connected());
+    /// ```
+    pub fn new(modbus_device: &str, serial_parameters: SerialParameters) -> Self {
+        ModbusBus {
+            modbus_device: modbus_device.to_string(),
+            serial_parameters: serial_parameters,
+            connection: None,
+            emulator: if modbus_device.starts_with(MODBUS_SIM_PREFIX) { Some(Emulator::new()) } else { None },
+            connect_count: 0,
+        }
+    }
+
+    /// Liefert die Schnittstelle des Busses
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::ModbusBus;
+    /// use xmz_mod_touch_server::modbus_bus::SerialParameters;
+    ///
+    /// let bus = ModbusBus::new("/dev/ttyUSB0", SerialParameters::new());
+    /// assert_eq!(bus.get_modbus_device(), "/dev/ttyUSB0".to_string());
+    /// ```
+    pub fn get_modbus_device(&self) -> String {
+        self.modbus_device.clone()
+    }
+
+    /// Wird die Leitung über Modbus TCP angesprochen?
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::ModbusBus;
+    /// use xmz_mod_touch_server::modbus_bus::SerialParameters;
+    ///
+    /// assert!(ModbusBus::new("tcp://192.168.1.20:502", SerialParameters::new()).is_tcp());
+    /// assert!(!ModbusBus::new("/dev/ttyS1", SerialParameters::new()).is_tcp());
+    /// ```
+    pub fn is_tcp(&self) -> bool {
+        parse_tcp_device(&self.modbus_device).is_some()
+    }
+
+    /// Wird die Leitung emuliert?
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::ModbusBus;
+    /// use xmz_mod_touch_server::modbus_bus::SerialParameters;
+    ///
+    /// assert!(ModbusBus::new("sim:///dev/ttyUSB0", SerialParameters::new()).is_emulated());
+    /// assert!(!ModbusBus::new("/dev/ttyUSB0", SerialParameters::new()).is_emulated());
+    /// ```
+    pub fn is_emulated(&self) -> bool {
+        self.emulator.is_some()
+    }
+
+    /// Ist die Verbindung zur Leitung aktuell offen?
+    ///
+    /// Eine emulierte Leitung ist immer verbunden.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::ModbusBus;
+    /// use xmz_mod_touch_server::modbus_bus::SerialParameters;
+    ///
+    /// let bus = ModbusBus::new("/dev/ttyUSB0", SerialParameters::new());
+    /// assert_eq!(bus.is_connected(), false);
+    ///
+    /// let bus = ModbusBus::new("sim:///dev/ttyUSB0", SerialParameters::new());
+    /// assert_eq!(bus.is_connected(), true);
+    /// ```
+    pub fn is_connected(&self) -> bool {
+        self.connection.is_some() || self.emulator.is_some()
+    }
+
+    /// Liefert die Anzahl der Verbindungsaufbauten
+    ///
+    /// Steigt dieser Zähler stetig an, dann wird die Verbindung immer wieder wegen Fehlern geschlossen.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::ModbusBus;
+    /// use xmz_mod_touch_server::modbus_bus::SerialParameters;
+    ///
+    /// let bus = ModbusBus::new("/dev/ttyUSB0", SerialParameters::new());
+    /// assert_eq!(bus.get_connect_count(), 0);
+    /// ```
+    pub fn get_connect_count(&self) -> u64 {
+        self.connect_count
+    }
+
+    /// Liefert die Parameter der Leitung
+    pub fn get_serial_parameters(&self) -> SerialParameters {
+        self.serial_parameters.clone()
+    }
+
+    /// Setzt die Parameter der Leitung
+    ///
+    /// Ändern sich die Leitungseinstellungen (Baudrate, Parität, ..., RTS), wird die Verbindung geschlossen und
+    /// beim nächsten Zugriff mit den neuen Einstellungen aufgebaut. Geänderte Timeouts gelten ab der nächsten
+    /// Abfrage, die Verbindung bleibt dafür offen.
+    ///
+    /// So können Kombisensoren mit unterschiedlichen Parametern an der selben Leitung abgefragt werden.
+    ///
+    /// # Parameters
+    ///
+    /// * `serial_parameters`   - neue Parameter der Leitung
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::ModbusBus;
+    /// use xmz_mod_touch_server::modbus_bus::SerialParameters;
+    ///
+    /// let mut bus = ModbusBus::new("/dev/ttyUSB0", SerialParameters::new());
+    /// let mut serial_parameters = SerialParameters::new();
+    /// serial_parameters.set_baud(19200);
+    /// bus.set_serial_parameters(&serial_parameters);
+    ///
+    /// assert_eq!(bus.get_serial_parameters().get_baud(), 19200);
+    /// ```
+    pub fn set_serial_parameters(&mut self, serial_parameters: &SerialParameters) {
+        if self.serial_parameters == *serial_parameters {
+            return;
+        }
+        if !self.serial_parameters.is_same_line(serial_parameters) && !self.is_tcp() {
+            debug!("Geänderte Leitungsparameter auf {}: {:?}", self.modbus_device, serial_parameters);
+            self.disconnect();
+        }
+        self.serial_parameters = serial_parameters.clone();
+    }
+
+    /// Schließt die Verbindung
+    ///
+    /// Der nächste Zugriff baut die Verbindung wieder neu auf.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::ModbusBus;
+    /// use xmz_mod_touch_server::modbus_bus::SerialParameters;
+    ///
+    /// let mut bus = ModbusBus::new("/dev/ttyUSB0", SerialParameters::new());
+    /// bus.disconnect();
+    /// assert!(!bus.is_connected());
+    /// ```
+    pub fn disconnect(&mut self) {
+        if self.connection.is_some() {
+            info!("Schließe Modbus Verbindung: {}", self.modbus_device);
+        }
+        // Der `Drop` Trait des libmodbus Kontext schließt die Schnittstelle
+        self.connection = None;
+    }
+
+    /// Liest `num` Register, ab der Adresse `address`, eines Teilnehmers am Bus
+    ///
+    /// # Return values
+    ///
+    /// Liefert ein Result mit den gelesenen Registern, oder einen Fehler wenn die Register nicht gelesen
+    /// werden konnten.
+    ///
+    /// # Parameters
+    ///
+    /// * `slave`       - Modbus Adresse des Teilnehmers
+    /// * `address`     - Adresse des ersten Registers
+    /// * `num`         - Anzahl der zu lesenden Register
+    /// * `debug`       - Modbus Debug Modus für diese Abfrage einschalten
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::ModbusBus;
+    /// use xmz_mod_touch_server::modbus_bus::SerialParameters;
+    ///
+    /// let mut bus = ModbusBus::new("/dev/nicht_vorhanden", SerialParameters::new());
+    /// assert!(bus.read_registers(247, 0, 30, false).is_err());
+    /// assert!(!bus.is_connected());
+    /// ```
+    pub fn read_registers(&mut self, slave: u8, address: u16, num: u16, debug: bool) -> Result<Vec<u16>> {
+        if let Some(ref mut emulator) = self.emulator {
+            return emulator.read_registers(slave, address, num);
+        }
+
+        self.transaction(slave, debug, |modbus| {
+            let mut response_register = vec![0u16; num as usize];
+            let count = modbus.read_registers(address as i32, num as i32, &mut response_register)?;
+            response_register.truncate(count as usize);
+
+            Ok(response_register)
+        })
+    }
+
+    /// Schreibt die Register `values`, ab der Adresse `address`, in einen Teilnehmer am Bus
+    ///
+    /// # Return values
+    ///
+    /// Liefert einen Fehler wenn die Register nicht, oder nicht vollständig geschrieben werden konnten.
+    ///
+    /// # Parameters
+    ///
+    /// * `slave`       - Modbus Adresse des Teilnehmers
+    /// * `address`     - Adresse des ersten Registers
+    /// * `values`      - Werte der Register
+    /// * `debug`       - Modbus Debug Modus für diese Abfrage einschalten
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::ModbusBus;
+    /// use xmz_mod_touch_server::modbus_bus::SerialParameters;
+    ///
+    /// let mut bus = ModbusBus::new("/dev/nicht_vorhanden", SerialParameters::new());
+    /// assert!(bus.write_registers(247, 14, &[100, 200], false).is_err());
+    /// ```
+    pub fn write_registers(&mut self, slave: u8, address: u16, values: &[u16], debug: bool) -> Result<()> {
+        if let Some(ref mut emulator) = self.emulator {
+            return emulator.write_registers(slave, address, values);
+        }
+
+        self.transaction(slave, debug, |modbus| {
+            let count = modbus.write_registers(address as i32, values.len() as i32, values)?;
+            if count as usize != values.len() {
+                bail!("Modbus write incomplete: {} of {} Registers written", count, values.len())
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Sucht Kombisensoren am Bus
+    ///
+    /// Jede Adresse von `first_address` bis `last_address` wird abgefragt. Die Register 0..3 (Firmware Version
+    /// und Modbus Adresse) der Teilnehmer die antworten werden ausgelesen. Teilnehmer die nicht antworten werden
+    /// übersprungen, jede dieser Adressen kostet allerdings einen Response Timeout.
+    ///
+    /// # Return values
+    ///
+    /// Liefert die gefundenen Kombisensoren, oder einen Fehler wenn die Leitung selbst nicht geöffnet
+    /// werden konnte.
+    ///
+    /// # Parameters
+    ///
+    /// * `first_address`   - erste Modbus Adresse, normal 1
+    /// * `last_address`    - letzte Modbus Adresse, normal 247
+    /// * `debug`           - Modbus Debug Modus für die Abfragen einschalten
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::ModbusBus;
+    /// use xmz_mod_touch_server::modbus_bus::SerialParameters;
+    ///
+    /// let mut bus = ModbusBus::new("/dev/nicht_vorhanden", SerialParameters::new());
+    /// assert!(bus.scan(1, 247, false).is_err());
+    /// ```
+    pub fn scan(&mut self, first_address: u8, last_address: u8, debug: bool) -> Result<Vec<DiscoveredKombisensor>> {
+        let mut discovered = vec![];
+
+        // Über u16, damit auch `last_address` 255 abgefragt wird
+        for modbus_address in (first_address as u16..last_address as u16 + 1).map(|modbus_address| modbus_address as u8) {
+            match self.read_registers(modbus_address, 0, HEADER_NUM_REGISTERS, debug) {
+                Ok(registers) => {
+                    match ModbusData::parse_firmware_version(&registers) {
+                        Ok(firmware_version) => {
+                            let reported_address = registers[REGISTER_MODBUS_ADDRESS as usize];
+                            if reported_address != modbus_address as u16 {
+                                warn!("Kombisensor {} meldet Modbus Adresse {}", modbus_address, reported_address);
+                            }
+                            info!("Kombisensor gefunden: {} Adresse {} Firmware {}", self.modbus_device, modbus_address, firmware_version);
+                            discovered.push(DiscoveredKombisensor::new(&self.modbus_device, modbus_address, &firmware_version));
+                        }
+                        Err(_) => debug!("Adresse {}: ungültige Antwort {:?}", modbus_address, registers),
+                    }
+                }
+                Err(e) => {
+                    // Ohne offene Verbindung lohnt es sich nicht die restlichen Adressen abzufragen
+                    if !self.is_connected() {
+                        bail!("Modbus Bus scan failed: {}: {}", self.modbus_device, e)
+                    }
+                    debug!("Adresse {}: keine Antwort ({})", modbus_address, e);
+                }
+            }
+        }
+
+        Ok(discovered)
+    }
+
+    // Führt eine Modbus Abfrage über die offene Verbindung aus
+    //
+    // Ist die Verbindung noch nicht offen, wird sie vorher aufgebaut. Schlägt die Abfrage wegen eines
+    // Verbindungsfehlers fehl, wird die Verbindung geschlossen.
+    fn transaction<F, T>(&mut self, slave: u8, debug: bool, function: F) -> Result<T>
+        where F: FnOnce(&mut Modbus) -> Result<T>
+    {
+        let response_timeout = self.serial_parameters.get_response_timeout_ms();
+        let byte_timeout = self.serial_parameters.get_byte_timeout_ms();
+
+        let result = match self.connect() {
+            Ok(modbus) => {
+                modbus.set_slave(slave)
+                    .and_then(|_| modbus.set_debug(debug))
+                    .and_then(|_| modbus.set_response_timeout(response_timeout / 1000, (response_timeout % 1000) * 1000))
+                    .and_then(|_| modbus.set_byte_timeout(byte_timeout / 1000, (byte_timeout % 1000) * 1000))
+                    .map_err(Error::from)
+                    .and_then(|_| function(modbus))
+            }
+            Err(e) => Err(e),
+        };
+
+        if let Err(ref e) = result {
+            if is_connection_error(e) {
+                warn!("Modbus Verbindungsfehler auf {}: {}", self.modbus_device, e);
+                self.disconnect();
+            }
+        }
+
+        result
+    }
+
+    // Baut die Verbindung auf, wenn sie noch nicht offen ist
+    //
+    fn connect(&mut self) -> Result<&mut Modbus> {
+        if self.connection.is_none() {
+            info!("Öffne Modbus Verbindung: {}", self.modbus_device);
+            let modbus = match parse_tcp_device(&self.modbus_device) {
+                Some((ref host, _)) if host.is_empty() => {
+                    bail!("Invalid Modbus TCP address: {}", self.modbus_device)
+                }
+                Some((host, port)) => Modbus::new_tcp_pi(&host, &port)?,
+                None => {
+                    let serial = &self.serial_parameters;
+                    let mut modbus = Modbus::new_rtu(&self.modbus_device,
+                                                     serial.get_baud(),
+                                                     serial.get_parity(),
+                                                     serial.get_data_bits(),
+                                                     serial.get_stop_bits())?;
+
+                    match serial.get_rts_mode() {
+                        RtsMode::Off => {}
+                        RtsMode::Up => {
+                            modbus.rtu_set_rts(RequestToSendMode::MODBUS_RTU_RTS_UP)?;
+                        }
+                        RtsMode::Down => {
+                            info!("Setze SerialMode RTS_DOWN");
+                            modbus.rtu_set_rts(RequestToSendMode::MODBUS_RTU_RTS_DOWN)?;
+                        }
+                    }
+                    if let Some(rts_delay_us) = serial.get_rts_delay_us() {
+                        modbus.rtu_set_rts_delay(rts_delay_us)?;
+                    }
+
+                    modbus
+                }
+            };
+
+            modbus.connect()?;
+            self.connect_count += 1;
+            self.connection = Some(Connection(modbus));
+        }
+
+        match self.connection {
+            Some(Connection(ref mut modbus)) => Ok(modbus),
+            None => bail!("Modbus Verbindung nicht offen: {}", self.modbus_device),
+        }
+    }
+}
+
+// Zerlegt eine Modbus TCP Schnittstelle `tcp://host:port` in Host und Port
+//
+// Liefert `None` wenn es sich nicht um eine Modbus TCP Schnittstelle handelt. IPv6 Adressen müssen in eckigen
+// Klammern stehen, z.B. `tcp://[::1]:502`.
+fn parse_tcp_device(modbus_device: &str) -> Option<(String, String)> {
+    if !modbus_device.starts_with(MODBUS_TCP_PREFIX) {
+        return None;
+    }
+    let address = &modbus_device[MODBUS_TCP_PREFIX.len()..];
+
+    let (host, port) = if address.starts_with('[') {
+        match address.find(']') {
+            Some(end) => {
+                let port = &address[end + 1..];
+                (&address[1..end], if port.starts_with(':') { &port[1..] } else { port })
+            }
+            None => (address, ""),
+        }
+    } else {
+        match address.rfind(':') {
+            Some(pos) => (&address[..pos], &address[pos + 1..]),
+            None => (address, ""),
+        }
+    };
+    let port = if port.is_empty() { MODBUS_TCP_DEFAULT_PORT } else { port };
+
+    Some((host.to_string(), port.to_string()))
+}
+
+// Muss die Verbindung nach diesem Fehler neu aufgebaut werden?
+//
+// Timeouts (Teilnehmer antwortet nicht) und Modbus Protokollfehler (CRC, Exception Response, ...) betreffen nur
+// einen Teilnehmer, die Verbindung zur Leitung ist dann weiterhin in Ordnung.
+fn is_connection_error(error: &Error) -> bool {
+    match *error.kind() {
+        ErrorKind::Libmodbus(::libmodbus_rs::errors::ErrorKind::Io(ref io_error)) => {
+            match io_error.raw_os_error() {
+                Some(errno) if errno >= MODBUS_ENOBASE as i32 => false,
+                _ => io_error.kind() != io::ErrorKind::TimedOut,
+            }
+        }
+        _ => true,
+    }
+}
+
+impl fmt::Debug for ModbusBus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ModbusBus")
+            .field("modbus_device", &self.modbus_device)
+            .field("tcp", &self.is_tcp())
+            .field("emulated", &self.is_emulated())
+            .field("serial_parameters", &self.serial_parameters)
+            .field("connected", &self.is_connected())
+            .field("connect_count", &self.connect_count)
+            .finish()
+    }
+}
+
+#[test]
+fn test_parse_tcp_device() {
+    assert_eq!(parse_tcp_device("/dev/ttyS1"), None);
+    assert_eq!(parse_tcp_device("tcp://192.168.1.20:502"), Some(("192.168.1.20".to_string(), "502".to_string())));
+    assert_eq!(parse_tcp_device("tcp://192.168.1.20"), Some(("192.168.1.20".to_string(), "502".to_string())));
+    assert_eq!(parse_tcp_device("tcp://gateway.local:1502"), Some(("gateway.local".to_string(), "1502".to_string())));
+    assert_eq!(parse_tcp_device("tcp://[::1]:1502"), Some(("::1".to_string(), "1502".to_string())));
+    assert_eq!(parse_tcp_device("tcp://[::1]"), Some(("::1".to_string(), "502".to_string())));
+}
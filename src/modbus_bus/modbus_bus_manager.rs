@@ -0,0 +1,186 @@
+//! Verwaltung der Modbus Leitungen
+//!
+use errors::*;
+use modbus_bus::{ModbusBus, PollRequest, PollResult, SerialParameters, TrafficRecord, TrafficRecorder, TrafficReplay};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+
+/// Hält für jede Modbus Leitung (`modbus_device`) genau einen [`ModbusBus`](struct.ModbusBus.html)
+///
+/// Alle Kombisensoren an der selben Leitung bekommen den selben Bus, und damit die selbe offene Verbindung.
+///
+/// Die Liste der Busse ist in ein Mutex gekapselt, so das neue Busse auch bei immutablen Referenzen auf den
+/// `ModbusBusManager` angelegt werden können. Das gilt auch für die Aufzeichnung und das Abspielen der
+/// Kommunikation, siehe [`TrafficRecorder`](struct.TrafficRecorder.html) und
+/// [`TrafficReplay`](struct.TrafficReplay.html).
+#[derive(Debug)]
+#[derive(Default)]
+pub struct ModbusBusManager {
+    buses: Mutex<HashMap<String, Arc<Mutex<ModbusBus>>>>,
+    traffic_recorder: Mutex<Option<TrafficRecorder>>,
+    traffic_replay: Mutex<Option<TrafficReplay>>,
+}
+
+impl ModbusBusManager {
+    /// Erstellt einen neuen, leeren `ModbusBusManager`
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::ModbusBusManager;
+    ///
+    /// let bus_manager = ModbusBusManager::new();
+    /// assert_eq!(bus_manager.get_modbus_devices().unwrap().len(), 0);
+    /// ```
+    pub fn new() -> Self {
+        ModbusBusManager {
+            buses: Mutex::new(HashMap::new()),
+            traffic_recorder: Mutex::new(None),
+            traffic_replay: Mutex::new(None),
+        }
+    }
+
+    /// Liefert den Bus der gegebenen Leitung
+    ///
+    /// Existiert für die Leitung noch kein Bus, wird er mit den gegebenen Parametern angelegt. Ein bereits
+    /// existierender Bus behält seine Parameter, diese werden vor einer Abfrage mit
+    /// [`set_serial_parameters`](struct.ModbusBus.html#method.set_serial_parameters) angepasst.
+    ///
+    /// # Parameters
+    ///
+    /// * `modbus_device`   - Schnittstelle der Leitung, z.B. `/dev/ttyS1`
+    /// * `serial_parameters`   - Parameter der Leitung, wenn der Bus neu angelegt wird
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::ModbusBusManager;
+    /// use xmz_mod_touch_server::modbus_bus::SerialParameters;
+    /// use std::sync::Arc;
+    ///
+    /// let bus_manager = ModbusBusManager::new();
+    /// let bus1 = bus_manager.get_bus("/dev/ttyS1", SerialParameters::new()).unwrap();
+    /// let bus2 = bus_manager.get_bus("/dev/ttyS1", SerialParameters::new()).unwrap();
+    /// let bus3 = bus_manager.get_bus("/dev/ttyUSB0", SerialParameters::new()).unwrap();
+    ///
+    /// assert!(Arc::ptr_eq(&bus1, &bus2));
+    /// assert!(!Arc::ptr_eq(&bus1, &bus3));
+    /// ```
+    pub fn get_bus(&self, modbus_device: &str, serial_parameters: SerialParameters) -> Result<Arc<Mutex<ModbusBus>>> {
+        if let Ok(mut buses) = self.buses.lock() {
+            let bus = buses.entry(modbus_device.to_string())
+                .or_insert_with(|| Arc::new(Mutex::new(ModbusBus::new(modbus_device, serial_parameters))));
+
+            Ok(bus.clone())
+        } else {
+            bail!("Could not lock buses member")
+        }
+    }
+
+    /// Liefert die Schnittstellen aller bekannten Leitungen
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::ModbusBusManager;
+    /// use xmz_mod_touch_server::modbus_bus::SerialParameters;
+    ///
+    /// let bus_manager = ModbusBusManager::new();
+    /// bus_manager.get_bus("/dev/ttyS1", SerialParameters::new()).unwrap();
+    ///
+    /// assert_eq!(bus_manager.get_modbus_devices().unwrap(), vec!["/dev/ttyS1".to_string()]);
+    /// ```
+    pub fn get_modbus_devices(&self) -> Result<Vec<String>> {
+        if let Ok(buses) = self.buses.lock() {
+            Ok(buses.keys().cloned().collect())
+        } else {
+            bail!("Could not lock buses member")
+        }
+    }
+
+    /// Startet oder beendet die Aufzeichnung der Abfragen
+    ///
+    /// # Parameters
+    ///
+    /// * `traffic_recorder`    - Ziel der Aufzeichnung, `None` beendet die Aufzeichnung
+    pub fn set_traffic_recorder(&self, traffic_recorder: Option<TrafficRecorder>) -> Result<()> {
+        if let Ok(mut current) = self.traffic_recorder.lock() {
+            *current = traffic_recorder;
+            Ok(())
+        } else {
+            bail!("Could not lock traffic_recorder member")
+        }
+    }
+
+    /// Startet oder beendet das Abspielen einer Aufzeichnung
+    ///
+    /// Solange eine Aufzeichnung abgespielt wird, werden die Abfragen der Kombisensoren aus der Aufzeichnung
+    /// beantwortet. Die Leitungen werden dafür nicht angesprochen.
+    ///
+    /// # Parameters
+    ///
+    /// * `traffic_replay`  - abzuspielende Aufzeichnung, `None` beendet das Abspielen
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::ModbusBusManager;
+    /// use xmz_mod_touch_server::modbus_bus::{PollRequest, SerialParameters, TrafficReplay};
+    ///
+    /// let bus_manager = ModbusBusManager::new();
+    /// let poll_request = PollRequest::new("sim:///dev/ttyUSB0", SerialParameters::new(), 247, 30, false);
+    ///
+    /// bus_manager.set_traffic_replay(Some(TrafficReplay::new(vec![]))).unwrap();
+    /// assert!(bus_manager.is_replaying());
+    /// // Die Aufzeichnung enthält keine Daten für diesen Kombisensor, der Emulator wird nicht gefragt
+    /// assert!(poll_request.execute(&bus_manager).into_result().is_err());
+    ///
+    /// bus_manager.set_traffic_replay(None).unwrap();
+    /// assert!(poll_request.execute(&bus_manager).into_result().is_ok());
+    /// ```
+    pub fn set_traffic_replay(&self, traffic_replay: Option<TrafficReplay>) -> Result<()> {
+        if let Ok(mut current) = self.traffic_replay.lock() {
+            *current = traffic_replay;
+            Ok(())
+        } else {
+            bail!("Could not lock traffic_replay member")
+        }
+    }
+
+    /// Liefert `true` wenn eine Aufzeichnung abgespielt wird
+    pub fn is_replaying(&self) -> bool {
+        self.traffic_replay.lock().map(|replay| replay.is_some()).unwrap_or(false)
+    }
+
+    /// Schreibt eine ausgeführte Abfrage in die Aufzeichnung, wenn eine Aufzeichnung läuft
+    ///
+    /// Fehler beim Schreiben werden nur protokolliert, die Abfrage selbst ist davon nicht betroffen.
+    ///
+    /// # Parameters
+    ///
+    /// * `poll_request`    - die Abfrage
+    /// * `poll_result`     - deren Ergebnis
+    pub fn record(&self, poll_request: &PollRequest, poll_result: &PollResult) {
+        if let Ok(traffic_recorder) = self.traffic_recorder.lock() {
+            if let Some(ref traffic_recorder) = *traffic_recorder {
+                if let Err(e) = traffic_recorder.record(&TrafficRecord::from_poll(poll_request, poll_result)) {
+                    warn!("Aufzeichnung nach {} fehlgeschlagen: {}", traffic_recorder.get_path(), e);
+                }
+            }
+        }
+    }
+
+    /// Beantwortet eine Abfrage aus der Aufzeichnung, wenn eine Aufzeichnung abgespielt wird
+    ///
+    /// # Parameters
+    ///
+    /// * `poll_request`    - die Abfrage
+    pub fn replay(&self, poll_request: &PollRequest) -> Option<PollResult> {
+        match self.traffic_replay.lock() {
+            Ok(traffic_replay) => traffic_replay.as_ref()
+                .map(|replay| replay.poll(&poll_request.get_modbus_device(), poll_request.get_modbus_address())),
+            Err(_) => None,
+        }
+    }
+}
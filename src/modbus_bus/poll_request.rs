@@ -0,0 +1,170 @@
+//! Abfrage eines Modbus Teilnehmers, getrennt von dessen Auswertung
+//!
+use errors::*;
+use modbus_bus::{ModbusBusManager, SerialParameters};
+use std::time::{Duration, Instant};
+
+
+/// Beschreibt die Abfrage der Register eines Kombisensors
+///
+/// Die Abfrage enthält alles was für die Modbus Kommunikation nötig ist. Sie kann deshalb ohne Zugriff auf den
+/// Kombisensor (und damit ohne den Lock des Servers) ausgeführt werden. Das Ergebnis, ein
+/// [`PollResult`](struct.PollResult.html), wird anschließend vom Kombisensor ausgewertet.
+///
+/// # Examples
+///
+/// ```rust
+/// use xmz_mod_touch_server::ModbusBusManager;
+/// use xmz_mod_touch_server::modbus_bus::{PollRequest, SerialParameters};
+///
+/// let bus_manager = ModbusBusManager::new();
+/// let poll_request = PollRequest::new("sim:///dev/ttyUSB0", SerialParameters::new(), 247, 30, false);
+///
+/// let poll_result = poll_request.execute(&bus_manager);
+/// assert_eq!(poll_result.into_result().unwrap().len(), 30);
+/// ```
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+pub struct PollRequest {
+    modbus_device: String,
+    serial_parameters: SerialParameters,
+    modbus_address: u8,
+    num_registers: u16,
+    modbus_debug: bool,
+}
+
+impl PollRequest {
+    /// Erstellt eine neue Abfrage
+    ///
+    /// # Parameters
+    ///
+    /// * `modbus_device`       - Schnittstelle der Leitung, z.B. `/dev/ttyS1`, `tcp://...` oder `sim://...`
+    /// * `serial_parameters`   - Parameter der Leitung
+    /// * `modbus_address`      - Modbus Adresse des Teilnehmers
+    /// * `num_registers`       - Anzahl der Register, ab Adresse 0
+    /// * `modbus_debug`        - Modbus Debug Modus für diese Abfrage einschalten
+    pub fn new(modbus_device: &str, serial_parameters: SerialParameters, modbus_address: u8, num_registers: u16, modbus_debug: bool) -> Self {
+        PollRequest {
+            modbus_device: modbus_device.to_string(),
+            serial_parameters: serial_parameters,
+            modbus_address: modbus_address,
+            num_registers: num_registers,
+            modbus_debug: modbus_debug,
+        }
+    }
+
+    /// Schnittstelle der Leitung
+    pub fn get_modbus_device(&self) -> String {
+        self.modbus_device.clone()
+    }
+
+    /// Modbus Adresse des Teilnehmers
+    pub fn get_modbus_address(&self) -> u8 {
+        self.modbus_address
+    }
+
+    /// Anzahl der abgefragten Register
+    pub fn get_num_registers(&self) -> u16 {
+        self.num_registers
+    }
+
+    /// Führt die Abfrage über den Bus der Leitung aus
+    ///
+    /// Der Bus ist nur für die Dauer der Abfrage gesperrt. Abfragen an verschiedenen Leitungen können deshalb
+    /// parallel, aus verschiedenen Threads, ausgeführt werden.
+    ///
+    /// Ist am `bus_manager` eine Aufzeichnung aktiv, wird das Ergebnis aufgezeichnet. Wird eine Aufzeichnung
+    /// abgespielt, liefert diese das Ergebnis, siehe [`TrafficReplay`](struct.TrafficReplay.html).
+    ///
+    /// # Parameters
+    ///
+    /// * `bus_manager` - Verwaltung der Modbus Leitungen
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::ModbusBusManager;
+    /// use xmz_mod_touch_server::modbus_bus::{PollRequest, SerialParameters};
+    ///
+    /// let bus_manager = ModbusBusManager::new();
+    /// let poll_request = PollRequest::new("/dev/nicht_vorhanden", SerialParameters::new(), 247, 30, false);
+    ///
+    /// assert!(poll_request.execute(&bus_manager).into_result().is_err());
+    /// ```
+    pub fn execute(&self, bus_manager: &ModbusBusManager) -> PollResult {
+        // Beim Abspielen einer Aufzeichnung wird der Bus nicht angesprochen
+        if let Some(poll_result) = bus_manager.replay(self) {
+            return poll_result;
+        }
+
+        let mut latency = Duration::from_secs(0);
+        let registers = match bus_manager.get_bus(&self.modbus_device, self.serial_parameters.clone()) {
+            Ok(bus) => {
+                if let Ok(mut bus) = bus.lock() {
+                    // Andere Kombisensoren an der Leitung können andere Parameter verwenden
+                    bus.set_serial_parameters(&self.serial_parameters);
+
+                    // Die Wartezeit auf den Lock des Busses zählt nicht zur Antwortzeit
+                    let started = Instant::now();
+                    let registers = bus.read_registers(self.modbus_address, 0, self.num_registers, self.modbus_debug);
+                    latency = started.elapsed();
+                    registers
+                } else {
+                    Err(format!("Could not lock Modbus Bus: {}", self.modbus_device).into())
+                }
+            }
+            Err(e) => Err(e),
+        };
+
+        let poll_result = PollResult {
+            registers: registers,
+            latency: latency,
+        };
+        bus_manager.record(self, &poll_result);
+
+        poll_result
+    }
+}
+
+/// Ergebnis einer [`PollRequest`](struct.PollRequest.html)
+#[derive(Debug)]
+pub struct PollResult {
+    registers: Result<Vec<u16>>,
+    latency: Duration,
+}
+
+impl PollResult {
+    /// Erstellt ein Ergebnis, z.B. aus einer aufgezeichneten Abfrage
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::modbus_bus::PollResult;
+    /// use std::time::Duration;
+    ///
+    /// let poll_result = PollResult::new(Ok(vec![0, 14, 0, 247]), Duration::from_millis(20));
+    /// assert_eq!(poll_result.get_latency(), Duration::from_millis(20));
+    /// ```
+    pub fn new(registers: Result<Vec<u16>>, latency: Duration) -> Self {
+        PollResult {
+            registers: registers,
+            latency: latency,
+        }
+    }
+
+    /// Dauer der Abfrage
+    pub fn get_latency(&self) -> Duration {
+        self.latency
+    }
+
+    /// Die gelesenen Register, oder der Fehler der Abfrage
+    pub fn get_registers(&self) -> &Result<Vec<u16>> {
+        &self.registers
+    }
+
+    /// Liefert die gelesenen Register, oder den Fehler der Abfrage
+    pub fn into_result(self) -> Result<Vec<u16>> {
+        self.registers
+    }
+}
@@ -0,0 +1,301 @@
+//! Parameter einer Modbus RTU Leitung
+//!
+
+
+/// RTS Modus der seriellen Schnittstelle
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub enum RtsMode {
+    // RTS wird nicht verwendet
+    Off,
+    // RTS ist während des Sendens high
+    Up,
+    // RTS ist während des Sendens low, wird auf der 'xMZ-Mod-Touch'-Hardware benötigt
+    Down,
+}
+
+/// Parameter einer Modbus Leitung
+///
+/// Baudrate, Parität, Daten- und Stop Bits sowie der RTS Modus gelten nur für Modbus RTU. Die Timeouts werden vor
+/// jeder Abfrage gesetzt und gelten auch für Modbus TCP.
+///
+/// Die Standardwerte sind 9600 Baud, 'N', 8, 1, ohne RTS und 500ms Timeouts. In der Konfiguration eines
+/// Kombisensors werden nur einzelne Werte überschrieben, siehe
+/// [`SerialParameterOverrides`](struct.SerialParameterOverrides.html).
+///
+/// # Examples
+///
+/// ```rust
+/// use xmz_mod_touch_server::modbus_bus::{RtsMode, SerialParameters};
+///
+/// let serial_parameters = SerialParameters::new();
+/// assert_eq!(serial_parameters.get_baud(), 9600);
+/// assert_eq!(serial_parameters.get_rts_mode(), RtsMode::Off);
+/// ```
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct SerialParameters {
+    baud: i32,
+    parity: char,
+    data_bits: i32,
+    stop_bits: i32,
+    rts_mode: RtsMode,
+    // Verzögerung vor und nach dem Senden, in Mikrosekunden. Ohne Wert wird der libmodbus Standard verwendet.
+    rts_delay_us: Option<i32>,
+    // Maximale Wartezeit auf die Antwort eines Teilnehmers, in Millisekunden
+    response_timeout_ms: u32,
+    // Maximale Wartezeit zwischen zwei Bytes einer Antwort, in Millisekunden
+    byte_timeout_ms: u32,
+}
+
+impl SerialParameters {
+    /// Erstellt die Standard Parameter, 9600 Baud, 'N', 8, 1, ohne RTS
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::modbus_bus::SerialParameters;
+    ///
+    /// let serial_parameters = SerialParameters::new();
+    /// assert_eq!(serial_parameters.get_parity(), 'N');
+    /// ```
+    pub fn new() -> Self {
+        SerialParameters {
+            baud: 9600,
+            parity: 'N',
+            data_bits: 8,
+            stop_bits: 1,
+            rts_mode: RtsMode::Off,
+            rts_delay_us: None,
+            response_timeout_ms: 500,
+            byte_timeout_ms: 500,
+        }
+    }
+
+    /// Erstellt die Standard Parameter mit dem gegebenen RTS Modus
+    ///
+    /// # Parameters
+    ///
+    /// * `rts_mode`    - RTS Modus der Schnittstelle
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::modbus_bus::{RtsMode, SerialParameters};
+    ///
+    /// let serial_parameters = SerialParameters::new_with_rts_mode(RtsMode::Down);
+    /// assert_eq!(serial_parameters.get_rts_mode(), RtsMode::Down);
+    /// ```
+    pub fn new_with_rts_mode(rts_mode: RtsMode) -> Self {
+        SerialParameters {
+            rts_mode: rts_mode,
+            ..Default::default()
+        }
+    }
+
+    /// Baudrate
+    pub fn get_baud(&self) -> i32 {
+        self.baud
+    }
+
+    /// Setzt die Baudrate
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::modbus_bus::SerialParameters;
+    ///
+    /// let mut serial_parameters = SerialParameters::new();
+    /// serial_parameters.set_baud(19200);
+    /// assert_eq!(serial_parameters.get_baud(), 19200);
+    /// ```
+    pub fn set_baud(&mut self, baud: i32) {
+        self.baud = baud;
+    }
+
+    /// Parität, 'N' (keine), 'E' (gerade) oder 'O' (ungerade)
+    pub fn get_parity(&self) -> char {
+        self.parity
+    }
+
+    /// Setzt die Parität
+    pub fn set_parity(&mut self, parity: char) {
+        self.parity = parity;
+    }
+
+    /// Anzahl Daten Bits
+    pub fn get_data_bits(&self) -> i32 {
+        self.data_bits
+    }
+
+    /// Setzt die Anzahl der Daten Bits
+    pub fn set_data_bits(&mut self, data_bits: i32) {
+        self.data_bits = data_bits;
+    }
+
+    /// Anzahl Stop Bits
+    pub fn get_stop_bits(&self) -> i32 {
+        self.stop_bits
+    }
+
+    /// Setzt die Anzahl der Stop Bits
+    pub fn set_stop_bits(&mut self, stop_bits: i32) {
+        self.stop_bits = stop_bits;
+    }
+
+    /// RTS Modus
+    pub fn get_rts_mode(&self) -> RtsMode {
+        self.rts_mode
+    }
+
+    /// Setzt den RTS Modus
+    pub fn set_rts_mode(&mut self, rts_mode: RtsMode) {
+        self.rts_mode = rts_mode;
+    }
+
+    /// RTS Verzögerung in Mikrosekunden
+    pub fn get_rts_delay_us(&self) -> Option<i32> {
+        self.rts_delay_us
+    }
+
+    /// Setzt die RTS Verzögerung in Mikrosekunden, `None` verwendet den libmodbus Standard
+    pub fn set_rts_delay_us(&mut self, rts_delay_us: Option<i32>) {
+        self.rts_delay_us = rts_delay_us;
+    }
+
+    /// Response Timeout in Millisekunden
+    pub fn get_response_timeout_ms(&self) -> u32 {
+        self.response_timeout_ms
+    }
+
+    /// Setzt den Response Timeout in Millisekunden
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::modbus_bus::SerialParameters;
+    ///
+    /// let mut serial_parameters = SerialParameters::new();
+    /// serial_parameters.set_response_timeout_ms(1500);
+    /// assert_eq!(serial_parameters.get_response_timeout_ms(), 1500);
+    /// ```
+    pub fn set_response_timeout_ms(&mut self, response_timeout_ms: u32) {
+        self.response_timeout_ms = response_timeout_ms;
+    }
+
+    /// Byte Timeout in Millisekunden
+    pub fn get_byte_timeout_ms(&self) -> u32 {
+        self.byte_timeout_ms
+    }
+
+    /// Setzt den Byte Timeout in Millisekunden
+    pub fn set_byte_timeout_ms(&mut self, byte_timeout_ms: u32) {
+        self.byte_timeout_ms = byte_timeout_ms;
+    }
+
+    /// Verwenden beide Parameter die selben Leitungseinstellungen?
+    ///
+    /// Verglichen werden alle Werte, die beim Öffnen der Schnittstelle gesetzt werden. Die Timeouts werden
+    /// vor jeder Abfrage gesetzt und spielen deshalb keine Rolle.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::modbus_bus::SerialParameters;
+    ///
+    /// let serial_parameters = SerialParameters::new();
+    /// let mut other = SerialParameters::new();
+    /// other.set_response_timeout_ms(2000);
+    /// assert!(serial_parameters.is_same_line(&other));
+    ///
+    /// other.set_baud(19200);
+    /// assert!(!serial_parameters.is_same_line(&other));
+    /// ```
+    pub fn is_same_line(&self, other: &SerialParameters) -> bool {
+        self.baud == other.baud &&
+        self.parity == other.parity &&
+        self.data_bits == other.data_bits &&
+        self.stop_bits == other.stop_bits &&
+        self.rts_mode == other.rts_mode &&
+        self.rts_delay_us == other.rts_delay_us
+    }
+}
+
+impl Default for SerialParameters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Abweichende Parameter einer Modbus Leitung, wie sie in der Konfiguration eines Kombisensors stehen
+///
+/// Nur die angegebenen Werte werden überschrieben, alle anderen kommen aus den Standardwerten des Kombisensor
+/// Typs. So bleibt z.B. bei einem `RAGas` Kombisensor mit `{"baud": 19200}` der RTS Modus `Down` erhalten.
+///
+/// # Examples
+///
+/// ```rust
+/// use xmz_mod_touch_server::modbus_bus::{RtsMode, SerialParameterOverrides, SerialParameters};
+///
+/// let overrides = SerialParameterOverrides { baud: Some(19200), ..Default::default() };
+/// let serial_parameters = overrides.apply_to(SerialParameters::new_with_rts_mode(RtsMode::Down));
+/// assert_eq!(serial_parameters.get_baud(), 19200);
+/// assert_eq!(serial_parameters.get_rts_mode(), RtsMode::Down);
+/// ```
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(Eq, PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct SerialParameterOverrides {
+    pub baud: Option<i32>,
+    pub parity: Option<char>,
+    pub data_bits: Option<i32>,
+    pub stop_bits: Option<i32>,
+    pub rts_mode: Option<RtsMode>,
+    pub rts_delay_us: Option<i32>,
+    pub response_timeout_ms: Option<u32>,
+    pub byte_timeout_ms: Option<u32>,
+}
+
+impl SerialParameterOverrides {
+    /// Überschreibt die angegebenen Werte in den Parametern
+    ///
+    /// # Parameters
+    ///
+    /// * `serial_parameters`   - Standardwerte, z.B. des Kombisensor Typs
+    pub fn apply_to(&self, serial_parameters: SerialParameters) -> SerialParameters {
+        let mut serial_parameters = serial_parameters;
+        if let Some(baud) = self.baud { serial_parameters.set_baud(baud); }
+        if let Some(parity) = self.parity { serial_parameters.set_parity(parity); }
+        if let Some(data_bits) = self.data_bits { serial_parameters.set_data_bits(data_bits); }
+        if let Some(stop_bits) = self.stop_bits { serial_parameters.set_stop_bits(stop_bits); }
+        if let Some(rts_mode) = self.rts_mode { serial_parameters.set_rts_mode(rts_mode); }
+        if self.rts_delay_us.is_some() { serial_parameters.set_rts_delay_us(self.rts_delay_us); }
+        if let Some(response_timeout_ms) = self.response_timeout_ms { serial_parameters.set_response_timeout_ms(response_timeout_ms); }
+        if let Some(byte_timeout_ms) = self.byte_timeout_ms { serial_parameters.set_byte_timeout_ms(byte_timeout_ms); }
+
+        serial_parameters
+    }
+}
+
+impl From<SerialParameters> for SerialParameterOverrides {
+    fn from(serial_parameters: SerialParameters) -> Self {
+        SerialParameterOverrides {
+            baud: Some(serial_parameters.baud),
+            parity: Some(serial_parameters.parity),
+            data_bits: Some(serial_parameters.data_bits),
+            stop_bits: Some(serial_parameters.stop_bits),
+            rts_mode: Some(serial_parameters.rts_mode),
+            rts_delay_us: serial_parameters.rts_delay_us,
+            response_timeout_ms: Some(serial_parameters.response_timeout_ms),
+            byte_timeout_ms: Some(serial_parameters.byte_timeout_ms),
+        }
+    }
+}
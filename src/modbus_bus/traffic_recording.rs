@@ -0,0 +1,512 @@
+//! Aufzeichnen und Abspielen der Modbus Kommunikation
+//!
+//! Jede Abfrage eines Kombisensors (siehe [`PollRequest`](struct.PollRequest.html)) kann mit Zeitstempel, Leitung
+//! und Modbus Adresse in eine Datei geschrieben werden. Pro Abfrage wird eine Zeile geschrieben, die Felder sind
+//! durch Tabulatoren getrennt:
+//!
+//! ```text
+//! 2017-06-01T12:00:00.123+00:00	/dev/ttyS1	1	32	0,14,0,1,...
+//! 2017-06-01T12:00:01.125+00:00	/dev/ttyS1	1	500	!timeout
+//! ```
+//!
+//! Das letzte Feld enthält die gelesenen Register, oder nach einem `!` die Art des Fehlers (`timeout`, `crc`,
+//! `exception` oder `other`).
+//!
+//! Eine solche Aufzeichnung kann mit [`TrafficReplay`](struct.TrafficReplay.html) wieder abgespielt werden, z.B.
+//! um einen Alarmverlauf oder einen flatternden Kabelbruch eines Kunden auf einem Schreibtisch nachzustellen.
+//!
+//! Die Datei der Aufzeichnung wird höchstens `max_bytes` groß, danach wird sie in `<Datei>.1` umbenannt und eine
+//! neue Datei begonnen. Auf dem Gerät belegt die Aufzeichnung so höchstens den doppelten Platz.
+//!
+use ::chrono::{DateTime, Duration, Utc};
+use errors::*;
+use modbus_bus::{FailureKind, PollRequest, PollResult};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, LineWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+
+/// Standard Größe (in Bytes), ab der die Datei der Aufzeichnung nach `<Datei>.1` umbenannt wird
+pub const TRAFFIC_RECORDING_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+
+/// Eine aufgezeichnete Abfrage
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate chrono;
+/// # extern crate xmz_mod_touch_server;
+/// use chrono::Utc;
+/// use xmz_mod_touch_server::modbus_bus::{FailureKind, TrafficRecord};
+///
+/// # fn main() {
+/// let record = TrafficRecord::new(Utc::now(), "/dev/ttyS1", 1, 32, Ok(vec![0, 14, 0, 1]));
+/// let line = record.to_line();
+/// assert_eq!(TrafficRecord::parse_line(&line).unwrap(), record);
+///
+/// let record = TrafficRecord::new(Utc::now(), "/dev/ttyS1", 1, 500, Err(FailureKind::Timeout));
+/// assert!(record.to_line().ends_with("\t!timeout"));
+/// # }
+/// ```
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+pub struct TrafficRecord {
+    timestamp: DateTime<Utc>,
+    modbus_device: String,
+    modbus_address: u8,
+    latency_ms: u32,
+    registers: ::std::result::Result<Vec<u16>, FailureKind>,
+}
+
+impl TrafficRecord {
+    /// Erstellt eine neue Aufzeichnung einer Abfrage
+    ///
+    /// # Parameters
+    ///
+    /// * `timestamp`       - Zeitpunkt der Abfrage
+    /// * `modbus_device`   - Schnittstelle der Leitung
+    /// * `modbus_address`  - Modbus Adresse des Teilnehmers
+    /// * `latency_ms`      - Dauer der Abfrage in Millisekunden
+    /// * `registers`       - gelesene Register, oder die Art des Fehlers
+    pub fn new(timestamp: DateTime<Utc>, modbus_device: &str, modbus_address: u8, latency_ms: u32,
+               registers: ::std::result::Result<Vec<u16>, FailureKind>) -> Self {
+        TrafficRecord {
+            timestamp: timestamp,
+            modbus_device: modbus_device.to_string(),
+            modbus_address: modbus_address,
+            latency_ms: latency_ms,
+            registers: registers,
+        }
+    }
+
+    /// Zeichnet eine ausgeführte Abfrage auf
+    ///
+    /// # Parameters
+    ///
+    /// * `poll_request`    - die Abfrage
+    /// * `poll_result`     - deren Ergebnis
+    pub fn from_poll(poll_request: &PollRequest, poll_result: &PollResult) -> Self {
+        let latency = poll_result.get_latency();
+        let latency_ms = latency.as_secs() as u32 * 1000 + latency.subsec_nanos() / 1_000_000;
+
+        TrafficRecord {
+            timestamp: Utc::now(),
+            modbus_device: poll_request.get_modbus_device(),
+            modbus_address: poll_request.get_modbus_address(),
+            latency_ms: latency_ms,
+            registers: match *poll_result.get_registers() {
+                Ok(ref registers) => Ok(registers.clone()),
+                Err(ref e) => Err(FailureKind::from_error(e)),
+            },
+        }
+    }
+
+    /// Zeitpunkt der Abfrage
+    pub fn get_timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    /// Schnittstelle der Leitung
+    pub fn get_modbus_device(&self) -> String {
+        self.modbus_device.clone()
+    }
+
+    /// Modbus Adresse des Teilnehmers
+    pub fn get_modbus_address(&self) -> u8 {
+        self.modbus_address
+    }
+
+    /// Gelesene Register, oder die Art des Fehlers
+    pub fn get_registers(&self) -> &::std::result::Result<Vec<u16>, FailureKind> {
+        &self.registers
+    }
+
+    /// Erzeugt aus der Aufzeichnung wieder das Ergebnis der Abfrage
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// extern crate chrono;
+    /// # extern crate xmz_mod_touch_server;
+    /// use chrono::Utc;
+    /// use std::time::Duration;
+    /// use xmz_mod_touch_server::modbus_bus::{FailureKind, TrafficRecord};
+    ///
+    /// # fn main() {
+    /// let record = TrafficRecord::new(Utc::now(), "/dev/ttyS1", 1, 500, Err(FailureKind::Crc));
+    /// let poll_result = record.to_poll_result();
+    ///
+    /// assert_eq!(poll_result.get_latency(), Duration::from_millis(500));
+    /// assert_eq!(FailureKind::from_error(&poll_result.into_result().unwrap_err()), FailureKind::Crc);
+    /// # }
+    /// ```
+    pub fn to_poll_result(&self) -> PollResult {
+        let registers = match self.registers {
+            Ok(ref registers) => Ok(registers.clone()),
+            Err(ref kind) => Err(kind.to_error()),
+        };
+
+        PollResult::new(registers, ::std::time::Duration::from_millis(self.latency_ms as u64))
+    }
+
+    /// Liefert die Zeile, mit der die Abfrage in die Aufzeichnung geschrieben wird
+    pub fn to_line(&self) -> String {
+        let registers = match self.registers {
+            Ok(ref registers) => registers.iter().map(|register| register.to_string()).collect::<Vec<_>>().join(","),
+            Err(FailureKind::Timeout) => "!timeout".to_string(),
+            Err(FailureKind::Crc) => "!crc".to_string(),
+            Err(FailureKind::Exception) => "!exception".to_string(),
+            Err(FailureKind::Other) => "!other".to_string(),
+        };
+
+        format!("{}\t{}\t{}\t{}\t{}", self.timestamp.to_rfc3339(), self.modbus_device, self.modbus_address,
+            self.latency_ms, registers)
+    }
+
+    /// Liest eine Zeile einer Aufzeichnung
+    ///
+    /// # Return values
+    ///
+    /// Liefert einen Fehler wenn die Zeile nicht dem Format der Aufzeichnung entspricht.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::modbus_bus::{FailureKind, TrafficRecord};
+    ///
+    /// let record = TrafficRecord::parse_line("2017-06-01T12:00:00+00:00\t/dev/ttyS1\t3\t20\t!exception").unwrap();
+    /// assert_eq!(record.get_modbus_address(), 3);
+    /// assert_eq!(record.get_registers(), &Err(FailureKind::Exception));
+    ///
+    /// assert!(TrafficRecord::parse_line("2017-06-01T12:00:00+00:00\t/dev/ttyS1\t3").is_err());
+    /// assert!(TrafficRecord::parse_line("gestern\t/dev/ttyS1\t3\t20\t1,2,3").is_err());
+    /// ```
+    pub fn parse_line(line: &str) -> Result<Self> {
+        let fields: Vec<&str> = line.lines().next().unwrap_or("").split('\t').collect();
+        if fields.len() != 5 {
+            bail!("Invalid traffic record, expected 5 fields: {}", line)
+        }
+
+        let timestamp = match DateTime::parse_from_rfc3339(fields[0]) {
+            Ok(timestamp) => timestamp.with_timezone(&Utc),
+            Err(e) => bail!("Invalid traffic record timestamp {}: {}", fields[0], e),
+        };
+        let modbus_address = match fields[2].parse::<u8>() {
+            Ok(modbus_address) => modbus_address,
+            Err(e) => bail!("Invalid traffic record Modbus address {}: {}", fields[2], e),
+        };
+        let latency_ms = match fields[3].parse::<u32>() {
+            Ok(latency_ms) => latency_ms,
+            Err(e) => bail!("Invalid traffic record latency {}: {}", fields[3], e),
+        };
+        let registers = match fields[4] {
+            "!timeout" => Err(FailureKind::Timeout),
+            "!crc" => Err(FailureKind::Crc),
+            "!exception" => Err(FailureKind::Exception),
+            "!other" => Err(FailureKind::Other),
+            "" => Ok(vec![]),
+            registers => {
+                let mut values = vec![];
+                for register in registers.split(',') {
+                    match register.parse::<u16>() {
+                        Ok(value) => values.push(value),
+                        Err(e) => bail!("Invalid traffic record register {}: {}", register, e),
+                    }
+                }
+                Ok(values)
+            }
+        };
+
+        Ok(TrafficRecord::new(timestamp, fields[1], modbus_address, latency_ms, registers))
+    }
+}
+
+/// Schreibt die Abfragen der Kombisensoren in eine Datei
+///
+/// Wird am [`ModbusBusManager`](struct.ModbusBusManager.html) gesetzt, siehe
+/// [`set_traffic_recorder`](struct.ModbusBusManager.html#method.set_traffic_recorder). Jede Zeile wird sofort
+/// geschrieben, nach einem Absturz des Servers fehlt höchstens die letzte Abfrage.
+///
+/// Würde die Datei größer als `max_bytes`, wird sie nach `<Datei>.1` umbenannt (eine ältere `.1` Datei wird
+/// überschrieben) und eine neue Datei begonnen. Schlägt das fehl, endet die Aufzeichnung mit einer Warnung.
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate chrono;
+/// # extern crate xmz_mod_touch_server;
+/// use chrono::Utc;
+/// use std::env;
+/// use std::fs;
+/// use xmz_mod_touch_server::modbus_bus::{TrafficRecord, TrafficRecorder};
+///
+/// # fn main() {
+/// let path = env::temp_dir().join("xmz_doctest_traffic_recorder.log").to_string_lossy().into_owned();
+/// # let _ = fs::remove_file(&path);
+/// let record = TrafficRecord::new(Utc::now(), "/dev/ttyS1", 1, 32, Ok(vec![0, 14, 0, 1]));
+///
+/// // Platz für genau eine Zeile
+/// let recorder = TrafficRecorder::create(&path, record.to_line().len() as u64 + 1).unwrap();
+/// recorder.record(&record).unwrap();
+/// recorder.record(&record).unwrap();
+///
+/// assert!(fs::metadata(format!("{}.1", path)).is_ok());
+/// assert!(recorder.is_recording());
+/// # let _ = fs::remove_file(&path);
+/// # let _ = fs::remove_file(format!("{}.1", path));
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TrafficRecorder {
+    path: String,
+    max_bytes: u64,
+    writer: Mutex<TrafficRecorderFile>,
+}
+
+// Geöffnete Datei der Aufzeichnung und deren Größe, ohne Datei ist die Aufzeichnung beendet
+#[derive(Debug)]
+struct TrafficRecorderFile {
+    writer: Option<LineWriter<File>>,
+    bytes: u64,
+}
+
+impl TrafficRecorder {
+    /// Öffnet die Datei der Aufzeichnung
+    ///
+    /// Eine existierende Datei wird nicht überschrieben, die neuen Abfragen werden angehängt. Ist sie bereits
+    /// `max_bytes` groß, wird sie zuerst nach `<Datei>.1` umbenannt.
+    ///
+    /// # Parameters
+    ///
+    /// * `path`        - Pfad der Datei
+    /// * `max_bytes`   - maximale Größe der Datei, z.B. `TRAFFIC_RECORDING_MAX_BYTES`
+    pub fn create(path: &str, max_bytes: u64) -> Result<Self> {
+        if max_bytes == 0 {
+            bail!("Invalid maximum size of traffic recording {}: 0 bytes", path)
+        }
+        let recorder = TrafficRecorder {
+            path: path.to_string(),
+            max_bytes: max_bytes,
+            writer: Mutex::new(TrafficRecorderFile { writer: None, bytes: 0 }),
+        };
+
+        let bytes = match fs::metadata(path) {
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        };
+        if bytes >= max_bytes {
+            recorder.rotate()?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        if let Ok(mut writer) = recorder.writer.lock() {
+            writer.writer = Some(LineWriter::new(file));
+            writer.bytes = if bytes >= max_bytes { 0 } else { bytes };
+        } else {
+            bail!("Could not lock traffic recording: {}", path)
+        }
+
+        Ok(recorder)
+    }
+
+    /// Pfad der Datei
+    pub fn get_path(&self) -> String {
+        self.path.clone()
+    }
+
+    /// Maximale Größe der Datei in Bytes
+    pub fn get_max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+
+    /// Läuft die Aufzeichnung noch?
+    ///
+    /// Liefert `false` wenn die Datei nicht umbenannt oder neu angelegt werden konnte.
+    pub fn is_recording(&self) -> bool {
+        match self.writer.lock() {
+            Ok(writer) => writer.writer.is_some(),
+            Err(_) => false,
+        }
+    }
+
+    /// Schreibt eine Abfrage in die Aufzeichnung
+    ///
+    /// Nach dem Ende der Aufzeichnung wird nichts mehr geschrieben.
+    ///
+    /// # Parameters
+    ///
+    /// * `record`  - die aufgezeichnete Abfrage
+    pub fn record(&self, record: &TrafficRecord) -> Result<()> {
+        let line = format!("{}\n", record.to_line());
+        if let Ok(mut writer) = self.writer.lock() {
+            if writer.writer.is_none() { return Ok(()); }
+
+            if writer.bytes > 0 && writer.bytes + line.len() as u64 > self.max_bytes {
+                writer.writer = None;
+                let file = self.rotate().and_then(|_| {
+                    OpenOptions::new().create(true).append(true).open(&self.path).map_err(|e| e.into())
+                });
+                match file {
+                    Ok(file) => {
+                        writer.writer = Some(LineWriter::new(file));
+                        writer.bytes = 0;
+                    }
+                    Err(e) => {
+                        warn!("Aufzeichnung nach {} beendet, {} Bytes erreicht: {}", self.path, self.max_bytes, e);
+                        return Ok(());
+                    }
+                }
+            }
+
+            if let Some(ref mut file) = writer.writer {
+                file.write_all(line.as_bytes())?;
+            }
+            writer.bytes += line.len() as u64;
+        } else {
+            bail!("Could not lock traffic recording: {}", self.path)
+        }
+
+        Ok(())
+    }
+
+    // Benennt die Datei der Aufzeichnung nach `<Datei>.1` um
+    //
+    fn rotate(&self) -> Result<()> {
+        let rotated = format!("{}.1", self.path);
+        info!("Aufzeichnung {} erreicht {} Bytes, weiter nach dem Umbenennen in {}", self.path, self.max_bytes, rotated);
+        fs::rename(&self.path, &rotated)?;
+
+        Ok(())
+    }
+}
+
+/// Spielt eine Aufzeichnung der Modbus Kommunikation ab
+///
+/// Ist am [`ModbusBusManager`](struct.ModbusBusManager.html) ein Replay gesetzt, wird keine Leitung mehr
+/// angesprochen. Jede Abfrage wird mit dem Datensatz beantwortet, der für Leitung und Modbus Adresse zum
+/// entsprechenden Zeitpunkt der Aufzeichnung aktuell war. Die Zeit läuft ab der ersten Abfrage, im selben Tempo wie
+/// bei der Aufzeichnung. Nach dem Ende der Aufzeichnung bleibt der jeweils letzte Datensatz aktuell.
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate chrono;
+/// # extern crate xmz_mod_touch_server;
+/// use chrono::{Duration, Utc};
+/// use xmz_mod_touch_server::modbus_bus::{TrafficRecord, TrafficReplay};
+///
+/// # fn main() {
+/// let start = Utc::now() - Duration::hours(1);
+/// let replay = TrafficReplay::new(vec![
+///     TrafficRecord::new(start, "/dev/ttyS1", 1, 30, Ok(vec![0, 14, 0, 1])),
+///     TrafficRecord::new(start + Duration::hours(1), "/dev/ttyS1", 1, 30, Ok(vec![0, 14, 0, 2])),
+/// ]);
+///
+/// // Zu Beginn ist der erste Datensatz aktuell
+/// assert_eq!(replay.poll("/dev/ttyS1", 1).into_result().unwrap(), vec![0, 14, 0, 1]);
+/// // Für andere Teilnehmer gibt es keine Daten
+/// assert!(replay.poll("/dev/ttyS1", 2).into_result().is_err());
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TrafficReplay {
+    records: HashMap<(String, u8), Vec<TrafficRecord>>,
+    first_timestamp: Option<DateTime<Utc>>,
+    started: Mutex<Option<Instant>>,
+}
+
+impl TrafficReplay {
+    /// Erstellt ein Replay aus den gegebenen Datensätzen
+    ///
+    /// # Parameters
+    ///
+    /// * `records` - aufgezeichnete Abfragen, in beliebiger Reihenfolge
+    pub fn new(records: Vec<TrafficRecord>) -> Self {
+        let first_timestamp = records.iter().map(|record| record.timestamp).min();
+        let mut by_sensor: HashMap<(String, u8), Vec<TrafficRecord>> = HashMap::new();
+        for record in records {
+            by_sensor.entry((record.modbus_device.clone(), record.modbus_address))
+                .or_insert_with(Vec::new)
+                .push(record);
+        }
+        for records in by_sensor.values_mut() {
+            records.sort_by_key(|record| record.timestamp);
+        }
+
+        TrafficReplay {
+            records: by_sensor,
+            first_timestamp: first_timestamp,
+            started: Mutex::new(None),
+        }
+    }
+
+    /// Liest eine Aufzeichnung aus einer Datei
+    ///
+    /// Leere Zeilen werden übersprungen.
+    ///
+    /// # Return values
+    ///
+    /// Liefert einen Fehler wenn die Datei nicht gelesen werden kann, oder eine Zeile nicht dem Format der
+    /// Aufzeichnung entspricht.
+    ///
+    /// # Parameters
+    ///
+    /// * `path`    - Pfad der Datei
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path)?;
+        let mut records = vec![];
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() { continue; }
+            records.push(TrafficRecord::parse_line(&line)?);
+        }
+
+        Ok(TrafficReplay::new(records))
+    }
+
+    /// Anzahl der Datensätze
+    pub fn len(&self) -> usize {
+        self.records.values().map(|records| records.len()).sum()
+    }
+
+    /// Liefert `true` wenn die Aufzeichnung keine Datensätze enthält
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Beantwortet eine Abfrage aus der Aufzeichnung
+    ///
+    /// # Parameters
+    ///
+    /// * `modbus_device`   - Schnittstelle der Leitung
+    /// * `modbus_address`  - Modbus Adresse des Teilnehmers
+    pub fn poll(&self, modbus_device: &str, modbus_address: u8) -> PollResult {
+        let records = match self.records.get(&(modbus_device.to_string(), modbus_address)) {
+            Some(records) => records,
+            None => return PollResult::new(
+                Err(format!("No recorded traffic for {} address {}", modbus_device, modbus_address).into()),
+                ::std::time::Duration::from_secs(0)),
+        };
+
+        // Zeitpunkt innerhalb der Aufzeichnung, der Jetzt entspricht
+        let elapsed = match self.started.lock() {
+            Ok(mut started) => started.get_or_insert_with(Instant::now).elapsed(),
+            Err(_) => ::std::time::Duration::from_secs(0),
+        };
+        let position = match self.first_timestamp {
+            Some(first_timestamp) => first_timestamp + Duration::from_std(elapsed).unwrap_or_else(|_| Duration::zero()),
+            None => Utc::now(),
+        };
+
+        let record = records.iter()
+            .take_while(|record| record.timestamp <= position)
+            .last()
+            .unwrap_or(&records[0]);
+
+        record.to_poll_result()
+    }
+}
@@ -0,0 +1,103 @@
+//! Firmware Version der CO-NO2-Kombisensor-Mod und unterstützte Versionen
+//!
+use errors::*;
+use std::fmt;
+
+
+/// Von diesem Server unterstützte Firmware Versionen, jeweils (älteste, neueste) Version, einschließlich
+///
+/// Messwerte einer Firmware außerhalb dieser Bereiche werden zwar gelesen, der Kombisensor wird aber als
+/// Ausnahme `KombisensorFirmwareUnsupported` gemeldet. Eine neue, getestete Firmware bekommt hier einen Eintrag.
+pub const SUPPORTED_FIRMWARE_VERSIONS: &[(FirmwareVersion, FirmwareVersion)] = &[
+    (FirmwareVersion { major: 0, minor: 14, patch: 0 }, FirmwareVersion { major: 0, minor: 14, patch: ::std::u16::MAX }),
+];
+
+/// Firmware Version eines Kombisensors
+///
+/// # Examples
+///
+/// ```rust
+/// use xmz_mod_touch_server::modbus_data::FirmwareVersion;
+///
+/// let firmware_version = FirmwareVersion::parse("0.14.0").unwrap();
+/// assert_eq!(firmware_version, FirmwareVersion::new(0, 14, 0));
+/// assert!(FirmwareVersion::new(0, 13, 9) < firmware_version);
+/// assert_eq!(firmware_version.to_string(), "0.14.0".to_string());
+/// ```
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Hash)]
+pub struct FirmwareVersion {
+    major: u16,
+    minor: u16,
+    patch: u16,
+}
+
+impl FirmwareVersion {
+    /// Erstellt eine Firmware Version
+    pub fn new(major: u16, minor: u16, patch: u16) -> Self {
+        FirmwareVersion {
+            major: major,
+            minor: minor,
+            patch: patch,
+        }
+    }
+
+    /// Liest eine Firmware Version im Format "major.minor.patch"
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::modbus_data::FirmwareVersion;
+    ///
+    /// assert!(FirmwareVersion::parse("0.14.0").is_ok());
+    /// assert!(FirmwareVersion::parse("0.14").is_err());
+    /// assert!(FirmwareVersion::parse("0.14.x").is_err());
+    /// ```
+    pub fn parse(firmware_version: &str) -> Result<Self> {
+        let parts: Vec<&str> = firmware_version.trim().split('.').collect();
+        if parts.len() != 3 {
+            bail!("Invalid firmware version: {}", firmware_version)
+        }
+
+        let mut version = [0u16; 3];
+        for (part, value) in parts.iter().zip(version.iter_mut()) {
+            *value = match part.parse() {
+                Ok(number) => number,
+                Err(_) => bail!("Invalid firmware version: {}", firmware_version),
+            };
+        }
+
+        Ok(FirmwareVersion::new(version[0], version[1], version[2]))
+    }
+
+    /// Major, Minor und Patch Version als Tuple
+    pub fn as_tuple(&self) -> (u16, u16, u16) {
+        (self.major, self.minor, self.patch)
+    }
+
+    /// Wird diese Firmware Version unterstützt?
+    ///
+    /// Siehe [`SUPPORTED_FIRMWARE_VERSIONS`](constant.SUPPORTED_FIRMWARE_VERSIONS.html)
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::modbus_data::FirmwareVersion;
+    ///
+    /// assert!(FirmwareVersion::new(0, 14, 0).is_supported());
+    /// assert!(FirmwareVersion::new(0, 14, 3).is_supported());
+    /// assert!(!FirmwareVersion::new(0, 13, 0).is_supported());
+    /// assert!(!FirmwareVersion::new(1, 0, 0).is_supported());
+    /// ```
+    pub fn is_supported(&self) -> bool {
+        SUPPORTED_FIRMWARE_VERSIONS.iter().any(|&(ref oldest, ref newest)| oldest <= self && self <= newest)
+    }
+}
+
+impl fmt::Display for FirmwareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
@@ -1,3 +1,9 @@
+//! Modbus Daten und Register Layout der Kombisensoren
+//!
+mod firmware_version;
 mod modbus_data;
+mod register_map;
 
-pub use self::modbus_data::ModbusData;
+pub use self::firmware_version::{FirmwareVersion, SUPPORTED_FIRMWARE_VERSIONS};
+pub use self::modbus_data::{ModbusData, SensorData, SIMULATION_DATA_STATIC};
+pub use self::register_map::{RegisterMap, SensorRegisters, HEADER_NUM_REGISTERS, REGISTER_FIRMWARE_VERSION, REGISTER_MODBUS_ADDRESS};
@@ -1,5 +1,7 @@
+//! Typisierte Modbus Daten eines Kombisensors
+//!
 use errors::*;
-use libmodbus_rs::{Modbus, ModbusRTU, ModbusClient, MODBUS_RTU_MAX_ADU_LENGTH};
+use modbus_data::register_map::{RegisterMap, HEADER_NUM_REGISTERS, REGISTER_FIRMWARE_VERSION, REGISTER_MODBUS_ADDRESS};
 
 
 /// Beispieldaten: 256 u16 values
@@ -12,109 +14,187 @@ pub const SIMULATION_DATA_STATIC: &[u16] = &[0, 14, 0, 247, 0, 0, 0, 0, 0, 0, 1,
  0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
 
 
+/// Werte einer Sensor Messzelle, so wie sie in den Registern des Kombisensors stehen
+#[derive(Clone)]
 #[derive(Debug)]
-#[derive(PartialEq,Eq)]
+#[derive(Default)]
+#[derive(Eq, PartialEq)]
+pub struct SensorData {
+    num: u16,
+    adc_value: u16,
+    min_value: u16,
+    max_value: u16,
+    adc_value_at_nullgas: u16,
+    adc_value_at_messgas: u16,
+    concentration_at_nullgas: u16,
+    concentration_at_messgas: u16,
+    config: u16,
+}
+
+/// Modbus Daten eines Kombisensors
+///
+/// Die Daten werden aus einem Registerblock geparst. Das Layout der Register wird über die Firmware Version
+/// (Register 0..2) bestimmt, siehe [`RegisterMap`](struct.RegisterMap.html).
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
 pub struct ModbusData {
     firmware_version_major: u16,
     firmware_version_minor: u16,
     firmware_version_patch: u16,
     modbus_address: u16,
-
-    sensor1_num: u16,
-    sensor1_adc_value: u16,
-    sensor1_min_value: u16,
-    sensor1_max_value: u16,
-    sensor1_adc_value_at_nullgas: u16,
-    sensor1_adc_value_at_messgas: u16,
-    sensor1_concentration_at_nullgas: u16,
-    sensor1_concentration_at_messgas: u16,
-    sensor1_configuration_bits: u16,
-
-    sensor2_num: u16,
-    sensor2_adc_value: u16,
-    sensor2_min_value: u16,
-    sensor2_max_value: u16,
-    sensor2_adc_value_at_nullgas: u16,
-    sensor2_adc_value_at_messgas: u16,
-    sensor2_concentration_at_nullgas: u16,
-    sensor2_concentration_at_messgas: u16,
-    sensor2_configuration_bits: u16,
+    sensors: Vec<SensorData>,
 }
 
 impl ModbusData {
-    pub fn new() -> Self {
+    /// Liest die Firmware Version aus einem Registerblock
+    ///
+    /// # Return values
+    ///
+    /// Liefert die Firmware Version als String, z.B. "0.14.0", oder einen Fehler wenn der Registerblock zu kurz ist.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::ModbusData;
+    ///
+    /// assert_eq!(ModbusData::parse_firmware_version(&[0, 14, 0, 247]).unwrap(), "0.14.0".to_string());
+    /// assert!(ModbusData::parse_firmware_version(&[0, 14]).is_err());
+    /// ```
+    pub fn parse_firmware_version(registers: &[u16]) -> Result<String> {
+        let (major, minor, patch) = parse_firmware_version(registers)?;
+
+        Ok(format!("{}.{}.{}", major, minor, patch))
+    }
 
+    /// Parst einen Registerblock
+    ///
+    /// Der Registerblock muss ab Register 0 gelesen worden sein.
+    ///
+    /// # Return values
+    ///
+    /// Liefert die Modbus Daten, oder einen Fehler wenn der Registerblock für das Layout der Firmware Version zu
+    /// kurz ist.
+    ///
+    /// # Parameters
+    ///
+    /// * `registers`   - Registerblock, ab Register 0
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::ModbusData;
+    /// use xmz_mod_touch_server::modbus_data::SIMULATION_DATA_STATIC;
+    ///
+    /// let modbus_data = ModbusData::parse(SIMULATION_DATA_STATIC).unwrap();
+    /// assert_eq!(modbus_data.get_firmware_version(), "0.14.0".to_string());
+    /// assert_eq!(modbus_data.get_modbus_address(), 247);
+    /// assert_eq!(modbus_data.get_sensors().len(), 2);
+    /// assert_eq!(modbus_data.get_sensor(1).unwrap().get_adc_value(), 107);
+    ///
+    /// assert!(ModbusData::parse(&SIMULATION_DATA_STATIC[..20]).is_err());
+    /// ```
+    pub fn parse(registers: &[u16]) -> Result<Self> {
+        let (major, minor, patch) = parse_firmware_version(registers)?;
+        let register_map = RegisterMap::for_firmware_version(major, minor, patch);
+
+        if registers.len() < register_map.get_num_registers() as usize {
+            bail!("Modbus Data too short for firmware {}.{}.{}: {} of {} Registers",
+                major, minor, patch, registers.len(), register_map.get_num_registers())
+        }
+
+        let register = |address: u16| -> Result<u16> {
+            match registers.get(address as usize) {
+                Some(value) => Ok(*value),
+                None => bail!("Modbus Data invalid, Register {} missing", address),
+            }
+        };
+
+        let sensor_registers = register_map.get_sensor_registers();
+        let mut sensors = vec![];
+        for sensor_id in 0..register_map.get_num_sensors() as usize {
+            if let Some(address) = register_map.get_sensor_address(sensor_id) {
+                sensors.push(SensorData {
+                    num: register(address + sensor_registers.num())?,
+                    adc_value: register(address + sensor_registers.adc_value())?,
+                    min_value: register(address + sensor_registers.min_value())?,
+                    max_value: register(address + sensor_registers.max_value())?,
+                    adc_value_at_nullgas: register(address + sensor_registers.adc_value_at_nullgas())?,
+                    adc_value_at_messgas: register(address + sensor_registers.adc_value_at_messgas())?,
+                    concentration_at_nullgas: register(address + sensor_registers.concentration_at_nullgas())?,
+                    concentration_at_messgas: register(address + sensor_registers.concentration_at_messgas())?,
+                    config: register(address + sensor_registers.config())?,
+                });
+            }
+        }
+
+        Ok(ModbusData {
+            firmware_version_major: major,
+            firmware_version_minor: minor,
+            firmware_version_patch: patch,
+            modbus_address: register(REGISTER_MODBUS_ADDRESS)?,
+            sensors: sensors,
+        })
     }
-}
 
-fn get_from_modbus() -> Result<Vec<u16>> {
-    let device: String = std::env::args().nth(1).unwrap_or("/dev/ttyUSB0".to_string());
-    let slave_id: u8 = std::env::args().nth(2).unwrap_or("247".to_string()).parse()?;
+    /// Firmware Version, z.B. "0.14.0"
+    pub fn get_firmware_version(&self) -> String {
+        format!("{}.{}.{}", self.firmware_version_major, self.firmware_version_minor, self.firmware_version_patch)
+    }
 
-    let mut modbus = Modbus::new_rtu(&device, 9600, 'N', 8, 1)?;
-    modbus.set_slave(247)?;
+    /// Register Layout der Firmware Version
+    pub fn get_register_map(&self) -> &'static RegisterMap {
+        RegisterMap::for_firmware_version(self.firmware_version_major, self.firmware_version_minor, self.firmware_version_patch)
+    }
 
-    modbus.set_debug(true);
-    modbus.connect()?;
+    /// Modbus Adresse, die im Kombisensor eingestellt ist
+    pub fn get_modbus_address(&self) -> u16 {
+        self.modbus_address
+    }
 
-    let mut response_register = vec![0u16; MODBUS_RTU_MAX_ADU_LENGTH as usize];
-    modbus.read_registers(0, 30, &mut response_register)?;
+    /// Werte aller Sensor Messzellen
+    pub fn get_sensors(&self) -> &Vec<SensorData> {
+        &self.sensors
+    }
 
-    Ok(response_register)
+    /// Werte einer Sensor Messzelle
+    ///
+    /// # Parameters
+    ///
+    /// * `id`  - Id der Sensormesszelle
+    pub fn get_sensor(&self, id: usize) -> Option<&SensorData> {
+        self.sensors.get(id)
+    }
 }
 
-fn parse(input: &[u16]) -> Result<ModbusData> {
-    // Check input
-    println!("{}", input.len());
-    if input.len() < 28 { panic!("Modbus Data invalid") }
-
-    let firmware_version_major = *input.get(0).unwrap();
-    let firmware_version_minor = *input.get(1).unwrap();
-    let firmware_version_patch = *input.get(2).unwrap();
-    let modbus_address = *input.get(3).unwrap();
-    let sensor1_num = *input.get(0).unwrap();
-    let sensor1_adc_value = *input.get(1).unwrap();
-    let sensor1_min_value = *input.get(2).unwrap();
-    let sensor1_max_value = *input.get(3).unwrap();
-    let sensor1_adc_value_at_nullgas = *input.get(4).unwrap();
-    let sensor1_adc_value_at_messgas = *input.get(5).unwrap();
-    let sensor1_concentration_at_nullgas = *input.get(6).unwrap();
-    let sensor1_concentration_at_messgas = *input.get(7).unwrap();
-    let sensor1_configuration_bits = *input.get(8).unwrap();
-    let sensor2_num = *input.get(0).unwrap();
-    let sensor2_adc_value = *input.get(1).unwrap();
-    let sensor2_min_value = *input.get(2).unwrap();
-    let sensor2_max_value = *input.get(3).unwrap();
-    let sensor2_adc_value_at_nullgas = *input.get(4).unwrap();
-    let sensor2_adc_value_at_messgas = *input.get(5).unwrap();
-    let sensor2_concentration_at_nullgas = *input.get(6).unwrap();
-    let sensor2_concentration_at_messgas = *input.get(7).unwrap();
-    let sensor2_configuration_bits = *input.get(8).unwrap();
-
-    let modbus_data = ModbusData {
-        firmware_version_major: firmware_version_major,
-        firmware_version_minor: firmware_version_minor,
-        firmware_version_patch: firmware_version_patch,
-        modbus_address: modbus_address,
-        sensor1_num: sensor1_num,
-        sensor1_adc_value: sensor1_adc_value,
-        sensor1_min_value: sensor1_min_value,
-        sensor1_max_value: sensor1_max_value,
-        sensor1_adc_value_at_nullgas: sensor1_adc_value_at_nullgas,
-        sensor1_adc_value_at_messgas: sensor1_adc_value_at_messgas,
-        sensor1_concentration_at_nullgas: sensor1_concentration_at_nullgas,
-        sensor1_concentration_at_messgas: sensor1_concentration_at_messgas,
-        sensor1_configuration_bits: sensor1_configuration_bits,
-        sensor2_num: sensor2_num,
-        sensor2_adc_value: sensor2_adc_value,
-        sensor2_min_value: sensor2_min_value,
-        sensor2_max_value: sensor2_max_value,
-        sensor2_adc_value_at_nullgas: sensor2_adc_value_at_nullgas,
-        sensor2_adc_value_at_messgas: sensor2_adc_value_at_messgas,
-        sensor2_concentration_at_nullgas: sensor2_concentration_at_nullgas,
-        sensor2_concentration_at_messgas: sensor2_concentration_at_messgas,
-        sensor2_configuration_bits: sensor2_configuration_bits,
-    };
-
-    Ok(modbus_data)
+impl SensorData {
+    /// Sensor Nummer
+    pub fn get_num(&self) -> u16 { self.num }
+    /// ADC Wert
+    pub fn get_adc_value(&self) -> u16 { self.adc_value }
+    /// minimaler Wert
+    pub fn get_min_value(&self) -> u16 { self.min_value }
+    /// maximaler Wert
+    pub fn get_max_value(&self) -> u16 { self.max_value }
+    /// ADC Wert bei Nullgas
+    pub fn get_adc_value_at_nullgas(&self) -> u16 { self.adc_value_at_nullgas }
+    /// ADC Wert bei Messgas
+    pub fn get_adc_value_at_messgas(&self) -> u16 { self.adc_value_at_messgas }
+    /// Konzentration bei Nullgas
+    pub fn get_concentration_at_nullgas(&self) -> u16 { self.concentration_at_nullgas }
+    /// Konzentration bei Messgas
+    pub fn get_concentration_at_messgas(&self) -> u16 { self.concentration_at_messgas }
+    /// Konfiguration (Bit 0: Messzelle aktiviert)
+    pub fn get_config(&self) -> u16 { self.config }
+}
+
+// Liest die Firmware Version (major, minor, patch) aus den Registern 0..2
+//
+fn parse_firmware_version(registers: &[u16]) -> Result<(u16, u16, u16)> {
+    if registers.len() < HEADER_NUM_REGISTERS as usize {
+        bail!("Modbus Data invalid: {:?}", registers)
+    }
+    let address = REGISTER_FIRMWARE_VERSION as usize;
+
+    Ok((registers[address], registers[address + 1], registers[address + 2]))
 }
@@ -0,0 +1,218 @@
+//! Register Layout der CO-NO2-Kombisensor-Mod Firmware
+//!
+//! Die Register 0..3 (Firmware Version und Modbus Adresse) sind in allen Firmware Versionen gleich. Alle weiteren
+//! Register werden über die [`RegisterMap`](struct.RegisterMap.html) der jeweiligen Firmware Version bestimmt.
+//!
+use modbus_data::FirmwareVersion;
+
+
+/// Register der Firmware Version (major, minor, patch)
+pub const REGISTER_FIRMWARE_VERSION: u16 = 0;
+/// Register der Modbus Adresse
+pub const REGISTER_MODBUS_ADDRESS: u16 = 3;
+/// Anzahl der Register die in allen Firmware Versionen gleich sind
+pub const HEADER_NUM_REGISTERS: u16 = 4;
+
+/// Lage der Register innerhalb des Registerblocks einer Sensor Messzelle
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+pub struct SensorRegisters {
+    num: u16,
+    adc_value: u16,
+    min_value: u16,
+    max_value: u16,
+    adc_value_at_nullgas: u16,
+    adc_value_at_messgas: u16,
+    concentration_at_nullgas: u16,
+    concentration_at_messgas: u16,
+    config: u16,
+}
+
+/// Register Layout einer Firmware Version
+///
+/// # Examples
+///
+/// ```rust
+/// use xmz_mod_touch_server::RegisterMap;
+///
+/// let register_map = RegisterMap::for_firmware_version(0, 14, 0);
+/// assert_eq!(register_map.get_num_registers(), 30);
+/// assert_eq!(register_map.get_num_sensors(), 2);
+/// ```
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+pub struct RegisterMap {
+    // Das Layout gilt ab dieser Firmware Version (major, minor, patch)
+    firmware_version: (u16, u16, u16),
+    // Anzahl der Register die bei einer Abfrage gelesen werden
+    num_registers: u16,
+    // erstes Register der ersten Sensor Messzelle
+    sensor_offset: u16,
+    // Abstand der Registerblöcke zweier Sensor Messzellen
+    sensor_size: u16,
+    num_sensors: u16,
+    sensor_registers: SensorRegisters,
+}
+
+// Bekannte Register Layouts, aufsteigend nach Firmware Version sortiert
+//
+// Eine neue Firmware mit geändertem Layout bekommt hier einen weiteren Eintrag.
+const REGISTER_MAPS: &[RegisterMap] = &[
+    // Firmware bis einschließlich 0.14.0
+    RegisterMap {
+        firmware_version: (0, 0, 0),
+        num_registers: 30,
+        sensor_offset: 10,
+        sensor_size: 10,
+        num_sensors: 2,
+        sensor_registers: SensorRegisters {
+            num: 0,
+            adc_value: 1,
+            min_value: 2,
+            max_value: 3,
+            adc_value_at_nullgas: 4,
+            adc_value_at_messgas: 5,
+            concentration_at_nullgas: 6,
+            concentration_at_messgas: 7,
+            config: 8,
+        },
+    },
+];
+
+impl RegisterMap {
+    /// Liefert das Register Layout einer Firmware Version
+    ///
+    /// Gewählt wird das Layout mit der höchsten Firmware Version, die nicht neuer als die gegebene ist.
+    ///
+    /// # Parameters
+    ///
+    /// * `major`   - Major Version der Firmware
+    /// * `minor`   - Minor Version der Firmware
+    /// * `patch`   - Patch Version der Firmware
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::RegisterMap;
+    ///
+    /// let register_map = RegisterMap::for_firmware_version(0, 14, 0);
+    /// assert_eq!(register_map.get_firmware_version(), "0.0.0".to_string());
+    ///
+    /// // Neuere Firmware Versionen verwenden das neueste bekannte Layout
+    /// assert_eq!(RegisterMap::for_firmware_version(1, 0, 0), register_map);
+    /// ```
+    pub fn for_firmware_version(major: u16, minor: u16, patch: u16) -> &'static RegisterMap {
+        REGISTER_MAPS.iter()
+            .rev()
+            .find(|register_map| register_map.firmware_version <= (major, minor, patch))
+            .unwrap_or(&REGISTER_MAPS[0])
+    }
+
+    /// Alle bekannten Register Layouts, aufsteigend nach Firmware Version sortiert
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::RegisterMap;
+    ///
+    /// let register_maps = RegisterMap::get_register_maps();
+    /// assert_eq!(register_maps[0].get_firmware_version(), "0.0.0".to_string());
+    /// ```
+    pub fn get_register_maps() -> &'static [RegisterMap] {
+        REGISTER_MAPS
+    }
+
+    /// Liefert das Register Layout einer Firmware Version, die als String (z.B. "0.14.0") gegeben ist
+    ///
+    /// Kann die Version nicht gelesen werden, z.B. weil der Kombisensor noch nie abgefragt wurde, dann wird das
+    /// älteste bekannte Layout geliefert.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::RegisterMap;
+    ///
+    /// assert_eq!(RegisterMap::for_firmware_version_str("0.14.0"), RegisterMap::for_firmware_version(0, 14, 0));
+    /// assert_eq!(RegisterMap::for_firmware_version_str("unbekannt"), RegisterMap::for_firmware_version(0, 0, 0));
+    /// ```
+    pub fn for_firmware_version_str(firmware_version: &str) -> &'static RegisterMap {
+        match FirmwareVersion::parse(firmware_version) {
+            Ok(firmware_version) => {
+                let (major, minor, patch) = firmware_version.as_tuple();
+                RegisterMap::for_firmware_version(major, minor, patch)
+            }
+            Err(_) => &REGISTER_MAPS[0],
+        }
+    }
+
+    /// Firmware Version, ab der das Layout gilt
+    pub fn get_firmware_version(&self) -> String {
+        let (major, minor, patch) = self.firmware_version;
+        format!("{}.{}.{}", major, minor, patch)
+    }
+
+    /// Anzahl der Register die bei einer Abfrage gelesen werden
+    pub fn get_num_registers(&self) -> u16 {
+        self.num_registers
+    }
+
+    /// Anzahl der Sensor Messzellen
+    pub fn get_num_sensors(&self) -> u16 {
+        self.num_sensors
+    }
+
+    /// Lage der Register innerhalb des Registerblocks einer Sensor Messzelle
+    pub fn get_sensor_registers(&self) -> &SensorRegisters {
+        &self.sensor_registers
+    }
+
+    /// Erstes Register des Registerblocks einer Sensor Messzelle
+    ///
+    /// # Return values
+    ///
+    /// Liefert `None` wenn die Firmware die Messzelle nicht kennt.
+    ///
+    /// # Parameters
+    ///
+    /// * `sensor_id`   - Id der Sensormesszelle
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::RegisterMap;
+    ///
+    /// let register_map = RegisterMap::for_firmware_version(0, 14, 0);
+    /// assert_eq!(register_map.get_sensor_address(0), Some(10));
+    /// assert_eq!(register_map.get_sensor_address(1), Some(20));
+    /// assert_eq!(register_map.get_sensor_address(2), None);
+    /// ```
+    pub fn get_sensor_address(&self, sensor_id: usize) -> Option<u16> {
+        if sensor_id < self.num_sensors as usize {
+            Some(self.sensor_offset + sensor_id as u16 * self.sensor_size)
+        } else {
+            None
+        }
+    }
+}
+
+impl SensorRegisters {
+    /// Register der Sensor Nummer
+    pub fn num(&self) -> u16 { self.num }
+    /// Register des ADC Werts
+    pub fn adc_value(&self) -> u16 { self.adc_value }
+    /// Register des minimalen Werts
+    pub fn min_value(&self) -> u16 { self.min_value }
+    /// Register des maximalen Werts
+    pub fn max_value(&self) -> u16 { self.max_value }
+    /// Register des ADC Werts bei Nullgas
+    pub fn adc_value_at_nullgas(&self) -> u16 { self.adc_value_at_nullgas }
+    /// Register des ADC Werts bei Messgas
+    pub fn adc_value_at_messgas(&self) -> u16 { self.adc_value_at_messgas }
+    /// Register der Konzentration bei Nullgas
+    pub fn concentration_at_nullgas(&self) -> u16 { self.concentration_at_nullgas }
+    /// Register der Konzentration bei Messgas
+    pub fn concentration_at_messgas(&self) -> u16 { self.concentration_at_messgas }
+    /// Register der Konfiguration (Bit 0: Messzelle aktiviert)
+    pub fn config(&self) -> u16 { self.config }
+}
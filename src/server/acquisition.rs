@@ -0,0 +1,165 @@
+//! Messwerterfassung, ein Thread pro Modbus Leitung
+//!
+use errors::*;
+use server::Server;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+
+/// Fragt die Kombisensoren parallel ab, mit einem Worker Thread pro Leitung
+///
+/// Jeder Worker fragt nur die Kombisensoren seiner Leitung ab. Ein Kombisensor der nicht antwortet verzögert
+/// damit nur die Abfragen an der eigenen Leitung, die Zykluszeit richtet sich nach der langsamsten Leitung.
+///
+/// Der Lock des Servers wird nur kurz gehalten, zum Sammeln der Abfragen und zum Übernehmen jedes einzelnen
+/// Ergebnisses. Die Modbus Kommunikation selbst läuft ohne den Lock des Servers.
+///
+/// [`start_workers()`](#method.start_workers) startet für jede neue Leitung einen Worker. Gibt es an einer
+/// Leitung keinen Kombisensor mehr, beendet sich ihr Worker.
+///
+/// # Examples
+///
+/// ```rust
+/// use xmz_mod_touch_server::{Kombisensor, KombisensorType, Server};
+/// use xmz_mod_touch_server::server::Acquisition;
+/// use std::sync::{Arc, Mutex};
+/// use std::time::Duration;
+///
+/// let mut xmz_mod_touch_server = Server::new();
+/// xmz_mod_touch_server.add_zone();
+/// xmz_mod_touch_server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+/// let xmz_mod_touch_server = Arc::new(Mutex::new(xmz_mod_touch_server));
+///
+/// let acquisition = Acquisition::new(xmz_mod_touch_server.clone(), Duration::from_millis(100));
+/// assert_eq!(acquisition.start_workers().unwrap(), 1);
+/// // Für jede Leitung läuft nur ein Worker
+/// assert_eq!(acquisition.start_workers().unwrap(), 0);
+/// ```
+#[derive(Debug)]
+pub struct Acquisition {
+    server: Arc<Mutex<Server>>,
+    // Leitungen, für die ein Worker läuft
+    workers: Arc<Mutex<HashSet<String>>>,
+    // Pause zwischen zwei Abfragezyklen eines Workers
+    interval: Duration,
+}
+
+impl Acquisition {
+    /// Erstellt eine neue Messwerterfassung, ohne Worker
+    ///
+    /// # Parameters
+    ///
+    /// * `server`      - Server, dessen Kombisensoren abgefragt werden
+    /// * `interval`    - Pause zwischen zwei Abfragezyklen einer Leitung
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::Server;
+    /// use xmz_mod_touch_server::server::Acquisition;
+    /// use std::sync::{Arc, Mutex};
+    /// use std::time::Duration;
+    ///
+    /// let xmz_mod_touch_server = Arc::new(Mutex::new(Server::new()));
+    /// let acquisition = Acquisition::new(xmz_mod_touch_server, Duration::from_millis(100));
+    /// assert_eq!(acquisition.get_worker_devices().unwrap().len(), 0);
+    /// ```
+    pub fn new(server: Arc<Mutex<Server>>, interval: Duration) -> Self {
+        Acquisition {
+            server: server,
+            workers: Arc::new(Mutex::new(HashSet::new())),
+            interval: interval,
+        }
+    }
+
+    /// Startet für jede Leitung, für die noch kein Worker läuft, einen Worker Thread
+    ///
+    /// Diese Funktion kann regelmäßig aufgerufen werden, so werden auch neu konfigurierte Leitungen abgefragt.
+    ///
+    /// # Return values
+    ///
+    /// Liefert die Anzahl der neu gestarteten Worker
+    pub fn start_workers(&self) -> Result<usize> {
+        let bus_devices = if let Ok(server) = self.server.lock() {
+            server.get_bus_devices()
+        } else {
+            bail!("Could not lock Server")
+        };
+
+        let mut num_started = 0;
+        if let Ok(mut workers) = self.workers.lock() {
+            for bus_device in bus_devices {
+                if workers.contains(&bus_device) {
+                    continue;
+                }
+
+                let (server, workers_clone, interval) = (self.server.clone(), self.workers.clone(), self.interval);
+                let worker_device = bus_device.clone();
+                thread::Builder::new()
+                    .name(format!("bus {}", bus_device))
+                    .spawn(move || run_worker(server, workers_clone, worker_device, interval))?;
+
+                info!("Messwerterfassung für Leitung {} gestartet", bus_device);
+                workers.insert(bus_device);
+                num_started += 1;
+            }
+        } else {
+            bail!("Could not lock workers member")
+        }
+
+        Ok(num_started)
+    }
+
+    /// Liefert die Leitungen, für die aktuell ein Worker läuft
+    pub fn get_worker_devices(&self) -> Result<Vec<String>> {
+        if let Ok(workers) = self.workers.lock() {
+            let mut bus_devices: Vec<String> = workers.iter().cloned().collect();
+            bus_devices.sort();
+
+            Ok(bus_devices)
+        } else {
+            bail!("Could not lock workers member")
+        }
+    }
+}
+
+// Abfrageschleife eines Workers
+//
+// Läuft bis an der Leitung kein Kombisensor mehr konfiguriert ist.
+fn run_worker(server: Arc<Mutex<Server>>, workers: Arc<Mutex<HashSet<String>>>, bus_device: String, interval: Duration) {
+    loop {
+        // Abfragen sammeln, der Lock des Servers wird sofort wieder frei gegeben
+        let (bus_manager, poll_requests) = match server.lock() {
+            Ok(server) => {
+                if !server.get_bus_devices().contains(&bus_device) {
+                    break;
+                }
+                (server.get_bus_manager(), server.get_poll_requests(&bus_device))
+            }
+            Err(_) => {
+                error!("Messwerterfassung {}: Could not lock Server", bus_device);
+                break;
+            }
+        };
+
+        for (zone_id, kombisensor_id, poll_request) in poll_requests {
+            // Modbus Kommunikation ohne den Lock des Servers
+            let poll_result = poll_request.execute(&bus_manager);
+
+            if let Ok(mut server) = server.lock() {
+                if let Err(e) = server.apply_poll_result(zone_id, kombisensor_id, &poll_request, poll_result) {
+                    debug!("Messwerterfassung {}: {}", bus_device, e);
+                }
+            }
+        }
+
+        thread::sleep(interval);
+    }
+
+    info!("Messwerterfassung für Leitung {} beendet", bus_device);
+    if let Ok(mut workers) = workers.lock() {
+        workers.remove(&bus_device);
+    }
+}
@@ -0,0 +1,443 @@
+//! Geführte Kalibrierung einer Sensor Messzelle
+//!
+//! Eine Kalibrierung läuft in zwei Schritten ab. Zuerst wird Nullgas aufgegeben und gewartet, bis der ADC Wert
+//! der Messzelle stabil ist. Danach wird Messgas mit bekannter Konzentration aufgegeben und wieder auf einen
+//! stabilen ADC Wert gewartet. Die resultierende Steigung wird geprüft, erst dann können die Werte in die Messzelle
+//! und den Kombisensor übernommen werden.
+//!
+//! Während der Kalibrierung sind die Alarmausgänge (Relais) der Zone gesperrt, siehe
+//! [`Server::start_calibration()`](../server/struct.Server.html#method.start_calibration).
+//!
+use ::chrono::{DateTime, Duration, Utc};
+use errors::*;
+use server::zone::kombisensor::sensor::Sensor;
+
+
+// Zeitraum in Sekunden, in dem der ADC Wert stabil sein muss
+pub const CALIBRATION_STABLE_SEC: i64 = 30;
+// Größte erlaubte Schwankung des ADC Werts innerhalb dieses Zeitraums
+pub const CALIBRATION_STABLE_MAX_DEVIATION: u16 = 3;
+// Wird ein Gas nicht innerhalb dieser Zeit stabil, schlägt die Kalibrierung fehl
+pub const CALIBRATION_STEP_TIMEOUT_SEC: i64 = 10 * 60;
+// Ist die Kalibrierung nicht innerhalb dieser Zeit beendet, wird sie abgebrochen und die Relais wieder freigegeben
+pub const CALIBRATION_SESSION_TIMEOUT_SEC: i64 = 30 * 60;
+// Mindestabstand der ADC Werte von Nullgas und Messgas
+pub const CALIBRATION_MIN_ADC_SPAN: u16 = 50;
+// Größte erlaubte Abweichung der neuen Steigung von der bisherigen (0.5 = 50%)
+pub const CALIBRATION_MAX_SLOPE_DEVIATION: f64 = 0.5;
+
+/// Einstellungen der geführten Kalibrierung
+///
+/// Fehlende Werte in der Konfigurationsdatei werden mit den Standardwerten belegt.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct CalibrationSettings {
+    /// Zeitraum in Sekunden, in dem der ADC Wert stabil sein muss
+    pub stable_sec: i64,
+    /// Größte erlaubte Schwankung (max - min) des ADC Werts innerhalb von `stable_sec`
+    pub stable_max_deviation: u16,
+    /// Zeit in Sekunden, nach der ein nicht stabiles Gas die Kalibrierung abbricht
+    pub step_timeout_sec: i64,
+    /// Zeit in Sekunden, nach der eine nicht beendete Kalibrierung, in jedem Schritt, abgebrochen wird
+    pub session_timeout_sec: i64,
+    /// Mindestabstand der ADC Werte von Nullgas und Messgas
+    pub min_adc_span: u16,
+    /// Größte erlaubte relative Abweichung der neuen Steigung von der bisherigen Kalibrierung
+    pub max_slope_deviation: f64,
+}
+
+impl Default for CalibrationSettings {
+    fn default() -> Self {
+        CalibrationSettings {
+            stable_sec: CALIBRATION_STABLE_SEC,
+            stable_max_deviation: CALIBRATION_STABLE_MAX_DEVIATION,
+            step_timeout_sec: CALIBRATION_STEP_TIMEOUT_SEC,
+            session_timeout_sec: CALIBRATION_SESSION_TIMEOUT_SEC,
+            min_adc_span: CALIBRATION_MIN_ADC_SPAN,
+            max_slope_deviation: CALIBRATION_MAX_SLOPE_DEVIATION,
+        }
+    }
+}
+
+/// Schritt der Kalibrierung
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize)]
+pub enum CalibrationStep {
+    /// Nullgas ist aufgegeben, es wird auf einen stabilen ADC Wert gewartet
+    Nullgas,
+    /// Nullgas ist erfasst, es wird auf das Messgas gewartet
+    NullgasCaptured,
+    /// Messgas ist aufgegeben, es wird auf einen stabilen ADC Wert gewartet
+    Messgas,
+    /// Messgas ist erfasst und die Steigung geprüft, die Werte können übernommen werden
+    MessgasCaptured,
+    /// Die Werte wurden übernommen
+    Committed,
+    /// Die Kalibrierung wurde abgebrochen
+    Aborted,
+    /// Die Kalibrierung ist fehlgeschlagen
+    Failed(String),
+}
+
+/// Kalibrierung einer Sensor Messzelle
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate chrono;
+/// # extern crate xmz_mod_touch_server;
+/// use chrono::{Duration, Utc};
+/// use xmz_mod_touch_server::{Sensor, SensorType};
+/// use xmz_mod_touch_server::server::{CalibrationSession, CalibrationSettings, CalibrationStep};
+///
+/// # fn main() {
+/// let mut sensor = Sensor::new_with_type(SensorType::SimulationNO2);
+/// let now = Utc::now();
+/// let mut session = CalibrationSession::new(0, 0, 0, &sensor, CalibrationSettings::default(), now);
+///
+/// // 30 Sekunden stabiles Nullgas
+/// for sec in 0..31 {
+///     session.add_sample(910 + sec as u16 % 2, now + Duration::seconds(sec));
+/// }
+/// assert_eq!(session.get_step(), &CalibrationStep::NullgasCaptured);
+/// assert_eq!(session.get_adc_value_at_nullgas(), Some(910));
+///
+/// // Messgas mit 20ppm
+/// let now = now + Duration::minutes(1);
+/// session.start_messgas(20, now).unwrap();
+/// for sec in 0..31 {
+///     session.add_sample(570, now + Duration::seconds(sec));
+/// }
+/// assert_eq!(session.get_step(), &CalibrationStep::MessgasCaptured);
+///
+/// session.apply(&mut sensor).unwrap();
+/// assert_eq!(sensor.get_adc_value_at_nullgas(), 910);
+/// assert_eq!(sensor.get_adc_value_at_messgas(), 570);
+/// assert_eq!(sensor.get_concentration_at_messgas(), 20);
+/// # }
+/// ```
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Serialize)]
+pub struct CalibrationSession {
+    zone_id: usize,
+    kombisensor_id: usize,
+    sensor_id: usize,
+    settings: CalibrationSettings,
+    step: CalibrationStep,
+    // Beginn der Kalibrierung
+    started: DateTime<Utc>,
+    // Beginn des aktuellen Schritts
+    step_started: DateTime<Utc>,
+    // letzter ADC Wert, zur Anzeige
+    adc_value: Option<u16>,
+    adc_value_at_nullgas: Option<u16>,
+    adc_value_at_messgas: Option<u16>,
+    concentration_at_messgas: Option<u16>,
+    // Steigung der bisherigen Kalibrierung, falls sie eine hat
+    previous_slope: Option<f64>,
+    // ADC Werte innerhalb von `stable_sec`
+    #[serde(skip_serializing)]
+    samples: Vec<(DateTime<Utc>, u16)>,
+    // Zeitpunkt des zuletzt übernommenen ADC Werts
+    #[serde(skip_serializing)]
+    last_sample: Option<DateTime<Utc>>,
+}
+
+impl CalibrationSession {
+    /// Beginnt eine neue Kalibrierung, mit dem Nullgas Schritt
+    ///
+    /// # Parameters
+    ///
+    /// * `zone_id`         - Id der Zone
+    /// * `kombisensor_id`  - Id des Kombisensors in der Zone
+    /// * `sensor_id`       - Id der Sensormesszelle
+    /// * `sensor`          - Messzelle mit der bisherigen Kalibrierung
+    /// * `settings`        - Einstellungen der Kalibrierung
+    /// * `now`             - Beginn der Kalibrierung
+    pub fn new(zone_id: usize, kombisensor_id: usize, sensor_id: usize, sensor: &Sensor, settings: CalibrationSettings, now: DateTime<Utc>) -> Self {
+        let adc_span = sensor.get_adc_value_at_messgas() as f64 - sensor.get_adc_value_at_nullgas() as f64;
+        let concentration_span = sensor.get_concentration_at_messgas() as f64 - sensor.get_concentration_at_nullgas() as f64;
+        let previous_slope = if adc_span != 0.0 && concentration_span != 0.0 {
+            Some(concentration_span / adc_span)
+        } else {
+            None
+        };
+
+        CalibrationSession {
+            zone_id: zone_id,
+            kombisensor_id: kombisensor_id,
+            sensor_id: sensor_id,
+            settings: settings,
+            step: CalibrationStep::Nullgas,
+            started: now,
+            step_started: now,
+            adc_value: None,
+            adc_value_at_nullgas: None,
+            adc_value_at_messgas: None,
+            concentration_at_messgas: None,
+            previous_slope: previous_slope,
+            samples: vec![],
+            last_sample: None,
+        }
+    }
+
+    /// Id der Zone
+    pub fn get_zone_id(&self) -> usize {
+        self.zone_id
+    }
+
+    /// Id des Kombisensors in der Zone
+    pub fn get_kombisensor_id(&self) -> usize {
+        self.kombisensor_id
+    }
+
+    /// Id der Sensormesszelle
+    pub fn get_sensor_id(&self) -> usize {
+        self.sensor_id
+    }
+
+    /// Aktueller Schritt der Kalibrierung
+    pub fn get_step(&self) -> &CalibrationStep {
+        &self.step
+    }
+
+    /// Erfasster ADC Wert bei Nullgas
+    pub fn get_adc_value_at_nullgas(&self) -> Option<u16> {
+        self.adc_value_at_nullgas
+    }
+
+    /// Erfasster ADC Wert bei Messgas
+    pub fn get_adc_value_at_messgas(&self) -> Option<u16> {
+        self.adc_value_at_messgas
+    }
+
+    /// Konzentration des Messgases
+    pub fn get_concentration_at_messgas(&self) -> Option<u16> {
+        self.concentration_at_messgas
+    }
+
+    /// Läuft die Kalibrierung noch?
+    ///
+    /// Eine übernommene, abgebrochene oder fehlgeschlagene Kalibrierung ist beendet.
+    pub fn is_active(&self) -> bool {
+        match self.step {
+            CalibrationStep::Committed | CalibrationStep::Aborted | CalibrationStep::Failed(_) => false,
+            _ => true,
+        }
+    }
+
+    /// Steigung (Konzentration pro ADC Wert) aus den erfassten Werten
+    pub fn get_slope(&self) -> Option<f64> {
+        match (self.adc_value_at_nullgas, self.adc_value_at_messgas, self.concentration_at_messgas) {
+            (Some(nullgas), Some(messgas), Some(concentration)) if nullgas != messgas => {
+                Some(concentration as f64 / (messgas as f64 - nullgas as f64))
+            }
+            _ => None,
+        }
+    }
+
+    /// Übernimmt einen ADC Wert der Messzelle
+    ///
+    /// Ist der ADC Wert über den Zeitraum `stable_sec` stabil, wird der Mittelwert dieses Zeitraums erfasst und
+    /// die Kalibrierung geht in den nächsten Schritt. Außerhalb der Schritte `Nullgas` und `Messgas` wird der Wert
+    /// ignoriert, ebenso ein Wert, der nicht neuer als der zuletzt übernommene ist. Ein eingefrorener Wert, z.B.
+    /// bei einem Kabelbruch, wird so nicht als stabil erfasst. Anschließend werden die Zeitgrenzen geprüft, siehe
+    /// [`check_timeout()`](#method.check_timeout).
+    ///
+    /// # Parameters
+    ///
+    /// * `adc_value`   - ADC Wert der Messzelle
+    /// * `now`         - Zeitpunkt des ADC Werts
+    pub fn add_sample(&mut self, adc_value: u16, now: DateTime<Utc>) {
+        let is_new = self.last_sample.map_or(true, |last_sample| now > last_sample);
+        if !is_new || (self.step != CalibrationStep::Nullgas && self.step != CalibrationStep::Messgas) {
+            self.check_timeout(now);
+            return;
+        }
+        self.last_sample = Some(now);
+
+        self.adc_value = Some(adc_value);
+        let stable_since = now - Duration::seconds(self.settings.stable_sec);
+        self.samples.retain(|&(timestamp, _)| timestamp >= stable_since);
+        self.samples.push((now, adc_value));
+
+        if let Some(stable_value) = self.stable_value(now) {
+            if self.step == CalibrationStep::Nullgas {
+                self.adc_value_at_nullgas = Some(stable_value);
+                self.set_step(CalibrationStep::NullgasCaptured, now);
+            } else {
+                self.adc_value_at_messgas = Some(stable_value);
+                match self.validate() {
+                    Ok(_) => self.set_step(CalibrationStep::MessgasCaptured, now),
+                    Err(e) => self.set_step(CalibrationStep::Failed(e.to_string()), now),
+                }
+            }
+        } else {
+            self.check_timeout(now);
+        }
+    }
+
+    /// Prüft die Zeitgrenzen der Kalibrierung
+    ///
+    /// Wird das Gas nicht innerhalb von `step_timeout_sec` stabil, schlägt die Kalibrierung fehl. Ist die
+    /// Kalibrierung nicht innerhalb von `session_timeout_sec` beendet, egal in welchem Schritt, wird sie ebenfalls
+    /// beendet. So bleiben die Alarmausgänge der Zone nicht gesperrt, wenn die Kalibrierung vergessen wurde.
+    ///
+    /// # Parameters
+    ///
+    /// * `now`     - aktueller Zeitpunkt
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// extern crate chrono;
+    /// # extern crate xmz_mod_touch_server;
+    /// use chrono::{Duration, Utc};
+    /// use xmz_mod_touch_server::{Sensor, SensorType};
+    /// use xmz_mod_touch_server::server::{CalibrationSession, CalibrationSettings, CalibrationStep};
+    ///
+    /// # fn main() {
+    /// let sensor = Sensor::new_with_type(SensorType::SimulationNO2);
+    /// let settings = CalibrationSettings { stable_sec: 0, session_timeout_sec: 600, ..Default::default() };
+    /// let now = Utc::now();
+    /// let mut session = CalibrationSession::new(0, 0, 0, &sensor, settings, now);
+    /// session.add_sample(910, now);
+    /// assert_eq!(session.get_step(), &CalibrationStep::NullgasCaptured);
+    ///
+    /// // Es wird kein Messgas aufgegeben
+    /// session.check_timeout(now + Duration::seconds(600));
+    /// assert!(session.is_active());
+    /// session.check_timeout(now + Duration::seconds(601));
+    /// assert!(!session.is_active());
+    /// # }
+    /// ```
+    pub fn check_timeout(&mut self, now: DateTime<Utc>) {
+        if !self.is_active() { return; }
+
+        if now - self.started > Duration::seconds(self.settings.session_timeout_sec) {
+            let reason = format!("Kalibrierung nicht innerhalb von {} Sekunden beendet", self.settings.session_timeout_sec);
+            self.set_step(CalibrationStep::Failed(reason), now);
+        } else if (self.step == CalibrationStep::Nullgas || self.step == CalibrationStep::Messgas) &&
+            now - self.step_started > Duration::seconds(self.settings.step_timeout_sec) {
+            let reason = format!("ADC Wert innerhalb von {} Sekunden nicht stabil", self.settings.step_timeout_sec);
+            self.set_step(CalibrationStep::Failed(reason), now);
+        }
+    }
+
+    /// Beginnt den Messgas Schritt
+    ///
+    /// Möglich nachdem das Nullgas erfasst wurde. Um das Messgas zu wiederholen auch nachdem es erfasst wurde.
+    ///
+    /// # Parameters
+    ///
+    /// * `concentration`   - Konzentration des aufgegebenen Messgases
+    /// * `now`             - Beginn des Schritts
+    pub fn start_messgas(&mut self, concentration: u16, now: DateTime<Utc>) -> Result<()> {
+        if self.step != CalibrationStep::NullgasCaptured && self.step != CalibrationStep::MessgasCaptured {
+            bail!("Messgas is not expected in calibration step {:?}", self.step)
+        }
+        if concentration == 0 {
+            bail!("Messgas concentration must be greater than 0")
+        }
+
+        self.concentration_at_messgas = Some(concentration);
+        self.adc_value_at_messgas = None;
+        self.set_step(CalibrationStep::Messgas, now);
+
+        Ok(())
+    }
+
+    /// Schreibt die erfassten Werte in die Messzelle
+    ///
+    /// **Diese Funktion ändert nur die Werte im Speicher**, in die Firmware werden sie mit
+    /// [`Server::commit_calibration()`](../server/struct.Server.html#method.commit_calibration) geschrieben.
+    ///
+    /// # Parameters
+    ///
+    /// * `sensor`  - kalibrierte Messzelle
+    pub fn apply(&self, sensor: &mut Sensor) -> Result<()> {
+        match (&self.step, self.adc_value_at_nullgas, self.adc_value_at_messgas, self.concentration_at_messgas) {
+            (&CalibrationStep::MessgasCaptured, Some(nullgas), Some(messgas), Some(concentration)) => {
+                sensor.set_adc_value_at_nullgas(nullgas);
+                sensor.set_adc_value_at_messgas(messgas);
+                sensor.set_concentration_at_nullgas(0);
+                sensor.set_concentration_at_messgas(concentration);
+                Ok(())
+            }
+            _ => bail!("Calibration values are not complete (step {:?})", self.step),
+        }
+    }
+
+    /// Markiert die Kalibrierung als übernommen
+    pub fn set_committed(&mut self, now: DateTime<Utc>) {
+        self.set_step(CalibrationStep::Committed, now);
+    }
+
+    /// Bricht die Kalibrierung ab
+    pub fn abort(&mut self, now: DateTime<Utc>) {
+        self.set_step(CalibrationStep::Aborted, now);
+    }
+
+    /// Lässt die laufende Kalibrierung fehlschlagen
+    ///
+    /// # Parameters
+    ///
+    /// * `reason`  - Grund, z.B. ein Kabelbruch des Kombisensors
+    /// * `now`     - aktueller Zeitpunkt
+    pub fn fail(&mut self, reason: String, now: DateTime<Utc>) {
+        if self.is_active() {
+            self.set_step(CalibrationStep::Failed(reason), now);
+        }
+    }
+
+    fn set_step(&mut self, step: CalibrationStep, now: DateTime<Utc>) {
+        self.step = step;
+        self.step_started = now;
+        self.samples.clear();
+    }
+
+    // Mittelwert der ADC Werte, wenn sie seit mindestens `stable_sec` innerhalb der erlaubten Schwankung liegen
+    fn stable_value(&self, now: DateTime<Utc>) -> Option<u16> {
+        if now - self.step_started < Duration::seconds(self.settings.stable_sec) { return None; }
+
+        let min = self.samples.iter().map(|&(_, adc_value)| adc_value).min();
+        let max = self.samples.iter().map(|&(_, adc_value)| adc_value).max();
+        let (min, max) = match (min, max) {
+            (Some(min), Some(max)) => (min, max),
+            _ => return None,
+        };
+        if max - min > self.settings.stable_max_deviation { return None; }
+
+        let sum: u64 = self.samples.iter().map(|&(_, adc_value)| adc_value as u64).sum();
+        Some(((sum as f64) / (self.samples.len() as f64)).round() as u16)
+    }
+
+    // Prüft die Steigung aus Nullgas und Messgas
+    fn validate(&self) -> Result<()> {
+        let (nullgas, messgas) = match (self.adc_value_at_nullgas, self.adc_value_at_messgas) {
+            (Some(nullgas), Some(messgas)) => (nullgas, messgas),
+            _ => bail!("Nullgas or Messgas not captured"),
+        };
+        let span = if messgas > nullgas { messgas - nullgas } else { nullgas - messgas };
+        if span < self.settings.min_adc_span {
+            bail!("ADC values of Nullgas ({}) and Messgas ({}) differ by less than {}", nullgas, messgas, self.settings.min_adc_span)
+        }
+
+        if let (Some(slope), Some(previous_slope)) = (self.get_slope(), self.previous_slope) {
+            if slope.signum() != previous_slope.signum() {
+                bail!("Slope {:.4} has the wrong sign (previous calibration {:.4})", slope, previous_slope)
+            }
+            if (slope / previous_slope - 1.0).abs() > self.settings.max_slope_deviation {
+                bail!("Slope {:.4} deviates too much from the previous calibration ({:.4})", slope, previous_slope)
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -2,10 +2,18 @@
 //!
 //! Hier werden alle Komponenten des Servers verwaltet.
 //!
+pub mod acquisition;
+pub mod calibration;
 pub mod configuration;
+pub mod scenario;
 pub mod server;
+pub mod state;
 pub mod zone;
 
+pub use self::acquisition::Acquisition;
+pub use self::calibration::{CalibrationSession, CalibrationSettings, CalibrationStep};
 pub use self::configuration::Configuration;
+pub use self::scenario::{GasScenario, SensorScenario};
 pub use self::server::{Server, ServerType};
+pub use self::state::{SensorState, ServerState, STATE_FILE};
 pub use self::zone::{Zone, ZoneStatus};
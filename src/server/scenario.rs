@@ -0,0 +1,139 @@
+//! Szenario für einen Simulation Server
+//!
+//! Ein Szenario ordnet simulierten Messzellen einen Gasverlauf zu (siehe
+//! [`GasProfile`](../zone/kombisensor/sensor/gas_profile/struct.GasProfile.html)). So kann z.B. für Vorführungen
+//! oder Schulungen ein Gasereignis von AP1 über AP2 bis DIW, und zurück, reproduzierbar abgespielt werden.
+//!
+use errors::*;
+use serde_json;
+use server::{Server, ServerType};
+use server::zone::kombisensor::sensor::GasProfile;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+
+/// Gasverlauf einer Messzelle im Szenario
+///
+/// Die Messzelle wird, wie in der Konfiguration, über Zone, Kombisensor und Nummer der Messzelle adressiert.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct SensorScenario {
+    pub num_zone: usize,
+    pub num_kombisensor: usize,
+    pub num_sensor: usize,
+    pub gas_profile: GasProfile,
+}
+
+/// Szenario eines Simulation Servers
+///
+/// # Examples
+///
+/// ```rust
+/// use xmz_mod_touch_server::{Kombisensor, KombisensorType, Server};
+/// use xmz_mod_touch_server::server::{GasScenario, SensorScenario};
+/// use xmz_mod_touch_server::server::zone::kombisensor::sensor::{GasProfile, GasProfilePoint, Transition};
+///
+/// let mut xmz_mod_touch_server = Server::new();
+/// xmz_mod_touch_server.add_zone();
+/// xmz_mod_touch_server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+///
+/// let scenario = GasScenario::new(vec![SensorScenario {
+///     num_zone: 0,
+///     num_kombisensor: 0,
+///     num_sensor: 1,
+///     gas_profile: GasProfile::new(vec![
+///         GasProfilePoint { time_sec: 0.0, concentration: 50.0, transition: Transition::Step },
+///     ]),
+/// }]);
+/// assert_eq!(scenario.apply(&mut xmz_mod_touch_server).unwrap(), 1);
+///
+/// xmz_mod_touch_server.evaluate();
+/// let sensor = xmz_mod_touch_server.get_zone(0).unwrap().get_kombisensor(0).unwrap().get_sensor(1).unwrap();
+/// assert!((sensor.get_concentration() - 50.0).abs() < 1.0);
+/// ```
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct GasScenario {
+    sensors: Vec<SensorScenario>,
+}
+
+impl GasScenario {
+    /// Erstellt ein Szenario
+    ///
+    /// # Parameters
+    ///
+    /// * `sensors` - Gasverläufe der Messzellen
+    pub fn new(sensors: Vec<SensorScenario>) -> Self {
+        GasScenario {
+            sensors: sensors,
+        }
+    }
+
+    /// Gasverläufe der Messzellen
+    pub fn get_sensors(&self) -> &Vec<SensorScenario> {
+        &self.sensors
+    }
+
+    /// Übergibt die Gasverläufe an die Messzellen des Servers
+    ///
+    /// Alle Gasverläufe beginnen mit dem nächsten Update der Messzellen.
+    ///
+    /// # Return values
+    ///
+    /// Liefert die Anzahl der übernommenen Gasverläufe, oder einen Fehler wenn der Server kein Simulation Server
+    /// ist, oder eine Messzelle nicht existiert bzw. nicht simuliert ist. Im Fehlerfall wird kein Gasverlauf
+    /// übernommen.
+    ///
+    /// # Parameters
+    ///
+    /// * `server`  - Server, dessen Messzellen die Gasverläufe abspielen
+    pub fn apply(&self, server: &mut Server) -> Result<usize> {
+        if server.get_server_type() != ServerType::Simulation {
+            bail!("Gas scenarios require a simulation server")
+        }
+
+        for scenario in &self.sensors {
+            let sensor = server.get_zone(scenario.num_zone)
+                .and_then(|zone| zone.get_kombisensor(scenario.num_kombisensor))
+                .and_then(|kombisensor| kombisensor.get_sensor(scenario.num_sensor));
+            match sensor {
+                Some(sensor) => if !sensor.get_sensor_type().is_simulation() {
+                    bail!("Sensor {} of Kombisensor {} in Zone {} is not simulated",
+                        scenario.num_sensor, scenario.num_kombisensor, scenario.num_zone)
+                },
+                None => bail!("Sensor {} of Kombisensor {} in Zone {} not found",
+                    scenario.num_sensor, scenario.num_kombisensor, scenario.num_zone),
+            }
+        }
+
+        for scenario in &self.sensors {
+            if let Some(sensor) = server.get_zone_mut(scenario.num_zone)
+                .and_then(|zone| zone.get_kombisensor_mut(scenario.num_kombisensor))
+                .and_then(|kombisensor| kombisensor.get_sensor_mut(scenario.num_sensor)) {
+                sensor.set_gas_profile(Some(scenario.gas_profile.clone()));
+            }
+        }
+
+        Ok(self.sensors.len())
+    }
+
+    /// Liest ein Szenario aus einer Datei
+    ///
+    /// # Parameters
+    ///
+    /// * `path`    - Pfad der Szenario Datei
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut content = String::new();
+        File::open(path)?.read_to_string(&mut content)?;
+
+        let mut scenario: GasScenario = serde_json::from_str(&content)?;
+        for sensor in &mut scenario.sensors {
+            sensor.gas_profile.sort_points();
+        }
+
+        Ok(scenario)
+    }
+}
@@ -4,18 +4,27 @@
 //!
 use chrono;
 use chrono::prelude::*;
+use server::calibration::{CalibrationSession, CalibrationSettings};
 use server::configuration::Configuration;
+use server::scenario::GasScenario;
+use server::state::{ServerState, STATE_FILE};
+use server::zone::kombisensor::sensor::{SensorStatus, SENSOR_REPLACE_SENSITIVITY_PERCENT};
 use errors::*;
 use exception::{Exception, ExceptionType};
+use modbus_bus::{DiscoveredKombisensor, ModbusBusManager, PollRequest, PollResult, SerialParameters, TrafficRecorder, TrafficReplay, MODBUS_SIM_PREFIX, MODBUS_TCP_PREFIX, TRAFFIC_RECORDING_MAX_BYTES};
 use serde_json;
 use shift_register::{ShiftRegister, ShiftRegisterType};
 use std::cell::RefCell;
 use std::collections::HashSet;
 use std::sync::{Arc, Mutex};
 use server::zone::{Zone, ZoneStatus};
-use server::zone::kombisensor::{Kombisensor, KombisensorStatus};
+use server::zone::kombisensor::{Kombisensor, KombisensorStatus, KombisensorType};
 
 
+fn default_state_file() -> Option<String> { Some(STATE_FILE.to_string()) }
+fn default_sensor_replace_sensitivity_percent() -> f64 { SENSOR_REPLACE_SENSITIVITY_PERCENT }
+fn default_traffic_recording_max_bytes() -> u64 { TRAFFIC_RECORDING_MAX_BYTES }
+
 #[derive(Clone)]
 #[derive(Debug)]
 #[derive(Eq, PartialEq)]
@@ -43,6 +52,36 @@ pub struct Server {
     zones: Vec<Zone>,
     leds: ShiftRegister,
     relais: ShiftRegister,
+    // IP Adresse und Port des Modbus TCP Slaves für die GLT, z.B. "0.0.0.0:502". Ohne Adresse ist der Slave aus.
+    #[serde(default)]
+    glt_listen_address: Option<String>,
+    // Datei, in die alle Abfragen der Kombisensoren aufgezeichnet werden. Ohne Datei wird nicht aufgezeichnet.
+    #[serde(default)]
+    traffic_recording_file: Option<String>,
+    // Maximale Größe der Aufzeichnung in Bytes, danach wird sie nach `<Datei>.1` umbenannt
+    #[serde(default = "default_traffic_recording_max_bytes")]
+    traffic_recording_max_bytes: u64,
+    // Aufzeichnung, die statt der Modbus Leitungen abgespielt wird. Nur bei einem Simulation Server.
+    #[serde(default)]
+    traffic_replay_file: Option<String>,
+    // Gasverlauf der simulierten Messzellen, siehe `GasScenario`. Nur bei einem Simulation Server.
+    #[serde(default)]
+    scenario_file: Option<String>,
+    // Datei für den Zustand des Servers (Mittelwerte), getrennt von der Konfiguration. Ohne Datei kein Zustand.
+    #[serde(default = "default_state_file")]
+    state_file: Option<String>,
+    // Einstellungen der geführten Kalibrierung
+    #[serde(default)]
+    calibration_settings: CalibrationSettings,
+    // Grenze der Empfindlichkeit (in Prozent der ersten Kalibrierung), unter der eine Messzelle getauscht werden sollte
+    #[serde(default = "default_sensor_replace_sensitivity_percent")]
+    sensor_replace_sensitivity_percent: f64,
+    // Laufende bzw. zuletzt beendete Kalibrierung, es kann immer nur eine Messzelle kalibriert werden
+    #[serde(skip_deserializing, skip_serializing)]
+    calibration_session: Option<CalibrationSession>,
+    // Offene Modbus Verbindungen, eine pro Leitung. Wird mit den Erfassungs Threads geteilt.
+    #[serde(skip_deserializing, skip_serializing)]
+    bus_manager: Arc<ModbusBusManager>,
 }
 
 impl Server {
@@ -71,6 +110,16 @@ impl Server {
             leds: ShiftRegister::new(ShiftRegisterType::Simulation),
             relais: ShiftRegister::new(ShiftRegisterType::Simulation),
             zones: vec![],
+            glt_listen_address: None,
+            traffic_recording_file: None,
+            traffic_recording_max_bytes: TRAFFIC_RECORDING_MAX_BYTES,
+            traffic_replay_file: None,
+            scenario_file: None,
+            state_file: default_state_file(),
+            calibration_settings: CalibrationSettings::default(),
+            sensor_replace_sensitivity_percent: SENSOR_REPLACE_SENSITIVITY_PERCENT,
+            calibration_session: None,
+            bus_manager: Arc::new(ModbusBusManager::new()),
         }
     }
 
@@ -131,12 +180,71 @@ impl Server {
             Err(_) => bail!("Konnte Konfigurationsdatei nicht lesen. Server konnte nicht erstellt werden."),
         };
 
+        xmz_mod_touch_server.check_serial_parameters()?;
+
         // Update start_time to now
         xmz_mod_touch_server.reset_start_time();
 
         Ok(xmz_mod_touch_server)
     }
 
+    /// Prüft die Leitungsparameter der Kombisensoren
+    ///
+    /// Alle Kombisensoren einer seriellen Leitung teilen sich eine Verbindung. Baudrate, Parität, Daten- und
+    /// Stopbits sowie RTS Modus und RTS Verzögerung (siehe
+    /// [`SerialParameters::is_same_line()`](../modbus_bus/struct.SerialParameters.html#method.is_same_line)) müssen
+    /// deshalb bei allen Kombisensoren der Leitung gleich sein, sonst müsste die Verbindung bei jeder Abfrage neu
+    /// aufgebaut werden. Nur die Timeouts dürfen sich unterscheiden. Modbus TCP und emulierte Leitungen haben keine
+    /// Leitungsparameter.
+    ///
+    /// # Return values
+    ///
+    /// Liefert einen Fehler mit den beiden Kombisensoren, deren Leitungsparameter sich widersprechen.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Kombisensor, KombisensorType, Server};
+    ///
+    /// let mut xmz_mod_touch_server = Server::new();
+    /// xmz_mod_touch_server.add_zone();
+    /// xmz_mod_touch_server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGas));
+    /// let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGas);
+    /// kombisensor.set_modbus_address(12);
+    /// xmz_mod_touch_server.get_zone_mut(0).unwrap().add_kombisensor(kombisensor);
+    /// assert!(xmz_mod_touch_server.check_serial_parameters().is_ok());
+    ///
+    /// let mut serial_parameters = xmz_mod_touch_server.get_zone(0).unwrap().get_kombisensor(1).unwrap().get_serial_parameters();
+    /// serial_parameters.set_baud(19200);
+    /// xmz_mod_touch_server.get_zone_mut(0).unwrap().get_kombisensor_mut(1).unwrap().set_serial_parameters(Some(serial_parameters));
+    /// assert!(xmz_mod_touch_server.check_serial_parameters().is_err());
+    /// ```
+    pub fn check_serial_parameters(&self) -> Result<()> {
+        // erster Kombisensor jeder Leitung: (Leitung, Zone, Kombisensor, Leitungsparameter)
+        let mut lines: Vec<(String, usize, usize, SerialParameters)> = vec![];
+
+        for (zone_id, zone) in self.get_zones().iter().enumerate() {
+            for (kombisensor_id, kombisensor) in zone.get_kombisensors().iter().enumerate() {
+                let modbus_device = kombisensor.get_modbus_device();
+                if modbus_device.starts_with(MODBUS_TCP_PREFIX) || modbus_device.starts_with(MODBUS_SIM_PREFIX) {
+                    continue;
+                }
+                let serial_parameters = kombisensor.get_serial_parameters();
+
+                if let Some(&(_, first_zone_id, first_kombisensor_id, ref first_serial_parameters)) = lines.iter().find(|line| line.0 == modbus_device) {
+                    if !first_serial_parameters.is_same_line(&serial_parameters) {
+                        bail!("Kombisensor {} in Zone {} uses different line parameters on {} than Kombisensor {} in Zone {}",
+                            kombisensor_id, zone_id, modbus_device, first_kombisensor_id, first_zone_id)
+                    }
+                    continue;
+                }
+                lines.push((modbus_device, zone_id, kombisensor_id, serial_parameters));
+            }
+        }
+
+        Ok(())
+    }
+
 
     /// Check Funktion des Server
     ///
@@ -150,6 +258,26 @@ impl Server {
     /// let xmz_mod_touch_server = Server::new();
     /// assert!(xmz_mod_touch_server.check().is_ok());
     /// ```
+    ///
+    /// Kombisensoren mit einer nicht unterstützten Firmware werden als Ausnahme gemeldet
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Exception, ExceptionType, Kombisensor, Server};
+    ///
+    /// let mut xmz_mod_touch_server = Server::new();
+    /// xmz_mod_touch_server.add_zone();
+    /// let mut kombisensor = Kombisensor::new();
+    /// kombisensor.set_firmware_version("9.9.99".to_string());
+    /// xmz_mod_touch_server.get_zone_mut(0).unwrap().add_kombisensor(kombisensor);
+    ///
+    /// xmz_mod_touch_server.check().unwrap();
+    /// let exception = Exception::new(ExceptionType::KombisensorFirmwareUnsupported { num_zone: 0, num_kombisensor: 0 });
+    /// assert!(xmz_mod_touch_server.get_exceptions().lock().unwrap().contains(&exception));
+    ///
+    /// xmz_mod_touch_server.get_zone_mut(0).unwrap().get_kombisensor_mut(0).unwrap().set_firmware_version("0.14.0".to_string());
+    /// xmz_mod_touch_server.check().unwrap();
+    /// assert!(!xmz_mod_touch_server.get_exceptions().lock().unwrap().contains(&exception));
+    /// ```
     pub fn check(&self) -> Result<()> {
         debug!("Check Server ...");
         if self.wartungsintervall_reached() {
@@ -164,37 +292,126 @@ impl Server {
             match zone.get_status() {
                 ZoneStatus::DIW => {
                     self.leds.set(5 + zone_offset)?; self.leds.set(6 + zone_offset)?; self.leds.set(7 + zone_offset)?;
-                    self.relais.set(2 + zone_offset)?; self.relais.set(3 + zone_offset)?; self.relais.set(4 + zone_offset)?;
                 }
                 ZoneStatus::AP2 => {
                     self.leds.set(5 + zone_offset)?; self.leds.set(6 + zone_offset)?; self.leds.clear(7 + zone_offset)?;
-                    self.relais.set(2 + zone_offset)?; self.relais.set(3 + zone_offset)?; self.relais.clear(4 + zone_offset)?;
                 }
                 ZoneStatus::AP1 => {
                     self.leds.set(5 + zone_offset)?; self.leds.clear(6 + zone_offset)?; self.leds.clear(7 + zone_offset)?;
-                    self.relais.set(2 + zone_offset)?; self.relais.clear(3 + zone_offset)?; self.relais.clear(4 + zone_offset)?;
                 }
                 ZoneStatus::Normal => {
                     self.leds.clear(5 + zone_offset)?; self.leds.clear(6 + zone_offset)?; self.leds.clear(7 + zone_offset)?;
+                }
+            }
+            // Während einer Kalibrierung bleiben die Alarmausgänge der Zone aus, die LEDs zeigen den Status weiter an
+            let relais_status = if self.is_zone_alarm_inhibited(num_zone) { ZoneStatus::Normal } else { zone.get_status() };
+            match relais_status {
+                ZoneStatus::DIW => {
+                    self.relais.set(2 + zone_offset)?; self.relais.set(3 + zone_offset)?; self.relais.set(4 + zone_offset)?;
+                }
+                ZoneStatus::AP2 => {
+                    self.relais.set(2 + zone_offset)?; self.relais.set(3 + zone_offset)?; self.relais.clear(4 + zone_offset)?;
+                }
+                ZoneStatus::AP1 => {
+                    self.relais.set(2 + zone_offset)?; self.relais.clear(3 + zone_offset)?; self.relais.clear(4 + zone_offset)?;
+                }
+                ZoneStatus::Normal => {
                     self.relais.clear(2 + zone_offset)?; self.relais.clear(3 + zone_offset)?; self.relais.clear(4 + zone_offset)?;
                 }
             }
 
             for (num_kombisensor, kombisensor) in zone.get_kombisensors().iter().enumerate() {
                 debug!("\t\tCheck Kombisensor {} ...", num_kombisensor);
+                let firmware_exception = Exception::new(ExceptionType::KombisensorFirmwareUnsupported { num_zone: num_zone, num_kombisensor: num_kombisensor });
+                if let Ok(mut exceptions) = self.exceptions.lock() {
+                    if kombisensor.is_firmware_unsupported() {
+                        exceptions.insert(firmware_exception);
+                    } else {
+                        exceptions.remove(&firmware_exception);
+                    }
+                } else {
+                    bail!("Could not lock exceptions member")
+                }
+
                 match kombisensor.get_status() {
-                    KombisensorStatus::Kabelbruch => {
+                    // Die Störung bleibt auch während des Wiederanlaufs angezeigt
+                    KombisensorStatus::Kabelbruch | KombisensorStatus::Wiederanlauf => {
                         self.leds.set(2)?;
                         //self.relais.clear(1)?;
                     }
+                    // Eine nicht unterstützte Firmware wird ebenfalls als Störung angezeigt
+                    _ if kombisensor.is_firmware_unsupported() => {
+                        self.leds.set(2)?;
+                    }
                     _ => {
                         self.leds.clear(2)?;
                         //self.relais.clear(1)?;
                     }
                 }
 
-                for (num_sensor, _sensor) in kombisensor.get_sensors().iter().enumerate() {
+                for (num_sensor, sensor) in kombisensor.get_sensors().iter().enumerate() {
                     debug!("\t\t\tCheck Sensor {} ...", num_sensor);
+                    // Lückenhafter 15min Mittelwert, AP1/ AP2 werden nicht ausgewertet
+                    let average_exception = Exception::new(ExceptionType::SensorAverage15minInvalid { num_zone: num_zone, num_kombisensor: num_kombisensor, num_sensor: num_sensor });
+                    if let Ok(mut exceptions) = self.exceptions.lock() {
+                        if sensor.is_enabled() && sensor.is_online() && !sensor.is_average_15min_valid() {
+                            exceptions.insert(average_exception);
+                        } else {
+                            exceptions.remove(&average_exception);
+                        }
+                    } else {
+                        bail!("Could not lock exceptions member")
+                    }
+
+                    // Messzelle wird kalibriert
+                    let calibration_exception = Exception::new(ExceptionType::SensorCalibration { num_zone: num_zone, num_kombisensor: num_kombisensor, num_sensor: num_sensor });
+                    let calibrating = self.calibration_session.as_ref().map_or(false, |session| {
+                        session.is_active() && session.get_zone_id() == num_zone &&
+                        session.get_kombisensor_id() == num_kombisensor && session.get_sensor_id() == num_sensor
+                    });
+                    if let Ok(mut exceptions) = self.exceptions.lock() {
+                        if calibrating {
+                            exceptions.insert(calibration_exception);
+                        } else {
+                            exceptions.remove(&calibration_exception);
+                        }
+                    } else {
+                        bail!("Could not lock exceptions member")
+                    }
+
+                    // Empfindlichkeit der Messzelle zu gering
+                    let replace_exception = Exception::new(ExceptionType::SensorReplaceSoon { num_zone: num_zone, num_kombisensor: num_kombisensor, num_sensor: num_sensor });
+                    if let Ok(mut exceptions) = self.exceptions.lock() {
+                        if sensor.is_enabled() && sensor.get_health().is_replace_soon(self.sensor_replace_sensitivity_percent) {
+                            exceptions.insert(replace_exception);
+                        } else {
+                            exceptions.remove(&replace_exception);
+                        }
+                    } else {
+                        bail!("Could not lock exceptions member")
+                    }
+
+                    // Messbereich unter- bzw. überschritten
+                    let under_range_exception = Exception::new(ExceptionType::SensorUnderRange { num_zone: num_zone, num_kombisensor: num_kombisensor, num_sensor: num_sensor });
+                    let over_range_exception = Exception::new(ExceptionType::SensorOverRange { num_zone: num_zone, num_kombisensor: num_kombisensor, num_sensor: num_sensor });
+                    if let Ok(mut exceptions) = self.exceptions.lock() {
+                        match sensor.get_status() {
+                            SensorStatus::UnderRange => {
+                                exceptions.insert(under_range_exception);
+                                exceptions.remove(&over_range_exception);
+                            }
+                            SensorStatus::OverRange => {
+                                exceptions.remove(&under_range_exception);
+                                exceptions.insert(over_range_exception);
+                            }
+                            SensorStatus::Normal => {
+                                exceptions.remove(&under_range_exception);
+                                exceptions.remove(&over_range_exception);
+                            }
+                        }
+                    } else {
+                        bail!("Could not lock exceptions member")
+                    }
                 }
             }
         }
@@ -204,7 +421,11 @@ impl Server {
 
     /// Update Funktion des Server
     ///
-    /// Hier werden die Zonen durchlaufen, und deren `update()` Funktion aufgerufen.
+    /// Hier werden alle Kombisensoren, einer nach dem anderen, abgefragt und anschließend die Zonen und Sensoren
+    /// ausgewertet (siehe [`evaluate()`](#method.evaluate)).
+    ///
+    /// Im Server Prozess werden die Leitungen statt dessen parallel, von einem Thread pro Leitung, abgefragt.
+    /// Siehe [`Acquisition`](../acquisition/struct.Acquisition.html)
     ///
     /// # Examples
     ///
@@ -216,13 +437,41 @@ impl Server {
     /// ```
     pub fn update(&mut self) {
         debug!("Check Server ...");
-        for (num_zone, mut zone) in &mut self.get_zones_mut().iter_mut().enumerate() {
+        {
+            // Alle Kombisensoren einer Leitung teilen sich die Verbindung des `bus_manager`
+            let bus_manager = &self.bus_manager;
+            for (num_zone, zone) in self.zones.iter_mut().enumerate() {
+                for (num_kombisensor, kombisensor) in zone.get_kombisensors_mut().iter_mut().enumerate() {
+                    debug!("\tCheck Zone {} Kombisensor {} ...", num_zone, num_kombisensor);
+                    kombisensor.update(bus_manager);
+                }
+            }
+        }
+
+        self.evaluate();
+    }
+
+    /// Wertet die Zonen und Sensoren aus, ohne die Kombisensoren abzufragen
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Server, ZoneStatus};
+    ///
+    /// let mut xmz_mod_touch_server = Server::new();
+    /// xmz_mod_touch_server.add_zone();
+    /// xmz_mod_touch_server.evaluate();
+    /// assert_eq!(xmz_mod_touch_server.get_zone(0).unwrap().get_status(), ZoneStatus::Normal);
+    /// ```
+    pub fn evaluate(&mut self) {
+        self.update_calibration(Utc::now());
+
+        for (num_zone, mut zone) in &mut self.zones.iter_mut().enumerate() {
             debug!("\tCheck Zone {} ...", num_zone);
             zone.update();
 
             for (num_kombisensor, mut kombisensor) in &mut zone.get_kombisensors_mut().iter_mut().enumerate() {
                 debug!("\t\tCheck Kombisensor {} ...", num_kombisensor);
-                kombisensor.update();
 
                 for (num_sensor, mut sensor) in &mut kombisensor.get_sensors_mut().iter_mut().enumerate() {
                     debug!("\t\t\tCheck Sensor {} ...", num_sensor);
@@ -232,65 +481,326 @@ impl Server {
         }
     }
 
-    /// `basic_configuration` - Grundkonfiguration/ Grundeistellungen der LEDs und Relais
+    /// Liefert die Verwaltung der Modbus Leitungen
+    ///
+    /// Der `ModbusBusManager` kann geteilt werden, so dass Abfragen auch ohne den Lock des Servers
+    /// ausgeführt werden können.
+    pub fn get_bus_manager(&self) -> Arc<ModbusBusManager> {
+        self.bus_manager.clone()
+    }
+
+    /// Liefert die Leitungen aller Kombisensoren, jede Leitung nur ein mal
+    ///
+    /// Siehe [`Kombisensor::get_bus_device()`](zone/kombisensor/kombisensor/struct.Kombisensor.html#method.get_bus_device)
     ///
     /// # Examples
     ///
-    /// ```rust,no_run
+    /// ```rust
+    /// use xmz_mod_touch_server::{Kombisensor, KombisensorType, Server};
+    ///
+    /// let mut xmz_mod_touch_server = Server::new();
+    /// xmz_mod_touch_server.add_zone();
+    /// xmz_mod_touch_server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGas));
+    /// xmz_mod_touch_server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGas));
+    /// xmz_mod_touch_server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+    ///
+    /// assert_eq!(xmz_mod_touch_server.get_bus_devices(), vec!["/dev/ttyS1".to_string(), "sim:///dev/ttyUSB0".to_string()]);
+    /// ```
+    pub fn get_bus_devices(&self) -> Vec<String> {
+        let mut bus_devices: Vec<String> = self.zones.iter()
+            .flat_map(|zone| zone.get_kombisensors().iter())
+            .map(|kombisensor| kombisensor.get_bus_device())
+            .collect();
+        bus_devices.sort();
+        bus_devices.dedup();
+
+        bus_devices
+    }
+
+    /// Liefert die anstehenden Abfragen aller Kombisensoren an einer Leitung
+    ///
+    /// Jede Abfrage wird zusammen mit der Id der Zone und der Id des Kombisensors geliefert.
+    ///
+    /// # Parameters
+    ///
+    /// * `bus_device`  - Leitung, siehe [`get_bus_devices()`](#method.get_bus_devices)
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Kombisensor, KombisensorType, Server};
+    ///
+    /// let mut xmz_mod_touch_server = Server::new();
+    /// xmz_mod_touch_server.add_zone();
+    /// xmz_mod_touch_server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGas));
+    ///
+    /// assert_eq!(xmz_mod_touch_server.get_poll_requests("/dev/ttyS1").len(), 1);
+    /// assert_eq!(xmz_mod_touch_server.get_poll_requests("/dev/ttyS2").len(), 0);
+    /// ```
+    pub fn get_poll_requests(&self, bus_device: &str) -> Vec<(usize, usize, PollRequest)> {
+        let mut poll_requests = vec![];
+        for (zone_id, zone) in self.zones.iter().enumerate() {
+            for (kombisensor_id, kombisensor) in zone.get_kombisensors().iter().enumerate() {
+                if kombisensor.get_bus_device() == bus_device {
+                    if let Some(poll_request) = kombisensor.get_poll_request() {
+                        poll_requests.push((zone_id, kombisensor_id, poll_request));
+                    }
+                }
+            }
+        }
+
+        poll_requests
+    }
+
+    /// Übergibt das Ergebnis einer Abfrage an den Kombisensor
+    ///
+    /// Wurde der Kombisensor während der Abfrage umkonfiguriert (andere Leitung oder Modbus Adresse, z.B.
+    /// über das JSON API), dann wird das Ergebnis verworfen.
+    ///
+    /// # Parameters
+    ///
+    /// * `zone_id`         - Id der Zone
+    /// * `kombisensor_id`  - Id des Kombisensors in der Zone
+    /// * `poll_request`    - ausgeführte Abfrage
+    /// * `poll_result`     - Ergebnis der Abfrage
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Kombisensor, KombisensorType, Server};
+    ///
+    /// let mut xmz_mod_touch_server = Server::new();
+    /// xmz_mod_touch_server.add_zone();
+    /// xmz_mod_touch_server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+    ///
+    /// let bus_manager = xmz_mod_touch_server.get_bus_manager();
+    /// for (zone_id, kombisensor_id, poll_request) in xmz_mod_touch_server.get_poll_requests("sim:///dev/ttyUSB0") {
+    ///     let poll_result = poll_request.execute(&bus_manager);
+    ///     xmz_mod_touch_server.apply_poll_result(zone_id, kombisensor_id, &poll_request, poll_result).unwrap();
+    /// }
+    ///
+    /// let kombisensor = xmz_mod_touch_server.get_zone(0).unwrap().get_kombisensor(0).unwrap();
+    /// assert_eq!(kombisensor.get_firmware_version(), "0.14.0".to_string());
+    /// ```
+    pub fn apply_poll_result(&mut self, zone_id: usize, kombisensor_id: usize, poll_request: &PollRequest, poll_result: PollResult) -> Result<()> {
+        match self.get_zone_mut(zone_id).and_then(|zone| zone.get_kombisensor_mut(kombisensor_id)) {
+            Some(kombisensor) => {
+                if kombisensor.get_bus_device() != poll_request.get_modbus_device() ||
+                   kombisensor.get_modbus_address() != poll_request.get_modbus_address() {
+                    bail!("Kombisensor {} in Zone {} changed during poll", kombisensor_id, zone_id)
+                }
+                kombisensor.apply_poll_result(poll_result);
+
+                Ok(())
+            }
+            None => bail!("Kombisensor {} in Zone {} not found", kombisensor_id, zone_id),
+        }
+    }
+
+    /// IP Adresse und Port des Modbus TCP Slaves für die GLT
+    ///
+    /// Siehe [`GltSlave`](../glt/struct.GltSlave.html)
+    ///
+    /// # Return values
+    ///
+    /// Liefert `None` wenn der Slave nicht aktiviert ist.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::Server;
+    ///
+    /// let xmz_mod_touch_server = Server::new();
+    /// assert_eq!(xmz_mod_touch_server.get_glt_listen_address(), None);
+    /// ```
+    pub fn get_glt_listen_address(&self) -> Option<String> {
+        self.glt_listen_address.clone()
+    }
+
+    /// Setzt IP Adresse und Port des Modbus TCP Slaves für die GLT
+    ///
+    /// # Parameters
+    ///
+    /// * `glt_listen_address`  - z.B. `Some("0.0.0.0:502".to_string())`, `None` schaltet den Slave aus
+    ///
+    /// # Examples
+    ///
+    /// ```rust
     /// use xmz_mod_touch_server::Server;
     ///
     /// let mut xmz_mod_touch_server = Server::new();
-    /// xmz_mod_touch_server.basic_configuration().unwrap();
+    /// xmz_mod_touch_server.set_glt_listen_address(Some("0.0.0.0:502".to_string()));
+    /// assert_eq!(xmz_mod_touch_server.get_glt_listen_address(), Some("0.0.0.0:502".to_string()));
     /// ```
-    pub fn basic_configuration(&mut self) -> Result<()> {
-        self.leds.reset()?;
-        // Power LED an
-        self.leds.set(1)?;
+    pub fn set_glt_listen_address(&mut self, glt_listen_address: Option<String>) {
+        self.glt_listen_address = glt_listen_address;
+    }
 
-        self.relais.reset()?;
-        // Relais Störung anziehen (normal closed)
-        self.relais.set(1)?;
+    /// Liefert die Datei, in die die Abfragen der Kombisensoren aufgezeichnet werden
+    ///
+    /// Siehe [`TrafficRecorder`](../modbus_bus/struct.TrafficRecorder.html)
+    pub fn get_traffic_recording_file(&self) -> Option<String> {
+        self.traffic_recording_file.clone()
+    }
 
-        Ok(())
+    /// Setzt die Datei, in die die Abfragen der Kombisensoren aufgezeichnet werden
+    ///
+    /// Die Aufzeichnung beginnt erst mit [`start_traffic_recording()`](#method.start_traffic_recording).
+    ///
+    /// # Parameters
+    ///
+    /// * `traffic_recording_file`  - z.B. `Some("/var/log/xmz-modbus.log".to_string())`, `None` zeichnet nicht auf
+    pub fn set_traffic_recording_file(&mut self, traffic_recording_file: Option<String>) {
+        self.traffic_recording_file = traffic_recording_file;
     }
 
-    /// Liefert den Typen des Servers
+    /// Liefert die maximale Größe der Aufzeichnung in Bytes
+    pub fn get_traffic_recording_max_bytes(&self) -> u64 {
+        self.traffic_recording_max_bytes
+    }
+
+    /// Setzt die maximale Größe der Aufzeichnung
+    ///
+    /// Erreicht die Datei diese Größe, wird sie nach `<Datei>.1` umbenannt und eine neue Datei begonnen.
+    ///
+    /// # Parameters
+    ///
+    /// * `traffic_recording_max_bytes` - maximale Größe in Bytes, Standard `TRAFFIC_RECORDING_MAX_BYTES`
+    pub fn set_traffic_recording_max_bytes(&mut self, traffic_recording_max_bytes: u64) {
+        self.traffic_recording_max_bytes = traffic_recording_max_bytes;
+    }
+
+    /// Liefert die Aufzeichnung, die statt der Modbus Leitungen abgespielt wird
+    ///
+    /// Siehe [`TrafficReplay`](../modbus_bus/struct.TrafficReplay.html)
+    pub fn get_traffic_replay_file(&self) -> Option<String> {
+        self.traffic_replay_file.clone()
+    }
+
+    /// Setzt die Aufzeichnung, die statt der Modbus Leitungen abgespielt wird
+    ///
+    /// Das Abspielen beginnt erst mit [`start_traffic_recording()`](#method.start_traffic_recording).
+    ///
+    /// # Parameters
+    ///
+    /// * `traffic_replay_file` - Datei einer Aufzeichnung, `None` fragt die Modbus Leitungen ab
+    pub fn set_traffic_replay_file(&mut self, traffic_replay_file: Option<String>) {
+        self.traffic_replay_file = traffic_replay_file;
+    }
+
+    /// Startet die Aufzeichnung bzw. das Abspielen der Modbus Kommunikation
+    ///
+    /// Beides wird über `traffic_recording_file` und `traffic_replay_file` konfiguriert. Eine Aufzeichnung kann
+    /// nur von einem Server vom Typ `ServerType::Simulation` abgespielt werden, so werden keine echten Relais
+    /// geschaltet.
+    ///
+    /// # Return values
+    ///
+    /// Liefert einen Fehler wenn eine der Dateien nicht geöffnet werden kann, oder ein Server vom Typ
+    /// `ServerType::Real` eine Aufzeichnung abspielen soll.
     ///
     /// # Examples
     ///
     /// ```rust
     /// use xmz_mod_touch_server::{Server, ServerType};
     ///
-    /// let xmz_mod_touch_server = Server::new_with_type(ServerType::Real);
-    /// assert_eq!(xmz_mod_touch_server.get_server_type(), ServerType::Real);
+    /// let mut xmz_mod_touch_server = Server::new_with_type(ServerType::Real);
+    /// xmz_mod_touch_server.set_traffic_replay_file(Some("/tmp/modbus.log".to_string()));
+    /// assert!(xmz_mod_touch_server.start_traffic_recording().is_err());
+    ///
+    /// let xmz_mod_touch_server = Server::new();
+    /// assert!(xmz_mod_touch_server.start_traffic_recording().is_ok());
+    /// assert!(!xmz_mod_touch_server.get_bus_manager().is_replaying());
     /// ```
-    pub fn get_server_type(&self) -> ServerType {
-        self.server_type.clone()
+    pub fn start_traffic_recording(&self) -> Result<()> {
+        if let Some(ref traffic_replay_file) = self.traffic_replay_file {
+            if self.server_type != ServerType::Simulation {
+                bail!("Traffic replay requires a simulation server")
+            }
+            let traffic_replay = TrafficReplay::open(traffic_replay_file)?;
+            info!("Spiele Modbus Aufzeichnung {} ab, {} Abfragen", traffic_replay_file, traffic_replay.len());
+            self.bus_manager.set_traffic_replay(Some(traffic_replay))?;
+        }
+        if let Some(ref traffic_recording_file) = self.traffic_recording_file {
+            info!("Zeichne Modbus Abfragen nach {} auf", traffic_recording_file);
+            self.bus_manager.set_traffic_recorder(Some(TrafficRecorder::create(traffic_recording_file, self.traffic_recording_max_bytes)?))?;
+        }
+
+        Ok(())
     }
 
-    /// Liefert die Versionsnummer des Server's
+    /// Liefert die Datei mit dem Gasverlauf der simulierten Messzellen
     ///
-    /// Die Versionsnummer entspricht der Crate Versionsnummer, wird aus dieser automatisch gebildet.
+    /// Siehe [`GasScenario`](../scenario/struct.GasScenario.html)
+    pub fn get_scenario_file(&self) -> Option<String> {
+        self.scenario_file.clone()
+    }
+
+    /// Setzt die Datei mit dem Gasverlauf der simulierten Messzellen
+    ///
+    /// Der Gasverlauf wird erst mit [`load_scenario()`](#method.load_scenario) übernommen.
+    ///
+    /// # Parameters
+    ///
+    /// * `scenario_file`   - Pfad der Szenario Datei, `None` spielt keinen Gasverlauf ab
+    pub fn set_scenario_file(&mut self, scenario_file: Option<String>) {
+        self.scenario_file = scenario_file;
+    }
+
+    /// Liest die Szenario Datei und übergibt die Gasverläufe an die simulierten Messzellen
     ///
     /// # Return values
     ///
-    /// Diese Funktion liefert eine neue Server Instanz
+    /// Liefert die Anzahl der übernommenen Gasverläufe, oder einen Fehler wenn die Szenario Datei nicht gelesen
+    /// werden kann, oder nicht zu den Messzellen des Servers passt. Ohne Szenario Datei wird 0 geliefert.
     ///
-    /// # Parameters
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::Server;
+    ///
+    /// let mut xmz_mod_touch_server = Server::new();
+    /// assert_eq!(xmz_mod_touch_server.load_scenario().unwrap(), 0);
+    ///
+    /// xmz_mod_touch_server.set_scenario_file(Some("/nonexistent/scenario.json".to_string()));
+    /// assert!(xmz_mod_touch_server.load_scenario().is_err());
+    /// ```
+    pub fn load_scenario(&mut self) -> Result<usize> {
+        let scenario = match self.scenario_file {
+            Some(ref scenario_file) => GasScenario::load(scenario_file)?,
+            None => return Ok(0),
+        };
+
+        scenario.apply(self)
+    }
+
+    /// Liefert die Datei, in der der Zustand des Servers gespeichert wird
     ///
     /// # Examples
     ///
     /// ```rust
     /// use xmz_mod_touch_server::Server;
+    /// use xmz_mod_touch_server::server::STATE_FILE;
     ///
     /// let xmz_mod_touch_server = Server::new();
-    /// assert_eq!(xmz_mod_touch_server.get_version(), env!("CARGO_PKG_VERSION").to_string());
+    /// assert_eq!(xmz_mod_touch_server.get_state_file(), Some(STATE_FILE.to_string()));
     /// ```
-    pub fn get_version(&self) -> String {
-        self.version.clone()
+    pub fn get_state_file(&self) -> Option<String> {
+        self.state_file.clone()
     }
 
-    /// Liefert eine Refernz auf die Exception des Servers
+    /// Setzt die Datei, in der der Zustand des Servers gespeichert wird
+    ///
+    /// # Parameters
+    ///
+    /// * `state_file`  - Pfad der Zustandsdatei, `None` speichert keinen Zustand
+    pub fn set_state_file(&mut self, state_file: Option<String>) {
+        self.state_file = state_file;
+    }
+
+    /// Speichert den Zustand des Servers in der Zustandsdatei
+    ///
+    /// Siehe [`ServerState`](../state/struct.ServerState.html). Ohne Zustandsdatei passiert nichts.
     ///
     /// # Examples
     ///
@@ -298,10 +808,704 @@ impl Server {
     /// use xmz_mod_touch_server::Server;
     ///
     /// let mut xmz_mod_touch_server = Server::new();
-    /// xmz_mod_touch_server.get_exceptions();
+    /// xmz_mod_touch_server.set_state_file(None);
+    /// assert!(xmz_mod_touch_server.save_state().is_ok());
     /// ```
-    pub fn get_exceptions(&self) -> &Mutex<HashSet<Exception>> {
-        &self.exceptions
+    pub fn save_state(&self) -> Result<()> {
+        match self.state_file {
+            Some(ref state_file) => ServerState::from_server(self).save(state_file),
+            None => Ok(()),
+        }
+    }
+
+    /// Stellt den Zustand des Servers aus der Zustandsdatei wieder her
+    ///
+    /// # Return values
+    ///
+    /// Liefert die Anzahl der wiederhergestellten Mittelwerte, oder einen Fehler wenn die Zustandsdatei nicht
+    /// gelesen werden kann.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Kombisensor, KombisensorType, Server};
+    ///
+    /// let state_file = ::std::env::temp_dir().join("xmz_doctest_restore_state.json").to_string_lossy().into_owned();
+    ///
+    /// let mut xmz_mod_touch_server = Server::new();
+    /// xmz_mod_touch_server.set_state_file(Some(state_file.clone()));
+    /// xmz_mod_touch_server.add_zone();
+    /// xmz_mod_touch_server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+    /// xmz_mod_touch_server.evaluate();
+    /// xmz_mod_touch_server.save_state().unwrap();
+    ///
+    /// let mut restarted = Server::new();
+    /// restarted.set_state_file(Some(state_file.clone()));
+    /// restarted.add_zone();
+    /// restarted.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+    /// assert_eq!(restarted.restore_state().unwrap(), 2);
+    /// # ::std::fs::remove_file(&state_file).unwrap();
+    /// ```
+    pub fn restore_state(&mut self) -> Result<usize> {
+        let state = match self.state_file {
+            Some(ref state_file) => ServerState::load(state_file)?,
+            None => return Ok(0),
+        };
+
+        Ok(state.restore(self))
+    }
+
+    /// Schreibt die Kalibrierwerte einer Sensor Messzelle in die Firmware des Kombisensors
+    ///
+    /// Siehe [`Kombisensor::write_sensor_calibration()`](zone/kombisensor/kombisensor/struct.Kombisensor.html#method.write_sensor_calibration)
+    ///
+    /// # Parameters
+    ///
+    /// * `zone_id`         - Id der Zone
+    /// * `kombisensor_id`  - Id des Kombisensors in der Zone
+    /// * `sensor_id`       - Id der Sensormesszelle
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::Server;
+    ///
+    /// let xmz_mod_touch_server = Server::new();
+    /// assert!(xmz_mod_touch_server.write_sensor_calibration(0, 0, 0).is_err());
+    /// ```
+    pub fn write_sensor_calibration(&self, zone_id: usize, kombisensor_id: usize, sensor_id: usize) -> Result<()> {
+        match self.get_zone(zone_id).and_then(|zone| zone.get_kombisensor(kombisensor_id)) {
+            Some(kombisensor) => kombisensor.write_sensor_calibration(&self.bus_manager, sensor_id),
+            None => bail!("Kombisensor {} in Zone {} not found", kombisensor_id, zone_id),
+        }
+    }
+
+    /// Aktiviert oder deaktiviert eine Sensor Messzelle eines Kombisensors
+    ///
+    /// Siehe [`Kombisensor::write_sensor_enabled()`](zone/kombisensor/kombisensor/struct.Kombisensor.html#method.write_sensor_enabled)
+    ///
+    /// # Parameters
+    ///
+    /// * `zone_id`         - Id der Zone
+    /// * `kombisensor_id`  - Id des Kombisensors in der Zone
+    /// * `sensor_id`       - Id der Sensormesszelle
+    /// * `enabled`         - `true` aktiviert, `false` deaktiviert die Messzelle
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Kombisensor, KombisensorType, Server, ZoneStatus};
+    ///
+    /// let mut xmz_mod_touch_server = Server::new();
+    /// xmz_mod_touch_server.add_zone();
+    /// let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGasSimulation);
+    /// kombisensor.get_sensor_mut(0).unwrap().set_concentration(20.0);
+    /// xmz_mod_touch_server.get_zone_mut(0).unwrap().add_kombisensor(kombisensor);
+    ///
+    /// xmz_mod_touch_server.write_sensor_enabled(0, 0, 0, false).unwrap();
+    /// xmz_mod_touch_server.evaluate();
+    /// assert_eq!(xmz_mod_touch_server.get_zone(0).unwrap().get_status(), ZoneStatus::Normal);
+    ///
+    /// // Kombisensor existiert nicht
+    /// assert!(xmz_mod_touch_server.write_sensor_enabled(0, 1, 0, false).is_err());
+    /// ```
+    pub fn write_sensor_enabled(&mut self, zone_id: usize, kombisensor_id: usize, sensor_id: usize, enabled: bool) -> Result<()> {
+        let mut kombisensor = self.copy_kombisensor(zone_id, kombisensor_id)?;
+        kombisensor.write_sensor_enabled(&self.bus_manager, sensor_id, enabled)?;
+
+        self.apply_sensor_write(zone_id, kombisensor_id, &kombisensor, sensor_id)
+    }
+
+    /// Liefert eine Kopie eines Kombisensors, für Schreibzugriffe ohne den Lock des Servers
+    ///
+    /// Die Modbus Kommunikation eines Schreibzugriffs kann einige Sekunden dauern. Damit Auswertung, GLT und JSON
+    /// API in dieser Zeit weiter laufen, wird eine Kopie des Kombisensors beschrieben (die Leitung liefert
+    /// [`get_bus_manager()`](#method.get_bus_manager)) und das Ergebnis anschließend mit
+    /// [`apply_sensor_write()`](#method.apply_sensor_write) übernommen.
+    ///
+    /// # Parameters
+    ///
+    /// * `zone_id`         - Id der Zone
+    /// * `kombisensor_id`  - Id des Kombisensors in der Zone
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::sync::{Arc, Mutex};
+    /// use xmz_mod_touch_server::{Kombisensor, KombisensorType, Server};
+    ///
+    /// let mut xmz_mod_touch_server = Server::new();
+    /// xmz_mod_touch_server.add_zone();
+    /// xmz_mod_touch_server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+    /// let server = Arc::new(Mutex::new(xmz_mod_touch_server));
+    ///
+    /// let (mut kombisensor, bus_manager) = {
+    ///     let server = server.lock().unwrap();
+    ///     (server.copy_kombisensor(0, 0).unwrap(), server.get_bus_manager())
+    /// };
+    /// // Ohne den Lock des Servers
+    /// kombisensor.write_sensor_enabled(&bus_manager, 1, false).unwrap();
+    ///
+    /// let mut server = server.lock().unwrap();
+    /// server.apply_sensor_write(0, 0, &kombisensor, 1).unwrap();
+    /// assert!(!server.get_zone(0).unwrap().get_kombisensor(0).unwrap().get_sensor(1).unwrap().is_enabled());
+    /// ```
+    pub fn copy_kombisensor(&self, zone_id: usize, kombisensor_id: usize) -> Result<Kombisensor> {
+        match self.get_zone(zone_id).and_then(|zone| zone.get_kombisensor(kombisensor_id)) {
+            Some(kombisensor) => Ok(kombisensor.clone()),
+            None => bail!("Kombisensor {} in Zone {} not found", kombisensor_id, zone_id),
+        }
+    }
+
+    /// Übernimmt Kalibrierwerte und Konfiguration einer Messzelle aus der beschriebenen Kopie des Kombisensors
+    ///
+    /// Siehe [`copy_kombisensor()`](#method.copy_kombisensor). Wurde der Kombisensor während des Schreibzugriffs
+    /// umkonfiguriert (andere Leitung oder Modbus Adresse), wird nichts übernommen.
+    ///
+    /// # Parameters
+    ///
+    /// * `zone_id`         - Id der Zone
+    /// * `kombisensor_id`  - Id des Kombisensors in der Zone
+    /// * `written`         - beschriebene Kopie des Kombisensors
+    /// * `sensor_id`       - Id der beschriebenen Sensormesszelle
+    pub fn apply_sensor_write(&mut self, zone_id: usize, kombisensor_id: usize, written: &Kombisensor, sensor_id: usize) -> Result<()> {
+        let written_sensor = match written.get_sensor(sensor_id) {
+            Some(sensor) => sensor,
+            None => bail!("Sensor {} not found", sensor_id),
+        };
+        let kombisensor = match self.zones.get_mut(zone_id).and_then(|zone| zone.get_kombisensor_mut(kombisensor_id)) {
+            Some(kombisensor) => kombisensor,
+            None => bail!("Kombisensor {} in Zone {} not found", kombisensor_id, zone_id),
+        };
+        if kombisensor.get_bus_device() != written.get_bus_device() ||
+           kombisensor.get_modbus_address() != written.get_modbus_address() {
+            bail!("Kombisensor {} in Zone {} changed during write", kombisensor_id, zone_id)
+        }
+
+        match kombisensor.get_sensor_mut(sensor_id) {
+            Some(sensor) => {
+                sensor.set_adc_value_at_nullgas(written_sensor.get_adc_value_at_nullgas());
+                sensor.set_adc_value_at_messgas(written_sensor.get_adc_value_at_messgas());
+                sensor.set_concentration_at_nullgas(written_sensor.get_concentration_at_nullgas());
+                sensor.set_concentration_at_messgas(written_sensor.get_concentration_at_messgas());
+                sensor.set_config(written_sensor.get_config());
+                sensor.set_enabled(written_sensor.is_enabled());
+            }
+            None => bail!("Sensor {} not found", sensor_id),
+        }
+
+        Ok(())
+    }
+
+    /// Liefert die Einstellungen der geführten Kalibrierung
+    pub fn get_calibration_settings(&self) -> &CalibrationSettings {
+        &self.calibration_settings
+    }
+
+    /// Setzt die Einstellungen der geführten Kalibrierung
+    ///
+    /// Die Einstellungen gelten ab der nächsten Kalibrierung.
+    ///
+    /// # Parameters
+    ///
+    /// * `calibration_settings`    - Einstellungen, z.B. das Stabilitätskriterium
+    pub fn set_calibration_settings(&mut self, calibration_settings: CalibrationSettings) {
+        self.calibration_settings = calibration_settings;
+    }
+
+    /// Liefert die Grenze der Empfindlichkeit, unter der eine Messzelle getauscht werden sollte
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::Server;
+    ///
+    /// let mut xmz_mod_touch_server = Server::new();
+    /// assert_eq!(xmz_mod_touch_server.get_sensor_replace_sensitivity_percent(), 50.0);
+    /// xmz_mod_touch_server.set_sensor_replace_sensitivity_percent(40.0);
+    /// assert_eq!(xmz_mod_touch_server.get_sensor_replace_sensitivity_percent(), 40.0);
+    /// ```
+    pub fn get_sensor_replace_sensitivity_percent(&self) -> f64 {
+        self.sensor_replace_sensitivity_percent
+    }
+
+    /// Setzt die Grenze der Empfindlichkeit, unter der eine Messzelle getauscht werden sollte
+    ///
+    /// # Parameters
+    ///
+    /// * `sensor_replace_sensitivity_percent`  - Empfindlichkeit in Prozent der ersten Kalibrierung
+    pub fn set_sensor_replace_sensitivity_percent(&mut self, sensor_replace_sensitivity_percent: f64) {
+        self.sensor_replace_sensitivity_percent = sensor_replace_sensitivity_percent;
+    }
+
+    /// Liefert die laufende bzw. zuletzt beendete Kalibrierung
+    pub fn get_calibration_session(&self) -> Option<&CalibrationSession> {
+        self.calibration_session.as_ref()
+    }
+
+    /// Sind die Alarmausgänge der Zone gesperrt?
+    ///
+    /// Die Alarmausgänge einer Zone sind gesperrt, solange eine ihrer Messzellen kalibriert wird, höchstens aber
+    /// `session_timeout_sec` (siehe [`CalibrationSettings`](../calibration/struct.CalibrationSettings.html)).
+    ///
+    /// # Parameters
+    ///
+    /// * `zone_id`     - Id der Zone
+    pub fn is_zone_alarm_inhibited(&self, zone_id: usize) -> bool {
+        self.calibration_session.as_ref().map_or(false, |session| session.is_active() && session.get_zone_id() == zone_id)
+    }
+
+    /// Beginnt die geführte Kalibrierung einer Sensor Messzelle
+    ///
+    /// Die Kalibrierung beginnt mit dem Nullgas. Bis die Kalibrierung übernommen, abgebrochen oder fehlgeschlagen
+    /// ist, sind die Alarmausgänge der Zone gesperrt. Die ADC Werte werden bei jeder Auswertung
+    /// ([`evaluate()`](#method.evaluate)) an die Kalibrierung übergeben.
+    ///
+    /// # Return values
+    ///
+    /// Liefert einen Fehler, wenn die Messzelle nicht existiert oder bereits eine Kalibrierung läuft.
+    ///
+    /// # Parameters
+    ///
+    /// * `zone_id`         - Id der Zone
+    /// * `kombisensor_id`  - Id des Kombisensors in der Zone
+    /// * `sensor_id`       - Id der Sensormesszelle
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Kombisensor, KombisensorType, Server, ZoneStatus};
+    /// use xmz_mod_touch_server::server::{CalibrationSettings, CalibrationStep};
+    ///
+    /// let mut xmz_mod_touch_server = Server::new();
+    /// xmz_mod_touch_server.set_calibration_settings(CalibrationSettings { stable_sec: 0, ..Default::default() });
+    /// xmz_mod_touch_server.add_zone();
+    /// xmz_mod_touch_server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+    ///
+    /// xmz_mod_touch_server.start_calibration(0, 0, 0).unwrap();
+    /// assert!(xmz_mod_touch_server.is_zone_alarm_inhibited(0));
+    /// // Es läuft bereits eine Kalibrierung
+    /// assert!(xmz_mod_touch_server.start_calibration(0, 0, 1).is_err());
+    ///
+    /// // Nullgas
+    /// xmz_mod_touch_server.evaluate();
+    /// assert_eq!(xmz_mod_touch_server.get_calibration_session().unwrap().get_step(), &CalibrationStep::NullgasCaptured);
+    ///
+    /// // Messgas, die Zone geht in den Alarm, die Relais bleiben aber aus
+    /// xmz_mod_touch_server.start_calibration_messgas(20).unwrap();
+    /// xmz_mod_touch_server.get_zone_mut(0).unwrap().get_kombisensor_mut(0).unwrap().get_sensor_mut(0).unwrap().set_concentration(20.0);
+    /// xmz_mod_touch_server.evaluate();
+    /// xmz_mod_touch_server.check().unwrap();
+    /// assert_eq!(xmz_mod_touch_server.get_zone(0).unwrap().get_status(), ZoneStatus::DIW);
+    /// assert_eq!(xmz_mod_touch_server.get_relais().get(2).unwrap(), false);
+    ///
+    /// xmz_mod_touch_server.commit_calibration().unwrap();
+    /// assert!(!xmz_mod_touch_server.is_zone_alarm_inhibited(0));
+    /// ```
+    pub fn start_calibration(&mut self, zone_id: usize, kombisensor_id: usize, sensor_id: usize) -> Result<()> {
+        if let Some(ref session) = self.calibration_session {
+            if session.is_active() {
+                bail!("Calibration of sensor {} (Kombisensor {}, Zone {}) is still running", session.get_sensor_id(), session.get_kombisensor_id(), session.get_zone_id())
+            }
+        }
+        let session = match self.get_zone(zone_id)
+            .and_then(|zone| zone.get_kombisensor(kombisensor_id))
+            .and_then(|kombisensor| kombisensor.get_sensor(sensor_id)) {
+            Some(sensor) => CalibrationSession::new(zone_id, kombisensor_id, sensor_id, sensor, self.calibration_settings.clone(), Utc::now()),
+            None => bail!("Sensor {} of Kombisensor {} in Zone {} not found", sensor_id, kombisensor_id, zone_id),
+        };
+        self.calibration_session = Some(session);
+
+        Ok(())
+    }
+
+    /// Beginnt den Messgas Schritt der laufenden Kalibrierung
+    ///
+    /// # Parameters
+    ///
+    /// * `concentration`   - Konzentration des aufgegebenen Messgases
+    pub fn start_calibration_messgas(&mut self, concentration: u16) -> Result<()> {
+        match self.calibration_session {
+            Some(ref mut session) => session.start_messgas(concentration, Utc::now()),
+            None => bail!("No calibration running"),
+        }
+    }
+
+    /// Übernimmt die Werte der Kalibrierung in die Messzelle und schreibt sie in den Kombisensor
+    ///
+    /// Kann der Kombisensor nicht beschrieben werden, bleiben die bisherigen Werte in der Messzelle und die
+    /// Kalibrierung läuft weiter, so dass das Übernehmen wiederholt oder die Kalibrierung abgebrochen werden kann.
+    /// Nach dem Übernehmen wird der 15min Mittelwert der Messzelle verworfen, damit das Prüfgas keinen Alarm
+    /// auslöst.
+    pub fn commit_calibration(&mut self) -> Result<()> {
+        let kombisensor = self.prepare_calibration_commit()?;
+        let sensor_id = self.calibration_session.as_ref().map_or(0, |session| session.get_sensor_id());
+        kombisensor.write_sensor_calibration(&self.bus_manager, sensor_id)?;
+
+        self.apply_calibration_commit(&kombisensor)
+    }
+
+    /// Liefert eine Kopie des Kombisensors mit den Werten der laufenden Kalibrierung
+    ///
+    /// Die Kopie wird, ohne den Lock des Servers, mit
+    /// [`Kombisensor::write_sensor_calibration()`](zone/kombisensor/kombisensor/struct.Kombisensor.html#method.write_sensor_calibration)
+    /// in den Kombisensor geschrieben und dann mit [`apply_calibration_commit()`](#method.apply_calibration_commit)
+    /// übernommen. Siehe [`commit_calibration()`](#method.commit_calibration)
+    ///
+    /// # Return values
+    ///
+    /// Liefert einen Fehler wenn keine Kalibrierung läuft oder ihre Werte noch nicht vollständig sind.
+    pub fn prepare_calibration_commit(&self) -> Result<Kombisensor> {
+        let session = match self.calibration_session {
+            Some(ref session) if session.is_active() => session,
+            _ => bail!("No calibration running"),
+        };
+        let mut kombisensor = self.copy_kombisensor(session.get_zone_id(), session.get_kombisensor_id())?;
+        match kombisensor.get_sensor_mut(session.get_sensor_id()) {
+            Some(sensor) => session.apply(sensor)?,
+            None => bail!("Sensor {} of Kombisensor {} in Zone {} not found", session.get_sensor_id(), session.get_kombisensor_id(), session.get_zone_id()),
+        }
+
+        Ok(kombisensor)
+    }
+
+    /// Übernimmt die geschriebenen Werte der Kalibrierung in die Messzelle und beendet die Kalibrierung
+    ///
+    /// Wurde die Kalibrierung während des Schreibzugriffs beendet (z.B. durch die Zeitgrenze), wird nichts
+    /// übernommen. Die Werte aus der Firmware kommen dann mit der nächsten Abfrage des Kombisensors.
+    ///
+    /// # Parameters
+    ///
+    /// * `written`     - beschriebene Kopie aus [`prepare_calibration_commit()`](#method.prepare_calibration_commit)
+    pub fn apply_calibration_commit(&mut self, written: &Kombisensor) -> Result<()> {
+        let (zone_id, kombisensor_id, sensor_id) = match self.calibration_session {
+            Some(ref session) if session.is_active() => (session.get_zone_id(), session.get_kombisensor_id(), session.get_sensor_id()),
+            _ => bail!("Calibration ended while writing the Kombisensor"),
+        };
+        // Die geschriebenen Werte müssen die der laufenden Kalibrierung sein
+        let written_sensor = match written.get_sensor(sensor_id) {
+            Some(sensor) => sensor,
+            None => bail!("Sensor {} not found", sensor_id),
+        };
+        let mut expected = written_sensor.clone();
+        if let Some(ref session) = self.calibration_session {
+            session.apply(&mut expected)?;
+        }
+        if written_sensor.get_adc_value_at_nullgas() != expected.get_adc_value_at_nullgas() ||
+           written_sensor.get_adc_value_at_messgas() != expected.get_adc_value_at_messgas() ||
+           written_sensor.get_concentration_at_nullgas() != expected.get_concentration_at_nullgas() ||
+           written_sensor.get_concentration_at_messgas() != expected.get_concentration_at_messgas() {
+            bail!("Calibration changed while writing the Kombisensor")
+        }
+
+        self.apply_sensor_write(zone_id, kombisensor_id, written, sensor_id)?;
+        if let Some(ref mut session) = self.calibration_session {
+            session.set_committed(Utc::now());
+        }
+        self.finish_calibration();
+
+        Ok(())
+    }
+
+    /// Bricht die laufende Kalibrierung ab
+    ///
+    /// Die Werte der Messzelle bleiben unverändert, der 15min Mittelwert der Messzelle wird verworfen.
+    pub fn abort_calibration(&mut self) -> Result<()> {
+        match self.calibration_session {
+            Some(ref mut session) if session.is_active() => session.abort(Utc::now()),
+            _ => bail!("No calibration running"),
+        }
+        self.finish_calibration();
+
+        Ok(())
+    }
+
+    // Übergibt den aktuellen ADC Wert der Messzelle an die laufende Kalibrierung und prüft deren Zeitgrenzen
+    //
+    // Übergeben wird nur ein neu ausgelesener Wert. Ist der Kombisensor nicht erreichbar, schlägt die Kalibrierung
+    // fehl.
+    //
+    fn update_calibration(&mut self, now: DateTime<Utc>) {
+        let (zone_id, kombisensor_id, sensor_id) = match self.calibration_session {
+            Some(ref session) if session.is_active() => (session.get_zone_id(), session.get_kombisensor_id(), session.get_sensor_id()),
+            _ => return,
+        };
+        // Nie ausgelesene Werte (Simulation) gelten, wie beim 15min Mittelwert, als gerade gemessen
+        let sample = self.get_zone(zone_id)
+            .and_then(|zone| zone.get_kombisensor(kombisensor_id))
+            .and_then(|kombisensor| kombisensor.get_sensor(sensor_id).map(|sensor| {
+                (kombisensor.get_status(), sensor.get_adc_value(), sensor.get_adc_value_timestamp().unwrap_or(now))
+            }));
+
+        let mut failed = false;
+        if let Some(ref mut session) = self.calibration_session {
+            match sample {
+                Some((KombisensorStatus::Normal, adc_value, timestamp)) => session.add_sample(adc_value, timestamp),
+                Some((status, _, _)) => session.fail(format!("Kombisensor im Status {:?}", status), now),
+                // Die Messzelle wurde aus der Konfiguration entfernt
+                None => session.abort(now),
+            }
+            session.check_timeout(now);
+            failed = !session.is_active();
+        }
+        if failed {
+            self.finish_calibration();
+        }
+    }
+
+    // Nach dem Ende der Kalibrierung soll das Prüfgas nicht in den 15min Mittelwert eingehen
+    //
+    fn finish_calibration(&mut self) {
+        let (zone_id, kombisensor_id, sensor_id) = match self.calibration_session {
+            Some(ref session) => (session.get_zone_id(), session.get_kombisensor_id(), session.get_sensor_id()),
+            None => return,
+        };
+        if let Some(sensor) = self.zones.get_mut(zone_id)
+            .and_then(|zone| zone.get_kombisensor_mut(kombisensor_id))
+            .and_then(|kombisensor| kombisensor.get_sensor_mut(sensor_id)) {
+            sensor.clear_adc_values_average();
+        }
+    }
+
+    /// Vergibt einem Kombisensor eine neue Modbus Adresse
+    ///
+    /// Siehe [`Kombisensor::write_modbus_address()`](zone/kombisensor/kombisensor/struct.Kombisensor.html#method.write_modbus_address)
+    ///
+    /// Ist die Adresse an der Leitung des Kombisensors bereits an einen anderen Kombisensor vergeben (in irgend
+    /// einer Zone), wird nichts geschrieben.
+    ///
+    /// # Parameters
+    ///
+    /// * `zone_id`         - Id der Zone
+    /// * `kombisensor_id`  - Id des Kombisensors in der Zone
+    /// * `modbus_address`  - neue Modbus Adresse, 1 bis 247
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Kombisensor, KombisensorType, Server};
+    ///
+    /// let mut xmz_mod_touch_server = Server::new();
+    /// xmz_mod_touch_server.add_zone();
+    /// let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGasSimulation);
+    /// kombisensor.set_modbus_address(1);
+    /// xmz_mod_touch_server.get_zone_mut(0).unwrap().add_kombisensor(kombisensor);
+    /// xmz_mod_touch_server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+    ///
+    /// // Adresse 1 ist bereits vergeben
+    /// assert!(xmz_mod_touch_server.write_modbus_address(0, 1, 1).is_err());
+    ///
+    /// xmz_mod_touch_server.write_modbus_address(0, 1, 2).unwrap();
+    /// assert_eq!(xmz_mod_touch_server.get_zone(0).unwrap().get_kombisensor(1).unwrap().get_modbus_address(), 2);
+    /// ```
+    pub fn write_modbus_address(&mut self, zone_id: usize, kombisensor_id: usize, modbus_address: u8) -> Result<()> {
+        let mut kombisensor = self.prepare_modbus_address_write(zone_id, kombisensor_id, modbus_address)?;
+        let previous_modbus_address = kombisensor.get_modbus_address();
+        kombisensor.write_modbus_address(&self.bus_manager, modbus_address)?;
+
+        self.apply_modbus_address_write(zone_id, kombisensor_id, previous_modbus_address, &kombisensor)
+    }
+
+    /// Liefert eine Kopie des Kombisensors, um ihm ohne den Lock des Servers eine neue Modbus Adresse zu vergeben
+    ///
+    /// Siehe [`write_modbus_address()`](#method.write_modbus_address)
+    ///
+    /// # Return values
+    ///
+    /// Liefert einen Fehler wenn der Kombisensor nicht existiert oder die Adresse an seiner Leitung bereits
+    /// vergeben ist.
+    ///
+    /// # Parameters
+    ///
+    /// * `zone_id`         - Id der Zone
+    /// * `kombisensor_id`  - Id des Kombisensors in der Zone
+    /// * `modbus_address`  - neue Modbus Adresse, 1 bis 247
+    pub fn prepare_modbus_address_write(&self, zone_id: usize, kombisensor_id: usize, modbus_address: u8) -> Result<Kombisensor> {
+        let kombisensor = self.copy_kombisensor(zone_id, kombisensor_id)?;
+        let modbus_device = kombisensor.get_modbus_device();
+
+        let is_assigned = self.zones.iter().enumerate().any(|(id, zone)| {
+            zone.get_kombisensors().iter().enumerate().any(|(kid, kombisensor)| {
+                (id, kid) != (zone_id, kombisensor_id) &&
+                kombisensor.get_modbus_device() == modbus_device &&
+                kombisensor.get_modbus_address() == modbus_address
+            })
+        });
+        if is_assigned {
+            bail!("Modbus address {} already assigned on {}", modbus_address, modbus_device)
+        }
+
+        Ok(kombisensor)
+    }
+
+    /// Übernimmt die neue Modbus Adresse aus der beschriebenen Kopie des Kombisensors
+    ///
+    /// Wurde der Kombisensor während des Schreibzugriffs umkonfiguriert, wird nichts übernommen.
+    ///
+    /// # Parameters
+    ///
+    /// * `zone_id`                 - Id der Zone
+    /// * `kombisensor_id`          - Id des Kombisensors in der Zone
+    /// * `previous_modbus_address` - Modbus Adresse vor dem Schreibzugriff
+    /// * `written`                 - beschriebene Kopie aus [`prepare_modbus_address_write()`](#method.prepare_modbus_address_write)
+    pub fn apply_modbus_address_write(&mut self, zone_id: usize, kombisensor_id: usize, previous_modbus_address: u8, written: &Kombisensor) -> Result<()> {
+        match self.zones.get_mut(zone_id).and_then(|zone| zone.get_kombisensor_mut(kombisensor_id)) {
+            Some(kombisensor) => {
+                if kombisensor.get_bus_device() != written.get_bus_device() ||
+                   kombisensor.get_modbus_address() != previous_modbus_address {
+                    bail!("Kombisensor {} in Zone {} changed during write", kombisensor_id, zone_id)
+                }
+                kombisensor.set_modbus_address(written.get_modbus_address());
+
+                Ok(())
+            }
+            None => bail!("Kombisensor {} in Zone {} not found", kombisensor_id, zone_id),
+        }
+    }
+
+    /// Übernimmt die bei einem Bus Scan gefundenen Kombisensoren in eine Zone
+    ///
+    /// Kombisensoren die bereits konfiguriert sind (gleiche Schnittstelle und Modbus Adresse, in irgend einer Zone)
+    /// werden übersprungen.
+    ///
+    /// # Return values
+    ///
+    /// Liefert die Anzahl der neu angelegten Kombisensoren, oder einen Fehler wenn die Zone nicht existiert.
+    ///
+    /// # Parameters
+    ///
+    /// * `zone_id`             - Id der Zone
+    /// * `discovered`          - gefundene Kombisensoren, siehe [`ModbusBus::scan()`](../modbus_bus/struct.ModbusBus.html#method.scan)
+    /// * `kombisensor_type`    - Typ der neu angelegten Kombisensoren
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{DiscoveredKombisensor, KombisensorType, Server};
+    ///
+    /// let mut xmz_mod_touch_server = Server::new();
+    /// xmz_mod_touch_server.add_zone();
+    /// let discovered = vec![
+    ///     DiscoveredKombisensor::new("/dev/ttyS1", 1, "0.14.0"),
+    ///     DiscoveredKombisensor::new("/dev/ttyS1", 2, "0.14.0"),
+    /// ];
+    ///
+    /// assert_eq!(xmz_mod_touch_server.merge_discovered_kombisensors(0, &discovered, KombisensorType::RAGas).unwrap(), 2);
+    /// // Ein zweiter Durchlauf legt keine doppelten Einträge an
+    /// assert_eq!(xmz_mod_touch_server.merge_discovered_kombisensors(0, &discovered, KombisensorType::RAGas).unwrap(), 0);
+    /// assert_eq!(xmz_mod_touch_server.get_zone(0).unwrap().get_kombisensors().len(), 2);
+    ///
+    /// assert!(xmz_mod_touch_server.merge_discovered_kombisensors(1, &discovered, KombisensorType::RAGas).is_err());
+    /// ```
+    pub fn merge_discovered_kombisensors(&mut self, zone_id: usize, discovered: &[DiscoveredKombisensor], kombisensor_type: KombisensorType) -> Result<usize> {
+        if self.get_zone(zone_id).is_none() {
+            bail!("Zone {} not found", zone_id)
+        }
+
+        let mut num_added = 0;
+        for discovered_kombisensor in discovered {
+            let is_configured = self.zones.iter()
+                .flat_map(|zone| zone.get_kombisensors().iter())
+                .any(|kombisensor| {
+                    kombisensor.get_modbus_device() == discovered_kombisensor.get_modbus_device() &&
+                    kombisensor.get_modbus_address() == discovered_kombisensor.get_modbus_address()
+                });
+
+            if !is_configured {
+                if let Some(zone) = self.get_zone_mut(zone_id) {
+                    zone.add_kombisensor(discovered_kombisensor.to_kombisensor(kombisensor_type.clone()));
+                    num_added += 1;
+                }
+            }
+        }
+
+        Ok(num_added)
+    }
+
+    /// `basic_configuration` - Grundkonfiguration/ Grundeistellungen der LEDs und Relais
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use xmz_mod_touch_server::Server;
+    ///
+    /// let mut xmz_mod_touch_server = Server::new();
+    /// xmz_mod_touch_server.basic_configuration().unwrap();
+    /// ```
+    pub fn basic_configuration(&mut self) -> Result<()> {
+        self.leds.reset()?;
+        // Power LED an
+        self.leds.set(1)?;
+
+        self.relais.reset()?;
+        // Relais Störung anziehen (normal closed)
+        self.relais.set(1)?;
+
+        Ok(())
+    }
+
+    /// Liefert den Typen des Servers
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Server, ServerType};
+    ///
+    /// let xmz_mod_touch_server = Server::new_with_type(ServerType::Real);
+    /// assert_eq!(xmz_mod_touch_server.get_server_type(), ServerType::Real);
+    /// ```
+    pub fn get_server_type(&self) -> ServerType {
+        self.server_type.clone()
+    }
+
+    /// Liefert die Versionsnummer des Server's
+    ///
+    /// Die Versionsnummer entspricht der Crate Versionsnummer, wird aus dieser automatisch gebildet.
+    ///
+    /// # Return values
+    ///
+    /// Diese Funktion liefert eine neue Server Instanz
+    ///
+    /// # Parameters
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::Server;
+    ///
+    /// let xmz_mod_touch_server = Server::new();
+    /// assert_eq!(xmz_mod_touch_server.get_version(), env!("CARGO_PKG_VERSION").to_string());
+    /// ```
+    pub fn get_version(&self) -> String {
+        self.version.clone()
+    }
+
+    /// Liefert eine Refernz auf die Exception des Servers
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::Server;
+    ///
+    /// let mut xmz_mod_touch_server = Server::new();
+    /// xmz_mod_touch_server.get_exceptions();
+    /// ```
+    pub fn get_exceptions(&self) -> &Mutex<HashSet<Exception>> {
+        &self.exceptions
+    }
+
+    /// Liefert eine Referenz auf die Relais (Alarmausgänge) des Servers
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::Server;
+    ///
+    /// let xmz_mod_touch_server = Server::new();
+    /// assert_eq!(xmz_mod_touch_server.get_relais().get(2).unwrap(), false);
+    /// ```
+    pub fn get_relais(&self) -> &ShiftRegister {
+        &self.relais
     }
 
     /// Zonen des Servers
@@ -0,0 +1,210 @@
+//! Zustand des Servers, der einen Neustart überstehen soll
+//!
+//! Im Gegensatz zur Konfiguration ändert sich der Zustand ständig. Er wird deshalb in einer eigenen Datei
+//! gespeichert, z.B. regelmäßig vom Server Prozess. Zur Zeit gehören die Ringpuffer der 15min Mittelwerte aller
+//! Messzellen zum Zustand. Ohne sie würde ein echter AP1/ AP2 Zustand nach einem Neustart (z.B. durch den Watchdog)
+//! bis zu 15 Minuten lang nicht erkannt. Außerdem werden der Verlauf von Kalibrierung und Nullpunkt der Messzellen
+//! und die ausgelösten selbsthaltenden Alarme gespeichert. Ein selbsthaltender Alarm bleibt so auch nach einem
+//! Stromausfall bestehen, bis er quittiert wird.
+//!
+use ::chrono::{DateTime, Utc};
+use errors::*;
+use serde_json;
+use server::Server;
+use server::zone::kombisensor::sensor::{AlarmLevel, AlarmState, AverageWindow, Sensor, SensorHealth};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+
+
+/// Standard Pfad der Zustandsdatei
+pub const STATE_FILE: &str = "/var/lib/xmz-mod-touch-server/state.json";
+
+/// Gespeicherter Mittelwert und Verlauf einer Messzelle
+///
+/// Die Messzelle wird über Leitung, Modbus Adresse und Nummer der Messzelle zugeordnet. So passt der Zustand auch
+/// dann noch, wenn Zonen oder Kombisensoren in der Konfiguration umsortiert wurden.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct SensorState {
+    pub modbus_device: String,
+    pub modbus_address: u8,
+    pub num_sensor: usize,
+    pub average_window: AverageWindow,
+    #[serde(default)]
+    pub health: SensorHealth,
+    #[serde(default)]
+    pub alarm1_state: AlarmState,
+    #[serde(default)]
+    pub alarm2_state: AlarmState,
+    #[serde(default)]
+    pub alarm3_state: AlarmState,
+}
+
+/// Zustand des Servers
+///
+/// # Examples
+///
+/// ```rust
+/// use xmz_mod_touch_server::{Kombisensor, KombisensorType, Server};
+/// use xmz_mod_touch_server::server::ServerState;
+///
+/// let mut xmz_mod_touch_server = Server::new();
+/// xmz_mod_touch_server.add_zone();
+/// xmz_mod_touch_server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+/// xmz_mod_touch_server.evaluate();
+///
+/// let state = ServerState::from_server(&xmz_mod_touch_server);
+/// assert_eq!(state.get_sensors().len(), 2);
+///
+/// // Nach einem Neustart
+/// let mut xmz_mod_touch_server = Server::new();
+/// xmz_mod_touch_server.add_zone();
+/// xmz_mod_touch_server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+///
+/// assert_eq!(state.restore(&mut xmz_mod_touch_server), 2);
+/// ```
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct ServerState {
+    timestamp: DateTime<Utc>,
+    sensors: Vec<SensorState>,
+}
+
+impl ServerState {
+    /// Erfasst den aktuellen Zustand des Servers
+    ///
+    /// Messzellen ohne Messwerte, ohne Verlauf und ohne selbsthaltenden Alarm werden nicht erfasst.
+    ///
+    /// # Parameters
+    ///
+    /// * `server`  - Server, dessen Zustand erfasst wird
+    pub fn from_server(server: &Server) -> Self {
+        let mut sensors = vec![];
+        for zone in server.get_zones() {
+            for kombisensor in zone.get_kombisensors() {
+                for (num_sensor, sensor) in kombisensor.get_sensors().iter().enumerate() {
+                    if sensor.get_average_window().is_empty() && sensor.get_health().is_empty() &&
+                        !has_latching_alarm(sensor) { continue; }
+                    sensors.push(SensorState {
+                        modbus_device: kombisensor.get_bus_device(),
+                        modbus_address: kombisensor.get_modbus_address(),
+                        num_sensor: num_sensor,
+                        average_window: sensor.get_average_window().clone(),
+                        health: sensor.get_health().clone(),
+                        alarm1_state: sensor.get_alarm_state(AlarmLevel::AP1).clone(),
+                        alarm2_state: sensor.get_alarm_state(AlarmLevel::AP2).clone(),
+                        alarm3_state: sensor.get_alarm_state(AlarmLevel::DIW).clone(),
+                    });
+                }
+            }
+        }
+
+        ServerState {
+            timestamp: Utc::now(),
+            sensors: sensors,
+        }
+    }
+
+    /// Zeitpunkt, an dem der Zustand erfasst wurde
+    pub fn get_timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    /// Gespeicherte Mittelwerte der Messzellen
+    pub fn get_sensors(&self) -> &Vec<SensorState> {
+        &self.sensors
+    }
+
+    /// Übernimmt den Zustand in den Server
+    ///
+    /// Übernommen werden nur Mittelwerte, deren Messzelle es noch gibt und deren Messwerte noch im Zeitraum des
+    /// Mittelwerts liegen. Der Verlauf von Kalibrierung und Nullpunkt wird immer übernommen, ausgelöste Alarme nur
+    /// wenn der Alarmpunkt selbsthaltend ist.
+    ///
+    /// # Return values
+    ///
+    /// Liefert die Anzahl der übernommenen Mittelwerte.
+    ///
+    /// # Parameters
+    ///
+    /// * `server`  - Server, in den der Zustand übernommen wird
+    pub fn restore(&self, server: &mut Server) -> usize {
+        let mut restored = 0;
+        for zone in server.get_zones_mut() {
+            for kombisensor in zone.get_kombisensors_mut() {
+                let modbus_device = kombisensor.get_bus_device();
+                let modbus_address = kombisensor.get_modbus_address();
+                for (num_sensor, sensor) in kombisensor.get_sensors_mut().iter_mut().enumerate() {
+                    let state = self.sensors.iter().find(|state| {
+                        state.modbus_device == modbus_device &&
+                        state.modbus_address == modbus_address &&
+                        state.num_sensor == num_sensor
+                    });
+                    if let Some(state) = state {
+                        if !state.health.is_empty() {
+                            sensor.restore_health(state.health.clone());
+                        }
+                        sensor.restore_alarm_state(AlarmLevel::AP1, state.alarm1_state.clone());
+                        sensor.restore_alarm_state(AlarmLevel::AP2, state.alarm2_state.clone());
+                        sensor.restore_alarm_state(AlarmLevel::DIW, state.alarm3_state.clone());
+                        if sensor.restore_average_window(state.average_window.clone()) {
+                            restored += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        restored
+    }
+
+    /// Liest den Zustand aus einer Datei
+    ///
+    /// # Parameters
+    ///
+    /// * `path`    - Pfad der Zustandsdatei
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut content = String::new();
+        File::open(path)?.read_to_string(&mut content)?;
+
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Schreibt den Zustand in eine Datei
+    ///
+    /// Geschrieben wird zuerst in eine temporäre Datei, die dann umbenannt wird. So bleibt bei einem Absturz
+    /// während des Schreibens der vorherige Zustand erhalten.
+    ///
+    /// # Parameters
+    ///
+    /// * `path`    - Pfad der Zustandsdatei
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        let tmp_path = path.with_extension("tmp");
+        {
+            let mut file = File::create(&tmp_path)?;
+            file.write_all(serde_json::to_string(self)?.as_bytes())?;
+            file.sync_all()?;
+        }
+        fs::rename(&tmp_path, path)?;
+
+        Ok(())
+    }
+}
+
+// Hat die Messzelle einen ausgelösten selbsthaltenden Alarm?
+//
+fn has_latching_alarm(sensor: &Sensor) -> bool {
+    [AlarmLevel::AP1, AlarmLevel::AP2, AlarmLevel::DIW].iter().any(|&level| {
+        sensor.get_alarm_settings(level).latching && sensor.get_alarm_state(level).is_active()
+    })
+}
@@ -1,9 +1,24 @@
 //! CO-NO2 Kombisensor mit Modbus Transceiver
 //!
+use ::chrono::{DateTime, Duration, Utc};
 use errors::*;
-use libmodbus_rs::{Modbus, ModbusRTU, ModbusClient, MODBUS_RTU_MAX_ADU_LENGTH, RequestToSendMode};
+use modbus_bus::{CommunicationStatistics, ModbusBus, ModbusBusManager, PollRequest, PollResult, RtsMode, SerialParameterOverrides, SerialParameters, MODBUS_SIM_PREFIX};
+use modbus_data::{FirmwareVersion, ModbusData, RegisterMap, HEADER_NUM_REGISTERS, REGISTER_MODBUS_ADDRESS};
 use server::zone::kombisensor::sensor::{Sensor, SensorType};
+use std::cmp;
 use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Anzahl zusammenhängender Fehler, nach denen der Kombisensor in den Status `Kabelbruch` wechselt
+pub const KABELBRUCH_ERROR_COUNT: u64 = 5;
+/// Wartezeit nach einem Kabelbruch, bis der Kombisensor erneut abgefragt wird (in Sekunden)
+pub const KABELBRUCH_COOLDOWN_SEC: i64 = 3 * 60;
+/// Obergrenze der Wartezeit zwischen zwei Wiederholungsversuchen (in Sekunden)
+pub const KABELBRUCH_COOLDOWN_MAX_SEC: i64 = 60 * 60;
+/// Anzahl erfolgreicher Abfragen in Folge, bis der Kombisensor wieder in den Status `Normal` wechselt
+pub const KABELBRUCH_RECOVERY_COUNT: u64 = 3;
+/// Höchste gültige Modbus Adresse, neue Kombisensoren werden mit dieser Adresse ausgeliefert
+pub const MODBUS_ADDRESS_MAX: u8 = 247;
 
 
 #[derive(Clone)]
@@ -15,7 +30,7 @@ pub enum KombisensorType {
     Unknown,    // ich wollte hier nicht Default als Member Name verwenden
     // echte Hardware, NO2/ CO Sensoren (Type: NemotoNO2/ NemotoCO)
     RAGas,
-    // simmulierte Hardware, und Hardware an den Entwickler PC angeschlossen
+    // simmulierte Hardware, die Register liefert ein Emulator im Server Prozess (`modbus_device` wird nicht geöffnet)
     RAGasSimulation,
 }
 
@@ -26,12 +41,15 @@ pub enum KombisensorType {
 pub enum KombisensorStatus {
     // alles Ok
     Normal,
+    // nach einem Kabelbruch wieder erreichbar, aber noch nicht oft genug in Folge
+    Wiederanlauf,
     // Kabelbruch
     Kabelbruch,
 }
 
 /// Ein Kombisensor kann `n` Sensormesszellen enthalten, nomal sind 2 Messzellen (NO2 und CO)
 ///
+#[derive(Clone)]
 #[derive(Debug)]
 #[derive(Serialize, Deserialize)]
 pub struct Kombisensor {
@@ -41,11 +59,42 @@ pub struct Kombisensor {
     modbus_device: String,
     modbus_address: u8,
     modbus_debug: bool,
+    // Abweichende Parameter der Modbus Leitung, fehlende Werte kommen aus den Standardwerten des Kombisensor Typs
+    #[serde(default)]
+    serial_parameters: Option<SerialParameterOverrides>,
     sensors: Vec<Sensor>,
     error_count: u64,
     status: KombisensorStatus,
+    // Wartezeit nach einem Kabelbruch (in Sekunden), verdoppelt sich nach jedem fehlgeschlagenen Versuch
+    #[serde(default = "default_kabelbruch_cooldown_sec")]
+    kabelbruch_cooldown_sec: i64,
+    // Obergrenze der Wartezeit (in Sekunden)
+    #[serde(default = "default_kabelbruch_cooldown_max_sec")]
+    kabelbruch_cooldown_max_sec: i64,
+    // Erfolgreiche Abfragen in Folge, bis der Kombisensor wieder als `Normal` gilt
+    #[serde(default = "default_kabelbruch_recovery_count")]
+    kabelbruch_recovery_count: u64,
+    // Fehlgeschlagene Wiederholungsversuche seit dem Kabelbruch
+    #[serde(default)]
+    kabelbruch_retry_count: u32,
+    // Zeitpunkt des nächsten Wiederholungsversuchs
+    #[serde(default)]
+    kabelbruch_next_retry: Option<DateTime<Utc>>,
+    // Erfolgreiche Abfragen in Folge, im Status `Wiederanlauf`
+    #[serde(default)]
+    success_count: u64,
+    // Zähler aller Abfragen, wird im Gegensatz zum `error_count` nicht zurück gesetzt
+    #[serde(default)]
+    communication_statistics: CommunicationStatistics,
+    // Messwerte einer nicht unterstützten Firmware verwerfen
+    #[serde(default)]
+    reject_unsupported_firmware: bool,
 }
 
+fn default_kabelbruch_cooldown_sec() -> i64 { KABELBRUCH_COOLDOWN_SEC }
+fn default_kabelbruch_cooldown_max_sec() -> i64 { KABELBRUCH_COOLDOWN_MAX_SEC }
+fn default_kabelbruch_recovery_count() -> u64 { KABELBRUCH_RECOVERY_COUNT }
+
 impl Kombisensor {
     /// Erzeugt eine neue Kombisensor Instanz
     ///
@@ -68,9 +117,18 @@ impl Kombisensor {
             modbus_address: 247,
             modbus_device: "/dev/ttyUSB0".to_string(),
             modbus_debug: false,
+            serial_parameters: None,
             sensors: vec![],
             error_count: 0,
             status: KombisensorStatus::Normal,
+            kabelbruch_cooldown_sec: KABELBRUCH_COOLDOWN_SEC,
+            kabelbruch_cooldown_max_sec: KABELBRUCH_COOLDOWN_MAX_SEC,
+            kabelbruch_recovery_count: KABELBRUCH_RECOVERY_COUNT,
+            kabelbruch_retry_count: 0,
+            kabelbruch_next_retry: None,
+            success_count: 0,
+            communication_statistics: CommunicationStatistics::new(),
+            reject_unsupported_firmware: false,
         }
     }
     /// Erzeugt eine spezielle Kombisensor Instanz
@@ -178,6 +236,79 @@ impl Kombisensor {
         self.firmware_version = firmware_version
     }
 
+    /// Meldet der Kombisensor eine Firmware, die nicht unterstützt wird?
+    ///
+    /// Siehe [`SUPPORTED_FIRMWARE_VERSIONS`](../../../modbus_data/constant.SUPPORTED_FIRMWARE_VERSIONS.html).
+    /// Solange der Kombisensor noch nie ausgelesen wurde (Firmware Version "0.0.0"), gilt die Firmware nicht als
+    /// nicht unterstützt.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::Kombisensor;
+    ///
+    /// let mut kombisensor = Kombisensor::new();
+    /// assert!(!kombisensor.is_firmware_unsupported());
+    ///
+    /// kombisensor.set_firmware_version("0.14.0".to_string());
+    /// assert!(!kombisensor.is_firmware_unsupported());
+    ///
+    /// kombisensor.set_firmware_version("9.9.99".to_string());
+    /// assert!(kombisensor.is_firmware_unsupported());
+    /// ```
+    pub fn is_firmware_unsupported(&self) -> bool {
+        match FirmwareVersion::parse(&self.firmware_version) {
+            Ok(firmware_version) => firmware_version != FirmwareVersion::new(0, 0, 0) && !firmware_version.is_supported(),
+            Err(_) => true,
+        }
+    }
+
+    /// Werden die Messwerte einer nicht unterstützten Firmware verworfen?
+    pub fn get_reject_unsupported_firmware(&self) -> bool {
+        self.reject_unsupported_firmware
+    }
+
+    /// Legt fest, ob die Messwerte einer nicht unterstützten Firmware verworfen werden
+    ///
+    /// Ohne diese Einstellung werden die Messwerte trotzdem übernommen, der Kombisensor wird aber als Ausnahme
+    /// gemeldet. Mit dieser Einstellung zählt jede Abfrage als Fehler: die Sensoren behalten ihre bisherigen Werte,
+    /// aber ohne neuen Zeitstempel (der 15min Mittelwert wird lückenhaft), und nach `KABELBRUCH_ERROR_COUNT`
+    /// Abfragen wechselt der Kombisensor in den Status `Kabelbruch`. So können veraltete Werte weder einen Alarm
+    /// auslösen noch eine Störung verdecken.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Kombisensor, KombisensorStatus, KombisensorType, ModbusBusManager};
+    /// use xmz_mod_touch_server::server::zone::kombisensor::kombisensor::KABELBRUCH_ERROR_COUNT;
+    /// use xmz_mod_touch_server::modbus_bus::{PollResult};
+    /// use xmz_mod_touch_server::modbus_data::SIMULATION_DATA_STATIC;
+    /// use std::time::Duration;
+    ///
+    /// let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGasSimulation);
+    /// kombisensor.set_reject_unsupported_firmware(true);
+    /// let adc_value = kombisensor.get_sensor(0).unwrap().get_adc_value();
+    ///
+    /// // Register Abbild einer Firmware 1.0.0
+    /// let mut registers = SIMULATION_DATA_STATIC.to_vec();
+    /// registers[0] = 1; registers[1] = 0; registers[2] = 0;
+    /// registers[11] = adc_value + 100;
+    /// kombisensor.apply_poll_result(PollResult::new(Ok(registers.clone()), Duration::from_millis(10)));
+    ///
+    /// assert!(kombisensor.is_firmware_unsupported());
+    /// assert_eq!(kombisensor.get_sensor(0).unwrap().get_adc_value(), adc_value);
+    /// assert!(kombisensor.get_sensor(0).unwrap().get_adc_value_timestamp().is_none());
+    /// assert_eq!(kombisensor.get_error_count(), 1);
+    ///
+    /// for _ in 1..KABELBRUCH_ERROR_COUNT {
+    ///     kombisensor.apply_poll_result(PollResult::new(Ok(registers.clone()), Duration::from_millis(10)));
+    /// }
+    /// assert_eq!(kombisensor.get_status(), KombisensorStatus::Kabelbruch);
+    /// ```
+    pub fn set_reject_unsupported_firmware(&mut self, reject_unsupported_firmware: bool) {
+        self.reject_unsupported_firmware = reject_unsupported_firmware;
+    }
+
     /// Get modbus_address
     ///
     /// # Return values
@@ -241,6 +372,9 @@ impl Kombisensor {
     ///
     /// * `modbus_device`    - String mit der neuen Modbus Device Adresse
     ///
+    /// Neben seriellen Schnittstellen (Modbus RTU) kann auch ein Modbus TCP Gateway angegeben werden,
+    /// z.B. `tcp://192.168.1.20:502`.
+    ///
     /// # Examples
     ///
     /// ```rust
@@ -249,6 +383,9 @@ impl Kombisensor {
     ///
     /// kombisensor.set_modbus_device("/dev/ttyS1".to_string());
     /// assert_eq!(kombisensor.get_modbus_device(), "/dev/ttyS1".to_string());
+    ///
+    /// kombisensor.set_modbus_device("tcp://192.168.1.20:502".to_string());
+    /// assert_eq!(kombisensor.get_modbus_device(), "tcp://192.168.1.20:502".to_string());
     /// ```
     pub fn set_modbus_device(&mut self, modbus_device: String) {
         self.modbus_device = modbus_device
@@ -291,6 +428,121 @@ impl Kombisensor {
         self.modbus_debug = modbus_debug
     }
 
+    /// Liefert die Kommunikationsstatistik des Kombisensors
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Kombisensor, KombisensorType, ModbusBusManager};
+    ///
+    /// let bus_manager = ModbusBusManager::new();
+    /// let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGasSimulation);
+    /// kombisensor.update(&bus_manager);
+    ///
+    /// assert_eq!(kombisensor.get_communication_statistics().get_request_count(), 1);
+    /// assert_eq!(kombisensor.get_communication_statistics().get_success_count(), 1);
+    /// ```
+    pub fn get_communication_statistics(&self) -> &CommunicationStatistics {
+        &self.communication_statistics
+    }
+
+    /// Setzt die Kommunikationsstatistik des Kombisensors zurück
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Kombisensor, KombisensorType, ModbusBusManager};
+    ///
+    /// let bus_manager = ModbusBusManager::new();
+    /// let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGas);
+    /// kombisensor.set_modbus_device("/dev/nicht_vorhanden".to_string());
+    /// kombisensor.update(&bus_manager);
+    /// assert_eq!(kombisensor.get_communication_statistics().get_failure_count(), 1);
+    ///
+    /// kombisensor.reset_communication_statistics();
+    /// assert_eq!(kombisensor.get_communication_statistics().get_failure_count(), 0);
+    /// ```
+    pub fn reset_communication_statistics(&mut self) {
+        self.communication_statistics.reset();
+    }
+
+    /// Liefert die Parameter der Modbus Leitung
+    ///
+    /// Grundlage sind die Standardwerte (9600 Baud, 'N', 8, 1). Ein `RAGas` Kombisensor hängt an der
+    /// 'xMZ-Mod-Touch'-Hardware, dort wird zusätzlich der RTS Pin genutzt. Die in der Konfiguration angegebenen
+    /// Werte überschreiben nur die jeweiligen Standardwerte. Alle Kombisensoren einer seriellen Leitung müssen die
+    /// selben Leitungsparameter verwenden, nur die Timeouts dürfen abweichen, siehe
+    /// [`Server::check_serial_parameters()`](../../../struct.Server.html#method.check_serial_parameters).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// extern crate serde_json;
+    /// # extern crate xmz_mod_touch_server;
+    /// use xmz_mod_touch_server::{Kombisensor, KombisensorType};
+    /// use xmz_mod_touch_server::modbus_bus::RtsMode;
+    ///
+    /// # fn main() {
+    /// let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGas);
+    /// assert_eq!(kombisensor.get_serial_parameters().get_baud(), 9600);
+    /// assert_eq!(kombisensor.get_serial_parameters().get_rts_mode(), RtsMode::Down);
+    ///
+    /// // Nur die Baudrate aus der Konfiguration, der RTS Modus bleibt erhalten
+    /// kombisensor.set_serial_parameter_overrides(Some(serde_json::from_str(r#"{"baud": 19200}"#).unwrap()));
+    /// assert_eq!(kombisensor.get_serial_parameters().get_baud(), 19200);
+    /// assert_eq!(kombisensor.get_serial_parameters().get_rts_mode(), RtsMode::Down);
+    /// # }
+    /// ```
+    pub fn get_serial_parameters(&self) -> SerialParameters {
+        let defaults = match self.kombisensor_type {
+            KombisensorType::RAGas => SerialParameters::new_with_rts_mode(RtsMode::Down),
+            _ => SerialParameters::new(),
+        };
+        match self.serial_parameters {
+            Some(ref overrides) => overrides.apply_to(defaults),
+            None => defaults,
+        }
+    }
+
+    /// Setzt die Parameter der Modbus Leitung
+    ///
+    /// `None` stellt die Standardwerte des Kombisensor Typs wieder her.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Kombisensor, KombisensorType};
+    /// use xmz_mod_touch_server::modbus_bus::SerialParameters;
+    ///
+    /// let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGas);
+    /// let mut serial_parameters = SerialParameters::new();
+    /// serial_parameters.set_baud(19200);
+    /// serial_parameters.set_response_timeout_ms(1000);
+    ///
+    /// kombisensor.set_serial_parameters(Some(serial_parameters.clone()));
+    /// assert_eq!(kombisensor.get_serial_parameters(), serial_parameters);
+    /// ```
+    pub fn set_serial_parameters(&mut self, serial_parameters: Option<SerialParameters>) {
+        self.serial_parameters = serial_parameters.map(SerialParameterOverrides::from);
+    }
+
+    /// Abweichende Parameter der Modbus Leitung, wie sie in der Konfiguration stehen
+    pub fn get_serial_parameter_overrides(&self) -> Option<&SerialParameterOverrides> {
+        self.serial_parameters.as_ref()
+    }
+
+    /// Setzt einzelne abweichende Parameter der Modbus Leitung
+    ///
+    /// Nicht angegebene Werte kommen aus den Standardwerten des Kombisensor Typs, `None` stellt alle
+    /// Standardwerte wieder her.
+    ///
+    /// # Parameters
+    ///
+    /// * `overrides`   - abweichende Parameter
+    pub fn set_serial_parameter_overrides(&mut self, overrides: Option<SerialParameterOverrides>) {
+        self.serial_parameters = overrides;
+    }
+
     /// TODO: Referenz auf Vector durch &[T] ersetzen
     /// Liefert eine Referenz auf einen Vector mit den Sensoren
     ///
@@ -415,6 +667,10 @@ impl Kombisensor {
 
     /// Reset den Fehlerzähler (Error Counter)
     ///
+    /// Ist der Kombisensor im Status `Kabelbruch` oder `Wiederanlauf`, dann wird er, wie bisher, wieder in den
+    /// Status `Normal` geschaltet und im nächsten Update sofort abgefragt. Die Wartezeit und die Anzahl der
+    /// Wiederholungen werden ebenfalls zurückgesetzt.
+    ///
     /// # Examples
     ///
     /// ```rust
@@ -426,108 +682,679 @@ impl Kombisensor {
     /// kombisensor.reset_error_count();
     /// assert_eq!(kombisensor.get_error_count(), 0);
     /// ```
+    ///
+    /// Ein Kabelbruch wird damit quittiert
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Kombisensor, KombisensorStatus, ModbusBusManager};
+    ///
+    /// let bus_manager = ModbusBusManager::new();
+    /// let mut kombisensor = Kombisensor::new();
+    /// kombisensor.set_modbus_device("/dev/nicht_vorhanden".to_string());
+    ///
+    /// for _ in 0..5 { kombisensor.update(&bus_manager); }
+    /// assert_eq!(kombisensor.get_status(), KombisensorStatus::Kabelbruch);
+    ///
+    /// kombisensor.reset_error_count();
+    /// assert_eq!(kombisensor.get_status(), KombisensorStatus::Normal);
+    /// assert!(kombisensor.get_kabelbruch_next_retry().is_none());
+    /// assert!(kombisensor.get_poll_request().is_some());
+    /// ```
     pub fn reset_error_count(&mut self) {
-        self.error_count = 0
+        self.error_count = 0;
+        if self.status != KombisensorStatus::Normal {
+            self.status = KombisensorStatus::Normal;
+            self.kabelbruch_retry_count = 0;
+            self.kabelbruch_next_retry = None;
+            self.success_count = 0;
+        }
     }
 
-    /// Fragt die Daten des Kombisensors via Modbus ab
+    /// Liefert die Abfrage, die im nächsten Update ausgeführt werden soll
+    ///
+    /// Die Abfrage läuft über den [`ModbusBus`](../../../modbus_bus/struct.ModbusBus.html) der Leitung
+    /// `modbus_device`. Die Verbindung der Leitung wird von allen Kombisensoren an dieser Leitung geteilt.
     ///
     /// # Return values
     ///
-    /// Die Funktion liefert ein Result
+    /// Liefert `None` wenn der Kombisensor im Status `Kabelbruch` ist und die Wartezeit noch nicht abgelaufen ist.
     ///
-    fn update_via_modbus(&mut self) -> Result<()> {
-        let mut modbus = Modbus::new_rtu(&self.modbus_device, 9600, 'N', 8, 1)?;
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Kombisensor, KombisensorType};
+    ///
+    /// let kombisensor = Kombisensor::new_with_type(KombisensorType::RAGasSimulation);
+    /// let poll_request = kombisensor.get_poll_request().unwrap();
+    ///
+    /// assert_eq!(poll_request.get_modbus_device(), "sim:///dev/ttyUSB0".to_string());
+    /// assert_eq!(poll_request.get_modbus_address(), kombisensor.get_modbus_address());
+    /// ```
+    pub fn get_poll_request(&self) -> Option<PollRequest> {
+        if self.status == KombisensorStatus::Kabelbruch && !self.is_retry_due() {
+            return None;
+        }
 
-        modbus.set_slave(self.modbus_address)?;
+        // Die Anzahl der Register hängt von der Firmware Version der letzten Abfrage ab
+        let register_map = RegisterMap::for_firmware_version_str(&self.firmware_version);
 
         // Debug Modus einschalten wenn gewünscht. Siehe Konfigurationsdatein und `Configuration` Modul
-        modbus.set_debug(self.modbus_debug)?;
+        Some(PollRequest::new(&self.get_bus_device(),
+                              self.get_serial_parameters(),
+                              self.modbus_address,
+                              register_map.get_num_registers(),
+                              self.modbus_debug))
+    }
+
+    /// Wertet das Ergebnis einer Abfrage aus
+    ///
+    /// Die gelesenen Register werden in die Sensoren übernommen, die Kommunikationsstatistik wird aktualisiert
+    /// und der Status des Kombisensors ausgewertet. Siehe [`update()`](#method.update)
+    ///
+    /// Register, die gelesen aber nicht ausgewertet werden konnten (z.B. eine zu kurze Antwort oder eine
+    /// abgelehnte Firmware), zählen in der Kommunikationsstatistik als Fehler.
+    ///
+    /// Meldet die Antwort eine neue Firmware, deren Register Layout mehr Register umfasst als gelesen wurden, dann
+    /// wird die Abfrage nicht als Fehler gezählt. Die nächste Abfrage verwendet das Layout der neuen Firmware.
+    ///
+    /// # Parameters
+    ///
+    /// * `poll_result` - Ergebnis der Abfrage aus [`get_poll_request()`](#method.get_poll_request)
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Kombisensor, KombisensorType, ModbusBusManager};
+    ///
+    /// let bus_manager = ModbusBusManager::new();
+    /// let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGasSimulation);
+    ///
+    /// // Die Abfrage braucht keinen Zugriff auf den Kombisensor
+    /// let poll_request = kombisensor.get_poll_request().unwrap();
+    /// let poll_result = poll_request.execute(&bus_manager);
+    ///
+    /// kombisensor.apply_poll_result(poll_result);
+    /// assert_eq!(kombisensor.get_firmware_version(), "0.14.0".to_string());
+    /// ```
+    pub fn apply_poll_result(&mut self, poll_result: PollResult) {
+        self.apply_poll(poll_result);
+    }
+
+    // Wertet das Ergebnis einer Abfrage aus, siehe `apply_poll_result()`
+    //
+    // Liefert `true` wenn die Antwort eine neue Firmware mit einem größeren Register Layout meldet. Die Abfrage
+    // lief dann noch mit dem alten Layout und wird weder als Fehler gezählt noch in der Kommunikationsstatistik
+    // erfasst, die nächste Abfrage verwendet das neue Layout.
+    fn apply_poll(&mut self, poll_result: PollResult) -> bool {
+        let latency = poll_result.get_latency();
+        let result = match poll_result.into_result() {
+            Ok(registers) => {
+                let previous_firmware_version = self.firmware_version.clone();
+                let result = self.apply_registers(&registers);
+                if result.is_err() && self.firmware_version != previous_firmware_version &&
+                    registers.len() < RegisterMap::for_firmware_version_str(&self.firmware_version).get_num_registers() as usize {
+                    info!("Kombisensor {} ({}): Firmware {}, Register Layout wird neu erkannt",
+                        self.modbus_address, self.modbus_device, self.firmware_version);
+                    return true;
+                }
+
+                // Eine Antwort, die nicht ausgewertet werden kann, zählt als Fehler
+                match result {
+                    Ok(_) => self.communication_statistics.record_success(latency),
+                    Err(ref e) => self.communication_statistics.record_failure(e),
+                }
+                result
+            }
+            Err(e) => {
+                self.communication_statistics.record_failure(&e);
+                Err(e)
+            }
+        };
+
+        match self.status {
+            KombisensorStatus::Normal => {
+                match result {
+                    Ok(_)  => {
+                        // Wenn die Modbus Kommunikation erfolgreich war, wird der Error Coutner wieder reseted
+                        self.reset_error_count();
+                    }
+                    Err(_) => {
+                        // Im Fehlerfall wird der Error Coutner um eins erhöht, sind 5 zusammenhängende Fehler hinter einander
+                        // aufgetreten, dann wird in der Funktion `update_status()` der Status des Kombisensors auf Kabelbruch gesetzt.
+                        self.inc_error_count();
+                    },
+                }
+            },
+            KombisensorStatus::Wiederanlauf => {
+                match result {
+                    Ok(_) => {
+                        self.success_count += 1;
+                    }
+                    Err(_) => {
+                        // Ein Fehler während des Wiederanlaufs gilt als fehlgeschlagener Wiederholungsversuch
+                        self.inc_error_count();
+                        self.status = KombisensorStatus::Kabelbruch;
+                        self.success_count = 0;
+                        self.kabelbruch_retry_count = self.kabelbruch_retry_count.saturating_add(1);
+                        self.schedule_retry();
+                    }
+                }
+            }
+            KombisensorStatus::Kabelbruch => {
+                match result {
+                    Ok(_) => {
+                        info!("Kombisensor {} ({}): wieder erreichbar", self.modbus_address, self.modbus_device);
+                        self.status = KombisensorStatus::Wiederanlauf;
+                        self.success_count = 1;
+                    }
+                    Err(_) => {
+                        self.inc_error_count();
+                        self.kabelbruch_retry_count = self.kabelbruch_retry_count.saturating_add(1);
+                        self.schedule_retry();
+                    }
+                }
+            }
+        }
+
+        self.update_status();
+
+        false
+    }
+
+    // Übernimmt die gelesenen Register in die Sensoren
+    //
+    fn apply_registers(&mut self, response_register: &[u16]) -> Result<()> {
+        // Nach einem Firmware Update passt die Antwort eventuell nicht mehr zum bisherigen Layout. Die Firmware
+        // Version wird deshalb vor dem Parsen übernommen, die nächste Abfrage verwendet dann das richtige Layout.
+        let firmware_version = ModbusData::parse_firmware_version(response_register)?;
+        if firmware_version != self.firmware_version {
+            self.set_firmware_version(firmware_version);
+            if self.is_firmware_unsupported() {
+                warn!("Kombisensor {} ({}): Firmware {} wird nicht unterstützt", self.modbus_address, self.modbus_device, self.firmware_version);
+            }
+        }
+        if self.reject_unsupported_firmware && self.is_firmware_unsupported() {
+            // Die Messwerte werden nicht übernommen, die Abfrage zählt als Fehler. Die Sensoren behalten ihre Werte
+            // ohne neuen Zeitstempel und der Kombisensor wechselt, wie bei einem Kabelbruch, in den Status Störung.
+            bail!("Firmware {} not supported", self.firmware_version)
+        }
+        let modbus_data = ModbusData::parse(response_register)?;
 
-        // Auf der 'xMZ-Mod-Touch'-Hardware muss der RTS Pin genutzt werden
-        if self.kombisensor_type == KombisensorType::RAGas {
-            info!("'xMZ-Mod-Touch'-Hardware erkannt, setze SerialMode RTS_DOWN");
-            modbus.rtu_set_rts(RequestToSendMode::MODBUS_RTU_RTS_DOWN)?;
+        // Run through all sensors and update the members
+        for (sensor, sensor_data) in self.sensors.iter_mut().zip(modbus_data.get_sensors()) {
+            sensor.set_adc_value(sensor_data.get_adc_value());
+            sensor.set_min_value(sensor_data.get_min_value());
+            sensor.set_max_value(sensor_data.get_max_value());
+            sensor.set_adc_value_at_nullgas(sensor_data.get_adc_value_at_nullgas());
+            sensor.set_adc_value_at_messgas(sensor_data.get_adc_value_at_messgas());
+            sensor.set_concentration_at_nullgas(sensor_data.get_concentration_at_nullgas());
+            sensor.set_concentration_at_messgas(sensor_data.get_concentration_at_messgas());
+            sensor.set_config(sensor_data.get_config());
         }
 
-        // Connecte via Modbus
-        modbus.connect()?;
-
-        let mut response_register = vec![0u16; MODBUS_RTU_MAX_ADU_LENGTH as usize];
-        modbus.read_registers(0, 30, &mut response_register)?;
-
-        if response_register.len() < 28 { bail!("Modbus Data invalid: {:?}", response_register) } // else { println!("{:?}", response_register); }
-
-        // Parse die empfangenen Daten
-        // TODO: unwrap() entfernen!
-        let firmware_version_major = *response_register.get(0).unwrap();
-        let firmware_version_minor = *response_register.get(1).unwrap();
-        let firmware_version_patch = *response_register.get(2).unwrap();
-        // let modbus_address = *response_register.get(3).unwrap();
-        let _sensor1_num = *response_register.get(10).unwrap();
-        let sensor1_adc_value = *response_register.get(11).unwrap();
-        let sensor1_min_value = *response_register.get(12).unwrap();
-        let sensor1_max_value = *response_register.get(13).unwrap();
-        let sensor1_adc_value_at_nullgas = *response_register.get(14).unwrap();
-        let sensor1_adc_value_at_messgas = *response_register.get(15).unwrap();
-        let sensor1_concentration_at_nullgas = *response_register.get(16).unwrap();
-        let sensor1_concentration_at_messgas = *response_register.get(17).unwrap();
-        let sensor1_config = *response_register.get(18).unwrap();
-        let _sensor2_num = *response_register.get(20).unwrap();
-        let sensor2_adc_value = *response_register.get(21).unwrap();
-        let sensor2_min_value = *response_register.get(22).unwrap();
-        let sensor2_max_value = *response_register.get(23).unwrap();
-        let sensor2_adc_value_at_nullgas = *response_register.get(24).unwrap();
-        let sensor2_adc_value_at_messgas = *response_register.get(25).unwrap();
-        let sensor2_concentration_at_nullgas = *response_register.get(26).unwrap();
-        let sensor2_concentration_at_messgas = *response_register.get(27).unwrap();
-        let sensor2_config = *response_register.get(28).unwrap();
-
-        self.set_firmware_version(format!("{}.{}.{}", firmware_version_major, firmware_version_minor, firmware_version_patch));
-
-        // Run through both sensors and update the mebers
-        if let Some(sensor1) = self.get_sensor_mut(0) {
-            sensor1.set_adc_value(sensor1_adc_value);
-            sensor1.set_adc_value(sensor1_adc_value);
-            sensor1.set_min_value(sensor1_min_value);
-            sensor1.set_max_value(sensor1_max_value);
-            sensor1.set_adc_value_at_nullgas(sensor1_adc_value_at_nullgas);
-            sensor1.set_adc_value_at_messgas(sensor1_adc_value_at_messgas);
-            sensor1.set_concentration_at_nullgas(sensor1_concentration_at_nullgas);
-            sensor1.set_concentration_at_messgas(sensor1_concentration_at_messgas);
-            sensor1.set_config(sensor1_config);
+        Ok(())
+    }
+
+    /// Schreibt die Kalibrierwerte einer Sensor Messzelle in die Firmware des Kombisensors
+    ///
+    /// Geschrieben werden `adc_value_at_nullgas`, `adc_value_at_messgas`, `concentration_at_nullgas` und
+    /// `concentration_at_messgas` der Messzelle, so wie sie aktuell im Speicher stehen. Die Konfiguration wird nur
+    /// über [`write_sensor_enabled()`](#method.write_sensor_enabled) geschrieben. Die Register
+    /// werden mit einem einzigen Schreibzugriff übertragen, anschließend zurück gelesen und mit den geschriebenen
+    /// Werten verglichen.
+    ///
+    /// # Return values
+    ///
+    /// Liefert einen Fehler wenn die Messzelle nicht existiert, die Register nicht geschrieben werden konnten
+    /// oder die zurück gelesenen Werte nicht mit den geschriebenen übereinstimmen.
+    ///
+    /// # Parameters
+    ///
+    /// * `bus_manager` - Verwaltung der Modbus Leitungen, liefert den Bus des Kombisensors
+    /// * `sensor_id`   - Id der Sensormesszelle
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Kombisensor, KombisensorType, ModbusBusManager};
+    ///
+    /// let bus_manager = ModbusBusManager::new();
+    /// let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGasSimulation);
+    ///
+    /// kombisensor.get_sensor_mut(0).unwrap().set_adc_value_at_messgas(600);
+    /// assert!(kombisensor.write_sensor_calibration(&bus_manager, 0).is_ok());
+    /// // Messzelle existiert nicht
+    /// assert!(kombisensor.write_sensor_calibration(&bus_manager, 2).is_err());
+    ///
+    /// let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGas);
+    /// kombisensor.set_modbus_device("/dev/nicht_vorhanden".to_string());
+    /// assert!(kombisensor.write_sensor_calibration(&bus_manager, 0).is_err());
+    /// ```
+    pub fn write_sensor_calibration(&self, bus_manager: &ModbusBusManager, sensor_id: usize) -> Result<()> {
+        let register_map = RegisterMap::for_firmware_version_str(&self.firmware_version);
+        let address = match register_map.get_sensor_address(sensor_id) {
+            Some(address) => address,
+            None => bail!("Sensor {} not supported by firmware {}", sensor_id, self.firmware_version),
+        };
+        let sensor_registers = register_map.get_sensor_registers();
+        let mut values = match self.get_sensor(sensor_id) {
+            Some(sensor) => vec![
+                (address + sensor_registers.adc_value_at_nullgas(), sensor.get_adc_value_at_nullgas()),
+                (address + sensor_registers.adc_value_at_messgas(), sensor.get_adc_value_at_messgas()),
+                (address + sensor_registers.concentration_at_nullgas(), sensor.get_concentration_at_nullgas()),
+                (address + sensor_registers.concentration_at_messgas(), sensor.get_concentration_at_messgas()),
+            ],
+            None => bail!("Sensor {} not found", sensor_id),
+        };
+
+        // Die Werte werden mit einem einzigen Schreibzugriff übertragen, die Register müssen deshalb im Layout
+        // der Firmware lückenlos hinter einander liegen.
+        values.sort_by_key(|&(register, _)| register);
+        let first_register = values[0].0;
+        if values.iter().enumerate().any(|(i, &(register, _))| register != first_register + i as u16) {
+            bail!("Calibration registers of firmware {} are not contiguous", self.firmware_version)
         }
+        let block: Vec<u16> = values.iter().map(|&(_, value)| value).collect();
 
-        if let Some(sensor2) = self.get_sensor_mut(1) {
-            sensor2.set_adc_value(sensor2_adc_value);
-            sensor2.set_adc_value(sensor2_adc_value);
-            sensor2.set_min_value(sensor2_min_value);
-            sensor2.set_max_value(sensor2_max_value);
-            sensor2.set_adc_value_at_nullgas(sensor2_adc_value_at_nullgas);
-            sensor2.set_adc_value_at_messgas(sensor2_adc_value_at_messgas);
-            sensor2.set_concentration_at_nullgas(sensor2_concentration_at_nullgas);
-            sensor2.set_concentration_at_messgas(sensor2_concentration_at_messgas);
-            sensor2.set_config(sensor2_config);
+        let bus = self.get_bus(bus_manager)?;
+        if let Ok(mut bus) = bus.lock() {
+            bus.set_serial_parameters(&self.get_serial_parameters());
+            bus.write_registers(self.modbus_address, first_register, &block, self.modbus_debug)?;
+
+            // Zur Kontrolle den gesammten Registerblock der Messzelle zurück lesen
+            let num_registers = values.iter().map(|&(register, _)| register - address + 1).max().unwrap_or(0);
+            let written = bus.read_registers(self.modbus_address, address, num_registers, self.modbus_debug)?;
+            for &(register, value) in &values {
+                let read_back = written.get((register - address) as usize).cloned();
+                if read_back != Some(value) {
+                    bail!("Modbus verify failed, Kombisensor {} Sensor {} Register {}: written {}, read back {:?}",
+                        self.modbus_address, sensor_id, register, value, read_back)
+                }
+            }
+        } else {
+            bail!("Could not lock Modbus Bus: {}", self.modbus_device)
         }
 
         Ok(())
     }
 
+    /// Aktiviert oder deaktiviert eine Sensor Messzelle in der Firmware des Kombisensors
+    ///
+    /// Das Konfigurationsregister der Messzelle wird aus der Firmware gelesen, das `enabled` Bit (Bit 0) gesetzt
+    /// bzw. gelöscht und das Register zurück geschrieben. Die übrigen Bits bleiben unverändert. Stimmt der zurück
+    /// gelesene Wert, wird er auch für die Messzelle im Speicher übernommen, siehe
+    /// [`Sensor::set_enabled()`](sensor/struct.Sensor.html#method.set_enabled).
+    ///
+    /// Eine deaktivierte Messzelle wird bei der Auswertung der Zone und beim 15min Mittelwert nicht mehr berücksichtigt.
+    ///
+    /// # Return values
+    ///
+    /// Liefert einen Fehler wenn die Messzelle nicht existiert, das Register nicht gelesen oder geschrieben werden
+    /// konnte, oder der zurück gelesene Wert nicht mit dem geschriebenen übereinstimmt.
+    ///
+    /// # Parameters
+    ///
+    /// * `bus_manager` - Verwaltung der Modbus Leitungen, liefert den Bus des Kombisensors
+    /// * `sensor_id`   - Id der Sensormesszelle
+    /// * `enabled`     - `true` aktiviert, `false` deaktiviert die Messzelle
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Kombisensor, KombisensorType, ModbusBusManager};
+    ///
+    /// let bus_manager = ModbusBusManager::new();
+    /// let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGasSimulation);
+    ///
+    /// kombisensor.write_sensor_enabled(&bus_manager, 0, false).unwrap();
+    /// assert_eq!(kombisensor.get_sensor(0).unwrap().is_enabled(), false);
+    ///
+    /// // Auch nach einer Abfrage bleibt die Messzelle deaktiviert
+    /// kombisensor.update(&bus_manager);
+    /// assert_eq!(kombisensor.get_sensor(0).unwrap().is_enabled(), false);
+    ///
+    /// kombisensor.write_sensor_enabled(&bus_manager, 0, true).unwrap();
+    /// assert_eq!(kombisensor.get_sensor(0).unwrap().is_enabled(), true);
+    ///
+    /// // Messzelle existiert nicht
+    /// assert!(kombisensor.write_sensor_enabled(&bus_manager, 2, false).is_err());
+    /// ```
+    pub fn write_sensor_enabled(&mut self, bus_manager: &ModbusBusManager, sensor_id: usize, enabled: bool) -> Result<()> {
+        let register_map = RegisterMap::for_firmware_version_str(&self.firmware_version);
+        let register = match register_map.get_sensor_address(sensor_id) {
+            Some(address) => address + register_map.get_sensor_registers().config(),
+            None => bail!("Sensor {} not supported by firmware {}", sensor_id, self.firmware_version),
+        };
+        if self.get_sensor(sensor_id).is_none() {
+            bail!("Sensor {} not found", sensor_id)
+        }
+
+        let bus = self.get_bus(bus_manager)?;
+        let config = if let Ok(mut bus) = bus.lock() {
+            bus.set_serial_parameters(&self.get_serial_parameters());
+            let current = match bus.read_registers(self.modbus_address, register, 1, self.modbus_debug)?.first() {
+                Some(&current) => current,
+                None => bail!("Kombisensor {} returned no config for Sensor {}", self.modbus_address, sensor_id),
+            };
+            let config = if enabled { current | 1 } else { current & !1 };
+            bus.write_registers(self.modbus_address, register, &[config], self.modbus_debug)?;
+
+            let read_back = bus.read_registers(self.modbus_address, register, 1, self.modbus_debug)?.first().cloned();
+            if read_back != Some(config) {
+                bail!("Modbus verify failed, Kombisensor {} Sensor {} Register {}: written {}, read back {:?}",
+                    self.modbus_address, sensor_id, register, config, read_back)
+            }
+            config
+        } else {
+            bail!("Could not lock Modbus Bus: {}", self.modbus_device)
+        };
+
+        if let Some(sensor) = self.get_sensor_mut(sensor_id) {
+            sensor.set_config(config);
+            sensor.set_enabled(enabled);
+        }
+        info!("Kombisensor {} an {}: Sensor {} {}", self.modbus_address, self.modbus_device, sensor_id,
+            if enabled { "aktiviert" } else { "deaktiviert" });
+
+        Ok(())
+    }
+
+    /// Vergibt dem Kombisensor eine neue Modbus Adresse
+    ///
+    /// Die neue Adresse wird in das Register 3 des Kombisensors, unter der bisherigen Adresse, geschrieben.
+    /// Anschließend wird der Kombisensor unter der neuen Adresse abgefragt. Meldet er dort die neue Adresse,
+    /// wird `modbus_address` übernommen.
+    ///
+    /// Die Firmware antwortet auf den Schreibzugriff eventuell schon unter der neuen Adresse. Ein Fehler beim
+    /// Schreiben ist deshalb nur dann ein Fehler, wenn auch die Kontrolle unter der neuen Adresse fehlschlägt.
+    ///
+    /// # Return values
+    ///
+    /// Liefert einen Fehler wenn die Adresse ungültig ist, oder der Kombisensor unter der neuen Adresse nicht
+    /// antwortet. Die `modbus_address` bleibt in diesem Fall unverändert.
+    ///
+    /// # Parameters
+    ///
+    /// * `bus_manager`     - Verwaltung der Modbus Leitungen, liefert den Bus des Kombisensors
+    /// * `modbus_address`  - neue Modbus Adresse, 1 bis 247
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Kombisensor, KombisensorType, ModbusBusManager};
+    ///
+    /// let bus_manager = ModbusBusManager::new();
+    /// let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGasSimulation);
+    /// kombisensor.set_modbus_address(247);
+    ///
+    /// kombisensor.write_modbus_address(&bus_manager, 12).unwrap();
+    /// assert_eq!(kombisensor.get_modbus_address(), 12);
+    ///
+    /// // Ungültige Adressen werden nicht geschrieben
+    /// assert!(kombisensor.write_modbus_address(&bus_manager, 0).is_err());
+    /// assert!(kombisensor.write_modbus_address(&bus_manager, 248).is_err());
+    /// assert_eq!(kombisensor.get_modbus_address(), 12);
+    /// ```
+    pub fn write_modbus_address(&mut self, bus_manager: &ModbusBusManager, modbus_address: u8) -> Result<()> {
+        if modbus_address < 1 || modbus_address > MODBUS_ADDRESS_MAX {
+            bail!("Invalid Modbus address: {}", modbus_address)
+        }
+        if modbus_address == self.modbus_address {
+            return Ok(());
+        }
+
+        let bus = self.get_bus(bus_manager)?;
+        if let Ok(mut bus) = bus.lock() {
+            bus.set_serial_parameters(&self.get_serial_parameters());
+            let write_result = bus.write_registers(self.modbus_address, REGISTER_MODBUS_ADDRESS, &[modbus_address as u16], self.modbus_debug);
+
+            match bus.read_registers(modbus_address, 0, HEADER_NUM_REGISTERS, self.modbus_debug) {
+                Ok(ref registers) if registers.get(REGISTER_MODBUS_ADDRESS as usize) == Some(&(modbus_address as u16)) => {
+                    if let Err(e) = write_result {
+                        debug!("Kombisensor {}: Antwort auf Adressänderung fehlerhaft ({})", self.modbus_address, e);
+                    }
+                }
+                Ok(registers) => {
+                    bail!("Modbus verify failed, Kombisensor {} reports address {:?} instead of {}",
+                        self.modbus_address, registers.get(REGISTER_MODBUS_ADDRESS as usize), modbus_address)
+                }
+                Err(e) => {
+                    write_result?;
+                    bail!("Kombisensor not responding at new address {}: {}", modbus_address, e)
+                }
+            }
+        } else {
+            bail!("Could not lock Modbus Bus: {}", self.modbus_device)
+        }
+
+        info!("Kombisensor {} an {}: neue Modbus Adresse {}", self.modbus_address, self.modbus_device, modbus_address);
+        self.modbus_address = modbus_address;
+
+        Ok(())
+    }
+
+    /// Liefert die Leitung, über die der Kombisensor abgefragt wird
+    ///
+    /// Simulierte Kombisensoren werden von einem Emulator beantwortet, ihre Leitung beginnt deshalb immer mit `sim://`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Kombisensor, KombisensorType};
+    ///
+    /// let kombisensor = Kombisensor::new_with_type(KombisensorType::RAGas);
+    /// assert_eq!(kombisensor.get_bus_device(), "/dev/ttyS1".to_string());
+    ///
+    /// let kombisensor = Kombisensor::new_with_type(KombisensorType::RAGasSimulation);
+    /// assert_eq!(kombisensor.get_bus_device(), "sim:///dev/ttyUSB0".to_string());
+    /// ```
+    pub fn get_bus_device(&self) -> String {
+        if self.kombisensor_type == KombisensorType::RAGasSimulation && !self.modbus_device.starts_with(MODBUS_SIM_PREFIX) {
+            format!("{}{}", MODBUS_SIM_PREFIX, self.modbus_device)
+        } else {
+            self.modbus_device.clone()
+        }
+    }
+
+    // Liefert den Modbus Bus, an dem der Kombisensor angeschlossen ist
+    //
+    fn get_bus(&self, bus_manager: &ModbusBusManager) -> Result<Arc<Mutex<ModbusBus>>> {
+        bus_manager.get_bus(&self.get_bus_device(), self.get_serial_parameters())
+    }
+
     /// Gibt den Status des Kombisensors wieder
     pub fn get_status(&self) -> KombisensorStatus {
         self.status.clone()
     }
 
+    /// Wartezeit nach einem Kabelbruch (in Sekunden)
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::Kombisensor;
+    ///
+    /// let kombisensor = Kombisensor::new();
+    /// assert_eq!(kombisensor.get_kabelbruch_cooldown_sec(), 180);
+    /// ```
+    pub fn get_kabelbruch_cooldown_sec(&self) -> i64 {
+        self.kabelbruch_cooldown_sec
+    }
+
+    /// Setzt die Wartezeit nach einem Kabelbruch (in Sekunden)
+    ///
+    /// Nach jedem fehlgeschlagenen Wiederholungsversuch verdoppelt sich die Wartezeit, bis maximal
+    /// [`get_kabelbruch_cooldown_max_sec()`](#method.get_kabelbruch_cooldown_max_sec).
+    ///
+    /// # Parameters
+    ///
+    /// * `cooldown_sec`    - Wartezeit in Sekunden
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::Kombisensor;
+    ///
+    /// let mut kombisensor = Kombisensor::new();
+    /// kombisensor.set_kabelbruch_cooldown_sec(60);
+    /// assert_eq!(kombisensor.get_kabelbruch_cooldown_sec(), 60);
+    /// ```
+    pub fn set_kabelbruch_cooldown_sec(&mut self, cooldown_sec: i64) {
+        self.kabelbruch_cooldown_sec = cooldown_sec;
+    }
+
+    /// Obergrenze der Wartezeit nach einem Kabelbruch (in Sekunden)
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::Kombisensor;
+    ///
+    /// let kombisensor = Kombisensor::new();
+    /// assert_eq!(kombisensor.get_kabelbruch_cooldown_max_sec(), 3600);
+    /// ```
+    pub fn get_kabelbruch_cooldown_max_sec(&self) -> i64 {
+        self.kabelbruch_cooldown_max_sec
+    }
+
+    /// Setzt die Obergrenze der Wartezeit nach einem Kabelbruch (in Sekunden)
+    ///
+    /// # Parameters
+    ///
+    /// * `cooldown_max_sec`    - maximale Wartezeit in Sekunden
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::Kombisensor;
+    ///
+    /// let mut kombisensor = Kombisensor::new();
+    /// kombisensor.set_kabelbruch_cooldown_max_sec(600);
+    /// assert_eq!(kombisensor.get_kabelbruch_cooldown_max_sec(), 600);
+    /// ```
+    pub fn set_kabelbruch_cooldown_max_sec(&mut self, cooldown_max_sec: i64) {
+        self.kabelbruch_cooldown_max_sec = cooldown_max_sec;
+    }
+
+    /// Anzahl erfolgreicher Abfragen in Folge, bis der Kombisensor nach einem Kabelbruch wieder als `Normal` gilt
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::Kombisensor;
+    ///
+    /// let kombisensor = Kombisensor::new();
+    /// assert_eq!(kombisensor.get_kabelbruch_recovery_count(), 3);
+    /// ```
+    pub fn get_kabelbruch_recovery_count(&self) -> u64 {
+        self.kabelbruch_recovery_count
+    }
+
+    /// Setzt die Anzahl erfolgreicher Abfragen in Folge, bis der Kombisensor wieder als `Normal` gilt
+    ///
+    /// # Parameters
+    ///
+    /// * `recovery_count`  - Anzahl erfolgreicher Abfragen
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::Kombisensor;
+    ///
+    /// let mut kombisensor = Kombisensor::new();
+    /// kombisensor.set_kabelbruch_recovery_count(1);
+    /// assert_eq!(kombisensor.get_kabelbruch_recovery_count(), 1);
+    /// ```
+    pub fn set_kabelbruch_recovery_count(&mut self, recovery_count: u64) {
+        self.kabelbruch_recovery_count = recovery_count;
+    }
+
+    /// Anzahl fehlgeschlagener Wiederholungsversuche seit dem Kabelbruch
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::Kombisensor;
+    ///
+    /// let kombisensor = Kombisensor::new();
+    /// assert_eq!(kombisensor.get_kabelbruch_retry_count(), 0);
+    /// ```
+    pub fn get_kabelbruch_retry_count(&self) -> u32 {
+        self.kabelbruch_retry_count
+    }
+
+    /// Zeitpunkt des nächsten Wiederholungsversuchs
+    ///
+    /// # Return values
+    ///
+    /// Liefert `None` wenn kein Kabelbruch vorliegt.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::Kombisensor;
+    ///
+    /// let kombisensor = Kombisensor::new();
+    /// assert!(kombisensor.get_kabelbruch_next_retry().is_none());
+    /// ```
+    pub fn get_kabelbruch_next_retry(&self) -> Option<DateTime<Utc>> {
+        self.kabelbruch_next_retry
+    }
+
+    // Aktuelle Wartezeit bis zum nächsten Wiederholungsversuch
+    //
+    // Die Wartezeit verdoppelt sich mit jedem fehlgeschlagenen Versuch, wird aber durch
+    // `kabelbruch_cooldown_max_sec` begrenzt.
+    fn kabelbruch_cooldown(&self) -> Duration {
+        let factor = 1i64 << cmp::min(self.kabelbruch_retry_count, 30);
+        let cooldown_sec = cmp::min(self.kabelbruch_cooldown_sec.saturating_mul(factor), self.kabelbruch_cooldown_max_sec);
+
+        Duration::seconds(cooldown_sec)
+    }
+
+    // Plant den nächsten Wiederholungsversuch
+    //
+    fn schedule_retry(&mut self) {
+        let cooldown = self.kabelbruch_cooldown();
+        info!("Kombisensor {} ({}): nächster Versuch in {} Sekunden", self.modbus_address, self.modbus_device, cooldown.num_seconds());
+        self.kabelbruch_next_retry = Some(Utc::now() + cooldown);
+    }
+
+    // Ist die Wartezeit nach einem Kabelbruch abgelaufen?
+    //
+    fn is_retry_due(&self) -> bool {
+        match self.kabelbruch_next_retry {
+            Some(next_retry) => Utc::now() >= next_retry,
+            None => true,
+        }
+    }
+
     // Update Status des Kombisensors
     //
     // Diese Funktion wird in der public `update()` Funktion aufgerufen.
+    //
+    // Normal       -> Kabelbruch:      nach `KABELBRUCH_ERROR_COUNT` Fehlern in Folge
+    // Wiederanlauf -> Normal:          nach `kabelbruch_recovery_count` erfolgreichen Abfragen in Folge
+    //
+    // Die Übergänge Kabelbruch -> Wiederanlauf und Wiederanlauf -> Kabelbruch passieren direkt in `update()`.
     fn update_status(&mut self) {
-        if self.error_count >= 5 {
-            self.status = KombisensorStatus::Kabelbruch;
-        } else {
-            self.status = KombisensorStatus::Normal;
+        match self.status {
+            KombisensorStatus::Normal => {
+                if self.error_count >= KABELBRUCH_ERROR_COUNT {
+                    warn!("Kombisensor {} ({}): Kabelbruch", self.modbus_address, self.modbus_device);
+                    self.status = KombisensorStatus::Kabelbruch;
+                    self.kabelbruch_retry_count = 0;
+                    self.schedule_retry();
+                }
+            }
+            KombisensorStatus::Wiederanlauf => {
+                if self.success_count >= self.kabelbruch_recovery_count {
+                    info!("Kombisensor {} ({}): Kabelbruch behoben", self.modbus_address, self.modbus_device);
+                    self.reset_error_count();
+                }
+            }
+            KombisensorStatus::Kabelbruch => {}
         }
-
     }
 
 
@@ -535,29 +1362,85 @@ impl Kombisensor {
     ///
     /// Diese Funktion fast die einzelnen Update Funktionen des Kombisensors zusammen.
     /// Hier werden auch der Status des Sensos verändert, wenn die Parameter erreicht sind.
-    pub fn update(&mut self) {
-        // Nur Kombisensoren im normal Status sollen via Modbus abegfragt werden
-        match self.status {
-            KombisensorStatus::Normal => {
-                match self.update_via_modbus() {
-                    Ok(_)  => {
-                        // Wenn die Modbus Kommunikation erfolgreich war, wird der Error Coutner wieder reseted
-                        self.reset_error_count();
+    ///
+    /// Die Abfrage und deren Auswertung können auch getrennt ausgeführt werden, siehe
+    /// [`get_poll_request()`](#method.get_poll_request) und [`apply_poll_result()`](#method.apply_poll_result).
+    ///
+    /// Nach einem Kabelbruch wird der Kombisensor erst nach Ablauf der Wartezeit erneut abgefragt. Schlägt der
+    /// Versuch fehl, verdoppelt sich die Wartezeit. Ist der Versuch erfolgreich, wechselt der Kombisensor in den
+    /// Status `Wiederanlauf` und nach `kabelbruch_recovery_count` erfolgreichen Abfragen in Folge zurück in den
+    /// Status `Normal`.
+    ///
+    /// # Parameters
+    ///
+    /// * `bus_manager` - Verwaltung der Modbus Leitungen, liefert den Bus des Kombisensors
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Kombisensor, KombisensorStatus, ModbusBusManager};
+    ///
+    /// let bus_manager = ModbusBusManager::new();
+    /// let mut kombisensor = Kombisensor::new();
+    /// kombisensor.set_modbus_device("/dev/nicht_vorhanden".to_string());
+    ///
+    /// kombisensor.update(&bus_manager);
+    /// assert_eq!(kombisensor.get_error_count(), 1);
+    ///
+    /// for _ in 0..4 { kombisensor.update(&bus_manager); }
+    /// assert_eq!(kombisensor.get_status(), KombisensorStatus::Kabelbruch);
+    /// assert!(kombisensor.get_kabelbruch_next_retry().is_some());
+    ///
+    /// // Die Wartezeit ist noch nicht abgelaufen, es findet kein neuer Versuch statt
+    /// kombisensor.update(&bus_manager);
+    /// assert_eq!(kombisensor.get_kabelbruch_retry_count(), 0);
+    /// ```
+    ///
+    /// Simulierte Kombisensoren werden von einem Emulator beantwortet
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Kombisensor, KombisensorStatus, KombisensorType, ModbusBusManager};
+    ///
+    /// let bus_manager = ModbusBusManager::new();
+    /// let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGasSimulation);
+    ///
+    /// for _ in 0..10 { kombisensor.update(&bus_manager); }
+    /// assert_eq!(kombisensor.get_status(), KombisensorStatus::Normal);
+    /// assert_eq!(kombisensor.get_firmware_version(), "0.14.0".to_string());
+    /// ```
+    ///
+    /// Ohne Wartezeit wird im nächsten Update erneut versucht
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Kombisensor, KombisensorStatus, ModbusBusManager};
+    ///
+    /// let bus_manager = ModbusBusManager::new();
+    /// let mut kombisensor = Kombisensor::new();
+    /// kombisensor.set_modbus_device("/dev/nicht_vorhanden".to_string());
+    /// kombisensor.set_kabelbruch_cooldown_sec(0);
+    ///
+    /// for _ in 0..5 { kombisensor.update(&bus_manager); }
+    /// assert_eq!(kombisensor.get_status(), KombisensorStatus::Kabelbruch);
+    ///
+    /// kombisensor.update(&bus_manager);
+    /// kombisensor.update(&bus_manager);
+    /// assert_eq!(kombisensor.get_status(), KombisensorStatus::Kabelbruch);
+    /// assert_eq!(kombisensor.get_kabelbruch_retry_count(), 2);
+    /// ```
+    pub fn update(&mut self, bus_manager: &ModbusBusManager) {
+        match self.get_poll_request() {
+            Some(poll_request) => {
+                let poll_result = poll_request.execute(bus_manager);
+                if self.apply_poll(poll_result) {
+                    // Nach einem Firmware Update sofort mit dem neuen Register Layout abfragen
+                    if let Some(poll_request) = self.get_poll_request() {
+                        let poll_result = poll_request.execute(bus_manager);
+                        self.apply_poll(poll_result);
                     }
-                    Err(_) => {
-                        // Im Fehlerfall wird der Error Coutner um eins erhöht, sind 5 zusammenhängende Fehler hinter einander
-                        // aufgetreten, dann wird in der Funktion `update_status()` der Status des Kombisensors auf Kabelbruch gesetzt.
-                        self.inc_error_count();
-                    },
                 }
-            },
-            KombisensorStatus::Kabelbruch => {
-                // Hier kann eine Cool Down funktion eingebaut werden.
-                // Wenn seit dem Kabelbruch 3 Minuten vergangen sind, versuche den Sensor wieder in den Normal Staus zu schalten.
             }
+            None => self.update_status(),
         }
-
-        self.update_status();
     }
 
 }
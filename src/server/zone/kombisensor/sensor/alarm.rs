@@ -0,0 +1,248 @@
+//! Auswertung der Alarmpunkte einer Messzelle
+//!
+//! Ein einfacher Vergleich mit dem Schwellwert lässt die Relais flattern, wenn der Messwert um den Schwellwert
+//! pendelt. Deshalb wird jeder Alarmpunkt (AP1, AP2, DIW) mit einer Hysterese, einer Ein- und Ausschaltverzögerung
+//! und optional selbsthaltend ausgewertet. Ein selbsthaltender Alarm bleibt bestehen, bis er quittiert wird.
+//!
+//! Die meisten Messzellen lösen beim Überschreiten des Schwellwerts aus, eine Sauerstoff Messzelle dagegen beim
+//! Unterschreiten. Die Richtung ist deshalb eine Eigenschaft jedes Alarmpunkts.
+//!
+use ::chrono::{DateTime, Duration, Utc};
+
+
+/// Alarmpunkte einer Messzelle
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub enum AlarmLevel {
+    /// Alarmpunkt 1, 15min Mittelwert
+    AP1,
+    /// Alarmpunkt 2, 15min Mittelwert
+    AP2,
+    /// Direktwert
+    DIW,
+}
+
+/// Richtung, in der ein Alarmpunkt auslöst
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub enum AlarmDirection {
+    /// Alarm beim Erreichen oder Überschreiten des Schwellwerts, z.B. CO, NO2 oder % UEG
+    Rising,
+    /// Alarm beim Erreichen oder Unterschreiten des Schwellwerts, z.B. Sauerstoffmangel
+    Falling,
+}
+
+impl AlarmDirection {
+    /// Erreicht der Messwert den Schwellwert?
+    ///
+    /// # Parameters
+    ///
+    /// * `value`       - Messwert
+    /// * `threshold`   - Schwellwert des Alarmpunkts
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::server::zone::kombisensor::sensor::AlarmDirection;
+    ///
+    /// assert!(AlarmDirection::Rising.is_reached(31.0, 30.0));
+    /// assert!(!AlarmDirection::Falling.is_reached(31.0, 30.0));
+    /// assert!(AlarmDirection::Falling.is_reached(19.0, 19.0));
+    /// ```
+    pub fn is_reached(&self, value: f64, threshold: f64) -> bool {
+        match *self {
+            AlarmDirection::Rising => value >= threshold,
+            AlarmDirection::Falling => value <= threshold,
+        }
+    }
+
+    /// Hält der Messwert einen ausgelösten Alarm, liegt er also noch innerhalb der Hysterese?
+    ///
+    /// # Parameters
+    ///
+    /// * `value`       - Messwert
+    /// * `threshold`   - Schwellwert des Alarmpunkts
+    /// * `hysteresis`  - Hysterese in der Einheit der Messzelle
+    pub fn is_held(&self, value: f64, threshold: f64, hysteresis: f64) -> bool {
+        match *self {
+            AlarmDirection::Rising => value >= threshold - hysteresis,
+            AlarmDirection::Falling => value <= threshold + hysteresis,
+        }
+    }
+}
+
+/// Einstellungen eines Alarmpunkts
+///
+/// Ohne Einstellungen wird, wie bisher, sofort beim Erreichen des Schwellwerts ausgelöst und beim Unterschreiten
+/// zurück gesetzt.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlarmSettings {
+    /// Hysterese in der Einheit der Messzelle. Ein ausgelöster Alarm fällt erst ab, wenn der Messwert um mehr als
+    /// die Hysterese auf der anderen Seite des Schwellwerts liegt.
+    pub hysteresis: f64,
+    /// So viele Sekunden muss der Schwellwert ununterbrochen erreicht sein, bevor der Alarm auslöst
+    pub on_delay_sec: i64,
+    /// So viele Sekunden muss der Messwert ununterbrochen außerhalb der Hysterese liegen, bevor der Alarm abfällt
+    pub off_delay_sec: i64,
+    /// Selbsthaltend, der Alarm fällt erst nach dem Quittieren ab
+    pub latching: bool,
+    /// Richtung des Alarmpunkts, ohne Angabe gilt die Richtung des Sensor Typs
+    pub direction: Option<AlarmDirection>,
+}
+
+/// Zustand eines Alarmpunkts
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate chrono;
+/// # extern crate xmz_mod_touch_server;
+/// use chrono::{Duration, Utc};
+/// use xmz_mod_touch_server::server::zone::kombisensor::sensor::{AlarmDirection, AlarmSettings, AlarmState};
+///
+/// # fn main() {
+/// let settings = AlarmSettings { hysteresis: 2.0, on_delay_sec: 10, ..Default::default() };
+/// let mut state = AlarmState::new();
+/// let now = Utc::now();
+///
+/// // Schwellwert 30, der Alarm löst erst nach 10 Sekunden aus
+/// state.update(&settings, AlarmDirection::Rising, 30.0, 30.0, now);
+/// assert!(!state.is_active());
+/// state.update(&settings, AlarmDirection::Rising, 31.0, 30.0, now + Duration::seconds(10));
+/// assert!(state.is_active());
+///
+/// // Innerhalb der Hysterese bleibt der Alarm bestehen
+/// state.update(&settings, AlarmDirection::Rising, 28.5, 30.0, now + Duration::seconds(11));
+/// assert!(state.is_active());
+/// state.update(&settings, AlarmDirection::Rising, 27.5, 30.0, now + Duration::seconds(12));
+/// assert!(!state.is_active());
+/// # }
+/// ```
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct AlarmState {
+    active: bool,
+    latched: bool,
+    // Seit wann die Bedingung zum Auslösen bzw. Abfallen ununterbrochen erfüllt ist
+    pending_since: Option<DateTime<Utc>>,
+}
+
+impl AlarmState {
+    /// Erstellt einen nicht ausgelösten Alarmpunkt
+    pub fn new() -> Self {
+        AlarmState {
+            active: false,
+            latched: false,
+            pending_since: None,
+        }
+    }
+
+    /// Liefert `true` wenn der Alarm ausgelöst ist
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Liefert `true` wenn der Alarm nur noch durch die Selbsthaltung besteht und quittiert werden kann
+    pub fn is_latched(&self) -> bool {
+        self.latched
+    }
+
+    /// Wertet den Alarmpunkt mit einem neuen Messwert aus
+    ///
+    /// # Parameters
+    ///
+    /// * `settings`    - Einstellungen des Alarmpunkts
+    /// * `direction`   - Richtung des Alarmpunkts
+    /// * `value`       - Messwert, `NAN` wenn kein gültiger Messwert vorliegt
+    /// * `threshold`   - Schwellwert des Alarmpunkts
+    /// * `now`         - Zeitpunkt der Auswertung
+    ///
+    /// Ohne gültigen Messwert ist der wahre Wert unbekannt, der Zustand (auch Selbsthaltung und laufende
+    /// Verzögerung) bleibt dann unverändert.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// extern crate chrono;
+    /// # extern crate xmz_mod_touch_server;
+    /// use chrono::Utc;
+    /// use xmz_mod_touch_server::server::zone::kombisensor::sensor::{AlarmDirection, AlarmSettings, AlarmState};
+    ///
+    /// # fn main() {
+    /// let settings = AlarmSettings { latching: true, ..Default::default() };
+    /// let mut state = AlarmState::new();
+    /// let now = Utc::now();
+    ///
+    /// state.update(&settings, AlarmDirection::Rising, 31.0, 30.0, now);
+    /// assert!(state.is_active());
+    /// // Unbekannter Messwert, der Alarm bleibt bestehen und kann nicht quittiert werden
+    /// state.update(&settings, AlarmDirection::Rising, ::std::f64::NAN, 30.0, now);
+    /// assert!(state.is_active());
+    /// assert!(!state.is_latched());
+    /// # }
+    /// ```
+    pub fn update(&mut self, settings: &AlarmSettings, direction: AlarmDirection, value: f64, threshold: f64, now: DateTime<Utc>) {
+        if !value.is_finite() { return; }
+
+        if self.active {
+            if direction.is_held(value, threshold, settings.hysteresis) {
+                self.latched = false;
+                self.pending_since = None;
+            } else if settings.latching {
+                self.latched = true;
+                self.pending_since = None;
+            } else if self.delay_elapsed(settings.off_delay_sec, now) {
+                self.active = false;
+                self.pending_since = None;
+            }
+        } else if direction.is_reached(value, threshold) {
+            if self.delay_elapsed(settings.on_delay_sec, now) {
+                self.active = true;
+                self.pending_since = None;
+            }
+        } else {
+            self.pending_since = None;
+        }
+    }
+
+    /// Quittiert einen selbsthaltenden Alarm
+    ///
+    /// Hält der Messwert den Alarm noch (siehe [`AlarmDirection::is_held()`](enum.AlarmDirection.html#method.is_held)),
+    /// bleibt der Alarm bestehen.
+    ///
+    /// # Return values
+    ///
+    /// Liefert `true` wenn der Alarm quittiert wurde.
+    pub fn reset(&mut self) -> bool {
+        if !self.latched { return false; }
+
+        *self = AlarmState::new();
+        true
+    }
+
+    // Startet bzw. prüft die Verzögerung, liefert `true` wenn sie abgelaufen ist
+    //
+    fn delay_elapsed(&mut self, delay_sec: i64, now: DateTime<Utc>) -> bool {
+        let since = match self.pending_since {
+            Some(since) => since,
+            None => {
+                self.pending_since = Some(now);
+                now
+            }
+        };
+
+        now.signed_duration_since(since) >= Duration::seconds(delay_sec)
+    }
+}
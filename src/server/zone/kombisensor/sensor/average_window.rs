@@ -0,0 +1,374 @@
+//! Zeitlich gewichteter Mittelwert mit begrenztem Speicherbedarf
+//!
+//! Statt alle Messwerte des Zeitraums zu speichern, werden sie in Zeitabschnitte (Buckets) fester Länge einsortiert.
+//! Pro Bucket werden nur die Summe der gewichteten Messwerte und die abgedeckte Zeit gespeichert, über alle Buckets
+//! zusätzlich laufende Summen. Der Speicherbedarf hängt damit nur von der Länge des Zeitraums ab, ein neuer Messwert
+//! und die Berechnung des Mittelwerts kosten konstante Zeit.
+//!
+use ::chrono::{DateTime, Duration, Utc};
+use super::sensor::{AVERAGE_15MIN_SEC, AVERAGE_BUCKET_SEC, AVERAGE_SAMPLE_VALIDITY_MAX_SEC};
+
+
+// Ein Bucket des Ringpuffers
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(Serialize, Deserialize)]
+struct Bucket {
+    // fortlaufende Nummer des Buckets (Zeitstempel in ms / Länge eines Buckets)
+    index: i64,
+    // Summe aus Messwert * Gültigkeitsdauer in ms
+    weighted_sum: i64,
+    // abgedeckte Zeit in ms
+    covered_ms: i64,
+}
+
+/// Ringpuffer für den zeitlich gewichteten Mittelwert einer Messzelle
+///
+/// Jeder Messwert gilt bis zum nächsten Messwert, höchstens aber `validity_max`. Längere Abstände zwischen zwei
+/// Messwerten sind Lücken. Sie gehen nicht in den Mittelwert ein, verringern aber die Abdeckung
+/// ([`coverage()`](#method.coverage)) des Zeitraums.
+///
+/// Der älteste Bucket liegt meist nur teilweise im Zeitraum, sein Anteil wird anteilig (gleichmäßig verteilt)
+/// berücksichtigt.
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate chrono;
+/// # extern crate xmz_mod_touch_server;
+/// use chrono::{Duration, Utc};
+/// use xmz_mod_touch_server::server::zone::kombisensor::sensor::AverageWindow;
+///
+/// # fn main() {
+/// let mut window = AverageWindow::new(Duration::minutes(15), Duration::seconds(10), Duration::seconds(10));
+/// let now = Utc::now();
+///
+/// // 2 Sekunden 100, danach 6 Sekunden 400
+/// window.add(100, now - Duration::seconds(8));
+/// window.add(400, now - Duration::seconds(6));
+/// window.expire(now);
+///
+/// assert_eq!(window.average(now), 325);
+/// assert_eq!(window.coverage(now), 1.0);
+/// # }
+/// ```
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct AverageWindow {
+    window_ms: i64,
+    bucket_ms: i64,
+    validity_max_ms: i64,
+    buckets: Vec<Bucket>,
+    // kleinster Bucket Index, der noch belegt sein kann
+    oldest_index: Option<i64>,
+    // laufende Summen über alle Buckets
+    weighted_sum: i64,
+    covered_ms: i64,
+    // Zeitstempel (ms) des ersten Messwerts
+    first_ms: Option<i64>,
+    // letzter Messwert mit Zeitstempel (ms), seine Gültigkeit ist noch offen
+    last: Option<(u16, i64)>,
+}
+
+impl AverageWindow {
+    /// Erstellt einen leeren Ringpuffer
+    ///
+    /// # Parameters
+    ///
+    /// * `window`          - Zeitraum des Mittelwerts, z.B. 15 Minuten
+    /// * `bucket`          - Länge eines Buckets, bestimmt Speicherbedarf und Genauigkeit am Rand des Zeitraums
+    /// * `validity_max`    - maximale Gültigkeit eines Messwerts
+    pub fn new(window: Duration, bucket: Duration, validity_max: Duration) -> Self {
+        let window_ms = window.num_milliseconds();
+        let bucket_ms = if bucket.num_milliseconds() > 0 { bucket.num_milliseconds() } else { 1 };
+
+        AverageWindow {
+            window_ms: window_ms,
+            bucket_ms: bucket_ms,
+            validity_max_ms: validity_max.num_milliseconds(),
+            buckets: vec![Bucket::default(); (window_ms / bucket_ms + 2) as usize],
+            oldest_index: None,
+            weighted_sum: 0,
+            covered_ms: 0,
+            first_ms: None,
+            last: None,
+        }
+    }
+
+    /// Liefert `true` wenn noch kein Messwert hinzugefügt wurde
+    pub fn is_empty(&self) -> bool {
+        self.last.is_none()
+    }
+
+    /// Letzter Messwert
+    pub fn get_last_value(&self) -> Option<u16> {
+        self.last.map(|(adc_value, _)| adc_value)
+    }
+
+    /// Liefert `true` wenn beide Ringpuffer den gleichen Aufbau haben
+    ///
+    /// Ein z.B. aus der Zustandsdatei gelesener Ringpuffer kann beschädigt sein, oder von einer Version mit anderer
+    /// Länge der Buckets stammen. Er darf nur übernommen werden, wenn Zeitraum, Länge und Anzahl der Buckets und
+    /// die maximale Gültigkeit eines Messwerts übereinstimmen.
+    ///
+    /// # Parameters
+    ///
+    /// * `other`   - zu prüfender Ringpuffer
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// extern crate chrono;
+    /// # extern crate xmz_mod_touch_server;
+    /// use chrono::Duration;
+    /// use xmz_mod_touch_server::server::zone::kombisensor::sensor::AverageWindow;
+    ///
+    /// # fn main() {
+    /// let window = AverageWindow::default();
+    /// assert!(window.has_same_layout(&AverageWindow::default()));
+    ///
+    /// let other = AverageWindow::new(Duration::minutes(15), Duration::seconds(5), Duration::seconds(10));
+    /// assert!(!window.has_same_layout(&other));
+    /// # }
+    /// ```
+    pub fn has_same_layout(&self, other: &AverageWindow) -> bool {
+        self.window_ms == other.window_ms &&
+        self.bucket_ms == other.bucket_ms &&
+        self.validity_max_ms == other.validity_max_ms &&
+        self.buckets.len() == other.buckets.len()
+    }
+
+    /// Liefert `true` wenn der letzte Messwert vor dem Zeitraum liegt, der Ringpuffer also nichts mehr zum
+    /// Mittelwert beiträgt
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// extern crate chrono;
+    /// # extern crate xmz_mod_touch_server;
+    /// use chrono::{Duration, Utc};
+    /// use xmz_mod_touch_server::server::zone::kombisensor::sensor::AverageWindow;
+    ///
+    /// # fn main() {
+    /// let mut window = AverageWindow::default();
+    /// assert!(window.is_outdated(Utc::now()));
+    ///
+    /// window.add(100, Utc::now() - Duration::minutes(20));
+    /// assert!(window.is_outdated(Utc::now()));
+    ///
+    /// window.add(100, Utc::now() - Duration::minutes(5));
+    /// assert!(!window.is_outdated(Utc::now()));
+    /// # }
+    /// ```
+    pub fn is_outdated(&self, now: DateTime<Utc>) -> bool {
+        match self.last {
+            Some((_, last_ms)) => last_ms < to_ms(now) - self.window_ms,
+            None => true,
+        }
+    }
+
+    /// Verwirft alle Messwerte
+    pub fn clear(&mut self) {
+        for bucket in &mut self.buckets {
+            *bucket = Bucket::default();
+        }
+        self.oldest_index = None;
+        self.weighted_sum = 0;
+        self.covered_ms = 0;
+        self.first_ms = None;
+        self.last = None;
+    }
+
+    /// Fügt einen Messwert hinzu
+    ///
+    /// Messwerte müssen in zeitlicher Reihenfolge hinzugefügt werden, ältere oder gleich alte Messwerte als der
+    /// letzte werden ignoriert.
+    ///
+    /// # Parameters
+    ///
+    /// * `adc_value`   - Messwert
+    /// * `timestamp`   - Zeitpunkt der Messung
+    pub fn add(&mut self, adc_value: u16, timestamp: DateTime<Utc>) {
+        let timestamp_ms = to_ms(timestamp);
+        if let Some((_, last_ms)) = self.last {
+            if timestamp_ms <= last_ms { return; }
+        }
+
+        self.expire_ms(timestamp_ms);
+        // Der bisher letzte Messwert gilt bis zu diesem Messwert, höchstens `validity_max`
+        if let Some((last_value, last_ms)) = self.last {
+            let valid_until = ::std::cmp::min(timestamp_ms, last_ms + self.validity_max_ms);
+            self.add_interval(last_value, last_ms, valid_until);
+        }
+
+        if self.first_ms.is_none() {
+            self.first_ms = Some(timestamp_ms);
+        }
+        if self.oldest_index.is_none() {
+            self.oldest_index = Some(self.bucket_index(timestamp_ms));
+        }
+        self.last = Some((adc_value, timestamp_ms));
+    }
+
+    /// Verwirft die Buckets, die vollständig vor dem Zeitraum liegen
+    ///
+    /// Muss vor [`average()`](#method.average) und [`coverage()`](#method.coverage) aufgerufen werden.
+    ///
+    /// # Parameters
+    ///
+    /// * `now`     - aktueller Zeitpunkt
+    pub fn expire(&mut self, now: DateTime<Utc>) {
+        self.expire_ms(to_ms(now));
+    }
+
+    /// Zeitlich gewichteter Mittelwert
+    ///
+    /// Ist noch keine Zeit vergangen (z.B. nach dem ersten Messwert), ist der Mittelwert der letzte Messwert.
+    ///
+    /// # Parameters
+    ///
+    /// * `now`     - aktueller Zeitpunkt
+    pub fn average(&self, now: DateTime<Utc>) -> u16 {
+        let (weighted_sum, covered_ms) = self.totals(to_ms(now));
+        if covered_ms > 0.0 {
+            (weighted_sum / covered_ms).round() as u16
+        } else {
+            self.get_last_value().unwrap_or(0)
+        }
+    }
+
+    /// Anteil des Zeitraums (0.0 bis 1.0), der von Messwerten abgedeckt ist
+    ///
+    /// Betrachtet wird der Zeitraum ab dem ersten Messwert, höchstens aber `window`.
+    ///
+    /// # Parameters
+    ///
+    /// * `now`     - aktueller Zeitpunkt
+    pub fn coverage(&self, now: DateTime<Utc>) -> f64 {
+        let now_ms = to_ms(now);
+        let window_start_ms = now_ms - self.window_ms;
+        let observed_ms = match self.first_ms {
+            Some(first_ms) => now_ms - ::std::cmp::max(first_ms, window_start_ms),
+            None => return 0.0,
+        };
+        let (_, covered_ms) = self.totals(now_ms);
+
+        if observed_ms > 0 && covered_ms > 0.0 {
+            (covered_ms / observed_ms as f64).min(1.0)
+        } else if observed_ms > 0 {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
+    // Summe der gewichteten Messwerte und abgedeckte Zeit im Zeitraum, inklusive des noch offenen letzten Messwerts
+    //
+    fn totals(&self, now_ms: i64) -> (f64, f64) {
+        let window_start_ms = now_ms - self.window_ms;
+        let mut weighted_sum = self.weighted_sum as f64;
+        let mut covered_ms = self.covered_ms as f64;
+
+        // Der älteste Bucket liegt nur zum Teil im Zeitraum
+        let first_index = self.bucket_index(window_start_ms);
+        let bucket = self.buckets[self.slot(first_index)];
+        if bucket.index == first_index && bucket.covered_ms > 0 {
+            let outside = (window_start_ms - first_index * self.bucket_ms) as f64 / self.bucket_ms as f64;
+            weighted_sum -= bucket.weighted_sum as f64 * outside;
+            covered_ms -= bucket.covered_ms as f64 * outside;
+        }
+
+        if let Some((last_value, last_ms)) = self.last {
+            let valid_from = ::std::cmp::max(last_ms, window_start_ms);
+            let valid_until = ::std::cmp::min(now_ms, last_ms + self.validity_max_ms);
+            if valid_until > valid_from {
+                weighted_sum += last_value as f64 * (valid_until - valid_from) as f64;
+                covered_ms += (valid_until - valid_from) as f64;
+            }
+        }
+
+        (weighted_sum, covered_ms)
+    }
+
+    fn expire_ms(&mut self, now_ms: i64) {
+        let first_index = self.bucket_index(now_ms - self.window_ms);
+        let mut oldest_index = match self.oldest_index {
+            Some(oldest_index) => oldest_index,
+            None => return,
+        };
+
+        if first_index - oldest_index >= self.buckets.len() as i64 {
+            // Alle Buckets sind veraltet
+            for bucket in &mut self.buckets {
+                *bucket = Bucket::default();
+            }
+            self.weighted_sum = 0;
+            self.covered_ms = 0;
+            oldest_index = first_index;
+        }
+        while oldest_index < first_index {
+            let slot = self.slot(oldest_index);
+            if self.buckets[slot].index == oldest_index {
+                self.weighted_sum -= self.buckets[slot].weighted_sum;
+                self.covered_ms -= self.buckets[slot].covered_ms;
+                self.buckets[slot] = Bucket::default();
+            }
+            oldest_index += 1;
+        }
+        self.oldest_index = Some(oldest_index);
+    }
+
+    // Verteilt den Messwert `adc_value`, gültig von `from_ms` bis `to_ms`, auf die Buckets
+    //
+    fn add_interval(&mut self, adc_value: u16, from_ms: i64, to_ms: i64) {
+        let oldest_index = self.oldest_index.unwrap_or(i64::min_value());
+        let mut from_ms = from_ms;
+        while from_ms < to_ms {
+            let index = self.bucket_index(from_ms);
+            let segment_end = ::std::cmp::min(to_ms, (index + 1) * self.bucket_ms);
+            let duration = segment_end - from_ms;
+
+            // Bereits verworfene Buckets werden nicht mehr belegt
+            if index >= oldest_index {
+                let slot = self.slot(index);
+                if self.buckets[slot].index != index {
+                    self.weighted_sum -= self.buckets[slot].weighted_sum;
+                    self.covered_ms -= self.buckets[slot].covered_ms;
+                    self.buckets[slot] = Bucket { index: index, ..Default::default() };
+                }
+                self.buckets[slot].weighted_sum += adc_value as i64 * duration;
+                self.buckets[slot].covered_ms += duration;
+                self.weighted_sum += adc_value as i64 * duration;
+                self.covered_ms += duration;
+            }
+            from_ms = segment_end;
+        }
+    }
+
+    fn bucket_index(&self, timestamp_ms: i64) -> i64 {
+        // Abrunden, auch bei negativen Zeitstempeln
+        let index = timestamp_ms / self.bucket_ms;
+        if timestamp_ms < 0 && timestamp_ms % self.bucket_ms != 0 { index - 1 } else { index }
+    }
+
+    fn slot(&self, index: i64) -> usize {
+        let len = self.buckets.len() as i64;
+        (((index % len) + len) % len) as usize
+    }
+}
+
+impl Default for AverageWindow {
+    fn default() -> Self {
+        Self::new(Duration::seconds(AVERAGE_15MIN_SEC),
+                  Duration::seconds(AVERAGE_BUCKET_SEC),
+                  Duration::seconds(AVERAGE_SAMPLE_VALIDITY_MAX_SEC))
+    }
+}
+
+// Zeitstempel in Millisekunden
+//
+fn to_ms(timestamp: DateTime<Utc>) -> i64 {
+    timestamp.timestamp() * 1000 + timestamp.timestamp_subsec_millis() as i64
+}
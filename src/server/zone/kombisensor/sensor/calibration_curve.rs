@@ -0,0 +1,429 @@
+//! Kalibrierkurve und Temperaturkompensation einer Messzelle
+//!
+//! Standardmäßig rechnet der Server den ADC Wert mit einer Geraden durch die beiden Kalibrierpunkte (Nullgas und
+//! Messgas) in eine Konzentration um. Elektrochemische Messzellen wie die Nemoto NAP-505/ NAP-550 sind aber
+//! spürbar nichtlinear und temperaturabhängig. Für diese Fälle kann pro Messzelle optional eine Kalibrierkurve mit
+//! beliebig vielen Punkten und eine Tabelle zur Temperaturkompensation konfiguriert werden.
+//!
+use std::cmp::Ordering;
+
+
+/// Größter ADC Wert der Kombisensoren (10Bit ADC)
+pub const ADC_VALUE_MAX: u16 = 1023;
+
+/// Ein Punkt der Kalibrierkurve
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct CalibrationPoint {
+    adc_value: u16,
+    concentration: f64,
+}
+
+impl CalibrationPoint {
+    /// Erstellt einen neuen Kalibrierpunkt
+    ///
+    /// # Parameters
+    ///
+    /// * `adc_value`       - ADC Wert der Messzelle bei dieser Konzentration
+    /// * `concentration`   - Konzentration des Prüfgases
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::server::zone::kombisensor::sensor::CalibrationPoint;
+    ///
+    /// let point = CalibrationPoint::new(564, 20.0);
+    /// assert_eq!(point.get_adc_value(), 564);
+    /// assert_eq!(point.get_concentration(), 20.0);
+    /// ```
+    pub fn new(adc_value: u16, concentration: f64) -> Self {
+        CalibrationPoint {
+            adc_value: adc_value,
+            concentration: concentration,
+        }
+    }
+
+    /// ADC Wert des Kalibrierpunkts
+    pub fn get_adc_value(&self) -> u16 {
+        self.adc_value
+    }
+
+    /// Konzentration des Kalibrierpunkts
+    pub fn get_concentration(&self) -> f64 {
+        self.concentration
+    }
+}
+
+/// Art der Interpolation zwischen den Kalibrierpunkten
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub enum Interpolation {
+    /// Geraden zwischen benachbarten Punkten, außerhalb der Punkte wird die äußere Gerade verlängert
+    PiecewiseLinear,
+    /// Polynom des gegebenen Grades, nach der Methode der kleinsten Quadrate an die Punkte angepasst. Ist der Grad
+    /// um eins kleiner als die Anzahl der Punkte, läuft das Polynom genau durch alle Punkte.
+    Polynomial(usize),
+}
+
+/// Kalibrierkurve einer Messzelle
+///
+/// # Examples
+///
+/// ```rust
+/// use xmz_mod_touch_server::server::zone::kombisensor::sensor::{CalibrationCurve, CalibrationPoint, Interpolation};
+///
+/// // NO2 Messzelle, der ADC Wert fällt mit steigender Konzentration
+/// let curve = CalibrationCurve::new(vec![
+///     CalibrationPoint::new(900, 0.0),
+///     CalibrationPoint::new(836, 4.0),
+///     CalibrationPoint::new(580, 24.0),
+/// ], Interpolation::PiecewiseLinear);
+///
+/// assert_eq!(curve.concentration(868), Some(2.0));
+/// assert_eq!(curve.concentration(708), Some(14.0));
+/// assert_eq!(curve.adc_value(14.0), Some(708));
+/// ```
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct CalibrationCurve {
+    points: Vec<CalibrationPoint>,
+    interpolation: Interpolation,
+}
+
+impl CalibrationCurve {
+    /// Erstellt eine neue Kalibrierkurve
+    ///
+    /// Die Reihenfolge der Punkte spielt keine Rolle. Für eine Umrechnung sind mindestens zwei Punkte mit
+    /// unterschiedlichem ADC Wert nötig.
+    ///
+    /// # Parameters
+    ///
+    /// * `points`          - Kalibrierpunkte
+    /// * `interpolation`   - Art der Interpolation zwischen den Punkten
+    pub fn new(points: Vec<CalibrationPoint>, interpolation: Interpolation) -> Self {
+        CalibrationCurve {
+            points: points,
+            interpolation: interpolation,
+        }
+    }
+
+    /// Kalibrierpunkte der Kurve
+    pub fn get_points(&self) -> &Vec<CalibrationPoint> {
+        &self.points
+    }
+
+    /// Art der Interpolation
+    pub fn get_interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    /// Berechnet die Konzentration zu einem ADC Wert
+    ///
+    /// # Return values
+    ///
+    /// Liefert `None`, wenn die Kurve weniger als zwei verwertbare Punkte hat.
+    ///
+    /// # Parameters
+    ///
+    /// * `adc_value`   - ADC Wert der Messzelle
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::server::zone::kombisensor::sensor::{CalibrationCurve, CalibrationPoint, Interpolation};
+    ///
+    /// // y = 0.0001 * (x - 100)^2, genau durch drei Punkte
+    /// let curve = CalibrationCurve::new(vec![
+    ///     CalibrationPoint::new(100, 0.0),
+    ///     CalibrationPoint::new(300, 4.0),
+    ///     CalibrationPoint::new(500, 16.0),
+    /// ], Interpolation::Polynomial(2));
+    ///
+    /// let concentration = curve.concentration(400).unwrap();
+    /// assert!((concentration - 9.0).abs() < 1e-9);
+    ///
+    /// // Ein Punkt reicht nicht
+    /// let curve = CalibrationCurve::new(vec![CalibrationPoint::new(100, 0.0)], Interpolation::PiecewiseLinear);
+    /// assert_eq!(curve.concentration(400), None);
+    /// ```
+    pub fn concentration(&self, adc_value: u16) -> Option<f64> {
+        let points = self.sorted_points();
+        if points.len() < 2 { return None; }
+
+        match self.interpolation {
+            Interpolation::PiecewiseLinear => Some(piecewise_linear(&points, adc_value as f64)),
+            Interpolation::Polynomial(degree) => {
+                Polynomial::fit(&points, degree).map(|polynomial| polynomial.value(adc_value as f64))
+            }
+        }
+    }
+
+    /// Berechnet den ADC Wert zu einer Konzentration
+    ///
+    /// Gesucht wird der ADC Wert (0 bis `ADC_VALUE_MAX`), dessen Konzentration am nächsten an der gegebenen
+    /// Konzentration liegt. Diese Funktion ist, wie
+    /// [`Sensor::set_concentration()`](../sensor/struct.Sensor.html#method.set_concentration), nur für
+    /// Testumgebungen vorgesehen.
+    ///
+    /// # Parameters
+    ///
+    /// * `concentration`   - Konzentration
+    pub fn adc_value(&self, concentration: f64) -> Option<u16> {
+        // Die Kurve wird nur einmal angepasst, nicht für jeden ADC Wert
+        let points = self.sorted_points();
+        if points.len() < 2 { return None; }
+        let polynomial = match self.interpolation {
+            Interpolation::PiecewiseLinear => None,
+            Interpolation::Polynomial(degree) => match Polynomial::fit(&points, degree) {
+                Some(polynomial) => Some(polynomial),
+                None => return None,
+            },
+        };
+
+        (0..ADC_VALUE_MAX + 1)
+            .map(|adc_value| {
+                let value = match polynomial {
+                    Some(ref polynomial) => polynomial.value(adc_value as f64),
+                    None => piecewise_linear(&points, adc_value as f64),
+                };
+                (adc_value, (value - concentration).abs())
+            })
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+            .map(|(adc_value, _)| adc_value)
+    }
+
+    // Punkte aufsteigend nach ADC Wert, je ADC Wert nur der erste Punkt
+    fn sorted_points(&self) -> Vec<(f64, f64)> {
+        let mut points: Vec<CalibrationPoint> = self.points.clone();
+        points.sort_by_key(|point| point.adc_value);
+        points.dedup_by_key(|point| point.adc_value);
+
+        points.iter().map(|point| (point.adc_value as f64, point.concentration)).collect()
+    }
+}
+
+/// Ein Punkt der Temperaturkompensation
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct TemperaturePoint {
+    temperature: f64,
+    sensitivity: f64,
+}
+
+impl TemperaturePoint {
+    /// Erstellt einen neuen Punkt der Temperaturkompensation
+    ///
+    /// # Parameters
+    ///
+    /// * `temperature`     - Temperatur in °C
+    /// * `sensitivity`     - Empfindlichkeit der Messzelle bei dieser Temperatur, bezogen auf die Empfindlichkeit
+    ///                       bei der Kalibrierung (1.0), siehe Datenblatt der Messzelle
+    pub fn new(temperature: f64, sensitivity: f64) -> Self {
+        TemperaturePoint {
+            temperature: temperature,
+            sensitivity: sensitivity,
+        }
+    }
+
+    /// Temperatur in °C
+    pub fn get_temperature(&self) -> f64 {
+        self.temperature
+    }
+
+    /// Relative Empfindlichkeit bei dieser Temperatur
+    pub fn get_sensitivity(&self) -> f64 {
+        self.sensitivity
+    }
+}
+
+/// Temperaturkompensation einer Messzelle
+///
+/// Die relative Empfindlichkeit wird zwischen den Punkten der Tabelle linear interpoliert. Außerhalb der Tabelle
+/// gilt der Wert des nächsten Punkts, die Tabelle wird also nicht verlängert.
+///
+/// # Examples
+///
+/// ```rust
+/// use xmz_mod_touch_server::server::zone::kombisensor::sensor::{TemperatureCompensation, TemperaturePoint};
+///
+/// let compensation = TemperatureCompensation::new(vec![
+///     TemperaturePoint::new(-12.0, 0.75),
+///     TemperaturePoint::new(20.0, 1.0),
+///     TemperaturePoint::new(40.0, 1.1),
+/// ]);
+///
+/// assert_eq!(compensation.sensitivity(4.0), Some(0.875));
+/// assert_eq!(compensation.sensitivity(-30.0), Some(0.75));
+/// // Bei 4°C zeigt die Messzelle nur 87.5% der tatsächlichen Konzentration an
+/// assert_eq!(compensation.compensate(7.0, 4.0), 8.0);
+/// ```
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct TemperatureCompensation {
+    points: Vec<TemperaturePoint>,
+}
+
+impl TemperatureCompensation {
+    /// Erstellt eine neue Temperaturkompensation
+    ///
+    /// # Parameters
+    ///
+    /// * `points`  - Punkte der Tabelle, die Reihenfolge spielt keine Rolle
+    pub fn new(points: Vec<TemperaturePoint>) -> Self {
+        TemperatureCompensation {
+            points: points,
+        }
+    }
+
+    /// Punkte der Tabelle
+    pub fn get_points(&self) -> &Vec<TemperaturePoint> {
+        &self.points
+    }
+
+    /// Relative Empfindlichkeit der Messzelle bei der gegebenen Temperatur
+    ///
+    /// # Return values
+    ///
+    /// Liefert `None` wenn die Tabelle leer ist.
+    ///
+    /// # Parameters
+    ///
+    /// * `temperature` - Temperatur in °C
+    pub fn sensitivity(&self, temperature: f64) -> Option<f64> {
+        let mut points: Vec<(f64, f64)> = self.points.iter()
+            .map(|point| (point.temperature, point.sensitivity))
+            .collect();
+        points.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+        let (first, last) = match (points.first(), points.last()) {
+            (Some(&first), Some(&last)) => (first, last),
+            _ => return None,
+        };
+        if temperature <= first.0 { return Some(first.1); }
+        if temperature >= last.0 { return Some(last.1); }
+
+        Some(piecewise_linear(&points, temperature))
+    }
+
+    /// Korrigiert eine gemessene Konzentration
+    ///
+    /// Ist für die Temperatur keine (sinnvolle) Empfindlichkeit bekannt, wird die Konzentration unverändert
+    /// geliefert.
+    ///
+    /// # Parameters
+    ///
+    /// * `concentration`   - gemessene Konzentration
+    /// * `temperature`     - Temperatur in °C
+    pub fn compensate(&self, concentration: f64, temperature: f64) -> f64 {
+        match self.sensitivity(temperature) {
+            Some(sensitivity) if sensitivity > 0.0 => concentration / sensitivity,
+            _ => concentration,
+        }
+    }
+
+    /// Rechnet eine tatsächliche Konzentration in die gemessene Konzentration um
+    ///
+    /// Das ist die Umkehrung zu [`compensate()`](#method.compensate).
+    ///
+    /// # Parameters
+    ///
+    /// * `concentration`   - tatsächliche Konzentration
+    /// * `temperature`     - Temperatur in °C
+    pub fn decompensate(&self, concentration: f64, temperature: f64) -> f64 {
+        match self.sensitivity(temperature) {
+            Some(sensitivity) if sensitivity > 0.0 => concentration * sensitivity,
+            _ => concentration,
+        }
+    }
+}
+
+// Lineare Interpolation zwischen den Punkten (aufsteigend nach x sortiert, mindestens zwei), außerhalb wird die
+// äußere Gerade verlängert
+fn piecewise_linear(points: &[(f64, f64)], x: f64) -> f64 {
+    let segment = points.windows(2)
+        .find(|segment| x <= segment[1].0)
+        .unwrap_or(&points[points.len() - 2..]);
+    let (x0, y0) = segment[0];
+    let (x1, y1) = segment[1];
+
+    y0 + (y1 - y0) / (x1 - x0) * (x - x0)
+}
+
+// Ausgleichspolynom, für die numerische Stabilität in einer normierten Variablen t = (x - offset) / scale
+struct Polynomial {
+    offset: f64,
+    scale: f64,
+    // Koeffizienten, aufsteigend nach Potenz von t
+    coefficients: Vec<f64>,
+}
+
+impl Polynomial {
+    // Passt ein Polynom nach der Methode der kleinsten Quadrate an die Punkte an (Normalgleichungen)
+    fn fit(points: &[(f64, f64)], degree: usize) -> Option<Self> {
+        let degree = degree.min(points.len() - 1);
+        let min = points[0].0;
+        let max = points[points.len() - 1].0;
+        let offset = (min + max) / 2.0;
+        let scale = if max > min { (max - min) / 2.0 } else { 1.0 };
+
+        let size = degree + 1;
+        let mut matrix = vec![vec![0.0; size + 1]; size];
+        for &(x, y) in points {
+            let t = (x - offset) / scale;
+            for row in 0..size {
+                for column in 0..size {
+                    matrix[row][column] += t.powi((row + column) as i32);
+                }
+                matrix[row][size] += y * t.powi(row as i32);
+            }
+        }
+
+        solve(matrix).map(|coefficients| Polynomial {
+            offset: offset,
+            scale: scale,
+            coefficients: coefficients,
+        })
+    }
+
+    fn value(&self, x: f64) -> f64 {
+        let t = (x - self.offset) / self.scale;
+        self.coefficients.iter().rev().fold(0.0, |value, coefficient| value * t + coefficient)
+    }
+}
+
+// Löst ein lineares Gleichungssystem (erweiterte Koeffizientenmatrix) mit dem Gauß Verfahren
+fn solve(mut matrix: Vec<Vec<f64>>) -> Option<Vec<f64>> {
+    let size = matrix.len();
+    for column in 0..size {
+        let pivot = (column..size)
+            .max_by(|&a, &b| matrix[a][column].abs().partial_cmp(&matrix[b][column].abs()).unwrap_or(Ordering::Equal))
+            .unwrap_or(column);
+        if matrix[pivot][column].abs() < 1e-12 { return None; }
+        matrix.swap(column, pivot);
+
+        for row in column + 1..size {
+            let factor = matrix[row][column] / matrix[column][column];
+            for k in column..size + 1 {
+                matrix[row][k] -= factor * matrix[column][k];
+            }
+        }
+    }
+
+    let mut solution = vec![0.0; size];
+    for row in (0..size).rev() {
+        let sum: f64 = (row + 1..size).map(|k| matrix[row][k] * solution[k]).sum();
+        solution[row] = (matrix[row][size] - sum) / matrix[row][row];
+    }
+
+    Some(solution)
+}
@@ -0,0 +1,191 @@
+//! Gasverlauf für simulierte Messzellen
+//!
+//! Ein Gasverlauf beschreibt die Konzentration über der Zeit, mit Rampen, Sprüngen und optionalem Rauschen. Eine
+//! simulierte Messzelle spielt ihn in der [`Sensor::update()`](../sensor/struct.Sensor.html#method.update) Funktion
+//! ab. So lässt sich z.B. ein realistischer Verlauf von AP1 über AP2 bis DIW, und zurück, ohne echtes Gas vorführen.
+//!
+use rand::{self, Rng};
+
+
+/// Übergang vom vorherigen Punkt zu diesem Punkt des Gasverlaufs
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub enum Transition {
+    /// Die Konzentration steigt bzw. fällt linear bis zu diesem Punkt
+    Ramp,
+    /// Die Konzentration springt zu diesem Zeitpunkt auf den neuen Wert
+    Step,
+}
+
+/// Punkt eines Gasverlaufs
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct GasProfilePoint {
+    /// Sekunden seit dem Start des Gasverlaufs
+    pub time_sec: f64,
+    /// Konzentration in der Einheit der Messzelle
+    pub concentration: f64,
+    /// Übergang vom vorherigen Punkt, ohne Angabe eine Rampe
+    #[serde(default = "default_transition")]
+    pub transition: Transition,
+}
+
+fn default_transition() -> Transition { Transition::Ramp }
+
+/// Gasverlauf einer simulierten Messzelle
+///
+/// # Examples
+///
+/// ```rust
+/// use xmz_mod_touch_server::server::zone::kombisensor::sensor::{GasProfile, GasProfilePoint, Transition};
+///
+/// let profile = GasProfile::new(vec![
+///     GasProfilePoint { time_sec: 0.0, concentration: 0.0, transition: Transition::Step },
+///     GasProfilePoint { time_sec: 60.0, concentration: 40.0, transition: Transition::Ramp },
+///     GasProfilePoint { time_sec: 120.0, concentration: 0.0, transition: Transition::Step },
+/// ]);
+///
+/// assert_eq!(profile.concentration_at(30.0), 20.0);
+/// assert_eq!(profile.concentration_at(90.0), 40.0);
+/// assert_eq!(profile.concentration_at(120.0), 0.0);
+/// // Nach dem letzten Punkt bleibt die Konzentration stehen
+/// assert_eq!(profile.concentration_at(600.0), 0.0);
+/// ```
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct GasProfile {
+    points: Vec<GasProfilePoint>,
+    /// Maximale Abweichung des Rauschens in der Einheit der Messzelle, 0.0 ohne Rauschen
+    #[serde(default)]
+    noise: f64,
+    /// Nach dem letzten Punkt beginnt der Gasverlauf von vorn
+    #[serde(default)]
+    repeat: bool,
+}
+
+impl GasProfile {
+    /// Erstellt einen Gasverlauf ohne Rauschen und ohne Wiederholung
+    ///
+    /// # Parameters
+    ///
+    /// * `points`  - Punkte des Gasverlaufs, sie werden nach der Zeit sortiert
+    pub fn new(points: Vec<GasProfilePoint>) -> Self {
+        let mut profile = GasProfile {
+            points: points,
+            noise: 0.0,
+            repeat: false,
+        };
+        profile.sort_points();
+        profile
+    }
+
+    /// Punkte des Gasverlaufs
+    pub fn get_points(&self) -> &Vec<GasProfilePoint> {
+        &self.points
+    }
+
+    /// Maximale Abweichung des Rauschens
+    pub fn get_noise(&self) -> f64 {
+        self.noise
+    }
+
+    /// Setzt die maximale Abweichung des Rauschens
+    ///
+    /// # Parameters
+    ///
+    /// * `noise`   - maximale Abweichung in der Einheit der Messzelle, 0.0 ohne Rauschen
+    pub fn set_noise(&mut self, noise: f64) {
+        self.noise = noise;
+    }
+
+    /// Beginnt der Gasverlauf nach dem letzten Punkt von vorn?
+    pub fn is_repeat(&self) -> bool {
+        self.repeat
+    }
+
+    /// Setzt, ob der Gasverlauf nach dem letzten Punkt von vorn beginnt
+    ///
+    /// # Parameters
+    ///
+    /// * `repeat`  - `true` wiederholt den Gasverlauf
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::server::zone::kombisensor::sensor::{GasProfile, GasProfilePoint, Transition};
+    ///
+    /// let mut profile = GasProfile::new(vec![
+    ///     GasProfilePoint { time_sec: 0.0, concentration: 0.0, transition: Transition::Step },
+    ///     GasProfilePoint { time_sec: 100.0, concentration: 10.0, transition: Transition::Ramp },
+    /// ]);
+    /// profile.set_repeat(true);
+    /// assert_eq!(profile.concentration_at(150.0), 5.0);
+    /// ```
+    pub fn set_repeat(&mut self, repeat: bool) {
+        self.repeat = repeat;
+    }
+
+    /// Konzentration zum gegebenen Zeitpunkt, ohne Rauschen
+    ///
+    /// Vor dem ersten Punkt gilt die Konzentration des ersten Punkts, nach dem letzten Punkt die des letzten
+    /// Punkts. Ohne Punkte wird 0.0 geliefert.
+    ///
+    /// # Parameters
+    ///
+    /// * `elapsed_sec` - Sekunden seit dem Start des Gasverlaufs
+    pub fn concentration_at(&self, elapsed_sec: f64) -> f64 {
+        let (first, last) = match (self.points.first(), self.points.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return 0.0,
+        };
+
+        let mut time_sec = elapsed_sec;
+        if self.repeat && last.time_sec > 0.0 && time_sec > last.time_sec {
+            time_sec %= last.time_sec;
+        }
+
+        if time_sec <= first.time_sec { return first.concentration; }
+        for window in self.points.windows(2) {
+            let (from, to) = (&window[0], &window[1]);
+            if time_sec < to.time_sec {
+                return match to.transition {
+                    Transition::Step => from.concentration,
+                    Transition::Ramp => {
+                        from.concentration + (to.concentration - from.concentration) *
+                            (time_sec - from.time_sec) / (to.time_sec - from.time_sec)
+                    }
+                };
+            }
+        }
+
+        last.concentration
+    }
+
+    /// Konzentration zum gegebenen Zeitpunkt, mit Rauschen
+    ///
+    /// Das Rauschen ist gleichverteilt innerhalb der maximalen Abweichung. Negative Konzentrationen werden auf
+    /// 0.0 begrenzt.
+    ///
+    /// # Parameters
+    ///
+    /// * `elapsed_sec` - Sekunden seit dem Start des Gasverlaufs
+    pub fn sample(&self, elapsed_sec: f64) -> f64 {
+        let mut concentration = self.concentration_at(elapsed_sec);
+        if self.noise > 0.0 {
+            concentration += rand::thread_rng().gen_range(-self.noise, self.noise);
+        }
+
+        if concentration < 0.0 { 0.0 } else { concentration }
+    }
+
+    /// Sortiert die Punkte nach der Zeit, z.B. nach dem Einlesen aus einer Datei
+    pub fn sort_points(&mut self) {
+        self.points.sort_by(|a, b| a.time_sec.partial_cmp(&b.time_sec).unwrap_or(::std::cmp::Ordering::Equal));
+    }
+}
@@ -0,0 +1,379 @@
+//! Zustand (Alterung) einer Messzelle
+//!
+//! Elektrochemische Messzellen verlieren mit der Zeit an Empfindlichkeit, ihr Nullpunkt driftet. Bei jeder Abfrage
+//! werden die Kalibrierwerte aus der Firmware gelesen, ändern sie sich (neue Kalibrierung), wird das festgehalten.
+//! Zusätzlich wird pro Tag der Messwert mit dem wenigsten Gas als Nullpunkt (Baseline) gespeichert. Messzellen mit
+//! fallendem Alarm (z.B. O2) messen im Normalbetrieb weit entfernt vom Nullgas, für sie gibt es keinen Nullpunkt.
+//!
+//! Fällt die Empfindlichkeit unter einen Anteil der ersten Kalibrierung, sollte die Messzelle bald getauscht werden.
+//! Die beim ersten Auslesen vorgefundenen Kalibrierwerte zählen dabei nicht als Kalibrierung, wann und ob die
+//! Messzelle damit kalibriert wurde ist unbekannt. Als erste Kalibrierung gilt die erste beobachtete Änderung.
+//!
+use ::chrono::{DateTime, NaiveDate, Utc};
+
+
+/// Standard Grenze der Empfindlichkeit (in Prozent der ersten Kalibrierung), unter der die Messzelle getauscht
+/// werden sollte
+pub const SENSOR_REPLACE_SENSITIVITY_PERCENT: f64 = 50.0;
+// Anzahl der Tage, für die der Nullpunkt gespeichert wird
+const BASELINE_HISTORY_DAYS: usize = 365;
+// Anzahl der gespeicherten Kalibrierungen, die erste Kalibrierung bleibt immer erhalten
+const CALIBRATION_HISTORY_MAX: usize = 100;
+
+/// Kalibrierwerte einer Messzelle zu einem Zeitpunkt
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct CalibrationRecord {
+    timestamp: DateTime<Utc>,
+    adc_value_at_nullgas: u16,
+    adc_value_at_messgas: u16,
+    concentration_at_nullgas: u16,
+    concentration_at_messgas: u16,
+    // Beim ersten Auslesen vorgefundene Werte, keine beobachtete Kalibrierung
+    #[serde(default)]
+    initial: bool,
+}
+
+impl CalibrationRecord {
+    /// Erstellt einen neuen Eintrag
+    ///
+    /// # Parameters
+    ///
+    /// * `timestamp`                   - Zeitpunkt, an dem die Kalibrierwerte gelesen wurden
+    /// * `adc_value_at_nullgas`        - ADC Wert bei Nullgas
+    /// * `adc_value_at_messgas`        - ADC Wert bei Messgas
+    /// * `concentration_at_nullgas`    - Konzentration des Nullgases
+    /// * `concentration_at_messgas`    - Konzentration des Messgases
+    pub fn new(timestamp: DateTime<Utc>, adc_value_at_nullgas: u16, adc_value_at_messgas: u16, concentration_at_nullgas: u16, concentration_at_messgas: u16) -> Self {
+        CalibrationRecord {
+            timestamp: timestamp,
+            adc_value_at_nullgas: adc_value_at_nullgas,
+            adc_value_at_messgas: adc_value_at_messgas,
+            concentration_at_nullgas: concentration_at_nullgas,
+            concentration_at_messgas: concentration_at_messgas,
+            initial: false,
+        }
+    }
+
+    /// Wurden die Werte beim ersten Auslesen vorgefunden?
+    ///
+    /// Solche Werte dienen als Bezug des Nullpunkts, aber nicht als Bezug der Empfindlichkeit.
+    pub fn is_initial(&self) -> bool {
+        self.initial
+    }
+
+    /// Zeitpunkt, an dem die Kalibrierwerte gelesen wurden
+    pub fn get_timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    /// ADC Wert bei Nullgas
+    pub fn get_adc_value_at_nullgas(&self) -> u16 {
+        self.adc_value_at_nullgas
+    }
+
+    /// ADC Wert bei Messgas
+    pub fn get_adc_value_at_messgas(&self) -> u16 {
+        self.adc_value_at_messgas
+    }
+
+    /// Empfindlichkeit, Abstand der ADC Werte von Messgas und Nullgas pro Einheit der Konzentration
+    ///
+    /// Liefert `None` wenn Nullgas und Messgas die gleiche Konzentration haben.
+    pub fn get_sensitivity(&self) -> Option<f64> {
+        let concentration_span = self.concentration_at_messgas as f64 - self.concentration_at_nullgas as f64;
+        if concentration_span == 0.0 { return None; }
+
+        Some(((self.adc_value_at_messgas as f64 - self.adc_value_at_nullgas as f64) / concentration_span).abs())
+    }
+
+    // Gleiche Kalibrierwerte, unabhängig vom Zeitpunkt
+    fn has_same_values(&self, other: &CalibrationRecord) -> bool {
+        self.adc_value_at_nullgas == other.adc_value_at_nullgas &&
+        self.adc_value_at_messgas == other.adc_value_at_messgas &&
+        self.concentration_at_nullgas == other.concentration_at_nullgas &&
+        self.concentration_at_messgas == other.concentration_at_messgas
+    }
+
+    // Abstand eines ADC Werts vom Nullgas, in Richtung des Messgases. Je kleiner, desto weniger Gas.
+    fn gas_offset(&self, adc_value: u16) -> i32 {
+        let offset = adc_value as i32 - self.adc_value_at_nullgas as i32;
+        if self.adc_value_at_messgas < self.adc_value_at_nullgas { -offset } else { offset }
+    }
+}
+
+/// Nullpunkt (Messwert mit dem wenigsten Gas) eines Tages
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct BaselineRecord {
+    day: NaiveDate,
+    adc_value: u16,
+}
+
+impl BaselineRecord {
+    /// Tag (UTC)
+    pub fn get_day(&self) -> NaiveDate {
+        self.day
+    }
+
+    /// ADC Wert des Nullpunkts
+    pub fn get_adc_value(&self) -> u16 {
+        self.adc_value
+    }
+}
+
+/// Auswertung des Zustands einer Messzelle, z.B. für die JSON Web Api
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Serialize)]
+pub struct SensorHealthReport {
+    /// Aktuelle Empfindlichkeit in Prozent der ersten Kalibrierung
+    pub sensitivity_percent: Option<f64>,
+    /// Abstand des letzten Nullpunkts vom ADC Wert bei Nullgas der letzten Kalibrierung
+    pub baseline_drift: Option<i32>,
+    /// Drift des Nullpunkts seit der letzten Kalibrierung in ADC Werten pro Woche
+    pub baseline_drift_per_week: Option<f64>,
+    /// Die Messzelle sollte bald getauscht werden
+    pub replace_soon: bool,
+    /// Gespeicherte Kalibrierungen
+    pub calibrations: Vec<CalibrationRecord>,
+    /// Gespeicherte Nullpunkte
+    pub baseline: Vec<BaselineRecord>,
+}
+
+/// Verlauf von Kalibrierung und Nullpunkt einer Messzelle
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate chrono;
+/// # extern crate xmz_mod_touch_server;
+/// use chrono::{Duration, Utc};
+/// use xmz_mod_touch_server::server::zone::kombisensor::sensor::{CalibrationRecord, SensorHealth};
+///
+/// # fn main() {
+/// let mut health = SensorHealth::new();
+/// let start = Utc::now() - Duration::weeks(52);
+///
+/// // Die beim ersten Auslesen vorgefundenen Werte sind kein Bezug der Empfindlichkeit
+/// health.record_calibration(CalibrationRecord::new(start, 900, 500, 0, 20));
+/// assert!(health.get_calibrations()[0].is_initial());
+/// assert_eq!(health.get_sensitivity_percent(), None);
+///
+/// // Erste Kalibrierung mit 20ppm Messgas, später nur noch der halbe Abstand
+/// health.record_calibration(CalibrationRecord::new(start + Duration::weeks(1), 920, 564, 0, 20));
+/// assert_eq!(health.get_sensitivity_percent(), Some(100.0));
+/// health.record_calibration(CalibrationRecord::new(start + Duration::weeks(40), 900, 740, 0, 20));
+///
+/// assert_eq!(health.get_calibrations().len(), 3);
+/// assert!(health.get_sensitivity_percent().unwrap() < 50.0);
+/// assert!(health.is_replace_soon(50.0));
+/// # }
+/// ```
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct SensorHealth {
+    calibrations: Vec<CalibrationRecord>,
+    baseline: Vec<BaselineRecord>,
+}
+
+impl SensorHealth {
+    /// Erstellt einen leeren Verlauf
+    pub fn new() -> Self {
+        SensorHealth {
+            calibrations: vec![],
+            baseline: vec![],
+        }
+    }
+
+    /// Liefert `true` wenn noch nichts aufgezeichnet wurde
+    pub fn is_empty(&self) -> bool {
+        self.calibrations.is_empty() && self.baseline.is_empty()
+    }
+
+    /// Gespeicherte Kalibrierungen, die älteste zuerst
+    pub fn get_calibrations(&self) -> &Vec<CalibrationRecord> {
+        &self.calibrations
+    }
+
+    /// Gespeicherte Nullpunkte, der älteste zuerst
+    pub fn get_baseline(&self) -> &Vec<BaselineRecord> {
+        &self.baseline
+    }
+
+    /// Speichert die Kalibrierwerte, wenn sie sich seit der letzten Kalibrierung geändert haben
+    ///
+    /// Die ersten Kalibrierwerte eines leeren Verlaufs werden als vorgefundene Werte gespeichert (siehe
+    /// [`CalibrationRecord::is_initial()`](struct.CalibrationRecord.html#method.is_initial)).
+    ///
+    /// # Return values
+    ///
+    /// Liefert `true` wenn eine neue Kalibrierung gespeichert wurde.
+    ///
+    /// # Parameters
+    ///
+    /// * `record`  - aktuelle Kalibrierwerte der Messzelle
+    pub fn record_calibration(&mut self, record: CalibrationRecord) -> bool {
+        let mut record = record;
+        match self.calibrations.last() {
+            Some(last) => if last.has_same_values(&record) { return false; },
+            None => record.initial = true,
+        }
+
+        self.calibrations.push(record);
+        if self.calibrations.len() > CALIBRATION_HISTORY_MAX {
+            // Die vorgefundenen Werte und die erste Kalibrierung, der Bezug der Empfindlichkeit, bleiben erhalten
+            let keep = self.calibrations.iter().position(|calibration| !calibration.initial).map_or(1, |index| index + 1);
+            self.calibrations.remove(keep);
+        }
+        true
+    }
+
+    /// Übernimmt einen Messwert für den Nullpunkt des Tages
+    ///
+    /// Pro Tag bleibt der Messwert mit dem wenigsten Gas, bezogen auf die letzte Kalibrierung, erhalten. Ohne
+    /// Kalibrierung wird nichts gespeichert. Nur für Messzellen mit steigendem Alarm sinnvoll, die
+    /// [`Sensor`](../sensor/struct.Sensor.html) übergibt Messwerte mit fallendem Alarm (z.B. O2) nicht.
+    ///
+    /// # Parameters
+    ///
+    /// * `adc_value`   - ADC Wert der Messzelle
+    /// * `timestamp`   - Zeitpunkt der Messung
+    pub fn record_zero_reading(&mut self, adc_value: u16, timestamp: DateTime<Utc>) {
+        let calibration = match self.calibrations.last() {
+            Some(calibration) => calibration,
+            None => return,
+        };
+        let day = timestamp.naive_utc().date();
+
+        if let Some(last) = self.baseline.last_mut() {
+            if last.day == day {
+                if calibration.gas_offset(adc_value) < calibration.gas_offset(last.adc_value) {
+                    last.adc_value = adc_value;
+                }
+                return;
+            }
+            // Ältere Messwerte, z.B. aus einer Aufzeichnung, werden ignoriert
+            if last.day > day { return; }
+        }
+
+        self.baseline.push(BaselineRecord { day: day, adc_value: adc_value });
+        if self.baseline.len() > BASELINE_HISTORY_DAYS {
+            self.baseline.remove(0);
+        }
+    }
+
+    /// Aktuelle Empfindlichkeit in Prozent der ersten Kalibrierung
+    ///
+    /// Liefert `None` solange noch keine Kalibrierung beobachtet wurde.
+    pub fn get_sensitivity_percent(&self) -> Option<f64> {
+        let initial = self.calibrations.iter().find(|calibration| !calibration.initial).and_then(|calibration| calibration.get_sensitivity());
+        let current = self.calibrations.last().and_then(|calibration| calibration.get_sensitivity());
+        match (initial, current) {
+            (Some(initial), Some(current)) if initial > 0.0 => Some(current / initial * 100.0),
+            _ => None,
+        }
+    }
+
+    /// Abstand des letzten Nullpunkts vom ADC Wert bei Nullgas der letzten Kalibrierung
+    ///
+    /// Positive Werte bedeuten eine Drift in Richtung des Messgases.
+    pub fn get_baseline_drift(&self) -> Option<i32> {
+        match (self.calibrations.last(), self.baseline.last()) {
+            (Some(calibration), Some(baseline)) => Some(calibration.gas_offset(baseline.adc_value)),
+            _ => None,
+        }
+    }
+
+    /// Drift des Nullpunkts seit der letzten Kalibrierung in ADC Werten pro Woche
+    ///
+    /// Die Gerade wird nach der Methode der kleinsten Quadrate durch die Nullpunkte seit der letzten Kalibrierung
+    /// gelegt. Positive Werte bedeuten eine Drift in Richtung des Messgases. Es werden mindestens zwei Tage
+    /// gebraucht.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// extern crate chrono;
+    /// # extern crate xmz_mod_touch_server;
+    /// use chrono::{Duration, Utc};
+    /// use xmz_mod_touch_server::server::zone::kombisensor::sensor::{CalibrationRecord, SensorHealth};
+    ///
+    /// # fn main() {
+    /// let mut health = SensorHealth::new();
+    /// let start = Utc::now() - Duration::weeks(4);
+    /// health.record_calibration(CalibrationRecord::new(start, 920, 564, 0, 20));
+    ///
+    /// // NO2, der ADC Wert fällt mit steigender Konzentration. Jeden Tag 1 ADC Wert in Richtung Messgas
+    /// for day in 0..28 {
+    ///     health.record_zero_reading(920 - day as u16, start + Duration::days(day));
+    ///     health.record_zero_reading(900 - day as u16, start + Duration::days(day) + Duration::hours(1));
+    /// }
+    ///
+    /// assert_eq!(health.get_baseline().len(), 28);
+    /// assert_eq!(health.get_baseline_drift(), Some(27));
+    /// assert!((health.get_baseline_drift_per_week().unwrap() - 7.0).abs() < 1e-9);
+    /// # }
+    /// ```
+    pub fn get_baseline_drift_per_week(&self) -> Option<f64> {
+        let calibration = match self.calibrations.last() {
+            Some(calibration) => calibration,
+            None => return None,
+        };
+        let since = calibration.timestamp.naive_utc().date();
+        let points: Vec<(f64, f64)> = self.baseline.iter()
+            .filter(|baseline| baseline.day >= since)
+            .map(|baseline| {
+                let weeks = baseline.day.signed_duration_since(since).num_days() as f64 / 7.0;
+                (weeks, calibration.gas_offset(baseline.adc_value) as f64)
+            })
+            .collect();
+        if points.len() < 2 { return None; }
+
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|&(x, _)| x).sum::<f64>() / n;
+        let mean_y = points.iter().map(|&(_, y)| y).sum::<f64>() / n;
+        let covariance: f64 = points.iter().map(|&(x, y)| (x - mean_x) * (y - mean_y)).sum();
+        let variance: f64 = points.iter().map(|&(x, _)| (x - mean_x) * (x - mean_x)).sum();
+        if variance == 0.0 { return None; }
+
+        Some(covariance / variance)
+    }
+
+    /// Sollte die Messzelle bald getauscht werden?
+    ///
+    /// # Parameters
+    ///
+    /// * `replace_sensitivity_percent` - Grenze der Empfindlichkeit in Prozent der ersten Kalibrierung
+    pub fn is_replace_soon(&self, replace_sensitivity_percent: f64) -> bool {
+        self.get_sensitivity_percent().map_or(false, |percent| percent < replace_sensitivity_percent)
+    }
+
+    /// Wertet den Verlauf aus
+    ///
+    /// # Parameters
+    ///
+    /// * `replace_sensitivity_percent` - Grenze der Empfindlichkeit in Prozent der ersten Kalibrierung
+    pub fn report(&self, replace_sensitivity_percent: f64) -> SensorHealthReport {
+        SensorHealthReport {
+            sensitivity_percent: self.get_sensitivity_percent(),
+            baseline_drift: self.get_baseline_drift(),
+            baseline_drift_per_week: self.get_baseline_drift_per_week(),
+            replace_soon: self.is_replace_soon(replace_sensitivity_percent),
+            calibrations: self.calibrations.clone(),
+            baseline: self.baseline.clone(),
+        }
+    }
+}
+
+impl Default for SensorHealth {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,3 +1,13 @@
+pub mod alarm;
+pub mod average_window;
+pub mod calibration_curve;
+pub mod gas_profile;
+pub mod health;
 pub mod sensor;
 
-pub use self::sensor::{Sensor, SensorType, SI};
+pub use self::alarm::{AlarmDirection, AlarmLevel, AlarmSettings, AlarmState};
+pub use self::average_window::AverageWindow;
+pub use self::calibration_curve::{CalibrationCurve, CalibrationPoint, Interpolation, TemperatureCompensation, TemperaturePoint};
+pub use self::gas_profile::{GasProfile, GasProfilePoint, Transition};
+pub use self::health::{BaselineRecord, CalibrationRecord, SensorHealth, SensorHealthReport, SENSOR_REPLACE_SENSITIVITY_PERCENT};
+pub use self::sensor::{Sensor, SensorStatus, SensorType, SI};
@@ -4,6 +4,7 @@
 //! `Firmware Version: 0.14.0`
 //!
 use ::chrono::{DateTime, Utc};
+use server::zone::kombisensor::sensor::{AlarmDirection, AlarmLevel, AlarmSettings, AlarmState, AverageWindow, CalibrationCurve, CalibrationRecord, GasProfile, SensorHealth, TemperatureCompensation};
 use std::fmt;
 
 
@@ -11,6 +12,16 @@ use std::fmt;
 // Die Konstante wird in Sekunden angegeben
 pub const AVERAGE_15MIN_SEC: i64 = 15 * 60;
 // pub const AVERAGE_15MIN_SEC: i64 = 10;
+// Ein Messwert gilt bis zum nächsten Messwert, aber höchstens so viele Sekunden. Fehlen länger Messwerte
+// (Modbus Timeouts, Kabelbruch), ist der Rest eine Lücke im Mittelwert.
+pub const AVERAGE_SAMPLE_VALIDITY_MAX_SEC: i64 = 10;
+// Länge eines Zeitabschnitts im Ringpuffer des Mittelwerts, siehe `AverageWindow`
+pub const AVERAGE_BUCKET_SEC: i64 = 10;
+// Mindestanteil des Mittelwert Zeitraums, der von Messwerten abgedeckt sein muss. Darunter ist der 15min
+// Mittelwert ungültig, die Alarmpunkte AP1/ AP2 werden dann über den abgedeckten Zeitraum ausgewertet.
+pub const AVERAGE_COVERAGE_MIN: f64 = 0.8;
+// Negative Drift bis zu diesem Anteil (in Prozent) des Messbereichs gilt noch nicht als Unterschreitung
+pub const UNDER_RANGE_TOLERANCE_PERCENT: f64 = 10.0;
 
 /// Typ der Messzelle
 #[derive(Clone)]
@@ -32,6 +43,26 @@ pub enum SensorType {
     SimulationNO2,
     /// Simulation einer CO Messzelle
     SimulationCO,
+    /// Sauerstoff Messzelle (Vol%), Alarm bei Sauerstoffmangel
+    O2,
+    /// Methan Messzelle (% UEG), z.B. Erdgas
+    CH4,
+    /// Flüssiggas Messzelle (% UEG), Propan/ Butan
+    LPG,
+}
+
+/// Zustand des Messbereichs einer Messzelle
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub enum SensorStatus {
+    // Messwert innerhalb des Messbereichs
+    Normal,
+    // Messbereich unterschritten, z.B. defekte Messzelle oder starke negative Drift
+    UnderRange,
+    // Messbereich überschritten, die Messzelle ist gesättigt und der wahre Wert unbekannt
+    OverRange,
 }
 
 /// SI Einheit des zu messenden Mediums
@@ -72,6 +103,9 @@ pub struct Sensor {
     error_count: u64,
     /// 15min Average
     adc_value_average_15min: u16,
+    /// Anteil des Mittelwert Zeitraums (0.0 bis 1.0), der von Messwerten abgedeckt ist
+    #[serde(default)]
+    adc_value_average_15min_coverage: f64,
     /// Alarm1 `15min` Mittelwert (zeitlich gewichtet)
     /// siehe DIN EN 50545-1 (VDE 0400-80):2012-04; Seite 15ff
     pub alarm1_average_15min: f64,
@@ -81,8 +115,50 @@ pub struct Sensor {
     /// Alarm3 Direktwert
     /// siehe DIN EN 50545-1 (VDE 0400-80):2012-04; Seite 15ff
     pub alarm3_direct_value: f64,
+    /// Hysterese, Verzögerung und Selbsthaltung des Alarmpunkts 1
+    #[serde(default)]
+    alarm1_settings: AlarmSettings,
+    /// Hysterese, Verzögerung und Selbsthaltung des Alarmpunkts 2
+    #[serde(default)]
+    alarm2_settings: AlarmSettings,
+    /// Hysterese, Verzögerung und Selbsthaltung des Direktwerts
+    #[serde(default)]
+    alarm3_settings: AlarmSettings,
+    /// Zustand der Alarmpunkte, wird von der Zone ausgewertet
+    #[serde(skip_deserializing)]
+    alarm1_state: AlarmState,
+    #[serde(skip_deserializing)]
+    alarm2_state: AlarmState,
+    #[serde(skip_deserializing)]
+    alarm3_state: AlarmState,
+    /// Optionale Kalibrierkurve, ohne sie wird eine Gerade durch Nullgas und Messgas verwendet
+    #[serde(default)]
+    calibration_curve: Option<CalibrationCurve>,
+    /// Optionale Temperaturkompensation
+    #[serde(default)]
+    temperature_compensation: Option<TemperatureCompensation>,
+    /// Temperatur der Messzelle in °C. Die Firmware 0.14.0 liefert keine Temperatur, sie kann aber z.B. für
+    /// Messstellen mit bekannter Umgebungstemperatur in der Konfiguration eingetragen werden.
+    #[serde(default)]
+    temperature: Option<f64>,
+    /// Zustand des Messbereichs, wird mit der `update()` Funktion aktualisiert
+    #[serde(skip_deserializing)]
+    status: SensorStatus,
+    /// Verlauf von Kalibrierung und Nullpunkt, siehe [`SensorHealth`](../health/struct.SensorHealth.html). Der
+    /// Verlauf steht in der Zustandsdatei und wird über die JSON Web Api nur als `health` Abfrage ausgeliefert.
     #[serde(skip_deserializing, skip_serializing)]
-    pub adc_values_average: Vec<(u16, DateTime<Utc>)>,
+    health: SensorHealth,
+    // Ringpuffer der Messwerte des 15min Mittelwerts
+    #[serde(skip_deserializing, skip_serializing)]
+    adc_values_average: AverageWindow,
+    // Zeitpunkt, an dem `adc_value` ausgelesen wurde. `None` wenn der Wert nie ausgelesen wurde (Simulation).
+    #[serde(skip_deserializing, skip_serializing)]
+    adc_value_timestamp: Option<DateTime<Utc>>,
+    // Gasverlauf, den eine simulierte Messzelle abspielt, und dessen Startzeitpunkt
+    #[serde(skip_deserializing, skip_serializing)]
+    gas_profile: Option<GasProfile>,
+    #[serde(skip_deserializing, skip_serializing)]
+    gas_profile_start: Option<DateTime<Utc>>,
 }
 
 impl Sensor {
@@ -109,10 +185,25 @@ impl Sensor {
             config: 0,
             error_count: 0,
             adc_value_average_15min: 0,
+            adc_value_average_15min_coverage: 0.0,
             alarm1_average_15min: 0.0,
             alarm2_average_15min: 0.0,
             alarm3_direct_value: 0.0,
-            adc_values_average: vec![],
+            alarm1_settings: AlarmSettings::default(),
+            alarm2_settings: AlarmSettings::default(),
+            alarm3_settings: AlarmSettings::default(),
+            alarm1_state: AlarmState::new(),
+            alarm2_state: AlarmState::new(),
+            alarm3_state: AlarmState::new(),
+            calibration_curve: None,
+            temperature_compensation: None,
+            temperature: None,
+            status: SensorStatus::Normal,
+            health: SensorHealth::new(),
+            adc_values_average: AverageWindow::default(),
+            adc_value_timestamp: None,
+            gas_profile: None,
+            gas_profile_start: None,
         }
     }
 
@@ -218,6 +309,48 @@ impl Sensor {
                     ..Default::default()
                 }
             }
+            SensorType::O2 => {
+                Sensor {
+                    sensor_type,
+                    si: SI::Vol,
+                    max_value: 25,
+                    adc_value_at_nullgas: 100,          // Stickstoff, 0 Vol% O2
+                    adc_value_at_messgas: 820,
+                    concentration_at_messgas: 18,       // 18 Vol% O2 Messgas
+                    alarm1_average_15min: 19.0, // Sauerstoffmangel, Alarm beim Unterschreiten von 19 Vol%
+                    alarm2_average_15min: 18.0, // Sauerstoffmangel, Alarm beim Unterschreiten von 18 Vol%
+                    alarm3_direct_value: 17.0,  // Sauerstoffmangel, Alarm beim Unterschreiten von 17 Vol%
+                    ..Default::default()
+                }
+            }
+            SensorType::CH4 => {
+                Sensor {
+                    sensor_type,
+                    si: SI::UEG,
+                    max_value: 100,
+                    adc_value_at_nullgas: 100,
+                    adc_value_at_messgas: 600,
+                    concentration_at_messgas: 50,       // 50% UEG Messgas (2,2 Vol% CH4)
+                    alarm1_average_15min: 10.0, // Voralarm bei 10% UEG
+                    alarm2_average_15min: 20.0, // Hauptalarm bei 20% UEG
+                    alarm3_direct_value: 40.0,  // Direktwert bei 40% UEG
+                    ..Default::default()
+                }
+            }
+            SensorType::LPG => {
+                Sensor {
+                    sensor_type,
+                    si: SI::UEG,
+                    max_value: 100,
+                    adc_value_at_nullgas: 100,
+                    adc_value_at_messgas: 600,
+                    concentration_at_messgas: 50,       // 50% UEG Messgas (0,85 Vol% Propan)
+                    alarm1_average_15min: 10.0, // Voralarm bei 10% UEG
+                    alarm2_average_15min: 20.0, // Hauptalarm bei 20% UEG
+                    alarm3_direct_value: 40.0,  // Direktwert bei 40% UEG
+                    ..Default::default()
+                }
+            }
         }
     }
 
@@ -239,6 +372,11 @@ impl Sensor {
         self.adc_value
     }
 
+    /// Zeitpunkt, an dem der ADC Wert ausgelesen wurde, `None` wenn er nie ausgelesen wurde (Simulation)
+    pub fn get_adc_value_timestamp(&self) -> Option<DateTime<Utc>> {
+        self.adc_value_timestamp
+    }
+
     /// Liefert den minimal Wert der für den Sensor konfiguriert wurde
     ///
     /// # Examples
@@ -380,6 +518,46 @@ impl Sensor {
         self.adc_value_average_15min
     }
 
+    /// Liefert den Anteil des 15min Zeitraums, der von Messwerten abgedeckt ist
+    ///
+    /// Betrachtet wird der Zeitraum ab dem ersten Messwert, höchstens aber die letzten 15 Minuten. Der Wert liegt
+    /// zwischen 0.0 (keine Messwerte) und 1.0 (lückenlos).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Sensor, SensorType};
+    /// let mut sensor = Sensor::new_with_type(SensorType::SimulationNO2Fix);
+    /// assert_eq!(sensor.get_adc_value_average_15min_coverage(), 0.0);
+    ///
+    /// sensor.update();
+    /// assert_eq!(sensor.get_adc_value_average_15min_coverage(), 1.0);
+    /// ```
+    pub fn get_adc_value_average_15min_coverage(&self) -> f64 {
+        self.adc_value_average_15min_coverage
+    }
+
+    /// Ist der 15min Mittelwert gültig?
+    ///
+    /// Der Mittelwert ist ungültig, wenn weniger als `AVERAGE_COVERAGE_MIN` des Zeitraums von Messwerten abgedeckt
+    /// sind, z.B. nach einem Kabelbruch. Der Server meldet dann eine `SensorAverage15minInvalid` Ausnahme. Damit ein
+    /// echter AP1/ AP2 nicht verdeckt wird, werden die Alarmpunkte trotzdem ausgewertet, mit dem Mittelwert über den
+    /// abgedeckten Teil des Zeitraums.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Sensor, SensorType};
+    /// let mut sensor = Sensor::new_with_type(SensorType::SimulationNO2Fix);
+    /// assert_eq!(sensor.is_average_15min_valid(), false);
+    ///
+    /// sensor.update();
+    /// assert_eq!(sensor.is_average_15min_valid(), true);
+    /// ```
+    pub fn is_average_15min_valid(&self) -> bool {
+        self.adc_value_average_15min_coverage >= AVERAGE_COVERAGE_MIN
+    }
+
     /// Liefert den Stand des Fehlerzählers
     ///
     /// # Examples
@@ -431,7 +609,10 @@ impl Sensor {
 
     /// Direktwert überschritten?
     ///
-    /// Liefert ein boolen `true` wenn der konfigurierte Direktwert überschritten wurden
+    /// Liefert ein boolen `true` wenn der konfigurierte Direktwert überschritten wurden. Bei einer Messzelle mit
+    /// fallendem Alarm (z.B. Sauerstoff) wenn er unterschritten wurde, siehe
+    /// [`get_alarm_direction()`](#method.get_alarm_direction). Solange noch kein Messwert gelesen wurde, wird `false`
+    /// geliefert.
     ///
     ///  # Examples
     ///
@@ -442,12 +623,27 @@ impl Sensor {
     /// assert_eq!(sensor.direct_value_reached(), true)
     /// ```
     pub fn direct_value_reached(&self) -> bool {
-        self.get_concentration() >= self.alarm3_direct_value as f64
+        self.get_alarm_direction(AlarmLevel::DIW).is_reached(self.get_direct_value(), self.alarm3_direct_value)
+    }
+
+    // Messwert für den Direktwert Alarm
+    //
+    // Solange noch kein Messwert gelesen wurde (z.B. direkt nach dem Start) ist die Konzentration 0.0, bei einer
+    // Messzelle mit fallendem Alarm (O2) läge das unter jedem Direktwert. Bis zum ersten Messwert wird deshalb NAN
+    // geliefert, damit wird kein Alarm ausgewertet. Simulierte Messzellen haben immer einen Messwert.
+    fn get_direct_value(&self) -> f64 {
+        if self.adc_value_timestamp.is_none() && !self.sensor_type.is_simulation() {
+            ::std::f64::NAN
+        } else {
+            self.get_concentration()
+        }
     }
 
     /// Alarmpunkt (AP2) erreicht?
     ///
-    /// Liefert ein boolen `true` wenn der konfigurierte Alarmpunkt2 erreicht wurden
+    /// Liefert ein boolen `true` wenn der konfigurierte Alarmpunkt2 erreicht wurden. Ist der 15min Mittelwert
+    /// ungültig (siehe [`is_average_15min_valid()`](#method.is_average_15min_valid)), zählt der abgedeckte Teil des
+    /// Zeitraums. Ohne Messwerte im Zeitraum wird `false` geliefert.
     ///
     ///  # Examples
     ///
@@ -471,12 +667,15 @@ impl Sensor {
     /// ```
     ///
     pub fn alarmpunkt2_reached(&self) -> bool {
-        self.get_concentration_average_15min() >= self.alarm2_average_15min as f64
+        self.has_average_15min() &&
+        self.get_alarm_direction(AlarmLevel::AP2).is_reached(self.get_concentration_average_15min(), self.alarm2_average_15min)
     }
 
     /// Alarmpunkt (AP1) erreicht?
     ///
-    /// Liefert ein boolen `true` wenn der konfigurierte Alarmpunkt1 erreicht wurden
+    /// Liefert ein boolen `true` wenn der konfigurierte Alarmpunkt1 erreicht wurden. Ist der 15min Mittelwert
+    /// ungültig (siehe [`is_average_15min_valid()`](#method.is_average_15min_valid)), zählt der abgedeckte Teil des
+    /// Zeitraums. Ohne Messwerte im Zeitraum wird `false` geliefert.
     ///
     ///  # Examples
     ///
@@ -487,7 +686,233 @@ impl Sensor {
     /// assert_eq!(sensor.alarmpunkt1_reached(), false)
     /// ```
     pub fn alarmpunkt1_reached(&self) -> bool {
-        self.get_concentration_average_15min() >= self.alarm1_average_15min as f64
+        self.has_average_15min() &&
+        self.get_alarm_direction(AlarmLevel::AP1).is_reached(self.get_concentration_average_15min(), self.alarm1_average_15min)
+    }
+
+    // Liegen Messwerte im Zeitraum des 15min Mittelwerts?
+    //
+    fn has_average_15min(&self) -> bool {
+        self.adc_value_average_15min_coverage > 0.0
+    }
+
+    /// Liefert die Einstellungen (Hysterese, Verzögerung, Selbsthaltung) eines Alarmpunkts
+    ///
+    /// # Parameters
+    ///
+    /// * `level`   - Alarmpunkt
+    pub fn get_alarm_settings(&self, level: AlarmLevel) -> &AlarmSettings {
+        match level {
+            AlarmLevel::AP1 => &self.alarm1_settings,
+            AlarmLevel::AP2 => &self.alarm2_settings,
+            AlarmLevel::DIW => &self.alarm3_settings,
+        }
+    }
+
+    /// Setzt die Einstellungen (Hysterese, Verzögerung, Selbsthaltung) eines Alarmpunkts
+    ///
+    /// # Parameters
+    ///
+    /// * `level`       - Alarmpunkt
+    /// * `settings`    - neue Einstellungen
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Sensor, SensorType};
+    /// use xmz_mod_touch_server::server::zone::kombisensor::sensor::{AlarmLevel, AlarmSettings};
+    ///
+    /// let mut sensor = Sensor::new_with_type(SensorType::SimulationCO);
+    /// sensor.set_alarm_settings(AlarmLevel::DIW, AlarmSettings { hysteresis: 10.0, ..Default::default() });
+    /// assert_eq!(sensor.get_alarm_settings(AlarmLevel::DIW).hysteresis, 10.0);
+    /// assert_eq!(sensor.get_alarm_settings(AlarmLevel::AP1).hysteresis, 0.0);
+    /// ```
+    pub fn set_alarm_settings(&mut self, level: AlarmLevel, settings: AlarmSettings) {
+        match level {
+            AlarmLevel::AP1 => self.alarm1_settings = settings,
+            AlarmLevel::AP2 => self.alarm2_settings = settings,
+            AlarmLevel::DIW => self.alarm3_settings = settings,
+        }
+    }
+
+    /// Liefert die Richtung, in der ein Alarmpunkt auslöst
+    ///
+    /// Ist in den Einstellungen des Alarmpunkts keine Richtung angegeben, gilt die Richtung des Sensor Typs, siehe
+    /// [`SensorType::get_alarm_direction()`](enum.SensorType.html#method.get_alarm_direction).
+    ///
+    /// # Parameters
+    ///
+    /// * `level`   - Alarmpunkt
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Sensor, SensorType};
+    /// use xmz_mod_touch_server::server::zone::kombisensor::sensor::{AlarmDirection, AlarmLevel, AlarmSettings};
+    ///
+    /// let mut sensor = Sensor::new_with_type(SensorType::O2);
+    /// assert_eq!(sensor.get_alarm_direction(AlarmLevel::AP1), AlarmDirection::Falling);
+    ///
+    /// // Zusätzlich Alarm bei Sauerstoffanreicherung
+    /// sensor.alarm3_direct_value = 23.0;
+    /// sensor.set_alarm_settings(AlarmLevel::DIW, AlarmSettings { direction: Some(AlarmDirection::Rising), ..Default::default() });
+    /// assert_eq!(sensor.get_alarm_direction(AlarmLevel::DIW), AlarmDirection::Rising);
+    /// ```
+    pub fn get_alarm_direction(&self, level: AlarmLevel) -> AlarmDirection {
+        match self.get_alarm_settings(level).direction {
+            Some(direction) => direction,
+            None => self.sensor_type.get_alarm_direction(),
+        }
+    }
+
+    /// Liefert den Zustand eines Alarmpunkts
+    ///
+    /// Ausgewertet werden die Alarmpunkte mit [`update_alarms()`](#method.update_alarms).
+    ///
+    /// # Parameters
+    ///
+    /// * `level`   - Alarmpunkt
+    pub fn get_alarm_state(&self, level: AlarmLevel) -> &AlarmState {
+        match level {
+            AlarmLevel::AP1 => &self.alarm1_state,
+            AlarmLevel::AP2 => &self.alarm2_state,
+            AlarmLevel::DIW => &self.alarm3_state,
+        }
+    }
+
+    /// Ist der Alarmpunkt ausgelöst?
+    ///
+    /// Im Gegensatz zu [`alarmpunkt1_reached()`](#method.alarmpunkt1_reached) usw. werden Hysterese, Verzögerung
+    /// und Selbsthaltung berücksichtigt.
+    ///
+    /// # Parameters
+    ///
+    /// * `level`   - Alarmpunkt
+    pub fn is_alarm_active(&self, level: AlarmLevel) -> bool {
+        self.get_alarm_state(level).is_active()
+    }
+
+    /// Wertet die Alarmpunkte aus
+    ///
+    /// Eine deaktivierte Messzelle löst keinen Alarm aus, auch selbsthaltende Alarme werden zurück gesetzt.
+    ///
+    /// # Parameters
+    ///
+    /// * `now`     - Zeitpunkt der Auswertung
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// extern crate chrono;
+    /// # extern crate xmz_mod_touch_server;
+    /// use chrono::{Duration, Utc};
+    /// use xmz_mod_touch_server::{Sensor, SensorType};
+    /// use xmz_mod_touch_server::server::zone::kombisensor::sensor::{AlarmLevel, AlarmSettings};
+    ///
+    /// # fn main() {
+    /// let mut sensor = Sensor::new_with_type(SensorType::SimulationCO);
+    /// sensor.set_alarm_settings(AlarmLevel::DIW, AlarmSettings { off_delay_sec: 60, ..Default::default() });
+    /// let now = Utc::now();
+    ///
+    /// sensor.set_concentration(150.0);
+    /// sensor.update_alarms(now);
+    /// assert!(sensor.is_alarm_active(AlarmLevel::DIW));
+    ///
+    /// // Der Alarm fällt erst nach 60 Sekunden ab
+    /// sensor.set_concentration(0.0);
+    /// sensor.update_alarms(now + Duration::seconds(1));
+    /// assert!(sensor.is_alarm_active(AlarmLevel::DIW));
+    /// sensor.update_alarms(now + Duration::seconds(61));
+    /// assert!(!sensor.is_alarm_active(AlarmLevel::DIW));
+    /// # }
+    /// ```
+    pub fn update_alarms(&mut self, now: DateTime<Utc>) {
+        if !self.is_enabled() {
+            self.alarm1_state = AlarmState::new();
+            self.alarm2_state = AlarmState::new();
+            self.alarm3_state = AlarmState::new();
+            return;
+        }
+
+        // Ein ungültiger 15min Mittelwert zählt über den abgedeckten Zeitraum. Nur ohne Messwerte im Zeitraum ändert
+        // sich der Zustand der Alarme AP1 und AP2 nicht.
+        let average_15min = if self.has_average_15min() {
+            self.get_concentration_average_15min()
+        } else {
+            ::std::f64::NAN
+        };
+        let direct_value = self.get_direct_value();
+
+        let direction1 = self.get_alarm_direction(AlarmLevel::AP1);
+        let direction2 = self.get_alarm_direction(AlarmLevel::AP2);
+        let direction3 = self.get_alarm_direction(AlarmLevel::DIW);
+        self.alarm1_state.update(&self.alarm1_settings, direction1, average_15min, self.alarm1_average_15min, now);
+        self.alarm2_state.update(&self.alarm2_settings, direction2, average_15min, self.alarm2_average_15min, now);
+        self.alarm3_state.update(&self.alarm3_settings, direction3, direct_value, self.alarm3_direct_value, now);
+    }
+
+    /// Quittiert die selbsthaltenden Alarme der Messzelle
+    ///
+    /// # Return values
+    ///
+    /// Liefert `true` wenn mindestens ein Alarm quittiert wurde.
+    pub fn reset_alarms(&mut self) -> bool {
+        let ap1 = self.alarm1_state.reset();
+        let ap2 = self.alarm2_state.reset();
+        let diw = self.alarm3_state.reset();
+
+        ap1 || ap2 || diw
+    }
+
+    /// Übernimmt den gespeicherten Zustand eines selbsthaltenden Alarmpunkts
+    ///
+    /// Wird z.B. beim Start des Servers aus der Zustandsdatei übernommen, siehe
+    /// [`ServerState`](../../../state/struct.ServerState.html). So bleibt ein selbsthaltender Alarm auch nach einem
+    /// Neustart bestehen, bis er quittiert wird. Der Zustand nicht selbsthaltender Alarmpunkte ergibt sich aus den
+    /// Messwerten, er wird nicht übernommen.
+    ///
+    /// # Return values
+    ///
+    /// Liefert `true` wenn der Zustand übernommen wurde.
+    ///
+    /// # Parameters
+    ///
+    /// * `level`   - Alarmpunkt
+    /// * `state`   - gespeicherter Zustand
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// extern crate chrono;
+    /// # extern crate xmz_mod_touch_server;
+    /// use chrono::Utc;
+    /// use xmz_mod_touch_server::{Sensor, SensorType};
+    /// use xmz_mod_touch_server::server::zone::kombisensor::sensor::{AlarmLevel, AlarmSettings};
+    ///
+    /// # fn main() {
+    /// let mut sensor = Sensor::new_with_type(SensorType::SimulationCO);
+    /// sensor.set_alarm_settings(AlarmLevel::DIW, AlarmSettings { latching: true, ..Default::default() });
+    /// sensor.set_concentration(200.0);
+    /// sensor.update_alarms(Utc::now());
+    ///
+    /// // Nach einem Neustart, das Gas ist weg
+    /// let mut restarted = Sensor::new_with_type(SensorType::SimulationCO);
+    /// restarted.set_alarm_settings(AlarmLevel::DIW, AlarmSettings { latching: true, ..Default::default() });
+    /// assert!(restarted.restore_alarm_state(AlarmLevel::DIW, sensor.get_alarm_state(AlarmLevel::DIW).clone()));
+    /// restarted.update_alarms(Utc::now());
+    /// assert!(restarted.is_alarm_active(AlarmLevel::DIW));
+    /// assert!(restarted.reset_alarms());
+    /// # }
+    /// ```
+    pub fn restore_alarm_state(&mut self, level: AlarmLevel, state: AlarmState) -> bool {
+        if !self.get_alarm_settings(level).latching || !state.is_active() { return false; }
+
+        match level {
+            AlarmLevel::AP1 => self.alarm1_state = state,
+            AlarmLevel::AP2 => self.alarm2_state = state,
+            AlarmLevel::DIW => self.alarm3_state = state,
+        }
+        true
     }
 
     /// Direktwert
@@ -506,7 +931,8 @@ impl Sensor {
 
     /// Setzt den Direktwert manuel
     ///
-    /// Diese Funktion setzt die Konzentration des Sensors von Hand auf einen fixen Wert.
+    /// Diese Funktion setzt die Konzentration des Sensors von Hand auf einen fixen Wert. Der Wert gilt, wie ein
+    /// ausgelesener Wert, als jetzt gemessen.
     /// **Diese Funktion sollte nur in Testumgebungen verwendet werden!**
     ///
     /// # Examples
@@ -521,7 +947,8 @@ impl Sensor {
     /// assert_eq!(sensor.get_concentration(), 10.0);
     /// ```
     pub fn set_concentration(&mut self, concentration: f64) {
-        self.adc_value = self.adc_value_from_concentration(concentration);
+        let adc_value = self.adc_value_from_concentration(concentration);
+        self.set_adc_value(adc_value);
         self.update();
     }
 
@@ -585,12 +1012,13 @@ impl Sensor {
     /// assert_eq!(sensor.is_online(), false);
     /// ```
     pub fn is_online(&self) -> bool {
-        self.adc_value > 0 && self.adc_values_average.len() > 0
+        self.adc_value > 0 && !self.adc_values_average.is_empty()
     }
 
-    /// Setzt den ADC Wert manuell von Hand
+    /// Setzt den ADC Wert
     ///
-    /// Diese Funktion sollte nur zu Test oder Simulationszwecken nötig sein
+    /// Wird nach jeder Abfrage des Kombisensors aufgerufen. Der Zeitpunkt des Aufrufs gilt als Zeitpunkt der
+    /// Messung, mit ihm wird der Wert in den 15min Mittelwert übernommen.
     ///
     /// # Examples
     ///
@@ -602,7 +1030,43 @@ impl Sensor {
     /// assert_eq!(sensor_sim_no2_fix.get_concentration(), 30.0);
     /// ```
     pub fn set_adc_value(&mut self, adc_value: u16) {
+        self.set_adc_value_at(adc_value, Utc::now());
+    }
+
+    /// Setzt den ADC Wert, gemessen zum gegebenen Zeitpunkt
+    ///
+    /// Mit der nächsten [`update()`](#method.update) Funktion wird der Wert mit diesem Zeitpunkt in den 15min
+    /// Mittelwert übernommen. Die Zeitpunkte müssen aufsteigend sein, z.B. beim Abspielen aufgezeichneter Werte.
+    ///
+    /// # Parameters
+    ///
+    /// * `adc_value`   - ADC Wert
+    /// * `timestamp`   - Zeitpunkt der Messung
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// extern crate chrono;
+    /// # extern crate xmz_mod_touch_server;
+    /// use chrono::{Duration, Utc};
+    /// use xmz_mod_touch_server::{Sensor, SensorType};
+    ///
+    /// # fn main() {
+    /// let mut sensor = Sensor::new_with_type(SensorType::SimulationNO2Fix);
+    /// let now = Utc::now();
+    ///
+    /// sensor.set_adc_value_at(100, now - Duration::seconds(4));
+    /// sensor.update();
+    /// sensor.set_adc_value_at(300, now - Duration::seconds(2));
+    /// sensor.update();
+    ///
+    /// assert_eq!(sensor.get_adc_value(), 300);
+    /// assert!(sensor.get_adc_value_average_15min() >= 199 && sensor.get_adc_value_average_15min() <= 201);
+    /// # }
+    /// ```
+    pub fn set_adc_value_at(&mut self, adc_value: u16, timestamp: DateTime<Utc>) {
         self.adc_value = adc_value;
+        self.adc_value_timestamp = Some(timestamp);
     }
 
     /// Setzt den minimal Wert der für den Sensor konfiguriert wurde
@@ -734,69 +1198,475 @@ impl Sensor {
         self.config = config;
     }
 
+    /// Aktiviert oder deaktiviert die Messzelle
+    ///
+    /// Setzt bzw. löscht das `enabled` Bit (Bit 0) der Konfiguration. Eine deaktivierte Messzelle verwirft
+    /// ihre bisher gesammelten Werte für den 15min Mittelwert.
+    ///
+    /// **Diese Funktion ändert nur den Wert im Speicher**, in die Firmware wird das Bit mit
+    /// [`Kombisensor::write_sensor_enabled()`](../struct.Kombisensor.html#method.write_sensor_enabled) geschrieben.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Sensor, SensorType};
+    /// let mut sensor = Sensor::new_with_type(SensorType::SimulationNO2Fix);
+    /// sensor.update();
+    /// assert_eq!(sensor.alarmpunkt1_reached(), true);
+    ///
+    /// sensor.set_enabled(false);
+    /// assert_eq!(sensor.is_enabled(), false);
+    /// assert_eq!(sensor.get_adc_value_average_15min(), 0);
+    /// assert_eq!(sensor.alarmpunkt1_reached(), false);
+    ///
+    /// sensor.set_enabled(true);
+    /// assert_eq!(sensor.get_config() & 1, 1);
+    /// ```
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if enabled {
+            self.config |= 1;
+        } else {
+            self.config &= !1;
+            self.clear_adc_values_average();
+        }
+    }
+
+    /// Liefert die Kalibrierkurve der Messzelle, falls konfiguriert
+    pub fn get_calibration_curve(&self) -> Option<&CalibrationCurve> {
+        self.calibration_curve.as_ref()
+    }
+
+    /// Setzt die Kalibrierkurve der Messzelle
+    ///
+    /// Mit `None` wird wieder die Gerade durch Nullgas und Messgas verwendet.
+    ///
+    /// # Parameters
+    ///
+    /// * `calibration_curve`   - Kalibrierkurve oder `None`
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Sensor, SensorType};
+    /// use xmz_mod_touch_server::server::zone::kombisensor::sensor::{CalibrationCurve, CalibrationPoint, Interpolation};
+    ///
+    /// let mut sensor = Sensor::new_with_type(SensorType::SimulationNO2);
+    /// sensor.set_adc_value(708);
+    /// assert!(sensor.get_concentration() > 11.9 && sensor.get_concentration() < 12.0);
+    ///
+    /// sensor.set_calibration_curve(Some(CalibrationCurve::new(vec![
+    ///     CalibrationPoint::new(900, 0.0),
+    ///     CalibrationPoint::new(836, 4.0),
+    ///     CalibrationPoint::new(580, 24.0),
+    /// ], Interpolation::PiecewiseLinear)));
+    /// assert_eq!(sensor.get_concentration(), 14.0);
+    /// ```
+    pub fn set_calibration_curve(&mut self, calibration_curve: Option<CalibrationCurve>) {
+        self.calibration_curve = calibration_curve;
+    }
+
+    /// Liefert die Temperaturkompensation der Messzelle, falls konfiguriert
+    pub fn get_temperature_compensation(&self) -> Option<&TemperatureCompensation> {
+        self.temperature_compensation.as_ref()
+    }
+
+    /// Setzt die Temperaturkompensation der Messzelle
+    ///
+    /// Die Kompensation wird nur angewendet, wenn die Temperatur der Messzelle bekannt ist
+    /// (siehe [`set_temperature()`](#method.set_temperature)).
+    ///
+    /// # Parameters
+    ///
+    /// * `temperature_compensation`    - Temperaturkompensation oder `None`
+    pub fn set_temperature_compensation(&mut self, temperature_compensation: Option<TemperatureCompensation>) {
+        self.temperature_compensation = temperature_compensation;
+    }
+
+    /// Liefert die Temperatur der Messzelle in °C, falls bekannt
+    pub fn get_temperature(&self) -> Option<f64> {
+        self.temperature
+    }
+
+    /// Setzt die Temperatur der Messzelle
+    ///
+    /// # Parameters
+    ///
+    /// * `temperature` - Temperatur in °C oder `None` wenn sie unbekannt ist
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Sensor, SensorType};
+    /// use xmz_mod_touch_server::server::zone::kombisensor::sensor::{TemperatureCompensation, TemperaturePoint};
+    ///
+    /// let mut sensor = Sensor::new_with_type(SensorType::SimulationNO2);
+    /// sensor.set_temperature_compensation(Some(TemperatureCompensation::new(vec![
+    ///     TemperaturePoint::new(-20.0, 0.5),
+    ///     TemperaturePoint::new(20.0, 1.0),
+    /// ])));
+    /// sensor.set_concentration(5.0);
+    /// let concentration = sensor.get_concentration();
+    ///
+    /// // Bei -20°C ist die Messzelle nur halb so empfindlich
+    /// sensor.set_temperature(Some(-20.0));
+    /// assert_eq!(sensor.get_concentration(), concentration * 2.0);
+    /// ```
+    pub fn set_temperature(&mut self, temperature: Option<f64>) {
+        self.temperature = temperature;
+    }
+
     /// Update Funktion des Sensors
     ///
     /// Diese Funktion fast die einzelnen Update Funktionen des Sensors zusammen
     pub fn update(&mut self) {
+        self.update_gas_profile(Utc::now());
         self.update_adc_values_average();
+        self.update_status();
+        self.update_health();
+    }
+
+    /// Liefert den Gasverlauf, den die simulierte Messzelle abspielt
+    pub fn get_gas_profile(&self) -> Option<&GasProfile> {
+        self.gas_profile.as_ref()
     }
 
-    /// Berechnet den Mittelwert
+    /// Setzt den Gasverlauf, den die simulierte Messzelle abspielt
     ///
-    /// Diese Funktion berechnet den Mittelwert aus einer Liste (Tuppel) von ADC Werten und Zeitstempeln.
-    /// Zu Begin der Funkton wird die Liste der ADC Werte/ Zeitstempel mit dem aktuellen ADC Wert/ Zeitstempel aktualisiert.
-    fn update_adc_values_average(&mut self) {
-        // Nur wenn die Messzelle aktiv ist wird der Mittelwert berechnet
+    /// Der Gasverlauf beginnt mit der nächsten [`update()`](#method.update) Funktion. Solange ein Gasverlauf
+    /// gesetzt ist, ersetzt er bei jeder `update()` Funktion den ADC Wert der Messzelle.
+    ///
+    /// # Parameters
+    ///
+    /// * `gas_profile` - Gasverlauf, `None` beendet das Abspielen
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Sensor, SensorType};
+    /// use xmz_mod_touch_server::server::zone::kombisensor::sensor::{GasProfile, GasProfilePoint, Transition};
+    ///
+    /// let mut sensor = Sensor::new_with_type(SensorType::SimulationCO);
+    /// sensor.set_gas_profile(Some(GasProfile::new(vec![
+    ///     GasProfilePoint { time_sec: 0.0, concentration: 200.0, transition: Transition::Step },
+    /// ])));
+    /// sensor.update();
+    /// assert!(sensor.direct_value_reached());
+    /// ```
+    pub fn set_gas_profile(&mut self, gas_profile: Option<GasProfile>) {
+        self.gas_profile = gas_profile;
+        self.gas_profile_start = None;
+    }
+
+    // Spielt den Gasverlauf ab, der Messwert gilt als zum Zeitpunkt `now` gemessen
+    //
+    fn update_gas_profile(&mut self, now: DateTime<Utc>) {
+        let concentration = match self.gas_profile {
+            Some(ref gas_profile) => {
+                let start = *self.gas_profile_start.get_or_insert(now);
+                let elapsed = now.signed_duration_since(start);
+                let elapsed_sec = elapsed.num_milliseconds() as f64 / 1000.0;
+                gas_profile.sample(elapsed_sec)
+            }
+            None => return,
+        };
+
+        let adc_value = self.adc_value_from_concentration(concentration);
+        self.set_adc_value_at(adc_value, now);
+    }
+
+    /// Liefert den Zustand des Messbereichs
+    ///
+    /// Liegt der Direktwert über dem maximal Wert der Messzelle, ist der Messbereich überschritten. Liegt er, mehr
+    /// als die Toleranz für negative Drift, unter dem minimal Wert, ist er unterschritten. Ohne Messbereich (maximal
+    /// Wert 0), ohne ausgelesenen Messwert oder bei einer deaktivierten Messzelle ist der Zustand immer `Normal`.
+    ///
+    /// Steht der ADC Wert am Anschlag (`0` bzw. `0xFFFF`), ist die Messzelle gesättigt und der wahre Wert unbekannt.
+    /// Der Messbereich gilt dann immer als über- bzw. unterschritten, je nachdem auf welcher Seite des Messbereichs
+    /// der Anschlag liegt. Bei den Nemoto Messzellen fällt der ADC Wert mit steigender Konzentration, der Anschlag
+    /// `0` ist dort eine Überschreitung.
+    ///
+    /// **Erst nach der `Sensor::update()` Funktion wird der Zustand aktualisiert.**
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Sensor, SensorStatus, SensorType};
+    ///
+    /// // NO2 Messzelle, Messbereich 0 bis 30ppm
+    /// let mut sensor = Sensor::new_with_type(SensorType::SimulationNO2);
+    /// sensor.set_concentration(25.0);
+    /// assert_eq!(sensor.get_status(), SensorStatus::Normal);
+    ///
+    /// sensor.set_concentration(35.0);
+    /// assert_eq!(sensor.get_status(), SensorStatus::OverRange);
+    ///
+    /// // Geringe negative Drift ist noch kein Fehler
+    /// sensor.set_concentration(-2.0);
+    /// assert_eq!(sensor.get_status(), SensorStatus::Normal);
+    /// sensor.set_concentration(-5.0);
+    /// assert_eq!(sensor.get_status(), SensorStatus::UnderRange);
+    ///
+    /// // Gesättigte Messzelle, der ADC Wert steht am Anschlag
+    /// sensor.set_adc_value(0);
+    /// sensor.update();
+    /// assert_eq!(sensor.get_status(), SensorStatus::OverRange);
+    /// ```
+    pub fn get_status(&self) -> SensorStatus {
+        self.status.clone()
+    }
+
+    // Prüft den Direktwert gegen den Messbereich der Messzelle
+    //
+    fn update_status(&mut self) {
+        self.status = SensorStatus::Normal;
+        if !self.is_enabled() || self.adc_value_timestamp.is_none() || self.max_value <= self.min_value { return; }
+
+        let concentration = self.unclamped_concentration_from_adc_value(self.adc_value);
+        let tolerance = (self.max_value - self.min_value) as f64 * UNDER_RANGE_TOLERANCE_PERCENT / 100.0;
+        if self.adc_value == 0 || self.adc_value == 0xFFFF {
+            // Am Anschlag entscheidet nur die Seite des Messbereichs
+            self.status = if concentration > (self.min_value + self.max_value) as f64 / 2.0 {
+                SensorStatus::OverRange
+            } else {
+                SensorStatus::UnderRange
+            };
+        } else if concentration > self.max_value as f64 {
+            self.status = SensorStatus::OverRange;
+        } else if concentration < self.min_value as f64 - tolerance {
+            self.status = SensorStatus::UnderRange;
+        }
+    }
+
+    /// Liefert den Verlauf von Kalibrierung und Nullpunkt der Messzelle
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Sensor, SensorType};
+    ///
+    /// let mut sensor = Sensor::new_with_type(SensorType::NemotoNO2);
+    /// sensor.set_config(1);
+    /// // Ohne ausgelesenen Messwert wird nichts aufgezeichnet
+    /// sensor.update();
+    /// assert!(sensor.get_health().is_empty());
+    ///
+    /// sensor.set_adc_value(915);
+    /// sensor.update();
+    /// assert_eq!(sensor.get_health().get_calibrations().len(), 1);
+    /// assert_eq!(sensor.get_health().get_baseline_drift(), Some(5));
+    /// ```
+    ///
+    /// Für Messzellen mit fallendem Alarm, z.B. O2, wird kein Nullpunkt aufgezeichnet
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Sensor, SensorType};
+    ///
+    /// let mut sensor = Sensor::new_with_type(SensorType::O2);
+    /// sensor.set_config(1);
+    /// sensor.set_adc_value(930);
+    /// sensor.update();
+    /// assert_eq!(sensor.get_health().get_calibrations().len(), 1);
+    /// assert!(sensor.get_health().get_baseline().is_empty());
+    /// ```
+    pub fn get_health(&self) -> &SensorHealth {
+        &self.health
+    }
+
+    /// Übernimmt einen gespeicherten Verlauf von Kalibrierung und Nullpunkt
+    ///
+    /// Wird z.B. beim Start des Servers aus der Zustandsdatei übernommen, siehe
+    /// [`ServerState`](../../../state/struct.ServerState.html).
+    ///
+    /// # Parameters
+    ///
+    /// * `health`  - gespeicherter Verlauf
+    pub fn restore_health(&mut self, health: SensorHealth) {
+        self.health = health;
+        self.update_health();
+    }
+
+    // Zeichnet Kalibrierwerte und Nullpunkt auf, nur für aus der Firmware gelesene Werte
+    //
+    fn update_health(&mut self) {
         if !self.is_enabled() { return; }
+        let timestamp = match self.adc_value_timestamp {
+            Some(timestamp) => timestamp,
+            None => return,
+        };
 
-        // Update der ADC Werte Liste (adc_value, timestamp)
-        self.adc_values_average.push((self.adc_value, Utc::now()));
-
-        // Die [`position()`](https://doc.rust-lang.org/std/iter/trait.Iterator.html#method.position) Funktion
-        // sucht in einem Iterator (Iterator über die Liste der ADC Werte/ Zeitstempel) nach einem Element und liefert dessen Index Wert.
-        // Dieser Index wird benutzt um die Liste der ADC Werte/ Zeitstempel zu teilen [`split_off()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.split_off)
-        if let Some(index) = self.adc_values_average.iter().position(|&(_, timestamp)| Utc::now().signed_duration_since(timestamp).num_seconds() < AVERAGE_15MIN_SEC ) {
-            // Mit `split_off()` kann man nun den Vector teilen, es bleiben nur noch die (Messerte, Zeitstempel) der letzten AVERAGE_15MIN_SEC übrig.
-            // **Dieser Rest wird nun wieder als adc_values_average übernommen, alle anderen Werte werden verworfen.**
-            //
-            self.adc_values_average = self.adc_values_average.split_off(index);
+        self.health.record_calibration(CalibrationRecord::new(timestamp,
+            self.adc_value_at_nullgas, self.adc_value_at_messgas,
+            self.concentration_at_nullgas, self.concentration_at_messgas));
+        // Bei fallendem Alarm (z.B. O2) liegt der Messwert im Normalbetrieb weit vom Nullgas entfernt, der
+        // Messwert mit dem wenigsten Gas wäre gerade ein Gasereignis. Ein Nullpunkt wird deshalb nicht aufgezeichnet.
+        if self.sensor_type.get_alarm_direction() == AlarmDirection::Rising {
+            self.health.record_zero_reading(self.adc_value, timestamp);
         }
-        // Länge der Liste der ADC Werte/ Zeitstempel ermitteln. Dieser Wert wird für den Mittelwert benötigt
-        let len_adc_values_average = self.adc_values_average.len();
-        debug!("Anzahl adc_values_average: {}", len_adc_values_average);
-        // Die Variable sum_adc_values_average speichert die Summe aller ADC Werte
-        let mut sum_adc_values_average: u64 = 0;
-        // Durchlaufe den Tuppel, verwende aber nur den ADC Wert (`value`), und bilde die Summer aller ADC Werte
-        for &(adc_value, _) in self.adc_values_average.iter(){
-            sum_adc_values_average += adc_value as u64;
+    }
+
+    /// Berechnet den zeitlich gewichteten Mittelwert
+    ///
+    /// Siehe DIN EN 50545-1 (VDE 0400-80):2012-04; Seite 15ff.
+    /// Zu Begin der Funkton wird der Ringpuffer der ADC Werte mit dem aktuellen ADC Wert/ Zeitstempel aktualisiert.
+    ///
+    /// Jeder Messwert wird mit der Zeit gewichtet, in der er gültig war: bis zum nächsten Messwert, höchstens aber
+    /// `AVERAGE_SAMPLE_VALIDITY_MAX_SEC`. Längere Abstände zwischen zwei Messwerten sind Lücken, sie gehen nicht in
+    /// den Mittelwert ein, verringern aber die Abdeckung (`adc_value_average_15min_coverage`) des Zeitraums.
+    /// Der Ringpuffer ([`AverageWindow`](../average_window/struct.AverageWindow.html)) hält dafür laufende Summen,
+    /// die Berechnung kostet unabhängig von der Anzahl der Messwerte konstante Zeit.
+    fn update_adc_values_average(&mut self) {
+        // Nur wenn die Messzelle aktiv ist wird der Mittelwert berechnet
+        // Solange die Konfiguration noch nicht aus der Firmware gelesen wurde, bleibt ein wiederhergestellter
+        // Mittelwert (siehe `restore_average_window()`) erhalten.
+        if !self.is_enabled() {
+            if self.adc_value_timestamp.is_some() {
+                self.clear_adc_values_average();
+            }
+            return;
         }
-        // Die Summe aller ADC Werte wird durch die Anzahl der ADC Werte geteilt um den Mittelwert zu erhalten.
-        // Dieser Mittelwert wird dann im `adc_value_average_15min` Member der `Sensor` Struct gespeichert
-        self.adc_value_average_15min = (sum_adc_values_average / len_adc_values_average as u64) as u16;
+
+        let now = Utc::now();
+
+        // Ein ausgelesener Wert wird nur ein mal, mit dem Zeitpunkt der Messung, übernommen (ältere oder gleich alte
+        // Zeitstempel ignoriert der Ringpuffer). Wurde der Wert nie ausgelesen (Simulation), gilt der aktuelle Wert
+        // als gerade gemessen.
+        let timestamp = self.adc_value_timestamp.unwrap_or(now);
+        self.adc_values_average.add(self.adc_value, timestamp);
+        self.calculate_average_15min(now);
+    }
+
+    // Berechnet Mittelwert und Abdeckung aus dem Ringpuffer
+    //
+    fn calculate_average_15min(&mut self, now: DateTime<Utc>) {
+        self.adc_values_average.expire(now);
+        self.adc_value_average_15min = self.adc_values_average.average(now);
+        self.adc_value_average_15min_coverage = self.adc_values_average.coverage(now);
+    }
+
+    /// Liefert den Ringpuffer des 15min Mittelwerts
+    ///
+    /// Wird z.B. für den Zustand des Servers (siehe [`ServerState`](../../../state/struct.ServerState.html))
+    /// gebraucht, mit dem der Mittelwert einen Neustart übersteht.
+    pub fn get_average_window(&self) -> &AverageWindow {
+        &self.adc_values_average
+    }
+
+    /// Übernimmt einen gespeicherten Ringpuffer des 15min Mittelwerts
+    ///
+    /// Messwerte außerhalb des Zeitraums werden verworfen. Liegt der letzte Messwert vor dem Zeitraum, oder hat der
+    /// Ringpuffer einen anderen Aufbau (z.B. eine beschädigte Zustandsdatei oder eine andere `AVERAGE_BUCKET_SEC`),
+    /// wird nichts übernommen.
+    ///
+    /// # Return values
+    ///
+    /// Liefert `true` wenn der Ringpuffer übernommen wurde.
+    ///
+    /// # Parameters
+    ///
+    /// * `average_window`  - gespeicherter Ringpuffer
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Sensor, SensorType};
+    ///
+    /// let mut sensor = Sensor::new_with_type(SensorType::SimulationNO2Fix);
+    /// sensor.update();
+    ///
+    /// let mut restarted = Sensor::new_with_type(SensorType::NemotoNO2);
+    /// assert!(restarted.restore_average_window(sensor.get_average_window().clone()));
+    /// assert_eq!(restarted.get_adc_value_average_15min(), 564);
+    /// // Bis zum ersten Auslesen der Konfiguration bleibt der Mittelwert erhalten
+    /// restarted.update();
+    /// assert_eq!(restarted.get_adc_value_average_15min(), 564);
+    /// ```
+    pub fn restore_average_window(&mut self, average_window: AverageWindow) -> bool {
+        let now = Utc::now();
+        if !self.adc_values_average.has_same_layout(&average_window) || average_window.is_outdated(now) {
+            return false;
+        }
+
+        self.adc_values_average = average_window;
+        self.calculate_average_15min(now);
+        true
+    }
+
+    /// Verwirft die Werte des 15min Mittelwerts
+    ///
+    /// Z.B. wenn die Messzelle deaktiviert wurde oder nach einer Kalibrierung, damit das Prüfgas nicht in den
+    /// Mittelwert eingeht.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Sensor, SensorType};
+    /// let mut sensor = Sensor::new_with_type(SensorType::SimulationNO2Fix);
+    /// sensor.update();
+    /// assert_eq!(sensor.alarmpunkt1_reached(), true);
+    ///
+    /// sensor.clear_adc_values_average();
+    /// assert_eq!(sensor.get_adc_value_average_15min(), 0);
+    /// assert_eq!(sensor.alarmpunkt1_reached(), false);
+    /// ```
+    pub fn clear_adc_values_average(&mut self) {
+        self.adc_values_average.clear();
+        self.adc_value_average_15min = 0;
+        self.adc_value_average_15min_coverage = 0.0;
     }
 
-    /// Berechnet die Gaskonzentration mit einer linearen Funktion
+    /// Berechnet die Gaskonzentration
     ///
     /// Diese Funktion ist eine Helper Funktion. Sie wird von `get_concentration()` und `get_concentration_average_15min()`
-    /// verwendet.
+    /// verwendet. Ohne Kalibrierkurve wird eine lineare Funktion durch Nullgas und Messgas verwendet. Ist die
+    /// Temperatur bekannt, wird das Ergebnis anschließend temperaturkompensiert.
     ///
     fn concentration_from_adc_value(&self, adc_value: u16) -> f64 {
         if adc_value == 0 { return 0.0 }
 
-        let concentration = (self.concentration_at_messgas as f64 - self.concentration_at_nullgas as f64) /
-            (self.adc_value_at_messgas as f64 - self.adc_value_at_nullgas as f64) *
-            (adc_value as f64 - self.adc_value_at_nullgas as f64) + self.concentration_at_nullgas as f64;
+        let concentration = self.unclamped_concentration_from_adc_value(adc_value);
 
         // Ist die Konzentration kleiner Null, wird Null ausgegeben, ansonnsten die berechnete Konzentration
         if concentration < 0.0 { 0.0 } else { concentration }
     }
 
+    // Konzentration ohne Begrenzung auf Null, negative Werte zeigen eine Unterschreitung des Messbereichs
+    //
+    fn unclamped_concentration_from_adc_value(&self, adc_value: u16) -> f64 {
+        let curve_concentration = self.calibration_curve.as_ref().and_then(|curve| curve.concentration(adc_value));
+        let mut concentration = match curve_concentration {
+            Some(concentration) => concentration,
+            None => self.linear_concentration_from_adc_value(adc_value),
+        };
+        if let (Some(compensation), Some(temperature)) = (self.temperature_compensation.as_ref(), self.temperature) {
+            concentration = compensation.compensate(concentration, temperature);
+        }
+
+        concentration
+    }
+
+    // Gerade durch die beiden Kalibrierpunkte Nullgas und Messgas
+    //
+    fn linear_concentration_from_adc_value(&self, adc_value: u16) -> f64 {
+        (self.concentration_at_messgas as f64 - self.concentration_at_nullgas as f64) /
+            (self.adc_value_at_messgas as f64 - self.adc_value_at_nullgas as f64) *
+            (adc_value as f64 - self.adc_value_at_nullgas as f64) + self.concentration_at_nullgas as f64
+    }
+
     /// Berechnet den ADC Wert aus der übergebenen Konzentration
     ///
     /// Diese Funktion ist nur für Testfälle vorgesehen. Die als Parameter übergebene Konzentration
-    /// wird mit einer linearen Funktion aus den Sensordaten errechnet. Diese Funktion ist das Reziprog zu [`concentration_from`](#method.concentration_from.html)
+    /// wird mit einer linearen Funktion bzw. der Kalibrierkurve aus den Sensordaten errechnet. Diese Funktion ist das Reziprog zu [`concentration_from`](#method.concentration_from.html)
     ///
     fn adc_value_from_concentration(&self, concentration: f64) -> u16 {
+        let concentration = match (self.temperature_compensation.as_ref(), self.temperature) {
+            (Some(compensation), Some(temperature)) => compensation.decompensate(concentration, temperature),
+            _ => concentration,
+        };
+        let curve_adc_value = self.calibration_curve.as_ref().and_then(|curve| curve.adc_value(concentration));
+        if let Some(adc_value) = curve_adc_value {
+            return adc_value;
+        }
+
         let adc_value = (self.adc_value_at_messgas as f64 - self.adc_value_at_nullgas as f64) /
             (self.concentration_at_messgas as f64 - self.concentration_at_nullgas as f64) *
             (concentration - self.concentration_at_nullgas as f64) + self.adc_value_at_nullgas as f64;
@@ -821,6 +1691,49 @@ fn test_adc_value_from_concentration() {
 }
 
 
+impl SensorType {
+    /// Richtung, in der die Alarmpunkte dieses Typs auslösen
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::SensorType;
+    /// use xmz_mod_touch_server::server::zone::kombisensor::sensor::AlarmDirection;
+    ///
+    /// assert_eq!(SensorType::NemotoCO.get_alarm_direction(), AlarmDirection::Rising);
+    /// assert_eq!(SensorType::CH4.get_alarm_direction(), AlarmDirection::Rising);
+    /// assert_eq!(SensorType::O2.get_alarm_direction(), AlarmDirection::Falling);
+    /// ```
+    pub fn get_alarm_direction(&self) -> AlarmDirection {
+        match *self {
+            SensorType::O2 => AlarmDirection::Falling,
+            _ => AlarmDirection::Rising,
+        }
+    }
+
+    /// Liefert `true` für simulierte Messzellen
+    ///
+    /// Nur simulierte Messzellen können einen [`GasProfile`](../gas_profile/struct.GasProfile.html) abspielen.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::SensorType;
+    ///
+    /// assert!(SensorType::SimulationCO.is_simulation());
+    /// assert!(!SensorType::NemotoCO.is_simulation());
+    /// ```
+    pub fn is_simulation(&self) -> bool {
+        match *self {
+            SensorType::SimulationNO2Fix |
+            SensorType::SimulationCOFix |
+            SensorType::SimulationNO2 |
+            SensorType::SimulationCO => true,
+            _ => false,
+        }
+    }
+}
+
 impl fmt::Display for SensorType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -832,6 +1745,9 @@ impl fmt::Display for SensorType {
             SensorType::SimulationCOFix => write!(f, "Simulation CO (Fix)"),
             SensorType::SimulationNO2 => write!(f, "Simulation NO2"),
             SensorType::SimulationCO => write!(f, "Simulation CO"),
+            SensorType::O2 => write!(f, "O2 Messzelle"),
+            SensorType::CH4 => write!(f, "CH4 Messzelle"),
+            SensorType::LPG => write!(f, "LPG Messzelle"),
         }
     }
 }
@@ -847,6 +1763,12 @@ impl fmt::Display for SI {
     }
 }
 
+impl Default for SensorStatus {
+    fn default() -> Self {
+        SensorStatus::Normal
+    }
+}
+
 impl Default for Sensor {
     fn default() -> Self {
         Self::new()
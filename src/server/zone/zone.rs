@@ -2,7 +2,9 @@
 //!
 //! Eine Zone kann `n` Kombisensoren enthalten
 //!
+use ::chrono::Utc;
 use server::zone::kombisensor::Kombisensor;
+use server::zone::kombisensor::sensor::{AlarmLevel, SensorStatus};
 
 
 // TODO: Check if Clone is needed, other structs too
@@ -153,9 +155,61 @@ impl Zone {
     }
 
 
+    /// Quittiert die selbsthaltenden Alarme aller Messzellen der Zone
+    ///
+    /// Der Status der Zone wird sofort neu ausgewertet. Liegt ein Messwert noch über dem Alarmpunkt, bleibt der
+    /// Alarm bestehen.
+    ///
+    /// # Return values
+    ///
+    /// Liefert `true` wenn mindestens ein Alarm quittiert wurde.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use xmz_mod_touch_server::{Kombisensor, Sensor, SensorType, Zone, ZoneStatus};
+    /// use xmz_mod_touch_server::server::zone::kombisensor::sensor::{AlarmLevel, AlarmSettings};
+    ///
+    /// let mut sensor = Sensor::new_with_type(SensorType::SimulationCO);
+    /// sensor.set_alarm_settings(AlarmLevel::DIW, AlarmSettings { latching: true, ..Default::default() });
+    /// let mut kombisensor = Kombisensor::new();
+    /// kombisensor.add_sensor(sensor);
+    /// let mut zone = Zone::new();
+    /// zone.add_kombisensor(kombisensor);
+    ///
+    /// zone.get_kombisensor_mut(0).unwrap().get_sensor_mut(0).unwrap().set_concentration(151.0);
+    /// zone.update();
+    /// zone.get_kombisensor_mut(0).unwrap().get_sensor_mut(0).unwrap().clear_adc_values_average();
+    /// zone.get_kombisensor_mut(0).unwrap().get_sensor_mut(0).unwrap().set_concentration(0.0);
+    /// zone.update();
+    /// assert_eq!(zone.get_status(), ZoneStatus::DIW);
+    ///
+    /// assert!(zone.reset_alarms());
+    /// assert_eq!(zone.get_status(), ZoneStatus::Normal);
+    /// ```
+    pub fn reset_alarms(&mut self) -> bool {
+        let mut reset = false;
+        for kombisensor in self.kombisensors.iter_mut() {
+            for sensor in kombisensor.get_sensors_mut().iter_mut() {
+                if sensor.reset_alarms() { reset = true; }
+            }
+        }
+        self.update();
+
+        reset
+    }
+
     // Update Funktion der Zone
     //
     pub fn update(&mut self) {
+        // Alarmpunkte der Messzellen auswerten (Hysterese, Verzögerung, Selbsthaltung)
+        let now = Utc::now();
+        for kombisensor in self.kombisensors.iter_mut() {
+            for sensor in kombisensor.get_sensors_mut().iter_mut() {
+                sensor.update_alarms(now);
+            }
+        }
+
         // Begin Status Auswertung
 
         // hightes_state wird erhöht wenn ein Sensor ein erhöhten Messwert liefert.
@@ -163,21 +217,22 @@ impl Zone {
         // gesetzt. Ist kein Sensor auffällig, dann ist es einfach ZoneStatus::Normal
         let mut hightes_state = ZoneStatus::Normal;
 
-        // duchlaufe alle Kombisensoren der Zone
+        // duchlaufe alle Kombisensoren der Zone, deaktivierte Messzellen lösen keinen Alarm aus
         for kombisensor in self.get_kombisensors() {
-            match kombisensor.get_sensors().iter().find(|&s| s.alarmpunkt1_reached() == true) {
+            match kombisensor.get_sensors().iter().find(|&s| s.is_alarm_active(AlarmLevel::AP1)) {
                 Some(_) => {
                     if hightes_state < ZoneStatus::AP1 { hightes_state = ZoneStatus::AP1; }
                 }
                 None => {}
             }
-            match kombisensor.get_sensors().iter().find(|&s| s.alarmpunkt2_reached() == true) {
+            match kombisensor.get_sensors().iter().find(|&s| s.is_alarm_active(AlarmLevel::AP2)) {
                 Some(_) => {
                     if hightes_state < ZoneStatus::AP2 { hightes_state = ZoneStatus::AP2; }
                 }
                 None => {}
             }
-            match kombisensor.get_sensors().iter().find(|&s| s.direct_value_reached() == true) {
+            // Ein überschrittener Messbereich zählt mindestens als Direktwert, der wahre Wert ist unbekannt
+            match kombisensor.get_sensors().iter().find(|&s| s.is_alarm_active(AlarmLevel::DIW) || s.get_status() == SensorStatus::OverRange) {
                 Some(_) => {
                     if hightes_state < ZoneStatus::DIW { hightes_state = ZoneStatus::DIW; }
                 }
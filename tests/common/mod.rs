@@ -0,0 +1,56 @@
+//! Gemeinsame Hilfsfunktionen der Integrationstests
+//!
+#![allow(dead_code)]
+
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+/// Eigenes temporäres Verzeichnis eines Tests
+///
+/// Der Name ist eindeutig, auch wenn mehrere Tests oder Test Programme gleichzeitig laufen. Das Verzeichnis wird
+/// samt Inhalt gelöscht, sobald es aus dem Scope geht, auch wenn der Test fehlschlägt.
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// Legt ein neues Verzeichnis im temporären Verzeichnis des Systems an
+    ///
+    /// # Parameters
+    ///
+    /// * `prefix`  - Anfang des Verzeichnisnamens, z.B. der Name des Tests
+    pub fn new(prefix: &str) -> Self {
+        loop {
+            let nanos = match SystemTime::now().duration_since(UNIX_EPOCH) {
+                Ok(duration) => duration.as_secs() * 1_000_000_000 + duration.subsec_nanos() as u64,
+                Err(_) => 0,
+            };
+            let path = env::temp_dir().join(format!("{}_{}", prefix, nanos));
+            // `create_dir` schlägt fehl wenn das Verzeichnis schon existiert, der Name gehört dann einem anderen Test
+            match fs::create_dir(&path) {
+                Ok(_) => return TempDir { path: path },
+                Err(ref e) if e.kind() == ErrorKind::AlreadyExists => continue,
+                Err(e) => panic!("Could not create temp dir {:?}: {}", path, e),
+            }
+        }
+    }
+
+    /// Pfad einer Datei in diesem Verzeichnis
+    ///
+    /// # Parameters
+    ///
+    /// * `name`    - Name der Datei
+    pub fn path(&self, name: &str) -> String {
+        self.path.join(name).to_string_lossy().into_owned()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,72 @@
+extern crate xmz_mod_touch_server;
+
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use xmz_mod_touch_server::{Kombisensor, KombisensorType, Server};
+use xmz_mod_touch_server::modbus_bus::SerialParameters;
+use xmz_mod_touch_server::server::Acquisition;
+
+
+// Startet einen TCP Server, der Verbindungen annimmt aber nie antwortet
+fn start_silent_tcp_slave(port: u16) {
+    let listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+
+    thread::spawn(move || {
+        let mut streams = vec![];
+        for stream in listener.incoming() {
+            streams.push(stream);
+        }
+    });
+}
+
+#[test]
+fn slow_bus_does_not_delay_other_buses() {
+    start_silent_tcp_slave(15040);
+
+    let mut server = Server::new();
+    server.add_zone();
+    let mut slow_kombisensor = Kombisensor::new_with_type(KombisensorType::RAGas);
+    slow_kombisensor.set_modbus_device("tcp://127.0.0.1:15040".to_string());
+    let mut serial_parameters = SerialParameters::new();
+    serial_parameters.set_response_timeout_ms(2000);
+    slow_kombisensor.set_serial_parameters(Some(serial_parameters));
+    server.get_zone_mut(0).unwrap().add_kombisensor(slow_kombisensor);
+    server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+    let server = Arc::new(Mutex::new(server));
+
+    let acquisition = Acquisition::new(server.clone(), Duration::from_millis(50));
+    assert_eq!(acquisition.start_workers().unwrap(), 2);
+    assert_eq!(acquisition.get_worker_devices().unwrap(),
+               vec!["sim:///dev/ttyUSB0".to_string(), "tcp://127.0.0.1:15040".to_string()]);
+
+    thread::sleep(Duration::from_millis(500));
+
+    // Während die langsame Leitung auf die erste Antwort wartet, wird die andere Leitung weiter abgefragt
+    let server = server.lock().unwrap();
+    let slow_statistics = server.get_zone(0).unwrap().get_kombisensor(0).unwrap().get_communication_statistics();
+    let fast_statistics = server.get_zone(0).unwrap().get_kombisensor(1).unwrap().get_communication_statistics();
+    assert_eq!(slow_statistics.get_request_count(), 0);
+    assert!(fast_statistics.get_success_count() >= 3);
+}
+
+#[test]
+fn worker_stops_without_kombisensors() {
+    let mut server = Server::new();
+    server.add_zone();
+    server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+    let server = Arc::new(Mutex::new(server));
+
+    let acquisition = Acquisition::new(server.clone(), Duration::from_millis(20));
+    assert_eq!(acquisition.start_workers().unwrap(), 1);
+
+    server.lock().unwrap().get_zone_mut(0).unwrap().get_kombisensor_mut(0).unwrap().set_modbus_device("/dev/ttyUSB1".to_string());
+    thread::sleep(Duration::from_millis(200));
+
+    assert_eq!(acquisition.get_worker_devices().unwrap().len(), 0);
+
+    // Die neue Leitung bekommt beim nächsten Aufruf einen Worker
+    assert_eq!(acquisition.start_workers().unwrap(), 1);
+    assert_eq!(acquisition.get_worker_devices().unwrap(), vec!["sim:///dev/ttyUSB1".to_string()]);
+}
@@ -0,0 +1,159 @@
+extern crate chrono;
+extern crate xmz_mod_touch_server;
+
+use chrono::{Duration, Utc};
+use std::thread;
+use std::time::Duration as StdDuration;
+use xmz_mod_touch_server::{Exception, ExceptionType, Kombisensor, KombisensorStatus, KombisensorType, ModbusBusManager, Sensor, SensorType, Server};
+use xmz_mod_touch_server::server::{CalibrationSession, CalibrationSettings, CalibrationStep};
+
+#[test]
+fn unstable_gas_fails_after_timeout() {
+    let sensor = Sensor::new_with_type(SensorType::SimulationCO);
+    let now = Utc::now();
+    let mut session = CalibrationSession::new(0, 0, 0, &sensor, CalibrationSettings::default(), now);
+
+    // Der ADC Wert driftet noch
+    for sec in 0..600 {
+        session.add_sample(112 + (sec / 5) as u16, now + Duration::seconds(sec));
+    }
+    assert_eq!(session.get_step(), &CalibrationStep::Nullgas);
+    assert!(session.is_active());
+
+    session.add_sample(232, now + Duration::seconds(601));
+    match *session.get_step() {
+        CalibrationStep::Failed(_) => {}
+        ref step => panic!("unexpected step {:?}", step),
+    }
+    assert!(!session.is_active());
+}
+
+#[test]
+fn implausible_slope_fails() {
+    let sensor = Sensor::new_with_type(SensorType::SimulationCO);
+    let settings = CalibrationSettings { stable_sec: 0, ..Default::default() };
+    let now = Utc::now();
+
+    // Messgas kaum vom Nullgas zu unterscheiden, z.B. leere Prüfgasflasche
+    let mut session = CalibrationSession::new(0, 0, 0, &sensor, settings.clone(), now);
+    session.add_sample(112, now);
+    session.start_messgas(270, now).unwrap();
+    session.add_sample(130, now + Duration::seconds(1));
+    assert!(!session.is_active());
+    assert!(session.apply(&mut sensor.clone()).is_err());
+
+    // Steigung mit falschem Vorzeichen, z.B. Nullgas und Messgas vertauscht
+    let mut session = CalibrationSession::new(0, 0, 0, &sensor, settings.clone(), now);
+    session.add_sample(760, now);
+    session.start_messgas(270, now).unwrap();
+    session.add_sample(112, now + Duration::seconds(1));
+    assert!(!session.is_active());
+
+    // Passende Steigung
+    let mut session = CalibrationSession::new(0, 0, 0, &sensor, settings, now);
+    session.add_sample(110, now);
+    session.start_messgas(270, now).unwrap();
+    session.add_sample(700, now + Duration::seconds(1));
+    assert_eq!(session.get_step(), &CalibrationStep::MessgasCaptured);
+}
+
+#[test]
+fn abort_restores_alarm_outputs() {
+    let mut server = Server::new();
+    server.set_calibration_settings(CalibrationSettings { stable_sec: 0, ..Default::default() });
+    server.add_zone();
+    server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+
+    // Sensor nicht vorhanden
+    assert!(server.start_calibration(0, 0, 2).is_err());
+
+    server.start_calibration(0, 0, 1).unwrap();
+    server.evaluate();
+    server.start_calibration_messgas(270).unwrap();
+    server.get_zone_mut(0).unwrap().get_kombisensor_mut(0).unwrap().get_sensor_mut(1).unwrap().set_concentration(270.0);
+    server.evaluate();
+    server.check().unwrap();
+    assert_eq!(server.get_relais().get(2).unwrap(), false);
+    let exception = Exception::new(ExceptionType::SensorCalibration { num_zone: 0, num_kombisensor: 0, num_sensor: 1 });
+    assert!(server.get_exceptions().lock().unwrap().contains(&exception));
+
+    // Nach dem Abbruch sind die Relais wieder frei, das Prüfgas zählt nicht zum Mittelwert
+    server.abort_calibration().unwrap();
+    assert!(server.abort_calibration().is_err());
+    assert_eq!(server.get_zone(0).unwrap().get_kombisensor(0).unwrap().get_sensor(1).unwrap().get_adc_value_average_15min(), 0);
+    server.evaluate();
+    server.check().unwrap();
+    assert_eq!(server.get_relais().get(2).unwrap(), true);
+    assert!(!server.get_exceptions().lock().unwrap().contains(&exception));
+    assert_eq!(server.get_calibration_session().unwrap().get_step(), &CalibrationStep::Aborted);
+}
+
+#[test]
+fn forgotten_session_releases_alarm_outputs() {
+    let sensor = Sensor::new_with_type(SensorType::SimulationCO);
+    let settings = CalibrationSettings { stable_sec: 0, ..Default::default() };
+    let now = Utc::now();
+
+    // Nullgas erfasst, aber nie Messgas aufgegeben
+    let mut session = CalibrationSession::new(0, 0, 0, &sensor, settings, now);
+    session.add_sample(112, now);
+    assert_eq!(session.get_step(), &CalibrationStep::NullgasCaptured);
+    session.check_timeout(now + Duration::minutes(30));
+    assert!(session.is_active());
+    session.check_timeout(now + Duration::minutes(31));
+    match *session.get_step() {
+        CalibrationStep::Failed(_) => {}
+        ref step => panic!("unexpected step {:?}", step),
+    }
+
+    // Im Server wird die Sperre der Relais aufgehoben
+    let mut server = Server::new();
+    server.set_calibration_settings(CalibrationSettings { stable_sec: 0, session_timeout_sec: 1, ..Default::default() });
+    server.add_zone();
+    server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+    server.start_calibration(0, 0, 1).unwrap();
+    server.evaluate();
+    assert_eq!(server.get_calibration_session().unwrap().get_step(), &CalibrationStep::NullgasCaptured);
+    assert!(server.is_zone_alarm_inhibited(0));
+
+    thread::sleep(StdDuration::from_millis(1100));
+    server.evaluate();
+    assert!(!server.is_zone_alarm_inhibited(0));
+    assert!(!server.get_calibration_session().unwrap().is_active());
+}
+
+#[test]
+fn frozen_or_unreachable_sensor_is_not_captured() {
+    let mut server = Server::new();
+    server.set_calibration_settings(CalibrationSettings { stable_sec: 0, ..Default::default() });
+    server.add_zone();
+    server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+
+    server.start_calibration(0, 0, 1).unwrap();
+    server.get_zone_mut(0).unwrap().get_kombisensor_mut(0).unwrap().get_sensor_mut(1).unwrap().set_adc_value_at(112, Utc::now());
+    server.evaluate();
+    assert_eq!(server.get_calibration_session().unwrap().get_step(), &CalibrationStep::NullgasCaptured);
+
+    // Der ADC Wert wird nicht mehr ausgelesen, der eingefrorene Nullgas Wert ist kein Messgas
+    server.start_calibration_messgas(270).unwrap();
+    server.evaluate();
+    server.evaluate();
+    assert_eq!(server.get_calibration_session().unwrap().get_step(), &CalibrationStep::Messgas);
+    server.abort_calibration().unwrap();
+
+    // Kabelbruch während der Kalibrierung
+    let bus_manager = ModbusBusManager::new();
+    let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGas);
+    kombisensor.set_modbus_device("/dev/nicht_vorhanden".to_string());
+    for _ in 0..5 { kombisensor.update(&bus_manager); }
+    assert_eq!(kombisensor.get_status(), KombisensorStatus::Kabelbruch);
+    server.get_zone_mut(0).unwrap().add_kombisensor(kombisensor);
+
+    server.start_calibration(0, 1, 0).unwrap();
+    server.evaluate();
+    match *server.get_calibration_session().unwrap().get_step() {
+        CalibrationStep::Failed(_) => {}
+        ref step => panic!("unexpected step {:?}", step),
+    }
+    assert!(!server.is_zone_alarm_inhibited(0));
+}
@@ -0,0 +1,60 @@
+extern crate libmodbus_rs;
+extern crate xmz_mod_touch_server;
+
+use libmodbus_rs::{Modbus, ModbusClient, ModbusTCP};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc;
+use std::thread;
+use xmz_mod_touch_server::{GltSlave, Kombisensor, KombisensorType, SensorStatus, Server, ZoneStatus};
+
+
+#[test]
+fn glt_read_input_registers() {
+    let mut server = Server::new();
+    server.add_zone();
+    server.add_zone();
+    server.get_zone_mut(1).unwrap().set_status(ZoneStatus::AP2);
+    server.get_zone_mut(1).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+    server.get_zone_mut(1).unwrap().get_kombisensor_mut(0).unwrap().get_sensor_mut(1).unwrap().set_concentration(42.0);
+    // Die Konzentration wird über den ADC Wert gerechnet, und ist deshalb nicht genau 42.0
+    let concentration = server.get_zone(1).unwrap().get_kombisensor(0).unwrap().get_sensor(1).unwrap().get_concentration();
+    let server = Arc::new(Mutex::new(server));
+
+    let server_clone = server.clone();
+    let (ready, ready_rx) = mpsc::channel();
+    thread::spawn(move || {
+        GltSlave::new("127.0.0.1:15030").run_with_ready_signal(server_clone, ready).unwrap();
+    });
+    ready_rx.recv().unwrap();
+
+    let modbus = Modbus::new_tcp("127.0.0.1", 15030).unwrap();
+    modbus.connect().unwrap();
+
+    let mut header = vec![0u16; 12];
+    modbus.read_input_registers(0, 12, &mut header).unwrap();
+    assert_eq!(header[0], 1);   // Layout Version
+    assert_eq!(header[1], 2);   // Anzahl Zonen
+    assert_eq!(header[10], 0);  // Zone 0 Normal
+    assert_eq!(header[11], 2);  // Zone 1 AP2
+
+    // Zone 1, Kombisensor 0, Sensor 1: Block (1 * 32 + 0) * 4 + 1 = 129
+    let mut sensor = vec![0u16; 4];
+    modbus.read_input_registers(100 + 129 * 4, 4, &mut sensor).unwrap();
+    assert_eq!(sensor[0], (concentration * 10.0).round() as u16);
+    assert_eq!(sensor[2], 0);   // keine Störung, Messzelle aktiviert
+}
+
+#[test]
+fn glt_flags_sensor_out_of_range() {
+    let mut server = Server::new();
+    server.add_zone();
+    server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+    // NO2 Messzelle, Messbereich 0 bis 30ppm
+    server.get_zone_mut(0).unwrap().get_kombisensor_mut(0).unwrap().get_sensor_mut(0).unwrap().set_concentration(40.0);
+    assert_eq!(server.get_zone(0).unwrap().get_kombisensor(0).unwrap().get_sensor(0).unwrap().get_status(), SensorStatus::OverRange);
+
+    let registers = GltSlave::input_registers(&server);
+    // Zone 0, Kombisensor 0: Sensor 0 im Block 0, Sensor 1 im Block 1
+    assert_eq!(registers[100 + 2], 1 << 2);
+    assert_eq!(registers[100 + 4 + 2], 0);
+}
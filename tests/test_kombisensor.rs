@@ -1,6 +1,10 @@
+extern crate serde_json;
 extern crate xmz_mod_touch_server;
 
-use xmz_mod_touch_server::Kombisensor;
+use serde_json::Value;
+use std::time::Duration;
+use xmz_mod_touch_server::{Kombisensor, KombisensorType, ModbusBusManager};
+use xmz_mod_touch_server::modbus_bus::{PollResult, RtsMode};
 
 #[test]
 fn basic() {
@@ -8,3 +12,68 @@ fn basic() {
 
     assert_eq!(kombisensor.get_sensors().len(), 0);
 }
+
+#[test]
+fn partial_serial_parameters_keep_type_defaults() {
+    let mut config = serde_json::to_value(&Kombisensor::new_with_type(KombisensorType::RAGas)).unwrap();
+    if let Value::Object(ref mut map) = config {
+        let mut serial_parameters = serde_json::Map::new();
+        serial_parameters.insert("baud".to_string(), Value::from(19200));
+        map.insert("serial_parameters".to_string(), Value::Object(serial_parameters));
+    }
+    let kombisensor: Kombisensor = serde_json::from_value(config).unwrap();
+
+    let serial_parameters = kombisensor.get_serial_parameters();
+    assert_eq!(serial_parameters.get_baud(), 19200);
+    assert_eq!(serial_parameters.get_rts_mode(), RtsMode::Down);
+    assert_eq!(serial_parameters.get_parity(), 'N');
+}
+
+#[test]
+fn unparsable_register_set_counts_as_failure() {
+    let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGasSimulation);
+
+    kombisensor.apply_poll_result(PollResult::new(Ok(vec![0, 14]), Duration::from_millis(5)));
+
+    let statistics = kombisensor.get_communication_statistics();
+    assert_eq!(statistics.get_success_count(), 0);
+    assert_eq!(statistics.get_failure_count(), 1);
+    assert_eq!(statistics.get_other_error_count(), 1);
+    assert_eq!(statistics.get_latencies_ms().len(), 0);
+    assert_eq!(kombisensor.get_error_count(), 1);
+}
+
+#[test]
+fn latencies_are_not_serialized() {
+    let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGasSimulation);
+    let poll_request = kombisensor.get_poll_request().unwrap();
+    kombisensor.apply_poll_result(poll_request.execute(&ModbusBusManager::new()));
+    assert_eq!(kombisensor.get_communication_statistics().get_latencies_ms().len(), 1);
+
+    let config = serde_json::to_value(&kombisensor).unwrap();
+    assert!(config["communication_statistics"].get("latencies_ms").is_none());
+
+    let restored: Kombisensor = serde_json::from_value(config).unwrap();
+    assert_eq!(restored.get_communication_statistics().get_success_count(), 1);
+    assert_eq!(restored.get_communication_statistics().get_latencies_ms().len(), 0);
+}
+
+#[test]
+fn layout_change_is_not_counted_as_failure() {
+    let bus_manager = ModbusBusManager::new();
+    let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGasSimulation);
+    kombisensor.update(&bus_manager);
+    assert_eq!(kombisensor.get_firmware_version(), "0.14.0".to_string());
+
+    // Die neue Firmware meldet sich, gelesen wurde aber nur mit dem alten Layout
+    kombisensor.apply_poll_result(PollResult::new(Ok(vec![0, 15, 0, 247]), Duration::from_millis(5)));
+
+    assert_eq!(kombisensor.get_firmware_version(), "0.15.0".to_string());
+    assert_eq!(kombisensor.get_error_count(), 0);
+    assert_eq!(kombisensor.get_communication_statistics().get_failure_count(), 0);
+
+    // Meldet auch die nächste Antwort keine vollständigen Daten, dann zählt sie als Fehler
+    kombisensor.apply_poll_result(PollResult::new(Ok(vec![0, 15, 0, 247]), Duration::from_millis(5)));
+    assert_eq!(kombisensor.get_error_count(), 1);
+    assert_eq!(kombisensor.get_communication_statistics().get_failure_count(), 1);
+}
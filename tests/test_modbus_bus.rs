@@ -0,0 +1,137 @@
+extern crate libmodbus_rs;
+extern crate xmz_mod_touch_server;
+
+use libmodbus_rs::{Modbus, ModbusMapping, ModbusServer, ModbusTCP, MODBUS_TCP_MAX_ADU_LENGTH};
+use std::sync::mpsc;
+use std::thread;
+use xmz_mod_touch_server::{Kombisensor, KombisensorType, ModbusBus, ModbusBusManager};
+use xmz_mod_touch_server::modbus_bus::SerialParameters;
+
+// Function Codes der Modbus Anfragen
+const MODBUS_FC_READ_HOLDING_REGISTERS: u8 = 0x03;
+const MODBUS_FC_WRITE_MULTIPLE_REGISTERS: u8 = 0x10;
+
+// Register Abbild eines CO-NO2-Kombisensor-Mod, Firmware 0.14.0, Modbus Adresse 247
+const KOMBISENSOR_REGISTERS: &[u16] = &[0, 14, 0, 247, 0, 0, 0, 0, 0, 0,
+                                        1, 923, 0, 30, 920, 564, 0, 20, 1, 0,
+                                        2, 107, 0, 300, 112, 760, 0, 270, 1, 0];
+
+// Startet einen lokalen Modbus TCP Slave, der genau eine Verbindung annimmt
+//
+// Über den Receiver werden die Function Codes aller empfangenen Anfragen gemeldet.
+fn start_tcp_slave(port: u32) -> mpsc::Receiver<u8> {
+    let (tx, rx) = mpsc::channel();
+    let (function_code_tx, function_code_rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut modbus = Modbus::new_tcp("127.0.0.1", port).unwrap();
+        let mut socket = modbus.tcp_listen(1).unwrap();
+        tx.send(()).unwrap();
+        modbus.tcp_accept(&mut socket).unwrap();
+
+        let modbus_mapping = ModbusMapping::new(0, 0, KOMBISENSOR_REGISTERS.len() as i32, 0).unwrap();
+        modbus_mapping.get_registers_mut().copy_from_slice(KOMBISENSOR_REGISTERS);
+
+        let mut query = vec![0u8; MODBUS_TCP_MAX_ADU_LENGTH as usize];
+        while let Ok(len) = modbus.receive(&mut query) {
+            // MBAP Header (7 Bytes), danach der Function Code
+            let _ = function_code_tx.send(query[7]);
+            modbus.reply(&query, len, &modbus_mapping).unwrap();
+        }
+    });
+
+    rx.recv().unwrap();
+    function_code_rx
+}
+
+#[test]
+fn read_registers_via_tcp() {
+    start_tcp_slave(15020);
+    let mut bus = ModbusBus::new("tcp://127.0.0.1:15020", SerialParameters::new());
+
+    assert_eq!(bus.read_registers(247, 0, 4, false).unwrap(), vec![0, 14, 0, 247]);
+    assert!(bus.is_connected());
+
+    // Die Verbindung bleibt offen
+    bus.write_registers(247, 14, &[900, 550], false).unwrap();
+    assert_eq!(bus.read_registers(247, 14, 2, false).unwrap(), vec![900, 550]);
+    assert_eq!(bus.get_connect_count(), 1);
+}
+
+#[test]
+fn kombisensor_update_via_tcp() {
+    start_tcp_slave(15021);
+    let bus_manager = ModbusBusManager::new();
+    let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGas);
+    kombisensor.set_modbus_device("tcp://127.0.0.1:15021".to_string());
+
+    kombisensor.update(&bus_manager);
+
+    assert_eq!(kombisensor.get_error_count(), 0);
+    assert_eq!(kombisensor.get_firmware_version(), "0.14.0".to_string());
+    assert_eq!(kombisensor.get_sensor(0).unwrap().get_adc_value(), 923);
+
+    let statistics = kombisensor.get_communication_statistics();
+    assert_eq!(statistics.get_success_count(), 1);
+    assert_eq!(statistics.get_failure_count(), 0);
+    assert_eq!(statistics.get_latencies_ms().len(), 1);
+}
+
+#[test]
+fn scan_via_tcp() {
+    start_tcp_slave(15022);
+    let mut bus = ModbusBus::new("tcp://127.0.0.1:15022", SerialParameters::new());
+
+    // Ein Modbus TCP Slave antwortet unter jeder Adresse
+    let discovered = bus.scan(245, 247, false).unwrap();
+
+    assert_eq!(discovered.len(), 3);
+    assert_eq!(discovered[2].get_modbus_address(), 247);
+    assert_eq!(discovered[2].get_firmware_version(), "0.14.0".to_string());
+}
+
+#[test]
+fn scan_includes_last_address_255() {
+    start_tcp_slave(15025);
+    let mut bus = ModbusBus::new("tcp://127.0.0.1:15025", SerialParameters::new());
+
+    // Modbus TCP erlaubt neben 1..247 nur die Adresse 255
+    let discovered = bus.scan(255, 255, false).unwrap();
+
+    assert_eq!(discovered.len(), 1);
+    assert_eq!(discovered[0].get_modbus_address(), 255);
+}
+
+#[test]
+fn kombisensor_write_sensor_calibration_via_tcp() {
+    let function_codes = start_tcp_slave(15023);
+    let bus_manager = ModbusBusManager::new();
+    let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGas);
+    kombisensor.set_modbus_device("tcp://127.0.0.1:15023".to_string());
+    kombisensor.update(&bus_manager);
+    assert_eq!(function_codes.try_iter().collect::<Vec<u8>>(), vec![MODBUS_FC_READ_HOLDING_REGISTERS]);
+
+    kombisensor.get_sensor_mut(1).unwrap().set_adc_value_at_messgas(800);
+    kombisensor.write_sensor_calibration(&bus_manager, 1).unwrap();
+
+    // Ein Schreibzugriff für alle Kalibrierwerte, danach das Zurücklesen
+    assert_eq!(function_codes.try_iter().collect::<Vec<u8>>(),
+               vec![MODBUS_FC_WRITE_MULTIPLE_REGISTERS, MODBUS_FC_READ_HOLDING_REGISTERS]);
+
+    kombisensor.update(&bus_manager);
+    assert_eq!(kombisensor.get_sensor(1).unwrap().get_adc_value_at_messgas(), 800);
+}
+
+#[test]
+fn kombisensor_write_modbus_address_via_tcp() {
+    start_tcp_slave(15024);
+    let bus_manager = ModbusBusManager::new();
+    let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGas);
+    kombisensor.set_modbus_device("tcp://127.0.0.1:15024".to_string());
+
+    kombisensor.write_modbus_address(&bus_manager, 12).unwrap();
+
+    assert_eq!(kombisensor.get_modbus_address(), 12);
+    let bus = bus_manager.get_bus("tcp://127.0.0.1:15024", SerialParameters::new()).unwrap();
+    assert_eq!(bus.lock().unwrap().read_registers(12, 3, 1, false).unwrap(), vec![12]);
+}
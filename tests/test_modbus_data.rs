@@ -0,0 +1,89 @@
+extern crate xmz_mod_touch_server;
+
+use xmz_mod_touch_server::{ModbusData, RegisterMap};
+use xmz_mod_touch_server::modbus_data::HEADER_NUM_REGISTERS;
+
+// Lage der Kalibrierwerte innerhalb des Registerblocks einer Messzelle
+fn calibration_registers(register_map: &RegisterMap) -> Vec<u16> {
+    let sensor_registers = register_map.get_sensor_registers();
+    let mut registers = vec![
+        sensor_registers.adc_value_at_nullgas(),
+        sensor_registers.adc_value_at_messgas(),
+        sensor_registers.concentration_at_nullgas(),
+        sensor_registers.concentration_at_messgas(),
+    ];
+    registers.sort();
+    registers
+}
+
+#[test]
+fn register_maps_sorted_by_firmware_version() {
+    let register_maps = RegisterMap::get_register_maps();
+    assert!(register_maps.len() > 0);
+
+    for register_map in register_maps {
+        let version: Vec<u16> = register_map.get_firmware_version().split('.').map(|part| part.parse().unwrap()).collect();
+        let found = RegisterMap::for_firmware_version(version[0], version[1], version[2]);
+        assert_eq!(found, register_map);
+        assert_eq!(RegisterMap::for_firmware_version_str(&register_map.get_firmware_version()), register_map);
+    }
+}
+
+#[test]
+fn sensor_blocks_inside_read_registers() {
+    for register_map in RegisterMap::get_register_maps() {
+        let sensor_registers = register_map.get_sensor_registers();
+        let last_register = *calibration_registers(register_map).iter()
+            .chain([sensor_registers.num(), sensor_registers.adc_value(), sensor_registers.config(),
+                    sensor_registers.min_value(), sensor_registers.max_value()].iter())
+            .max().unwrap();
+
+        for sensor_id in 0..register_map.get_num_sensors() as usize {
+            let address = register_map.get_sensor_address(sensor_id).unwrap();
+            assert!(address >= HEADER_NUM_REGISTERS, "Firmware {}", register_map.get_firmware_version());
+            assert!(address + last_register < register_map.get_num_registers(), "Firmware {}", register_map.get_firmware_version());
+        }
+        assert_eq!(register_map.get_sensor_address(register_map.get_num_sensors() as usize), None);
+    }
+}
+
+#[test]
+fn calibration_registers_contiguous() {
+    // Die Kalibrierwerte werden mit einem einzigen Schreibzugriff übertragen
+    for register_map in RegisterMap::get_register_maps() {
+        let registers = calibration_registers(register_map);
+        for (i, register) in registers.iter().enumerate() {
+            assert_eq!(*register, registers[0] + i as u16, "Firmware {}", register_map.get_firmware_version());
+        }
+    }
+}
+
+#[test]
+fn parse_every_register_map() {
+    for register_map in RegisterMap::get_register_maps() {
+        let version: Vec<u16> = register_map.get_firmware_version().split('.').map(|part| part.parse().unwrap()).collect();
+        let mut registers = vec![0u16; register_map.get_num_registers() as usize];
+        registers[0..4].copy_from_slice(&[version[0], version[1], version[2], 247]);
+
+        let sensor_registers = register_map.get_sensor_registers();
+        for sensor_id in 0..register_map.get_num_sensors() as usize {
+            let address = register_map.get_sensor_address(sensor_id).unwrap();
+            registers[(address + sensor_registers.adc_value()) as usize] = 100 + sensor_id as u16;
+            registers[(address + sensor_registers.adc_value_at_messgas()) as usize] = 500 + sensor_id as u16;
+            registers[(address + sensor_registers.config()) as usize] = 1;
+        }
+
+        let modbus_data = ModbusData::parse(&registers).unwrap();
+        assert_eq!(modbus_data.get_register_map(), register_map);
+        assert_eq!(modbus_data.get_modbus_address(), 247);
+        assert_eq!(modbus_data.get_sensors().len(), register_map.get_num_sensors() as usize);
+        for (sensor_id, sensor_data) in modbus_data.get_sensors().iter().enumerate() {
+            assert_eq!(sensor_data.get_adc_value(), 100 + sensor_id as u16);
+            assert_eq!(sensor_data.get_adc_value_at_messgas(), 500 + sensor_id as u16);
+            assert_eq!(sensor_data.get_config(), 1);
+        }
+
+        // Ein Register zu wenig
+        assert!(ModbusData::parse(&registers[..registers.len() - 1]).is_err());
+    }
+}
@@ -1,6 +1,10 @@
+extern crate chrono;
+extern crate serde_json;
 extern crate xmz_mod_touch_server;
 
+use chrono::{Duration, Utc};
 use xmz_mod_touch_server::{Sensor, SensorType};
+use xmz_mod_touch_server::server::zone::kombisensor::sensor::{AlarmLevel, AlarmSettings, Interpolation};
 
 #[test]
 fn basic() {
@@ -8,3 +12,160 @@ fn basic() {
 
     assert_eq!(sensor.get_concentration(), 20.0);
 }
+
+#[test]
+fn average_15min_is_time_weighted() {
+    let mut sensor = Sensor::new_with_type(SensorType::SimulationNO2Fix);
+    let now = Utc::now();
+
+    // 10 Minuten lang alle 5 Sekunden 100, danach 5 Minuten lang jede Sekunde 400
+    for sec in (0..120).map(|i| 900 - i * 5) {
+        sensor.set_adc_value_at(100, now - Duration::seconds(sec));
+        sensor.update();
+    }
+    for sec in (1..301).rev() {
+        sensor.set_adc_value_at(400, now - Duration::seconds(sec));
+        sensor.update();
+    }
+    sensor.set_adc_value(400);
+    sensor.update();
+
+    // Das arithmetische Mittel der Messwerte wäre 314
+    let average = sensor.get_adc_value_average_15min();
+    assert!(average >= 199 && average <= 201, "average {}", average);
+    assert!(sensor.is_average_15min_valid());
+}
+
+#[test]
+fn average_15min_invalid_after_gap() {
+    let mut sensor = Sensor::new_with_type(SensorType::SimulationNO2Fix);
+    let now = Utc::now();
+
+    // Kurze Lücke von einer Minute, der Mittelwert bleibt gültig
+    for sec in (0..168).map(|i| 900 - i * 5) {
+        sensor.set_adc_value_at(564, now - Duration::seconds(sec));
+        sensor.update();
+    }
+    sensor.set_adc_value(564);
+    sensor.update();
+    assert!(sensor.is_average_15min_valid());
+    assert_eq!(sensor.get_adc_value_average_15min(), 564);
+    assert!(sensor.alarmpunkt1_reached());
+
+    // Lücke von 5 Minuten (z.B. Kabelbruch), der Mittelwert ist ungültig
+    let mut sensor = Sensor::new_with_type(SensorType::SimulationNO2Fix);
+    for sec in (0..120).map(|i| 900 - i * 5) {
+        sensor.set_adc_value_at(564, now - Duration::seconds(sec));
+        sensor.update();
+    }
+    sensor.set_adc_value(564);
+    sensor.update();
+    assert!(sensor.get_adc_value_average_15min_coverage() < 0.8);
+    assert!(!sensor.is_average_15min_valid());
+    // Die Alarmpunkte werden über den abgedeckten Zeitraum ausgewertet, ein echter Alarm wird nicht verdeckt
+    assert_eq!(sensor.get_adc_value_average_15min(), 564);
+    assert!(sensor.alarmpunkt1_reached());
+    assert!(sensor.alarmpunkt2_reached());
+    assert!(sensor.direct_value_reached());
+}
+
+#[test]
+fn average_15min_forgets_samples_outside_window() {
+    let mut sensor = Sensor::new_with_type(SensorType::SimulationNO2Fix);
+    let now = Utc::now();
+
+    // 30 Minuten lang alle 100ms 100, danach 15 Minuten lang 200
+    for ms in (1..27001).rev().map(|i| i as i64 * 100) {
+        let adc_value = if ms > 900_000 { 100 } else { 200 };
+        sensor.set_adc_value_at(adc_value, now - Duration::milliseconds(ms));
+        sensor.update();
+    }
+
+    let average = sensor.get_adc_value_average_15min();
+    assert!(average >= 199 && average <= 200, "average {}", average);
+    assert!(sensor.is_average_15min_valid());
+}
+
+#[test]
+fn calibration_curve_from_config() {
+    // Kalibrierkurve und Temperaturkompensation, wie sie in der Konfigurationsdatei stehen
+    let mut config = serde_json::to_value(&Sensor::new_with_type(SensorType::SimulationNO2)).unwrap();
+    config["calibration_curve"] = serde_json::from_str(r#"{
+        "points": [
+            { "adc_value": 920, "concentration": 0.0 },
+            { "adc_value": 860, "concentration": 2.0 },
+            { "adc_value": 760, "concentration": 6.0 },
+            { "adc_value": 564, "concentration": 20.0 }
+        ],
+        "interpolation": { "Polynomial": 3 }
+    }"#).unwrap();
+    config["temperature_compensation"] = serde_json::from_str(r#"{
+        "points": [
+            { "temperature": -20.0, "sensitivity": 0.5 },
+            { "temperature": 20.0, "sensitivity": 1.0 }
+        ]
+    }"#).unwrap();
+    let mut sensor: Sensor = serde_json::from_value(config).unwrap();
+    assert_eq!(sensor.get_calibration_curve().unwrap().get_interpolation(), Interpolation::Polynomial(3));
+    assert_eq!(sensor.get_temperature(), None);
+
+    // Das Polynom läuft durch alle vier Punkte
+    sensor.set_adc_value(760);
+    assert!((sensor.get_concentration() - 6.0).abs() < 1e-6);
+
+    // Die Gerade durch Nullgas und Messgas liefert fast 9ppm
+    sensor.set_calibration_curve(None);
+    assert!(sensor.get_concentration() > 8.9);
+
+    // Ohne Temperatur wird nicht kompensiert
+    sensor.set_adc_value(860);
+    let concentration = sensor.get_concentration();
+    sensor.set_temperature(Some(-20.0));
+    assert_eq!(sensor.get_concentration(), concentration * 2.0);
+}
+
+#[test]
+fn alarm_state_held_while_average_invalid() {
+    let mut sensor = Sensor::new_with_type(SensorType::SimulationNO2Fix);
+    sensor.set_alarm_settings(AlarmLevel::AP1, AlarmSettings { latching: true, ..Default::default() });
+    let now = Utc::now();
+
+    for sec in (0..181).map(|i| 900 - i * 5) {
+        sensor.set_adc_value_at(564, now - Duration::seconds(sec));
+        sensor.update();
+    }
+    sensor.update_alarms(now);
+    assert!(sensor.is_alarm_active(AlarmLevel::AP1));
+
+    // Keine Messwerte mehr (z.B. Kabelbruch), der Mittelwert ist ungültig
+    sensor.clear_adc_values_average();
+    assert!(!sensor.is_average_15min_valid());
+    sensor.update_alarms(now + Duration::seconds(1));
+    assert!(sensor.is_alarm_active(AlarmLevel::AP1));
+    assert!(!sensor.reset_alarms());
+}
+
+#[test]
+fn broken_average_window_is_not_restored() {
+    let mut sensor = Sensor::new_with_type(SensorType::SimulationNO2Fix);
+    sensor.update();
+    let saved = serde_json::to_value(sensor.get_average_window()).unwrap();
+
+    // Beschädigte Zustandsdatei bzw. andere Länge der Buckets
+    let broken_fields = vec![
+        ("bucket_ms", serde_json::Value::from(0)),
+        ("buckets", serde_json::Value::Array(vec![])),
+        ("bucket_ms", serde_json::Value::from(5000)),
+    ];
+    for &(field, ref value) in &broken_fields {
+        let mut broken = saved.clone();
+        broken[field] = value.clone();
+        let mut restarted = Sensor::new_with_type(SensorType::NemotoNO2);
+        assert!(!restarted.restore_average_window(serde_json::from_value(broken).unwrap()), "{}", field);
+        restarted.update();
+        assert_eq!(restarted.get_adc_value_average_15min(), 0);
+    }
+
+    let mut restarted = Sensor::new_with_type(SensorType::NemotoNO2);
+    assert!(restarted.restore_average_window(serde_json::from_value(saved).unwrap()));
+}
@@ -1,6 +1,14 @@
+extern crate chrono;
 extern crate xmz_mod_touch_server;
 
-use xmz_mod_touch_server::Server;
+mod common;
+
+use chrono::{Duration, Utc};
+use common::TempDir;
+use std::fs::File;
+use std::io::Write;
+use xmz_mod_touch_server::{Exception, ExceptionType, Kombisensor, KombisensorType, Server, ServerType, ZoneStatus};
+use xmz_mod_touch_server::server::zone::kombisensor::sensor::{AlarmLevel, AlarmSettings};
 
 /// 256 u16 values
 pub const SIMULATION_DATA_STATIC: &[u16] = &[0, 14, 0, 247, 0, 0, 0, 0, 0, 0, 1, 923, 0, 30, 920, 564, 0, 20, 1, 0, 2, 107, 0, 300, 112, 760, 0, 270, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
@@ -45,3 +53,250 @@ fn basic() {
 
     assert_eq!(server.get_zones().len(), 0);
 }
+
+// Server mit einem simulierten Kombisensor und der gegebenen Zustandsdatei
+fn server_with_state_file(state_file: &str) -> Server {
+    let mut server = Server::new();
+    server.set_state_file(Some(state_file.to_string()));
+    server.add_zone();
+    server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+    server
+}
+
+#[test]
+fn average_15min_survives_restart() {
+    let temp_dir = TempDir::new("xmz_test_server_state");
+    let state_file = temp_dir.path("state.json");
+
+    let mut server = server_with_state_file(&state_file);
+    // NO2 über AP1 (3ppm), aber unter dem Direktwert (15ppm)
+    server.get_zone_mut(0).unwrap().get_kombisensor_mut(0).unwrap().get_sensor_mut(0).unwrap().set_concentration(5.0);
+    server.evaluate();
+    server.evaluate();
+    assert_eq!(server.get_zone(0).unwrap().get_status(), ZoneStatus::AP1);
+    server.save_state().unwrap();
+
+    // Nach dem Neustart liefert die Messzelle wieder 0ppm, der Mittelwert liegt aber noch über AP1
+    let mut restarted = server_with_state_file(&state_file);
+    assert_eq!(restarted.restore_state().unwrap(), 2);
+    restarted.evaluate();
+    restarted.evaluate();
+    assert_eq!(restarted.get_zone(0).unwrap().get_status(), ZoneStatus::AP1);
+
+    // Ohne Zustandsdatei beginnt der Mittelwert neu
+    let mut fresh = server_with_state_file(&state_file);
+    fresh.set_state_file(None);
+    assert_eq!(fresh.restore_state().unwrap(), 0);
+    fresh.evaluate();
+    fresh.evaluate();
+    assert_eq!(fresh.get_zone(0).unwrap().get_status(), ZoneStatus::Normal);
+}
+
+#[test]
+fn sensor_replace_soon_after_calibration() {
+    let temp_dir = TempDir::new("xmz_test_server_health");
+    let state_file = temp_dir.path("state.json");
+    let replace_exception = Exception::new(ExceptionType::SensorReplaceSoon { num_zone: 0, num_kombisensor: 0, num_sensor: 0 });
+
+    let mut server = server_with_state_file(&state_file);
+    server.update();
+    server.check().unwrap();
+    assert_eq!(server.get_zone(0).unwrap().get_kombisensor(0).unwrap().get_sensor(0).unwrap().get_health().get_calibrations().len(), 1);
+    assert!(!server.get_exceptions().lock().unwrap().contains(&replace_exception));
+
+    // Erste Kalibrierung, bezogen auf sie wird die Empfindlichkeit berechnet
+    let adc_value_at_messgas = server.get_zone(0).unwrap().get_kombisensor(0).unwrap().get_sensor(0).unwrap().get_adc_value_at_messgas();
+    server.get_zone_mut(0).unwrap().get_kombisensor_mut(0).unwrap().get_sensor_mut(0).unwrap().set_adc_value_at_messgas(adc_value_at_messgas - 1);
+    server.write_sensor_calibration(0, 0, 0).unwrap();
+    server.update();
+    server.check().unwrap();
+    assert_eq!(server.get_zone(0).unwrap().get_kombisensor(0).unwrap().get_sensor(0).unwrap().get_health().get_calibrations().len(), 2);
+    assert!(!server.get_exceptions().lock().unwrap().contains(&replace_exception));
+
+    // Neue Kalibrierung, das Messgas erreicht nicht mehr die Hälfte des ADC Abstands
+    server.get_zone_mut(0).unwrap().get_kombisensor_mut(0).unwrap().get_sensor_mut(0).unwrap().set_adc_value_at_messgas(750);
+    server.write_sensor_calibration(0, 0, 0).unwrap();
+    server.update();
+    server.check().unwrap();
+    assert_eq!(server.get_zone(0).unwrap().get_kombisensor(0).unwrap().get_sensor(0).unwrap().get_health().get_calibrations().len(), 3);
+    assert!(server.get_exceptions().lock().unwrap().contains(&replace_exception));
+
+    // Die Grenze ist einstellbar
+    server.set_sensor_replace_sensitivity_percent(40.0);
+    server.check().unwrap();
+    assert!(!server.get_exceptions().lock().unwrap().contains(&replace_exception));
+
+    // Der Verlauf übersteht einen Neustart
+    server.save_state().unwrap();
+    let mut restarted = server_with_state_file(&state_file);
+    restarted.restore_state().unwrap();
+    restarted.check().unwrap();
+    assert!(restarted.get_exceptions().lock().unwrap().contains(&replace_exception));
+}
+
+#[test]
+fn sensor_range_exceptions() {
+    let mut server = Server::new();
+    server.add_zone();
+    server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+    let under_range_exception = Exception::new(ExceptionType::SensorUnderRange { num_zone: 0, num_kombisensor: 0, num_sensor: 0 });
+    let over_range_exception = Exception::new(ExceptionType::SensorOverRange { num_zone: 0, num_kombisensor: 0, num_sensor: 0 });
+
+    // NO2 Messzelle, Messbereich 0 bis 30ppm
+    server.get_zone_mut(0).unwrap().get_kombisensor_mut(0).unwrap().get_sensor_mut(0).unwrap().set_concentration(40.0);
+    server.check().unwrap();
+    assert!(server.get_exceptions().lock().unwrap().contains(&over_range_exception));
+    assert!(!server.get_exceptions().lock().unwrap().contains(&under_range_exception));
+
+    server.get_zone_mut(0).unwrap().get_kombisensor_mut(0).unwrap().get_sensor_mut(0).unwrap().set_concentration(-10.0);
+    server.check().unwrap();
+    assert!(!server.get_exceptions().lock().unwrap().contains(&over_range_exception));
+    assert!(server.get_exceptions().lock().unwrap().contains(&under_range_exception));
+
+    server.get_zone_mut(0).unwrap().get_kombisensor_mut(0).unwrap().get_sensor_mut(0).unwrap().set_concentration(1.0);
+    server.check().unwrap();
+    assert!(!server.get_exceptions().lock().unwrap().contains(&over_range_exception));
+    assert!(!server.get_exceptions().lock().unwrap().contains(&under_range_exception));
+}
+
+#[test]
+fn scenario_file_drives_simulated_sensors() {
+    let temp_dir = TempDir::new("xmz_test_server_scenario");
+    let scenario_file = temp_dir.path("scenario.json");
+    // CO Messzelle springt sofort über den Direktwert
+    File::create(&scenario_file).unwrap().write_all(br#"{
+        "sensors": [{
+            "num_zone": 0, "num_kombisensor": 0, "num_sensor": 1,
+            "gas_profile": { "points": [{ "time_sec": 0, "concentration": 200, "transition": "Step" }] }
+        }]
+    }"#).unwrap();
+
+    let mut server = Server::new();
+    server.add_zone();
+    server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+    server.set_scenario_file(Some(scenario_file.clone()));
+    assert_eq!(server.load_scenario().unwrap(), 1);
+    server.evaluate();
+    server.evaluate();
+    assert_eq!(server.get_zone(0).unwrap().get_status(), ZoneStatus::DIW);
+
+    // Ohne Kombisensor passt das Szenario nicht zum Server
+    let mut empty = Server::new();
+    empty.set_scenario_file(Some(scenario_file.clone()));
+    assert!(empty.load_scenario().is_err());
+
+    // Ein echter Server spielt kein Szenario ab
+    let mut real = Server::new_with_type(ServerType::Real);
+    real.set_scenario_file(Some(scenario_file.clone()));
+    assert!(real.load_scenario().is_err());
+}
+
+#[test]
+fn latched_alarm_survives_restart() {
+    let temp_dir = TempDir::new("xmz_test_server_latched_alarm");
+    let state_file = temp_dir.path("state.json");
+    let latching = AlarmSettings { latching: true, ..Default::default() };
+
+    let mut server = server_with_state_file(&state_file);
+    server.get_zone_mut(0).unwrap().get_kombisensor_mut(0).unwrap().get_sensor_mut(1).unwrap().set_alarm_settings(AlarmLevel::DIW, latching.clone());
+    // CO über dem Direktwert (150ppm)
+    server.get_zone_mut(0).unwrap().get_kombisensor_mut(0).unwrap().get_sensor_mut(1).unwrap().set_concentration(200.0);
+    server.evaluate();
+    assert_eq!(server.get_zone(0).unwrap().get_status(), ZoneStatus::DIW);
+    server.save_state().unwrap();
+
+    // Nach dem Stromausfall ist das Gas weg, der Alarm bleibt bis zum Quittieren bestehen
+    let mut restarted = server_with_state_file(&state_file);
+    restarted.get_zone_mut(0).unwrap().get_kombisensor_mut(0).unwrap().get_sensor_mut(1).unwrap().set_alarm_settings(AlarmLevel::DIW, latching);
+    restarted.restore_state().unwrap();
+    restarted.evaluate();
+    assert_eq!(restarted.get_zone(0).unwrap().get_status(), ZoneStatus::DIW);
+    assert!(restarted.get_zone_mut(0).unwrap().reset_alarms());
+    restarted.evaluate();
+    // Der wiederhergestellte Mittelwert hält noch AP2
+    assert_eq!(restarted.get_zone(0).unwrap().get_status(), ZoneStatus::AP2);
+    assert!(!restarted.get_zone(0).unwrap().get_kombisensor(0).unwrap().get_sensor(1).unwrap().is_alarm_active(AlarmLevel::DIW));
+}
+
+#[test]
+fn average_gap_does_not_hide_ap1() {
+    let mut server = Server::new();
+    server.add_zone();
+    server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+    let now = Utc::now();
+
+    // NO2 mit 5ppm (über AP1), aber nach einer Lücke von 11 Minuten nur noch 4 Minuten abgedeckt
+    {
+        let sensor = server.get_zone_mut(0).unwrap().get_kombisensor_mut(0).unwrap().get_sensor_mut(0).unwrap();
+        sensor.set_adc_value_at(831, now - Duration::seconds(900));
+        sensor.update();
+        for sec in (0..49).map(|i| 240 - i * 5) {
+            sensor.set_adc_value_at(831, now - Duration::seconds(sec));
+            sensor.update();
+        }
+        assert!(!sensor.is_average_15min_valid());
+    }
+    server.evaluate();
+    server.check().unwrap();
+
+    assert_eq!(server.get_zone(0).unwrap().get_status(), ZoneStatus::AP1);
+    let average_exception = Exception::new(ExceptionType::SensorAverage15minInvalid { num_zone: 0, num_kombisensor: 0, num_sensor: 0 });
+    assert!(server.get_exceptions().lock().unwrap().contains(&average_exception));
+}
+
+#[test]
+fn kombisensor_write_without_server_lock() {
+    let mut server = Server::new();
+    server.add_zone();
+    server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+    let bus_manager = server.get_bus_manager();
+
+    // Die Kopie wird beschrieben, der Server bleibt bis zum Übernehmen unverändert
+    let mut kombisensor = server.copy_kombisensor(0, 0).unwrap();
+    kombisensor.write_sensor_enabled(&bus_manager, 1, false).unwrap();
+    assert!(server.get_zone(0).unwrap().get_kombisensor(0).unwrap().get_sensor(1).unwrap().is_enabled());
+    server.apply_sensor_write(0, 0, &kombisensor, 1).unwrap();
+    assert!(!server.get_zone(0).unwrap().get_kombisensor(0).unwrap().get_sensor(1).unwrap().is_enabled());
+
+    // Wurde der Kombisensor in der Zwischenzeit umkonfiguriert, wird nichts übernommen
+    let mut kombisensor = server.copy_kombisensor(0, 0).unwrap();
+    kombisensor.write_sensor_enabled(&bus_manager, 1, true).unwrap();
+    server.get_zone_mut(0).unwrap().get_kombisensor_mut(0).unwrap().set_modbus_address(12);
+    assert!(server.apply_sensor_write(0, 0, &kombisensor, 1).is_err());
+    assert!(!server.get_zone(0).unwrap().get_kombisensor(0).unwrap().get_sensor(1).unwrap().is_enabled());
+}
+
+#[test]
+fn conflicting_line_parameters_are_rejected() {
+    let mut server = Server::new();
+    server.add_zone();
+    server.add_zone();
+    let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGas);
+    kombisensor.set_modbus_device("/dev/ttyS2".to_string());
+    server.get_zone_mut(0).unwrap().add_kombisensor(kombisensor);
+
+    // Andere Timeouts auf der selben Leitung sind erlaubt
+    let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGas);
+    kombisensor.set_modbus_device("/dev/ttyS2".to_string());
+    kombisensor.set_modbus_address(12);
+    let mut serial_parameters = kombisensor.get_serial_parameters();
+    serial_parameters.set_response_timeout_ms(2000);
+    kombisensor.set_serial_parameters(Some(serial_parameters));
+    server.get_zone_mut(1).unwrap().add_kombisensor(kombisensor);
+    assert!(server.check_serial_parameters().is_ok());
+
+    // Eine andere Baudrate auf einer eigenen Leitung ebenso
+    let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGas);
+    kombisensor.set_modbus_device("/dev/ttyS3".to_string());
+    let mut serial_parameters = kombisensor.get_serial_parameters();
+    serial_parameters.set_baud(19200);
+    kombisensor.set_serial_parameters(Some(serial_parameters.clone()));
+    server.get_zone_mut(1).unwrap().add_kombisensor(kombisensor);
+    assert!(server.check_serial_parameters().is_ok());
+
+    // Auf der gemeinsamen Leitung nicht
+    server.get_zone_mut(1).unwrap().get_kombisensor_mut(0).unwrap().set_serial_parameters(Some(serial_parameters));
+    let error = server.check_serial_parameters().unwrap_err().to_string();
+    assert!(error.contains("Kombisensor 0 in Zone 1"));
+    assert!(error.contains("/dev/ttyS2"));
+}
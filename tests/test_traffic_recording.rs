@@ -0,0 +1,115 @@
+extern crate xmz_mod_touch_server;
+
+mod common;
+
+use common::TempDir;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use xmz_mod_touch_server::{Kombisensor, KombisensorType, Server};
+use xmz_mod_touch_server::modbus_bus::{TrafficRecorder, TrafficReplay, TRAFFIC_RECORDING_MAX_BYTES};
+
+
+#[test]
+fn recorded_traffic_replays_into_simulation_server() {
+    let temp_dir = TempDir::new("xmz_test_traffic_recording");
+    let path = temp_dir.path("traffic.log");
+
+    let mut server = Server::new();
+    server.add_zone();
+    server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+    server.get_bus_manager().set_traffic_recorder(Some(TrafficRecorder::create(&path, TRAFFIC_RECORDING_MAX_BYTES).unwrap())).unwrap();
+    server.update();
+    server.update();
+
+    let lines: Vec<String> = BufReader::new(File::open(&path).unwrap()).lines().map(|line| line.unwrap()).collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].contains("\tsim:///dev/ttyUSB0\t247\t"));
+
+    let recorded_adc_value = server.get_zone(0).unwrap().get_kombisensor(0).unwrap().get_sensor(0).unwrap().get_adc_value();
+
+    // Ein zweiter Server bekommt die Daten nur aus der Aufzeichnung
+    let mut replay_server = Server::new();
+    replay_server.add_zone();
+    replay_server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+    replay_server.set_traffic_replay_file(Some(path.clone()));
+    replay_server.start_traffic_recording().unwrap();
+    replay_server.update();
+
+    let kombisensor = replay_server.get_zone(0).unwrap().get_kombisensor(0).unwrap();
+    assert_eq!(kombisensor.get_firmware_version(), "0.14.0".to_string());
+    assert_eq!(kombisensor.get_sensor(0).unwrap().get_adc_value(), recorded_adc_value);
+    assert_eq!(kombisensor.get_communication_statistics().get_success_count(), 1);
+}
+
+#[test]
+fn replayed_failures_count_as_communication_errors() {
+    let temp_dir = TempDir::new("xmz_test_traffic_replay_failures");
+    let path = temp_dir.path("traffic.log");
+    {
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "2017-06-01T12:00:00+00:00\t/dev/ttyS1\t1\t500\t!timeout").unwrap();
+        writeln!(file, "").unwrap();
+        writeln!(file, "2017-06-01T13:00:00+00:00\t/dev/ttyS1\t1\t500\t!crc").unwrap();
+    }
+
+    let replay = TrafficReplay::open(&path).unwrap();
+    assert_eq!(replay.len(), 2);
+
+    // Die Schnittstelle existiert nicht, ohne Replay wäre das ein Fehler der Art `Other`
+    let mut server = Server::new();
+    server.add_zone();
+    let mut kombisensor = Kombisensor::new_with_type(KombisensorType::RAGas);
+    kombisensor.set_modbus_address(1);
+    server.get_zone_mut(0).unwrap().add_kombisensor(kombisensor);
+    server.get_bus_manager().set_traffic_replay(Some(replay)).unwrap();
+    server.update();
+
+    let statistics = server.get_zone(0).unwrap().get_kombisensor(0).unwrap().get_communication_statistics();
+    assert_eq!(statistics.get_timeout_count(), 1);
+    assert_eq!(statistics.get_other_error_count(), 0);
+}
+
+#[test]
+fn recording_rotates_at_max_size() {
+    let temp_dir = TempDir::new("xmz_test_traffic_rotation");
+    let path = temp_dir.path("traffic.log");
+    let rotated = format!("{}.1", path);
+
+    let mut server = Server::new();
+    server.add_zone();
+    server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+    server.update();
+    server.get_bus_manager().set_traffic_recorder(Some(TrafficRecorder::create(&path, 400).unwrap())).unwrap();
+    for _ in 0..5 {
+        server.update();
+    }
+
+    // Jede Datei bleibt unter der Grenze, die älteren Abfragen stehen in der `.1` Datei
+    assert!(fs::metadata(&path).unwrap().len() <= 400);
+    assert!(fs::metadata(&rotated).unwrap().len() <= 400);
+    let lines = BufReader::new(File::open(&path).unwrap()).lines().count() +
+        BufReader::new(File::open(&rotated).unwrap()).lines().count();
+    assert!(lines >= 2 && lines < 5);
+}
+
+#[test]
+fn recording_stops_when_rotation_fails() {
+    let temp_dir = TempDir::new("xmz_test_traffic_rotation_fails");
+    let path = temp_dir.path("traffic.log");
+    // Ein Verzeichnis mit Inhalt kann nicht durch die Datei ersetzt werden
+    let rotated = format!("{}.1", path);
+    fs::create_dir_all(format!("{}/blocked", rotated)).unwrap();
+
+    let recorder = TrafficRecorder::create(&path, 400).unwrap();
+    let mut server = Server::new();
+    server.add_zone();
+    server.get_zone_mut(0).unwrap().add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+    server.get_bus_manager().set_traffic_recorder(Some(recorder)).unwrap();
+    for _ in 0..5 {
+        server.update();
+    }
+
+    // Die Datei bleibt unter der Grenze
+    assert!(fs::metadata(&path).unwrap().len() <= 400);
+    assert!(BufReader::new(File::open(&path).unwrap()).lines().count() >= 1);
+}
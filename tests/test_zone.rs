@@ -1,6 +1,7 @@
 extern crate xmz_mod_touch_server;
 
-use xmz_mod_touch_server::{Kombisensor, KombisensorType, Sensor, SensorType, Zone, ZoneStatus};
+use xmz_mod_touch_server::{Kombisensor, KombisensorType, Sensor, SensorStatus, SensorType, Zone, ZoneStatus};
+use xmz_mod_touch_server::server::zone::kombisensor::sensor::{AlarmLevel, AlarmSettings};
 
 
 #[test]
@@ -243,3 +244,168 @@ fn zone_in_status_ap2_ein_kombisensor_ein_sensor_diw() {
     // der neue Status der Zone Normal
     assert_eq!(zone.get_status(), ZoneStatus::DIW);
 }
+
+// Hysterese und Selbsthaltung
+
+#[test]
+fn zone_in_status_diw_haelt_mit_hysterese() {
+    let mut sensor = Sensor::new_with_type(SensorType::SimulationCO);
+    sensor.set_alarm_settings(AlarmLevel::DIW, AlarmSettings { hysteresis: 10.0, ..Default::default() });
+    let mut kombisensor = Kombisensor::new();
+    kombisensor.add_sensor(sensor);
+    let mut zone = Zone::new();
+    zone.add_kombisensor(kombisensor);
+
+    zone.get_kombisensor_mut(0).unwrap().get_sensor_mut(0).unwrap().set_concentration(151.0); // DIW bei 150 überschritten
+    zone.update();
+    assert_eq!(zone.get_status(), ZoneStatus::DIW);
+
+    // Pendelt der Messwert knapp unter den Direktwert, bleibt die Zone im DIW Status
+    zone.get_kombisensor_mut(0).unwrap().get_sensor_mut(0).unwrap().set_concentration(145.0);
+    zone.update();
+    assert_eq!(zone.get_status(), ZoneStatus::DIW);
+
+    // erst unterhalb der Hysterese fällt der Alarm ab
+    zone.get_kombisensor_mut(0).unwrap().get_sensor_mut(0).unwrap().set_concentration(139.0);
+    zone.update();
+    assert_ne!(zone.get_status(), ZoneStatus::DIW);
+}
+
+#[test]
+fn zone_in_status_ap2_selbsthaltend_bis_quittiert() {
+    let mut zone = Zone::new();
+    zone.add_kombisensor(Kombisensor::new_with_type(KombisensorType::RAGasSimulation));
+    zone.get_kombisensor_mut(0).unwrap().get_sensor_mut(1).unwrap()
+        .set_alarm_settings(AlarmLevel::AP2, AlarmSettings { latching: true, ..Default::default() });
+
+    zone.get_kombisensor_mut(0).unwrap().get_sensor_mut(1).unwrap().set_concentration(61.0); // AP2 bei 60 überschritten
+    zone.update();
+    assert_eq!(zone.get_status(), ZoneStatus::AP2);
+
+    // Solange der Messwert noch anliegt, kann nicht quittiert werden
+    assert!(!zone.reset_alarms());
+    assert_eq!(zone.get_status(), ZoneStatus::AP2);
+
+    // Ohne Gas bleibt die Zone im AP2 Status
+    zone.get_kombisensor_mut(0).unwrap().get_sensor_mut(1).unwrap().clear_adc_values_average();
+    zone.get_kombisensor_mut(0).unwrap().get_sensor_mut(1).unwrap().set_concentration(0.0);
+    zone.update();
+    assert_eq!(zone.get_status(), ZoneStatus::AP2);
+
+    // bis der Alarm quittiert wird
+    assert!(zone.reset_alarms());
+    assert_eq!(zone.get_status(), ZoneStatus::Normal);
+}
+
+// Messzellen mit fallendem Alarm (Sauerstoff) und % UEG
+
+#[test]
+fn ein_kombisensor_ein_o2_sensor_diw_bei_sauerstoffmangel() {
+    let mut sensor = Sensor::new_with_type(SensorType::O2);
+    sensor.set_config(1);
+    let mut kombisensor = Kombisensor::new();
+    kombisensor.add_sensor(sensor);
+    let mut zone = Zone::new();
+    zone.add_kombisensor(kombisensor);
+
+    // Umgebungsluft
+    zone.get_kombisensor_mut(0).unwrap().get_sensor_mut(0).unwrap().set_concentration(20.9);
+    zone.update();
+    assert_eq!(zone.get_status(), ZoneStatus::Normal);
+
+    // Unterschreitet der Sauerstoff Gehalt den Direktwert (17 Vol%),
+    zone.get_kombisensor_mut(0).unwrap().get_sensor_mut(0).unwrap().set_concentration(16.5);
+    // dann ist nach einem Update,
+    zone.update();
+    // der neue Status der Zone DIW
+    assert_eq!(zone.get_status(), ZoneStatus::DIW);
+
+    // Mit Umgebungsluft fällt der Alarm wieder ab
+    zone.get_kombisensor_mut(0).unwrap().get_sensor_mut(0).unwrap().clear_adc_values_average();
+    zone.get_kombisensor_mut(0).unwrap().get_sensor_mut(0).unwrap().set_concentration(20.9);
+    zone.update();
+    assert_eq!(zone.get_status(), ZoneStatus::Normal);
+}
+
+#[test]
+fn ein_kombisensor_ein_o2_sensor_ohne_messwert_normal() {
+    let mut sensor = Sensor::new_with_type(SensorType::O2);
+    sensor.set_config(1);
+    let mut kombisensor = Kombisensor::new();
+    kombisensor.add_sensor(sensor);
+    let mut zone = Zone::new();
+    zone.add_kombisensor(kombisensor);
+
+    // Vor der ersten Abfrage ist die Konzentration 0.0 Vol%, das ist aber kein Sauerstoffmangel
+    zone.update();
+    zone.update();
+    assert_eq!(zone.get_status(), ZoneStatus::Normal);
+    assert!(!zone.get_kombisensor(0).unwrap().get_sensor(0).unwrap().direct_value_reached());
+}
+
+#[test]
+fn zwei_kombisensoren_o2_und_ch4() {
+    let mut o2_sensor = Sensor::new_with_type(SensorType::O2);
+    o2_sensor.set_config(1);
+    let mut ch4_sensor = Sensor::new_with_type(SensorType::CH4);
+    ch4_sensor.set_config(1);
+    let mut kombisensor1 = Kombisensor::new();
+    let mut kombisensor2 = Kombisensor::new();
+    kombisensor1.add_sensor(o2_sensor);
+    kombisensor2.add_sensor(ch4_sensor);
+    let mut zone = Zone::new();
+    zone.add_kombisensor(kombisensor1);
+    zone.add_kombisensor(kombisensor2);
+
+    // Umgebungsluft, kein Methan
+    zone.get_kombisensor_mut(0).unwrap().get_sensor_mut(0).unwrap().set_concentration(20.9);
+    zone.get_kombisensor_mut(1).unwrap().get_sensor_mut(0).unwrap().set_concentration(0.0);
+    zone.update();
+    assert_eq!(zone.get_status(), ZoneStatus::Normal);
+
+    // Überschreitet der Methan Gehalt den Alarmpunkt1 (10% UEG),
+    zone.get_kombisensor_mut(1).unwrap().get_sensor_mut(0).unwrap().clear_adc_values_average();
+    zone.get_kombisensor_mut(1).unwrap().get_sensor_mut(0).unwrap().set_concentration(12.0);
+    // dann ist nach einem Update,
+    zone.update();
+    // der neue Status der Zone AP1
+    assert_eq!(zone.get_status(), ZoneStatus::AP1);
+}
+
+// Messbereich überschritten
+
+#[test]
+fn ein_kombisensor_ein_o2_sensor_messbereich_ueberschritten() {
+    let mut sensor = Sensor::new_with_type(SensorType::O2);
+    sensor.set_config(1);
+    let mut kombisensor = Kombisensor::new();
+    kombisensor.add_sensor(sensor);
+    let mut zone = Zone::new();
+    zone.add_kombisensor(kombisensor);
+
+    // Überschreitet der Messwert den Messbereich (25 Vol%), obwohl die O2 Alarmpunkte erst beim Unterschreiten auslösen,
+    zone.get_kombisensor_mut(0).unwrap().get_sensor_mut(0).unwrap().set_concentration(26.0);
+    // dann ist nach einem Update,
+    zone.update();
+    // der neue Status der Zone mindestens DIW
+    assert_eq!(zone.get_status(), ZoneStatus::DIW);
+}
+
+#[test]
+fn gesaettigte_nemoto_messzelle_diw() {
+    let mut zone = Zone::new();
+    let mut kombisensor = Kombisensor::new();
+    let mut sensor = Sensor::new_with_type(SensorType::NemotoNO2);
+    sensor.set_config(1);
+    // Nur der Messbereich soll die Zone in den DIW Status bringen
+    sensor.alarm3_direct_value = 1000.0;
+    kombisensor.add_sensor(sensor);
+    zone.add_kombisensor(kombisensor);
+
+    // Der ADC Wert der Nemoto Messzelle fällt mit steigender Konzentration, 0 ist der Anschlag
+    zone.get_kombisensor_mut(0).unwrap().get_sensor_mut(0).unwrap().set_adc_value(0);
+    zone.get_kombisensor_mut(0).unwrap().get_sensor_mut(0).unwrap().update();
+    zone.update();
+    assert_eq!(zone.get_kombisensor(0).unwrap().get_sensor(0).unwrap().get_status(), SensorStatus::OverRange);
+    assert_eq!(zone.get_status(), ZoneStatus::DIW);
+}